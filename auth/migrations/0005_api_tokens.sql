@@ -0,0 +1,13 @@
+-- Tokens with fewer rights than the key of their user, for CI systems. Only the SHA-256 of a key is
+-- stored.
+CREATE TABLE IF NOT EXISTS api_tokens (
+  token_id TEXT PRIMARY KEY,
+  user_id TEXT NOT NULL,
+  name TEXT NOT NULL,
+  key_hash TEXT UNIQUE NOT NULL,
+  scopes TEXT[] NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  expires_at TIMESTAMPTZ,
+  UNIQUE (user_id, name),
+  FOREIGN KEY (user_id) REFERENCES users (user_id) ON DELETE CASCADE
+);
@@ -20,8 +20,8 @@ use crate::{
 };
 
 use super::handlers::{
-    convert_key, delete_subscription, get_public_key, get_user, get_user_by_name,
-    post_subscription, post_user, put_user_reset_key,
+    convert_key, delete_subscription, delete_token, get_current_user, get_public_key, get_tokens,
+    get_user, get_user_by_name, post_subscription, post_token, post_user, put_user_reset_key,
 };
 
 pub type UserManagerState = Arc<Box<dyn UserManagement>>;
@@ -78,8 +78,11 @@ where
             .route("/users/name/:account_name", get(get_user_by_name))
             // users are created based on auth0 name by console
             .route("/users/:account_name/:account_tier", post(post_user))
+            .route("/users/me", get(get_current_user))
             .route("/users/:user_id", get(get_user))
             .route("/users/reset-api-key", put(put_user_reset_key))
+            .route("/users/tokens", get(get_tokens).post(post_token))
+            .route("/users/tokens/:token_id", delete(delete_token))
             .route("/users/:user_id/subscribe", post(post_subscription))
             .route(
                 "/users/:user_id/subscribe/:subscription_id",
@@ -1,6 +1,6 @@
 use crate::{
     error::Error,
-    user::{Admin, Key},
+    user::{Admin, Key, User},
 };
 use axum::{
     extract::{Path, State},
@@ -9,7 +9,11 @@ use axum::{
 use http::StatusCode;
 use shuttle_common::{
     claims::{AccountTier, Claim},
-    models::user::{self, SubscriptionRequest, UserId},
+    models::{
+        token,
+        user::{self, SubscriptionRequest, UserId},
+    },
+    ApiKey,
 };
 use tracing::{field, instrument, Span};
 
@@ -84,24 +88,95 @@ pub(crate) async fn delete_subscription(
     Ok(())
 }
 
-/// Convert a valid API-key bearer token to a JWT.
+/// The account of the API key or API token of the request
+pub(crate) async fn get_current_user(
+    State(user_manager): State<UserManagerState>,
+    key: Key,
+) -> Result<Json<user::AccountResponse>, Error> {
+    let key: ApiKey = key.into();
+
+    let (user, token) = match user_manager.get_user_by_key(key.clone()).await {
+        Ok(user) => (user, None),
+        Err(Error::UserNotFound) => {
+            let (user, token) = user_manager
+                .get_user_by_token(key)
+                .await
+                .map_err(|_| Error::Unauthorized)?;
+
+            (user, Some(token.into()))
+        }
+        Err(error) => return Err(error),
+    };
+
+    Ok(Json(user::AccountResponse {
+        name: user.name.to_string(),
+        id: user.id,
+        account_tier: user.account_tier.to_string(),
+        token,
+    }))
+}
+
+pub(crate) async fn post_token(
+    State(user_manager): State<UserManagerState>,
+    user: User,
+    Json(request): Json<token::CreateRequest>,
+) -> Result<Json<token::CreatedResponse>, Error> {
+    let (token, key) = user_manager.create_token(&user.id, request).await?;
+
+    Ok(Json(token::CreatedResponse {
+        token: token.into(),
+        key: key.as_ref().to_string(),
+    }))
+}
+
+pub(crate) async fn get_tokens(
+    State(user_manager): State<UserManagerState>,
+    user: User,
+) -> Result<Json<Vec<token::Response>>, Error> {
+    let tokens = user_manager.get_tokens(&user.id).await?;
+
+    Ok(Json(tokens.into_iter().map(Into::into).collect()))
+}
+
+pub(crate) async fn delete_token(
+    State(user_manager): State<UserManagerState>,
+    user: User,
+    Path(token_id): Path<String>,
+) -> Result<(), Error> {
+    user_manager.delete_token(&user.id, &token_id).await
+}
+
+/// Convert a valid API-key or API-token bearer token to a JWT.
 pub(crate) async fn convert_key(
     _: Admin,
     State(user_manager): State<UserManagerState>,
     State(key_manager): State<KeyManagerState>,
     key: Key,
 ) -> Result<Json<shuttle_backends::auth::ConvertResponse>, StatusCode> {
-    let user = user_manager
-        .get_user_by_key(key.into())
-        .await
-        .map_err(|_| StatusCode::UNAUTHORIZED)?;
-
-    let claim = Claim::new(
-        user.id.clone(),
-        user.account_tier.into(),
-        user.account_tier,
-        user,
-    );
+    let key: ApiKey = key.into();
+
+    let claim = match user_manager.get_user_by_key(key.clone()).await {
+        Ok(user) => Claim::new(
+            user.id.clone(),
+            user.account_tier.into(),
+            user.account_tier,
+            user,
+        ),
+        Err(_) => {
+            let (user, token) = user_manager
+                .get_user_by_token(key)
+                .await
+                .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+            Claim::new(
+                user.id.clone(),
+                token.claim_scopes(user.account_tier.into()),
+                user.account_tier,
+                user,
+            )
+            .restricted(token.projects(), token.expires_at)
+        }
+    };
 
     let token = claim.into_token(key_manager.private_key())?;
 
@@ -12,6 +12,12 @@ use stripe::StripeError;
 pub enum Error {
     #[error("User could not be found")]
     UserNotFound,
+    #[error("API token could not be found")]
+    TokenNotFound,
+    #[error("An API token called '{0}' already exists")]
+    TokenExists(String),
+    #[error("Invalid API token: {0}")]
+    InvalidToken(String),
     #[error("API key is missing.")]
     KeyMissing,
     #[error("Unauthorized.")]
@@ -48,9 +54,11 @@ impl IntoResponse for Error {
         let code = match self {
             Error::Forbidden => StatusCode::FORBIDDEN,
             Error::Unauthorized | Error::KeyMissing => StatusCode::UNAUTHORIZED,
-            Error::Database(sqlx::Error::RowNotFound) | Error::UserNotFound => {
-                StatusCode::NOT_FOUND
-            }
+            Error::Database(sqlx::Error::RowNotFound)
+            | Error::UserNotFound
+            | Error::TokenNotFound => StatusCode::NOT_FOUND,
+            Error::TokenExists(_) => StatusCode::CONFLICT,
+            Error::InvalidToken(_) => StatusCode::BAD_REQUEST,
             _ => {
                 // We only want to emit error events for internal errors, not e.g. 404s.
                 tracing::error!(
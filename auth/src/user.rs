@@ -8,9 +8,14 @@ use axum::{
     TypedHeader,
 };
 use chrono::{DateTime, Utc};
+use ring::digest::{digest, SHA256};
 use shuttle_backends::{client::PermissionsDal, headers::XShuttleAdminSecret};
 use shuttle_common::{
-    claims::AccountTier, limits::Limits, models, models::user::UserId, ApiKey, Secret,
+    claims::{AccountTier, Scope},
+    limits::Limits,
+    models,
+    models::user::UserId,
+    ApiKey, Secret,
 };
 use sqlx::{postgres::PgRow, query, FromRow, PgPool, Row};
 use stripe::{SubscriptionId, SubscriptionStatus};
@@ -40,6 +45,16 @@ pub trait UserManagement: Send + Sync {
         user_id: &UserId,
         subscription_id: &str,
     ) -> Result<(), Error>;
+    /// Create an API token for a user, and return it with its key
+    async fn create_token(
+        &self,
+        user_id: &UserId,
+        request: models::token::CreateRequest,
+    ) -> Result<(Token, ApiKey), Error>;
+    async fn get_tokens(&self, user_id: &UserId) -> Result<Vec<Token>, Error>;
+    async fn delete_token(&self, user_id: &UserId, token_id: &str) -> Result<(), Error>;
+    /// Get an unexpired API token by its key, together with its user
+    async fn get_user_by_token(&self, key: ApiKey) -> Result<(User, Token), Error>;
 }
 
 #[derive(Clone)]
@@ -269,6 +284,106 @@ where
 
         Ok(())
     }
+
+    async fn create_token(
+        &self,
+        user_id: &UserId,
+        request: models::token::CreateRequest,
+    ) -> Result<(Token, ApiKey), Error> {
+        let models::token::CreateRequest {
+            name,
+            scopes,
+            expires_at,
+        } = request;
+
+        let name = name.trim().to_string();
+        if name.is_empty() {
+            return Err(Error::InvalidToken("a token needs a name".to_string()));
+        }
+        if scopes.is_empty() {
+            return Err(Error::InvalidToken(
+                "a token needs at least one scope".to_string(),
+            ));
+        }
+        // The claims of a token only hold one set of scopes and projects, so a token is for one
+        // project or for all of them
+        if scopes
+            .iter()
+            .any(|scope| scope.project != scopes[0].project)
+        {
+            return Err(Error::InvalidToken(
+                "the scopes of a token have to be for the same project".to_string(),
+            ));
+        }
+        if expires_at.is_some_and(|expires_at| expires_at <= Utc::now()) {
+            return Err(Error::InvalidToken(
+                "a token can not expire in the past".to_string(),
+            ));
+        }
+
+        let key = ApiKey::generate();
+        let token: Token = sqlx::query_as(
+            r#"INSERT INTO api_tokens (token_id, user_id, name, key_hash, scopes, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+        "#,
+        )
+        .bind(Token::new_token_id())
+        .bind(user_id)
+        .bind(&name)
+        .bind(Token::hash_key(&key))
+        .bind(scopes.iter().map(ToString::to_string).collect::<Vec<_>>())
+        .bind(expires_at)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|error| match error {
+            sqlx::Error::Database(error) if error.is_unique_violation() => {
+                Error::TokenExists(name.clone())
+            }
+            error => error.into(),
+        })?;
+
+        Ok((token, key))
+    }
+
+    async fn get_tokens(&self, user_id: &UserId) -> Result<Vec<Token>, Error> {
+        let tokens =
+            sqlx::query_as("SELECT * FROM api_tokens WHERE user_id = $1 ORDER BY created_at")
+                .bind(user_id)
+                .fetch_all(&self.pool)
+                .await?;
+
+        Ok(tokens)
+    }
+
+    async fn delete_token(&self, user_id: &UserId, token_id: &str) -> Result<(), Error> {
+        let rows_affected = query("DELETE FROM api_tokens WHERE token_id = $1 AND user_id = $2")
+            .bind(token_id)
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        if rows_affected > 0 {
+            Ok(())
+        } else {
+            Err(Error::TokenNotFound)
+        }
+    }
+
+    async fn get_user_by_token(&self, key: ApiKey) -> Result<(User, Token), Error> {
+        let token: Token = sqlx::query_as(
+            "SELECT * FROM api_tokens WHERE key_hash = $1 AND (expires_at IS NULL OR expires_at > now())",
+        )
+        .bind(Token::hash_key(&key))
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(Error::TokenNotFound)?;
+
+        let user = self.get_user(token.user_id.clone()).await?;
+
+        Ok((user, token))
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -289,6 +404,79 @@ pub struct Subscription {
     pub updated_at: DateTime<Utc>,
 }
 
+/// An API token of a user, with fewer rights than the key of the user
+#[derive(Clone, Debug)]
+pub struct Token {
+    pub id: String,
+    pub user_id: UserId,
+    pub name: String,
+    pub scopes: Vec<models::token::Scope>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl Token {
+    pub fn new_token_id() -> String {
+        format!("token_{}", ulid::Ulid::new())
+    }
+
+    /// Keys of tokens are only stored as their SHA-256
+    pub fn hash_key(key: &ApiKey) -> String {
+        digest(&SHA256, key.as_ref().as_bytes())
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// The scopes of claims for this token, which never go beyond the `user_scopes` of its user
+    pub fn claim_scopes(&self, user_scopes: Vec<Scope>) -> Vec<Scope> {
+        let granted = self
+            .scopes
+            .iter()
+            .flat_map(|scope| permission_scopes(scope.permission))
+            .collect::<Vec<_>>();
+
+        user_scopes
+            .into_iter()
+            .filter(|scope| granted.contains(scope))
+            .collect()
+    }
+
+    /// The projects claims for this token are limited to, if any
+    pub fn projects(&self) -> Option<Vec<String>> {
+        let projects = self
+            .scopes
+            .iter()
+            .filter_map(|scope| scope.project.clone())
+            .collect::<Vec<_>>();
+
+        (!projects.is_empty()).then_some(projects)
+    }
+}
+
+fn permission_scopes(permission: models::token::Permission) -> Vec<Scope> {
+    use models::token::Permission;
+
+    match permission {
+        Permission::Read => vec![
+            Scope::Project,
+            Scope::Deployment,
+            Scope::Service,
+            Scope::Resources,
+        ],
+        Permission::Logs => vec![Scope::Project, Scope::Deployment, Scope::Logs],
+        Permission::Deploy => vec![
+            Scope::Project,
+            Scope::Deployment,
+            Scope::DeploymentPush,
+            Scope::Service,
+            Scope::ServiceCreate,
+            Scope::Resources,
+        ],
+    }
+}
+
 impl User {
     pub fn is_admin(&self) -> bool {
         self.account_tier == AccountTier::Admin
@@ -414,6 +602,28 @@ impl FromRow<'_, PgRow> for Subscription {
     }
 }
 
+impl FromRow<'_, PgRow> for Token {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let scopes: Vec<String> = row.try_get("scopes")?;
+
+        Ok(Token {
+            id: row.try_get("token_id")?,
+            user_id: row.try_get("user_id")?,
+            name: row.try_get("name")?,
+            scopes: scopes
+                .iter()
+                .map(|scope| scope.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|err: String| sqlx::Error::ColumnDecode {
+                    index: "scopes".to_string(),
+                    source: Box::new(std::io::Error::new(ErrorKind::Other, err)),
+                })?,
+            created_at: row.try_get("created_at")?,
+            expires_at: row.try_get("expires_at")?,
+        })
+    }
+}
+
 impl From<User> for Limits {
     fn from(user: User) -> Self {
         let mut limits: Limits = user.account_tier.into();
@@ -481,6 +691,18 @@ impl From<Subscription> for models::user::Subscription {
     }
 }
 
+impl From<Token> for models::token::Response {
+    fn from(token: Token) -> Self {
+        Self {
+            id: token.id,
+            name: token.name,
+            scopes: token.scopes,
+            created_at: token.created_at,
+            expires_at: token.expires_at,
+        }
+    }
+}
+
 /// A wrapper around [ApiKey] so we can implement [FromRequestParts] for it.
 pub struct Key(ApiKey);
 
@@ -528,9 +750,11 @@ where
     type Rejection = Error;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let user = User::from_request_parts(parts, state).await?;
-        if user.is_admin() {
-            return Ok(Self { user });
+        // The bearer can be an API token, which is not a user, when an admin converts it
+        if let Ok(user) = User::from_request_parts(parts, state).await {
+            if user.is_admin() {
+                return Ok(Self { user });
+            }
         }
 
         match parts.headers.typed_try_get::<XShuttleAdminSecret>() {
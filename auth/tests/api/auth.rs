@@ -2,8 +2,8 @@ mod needs_docker {
     use http::header::AUTHORIZATION;
     use http::{Request, StatusCode};
     use hyper::Body;
-    use serde_json::Value;
-    use shuttle_common::claims::AccountTier;
+    use serde_json::{json, Value};
+    use shuttle_common::claims::{AccountTier, Scope};
 
     use crate::helpers::{app, ADMIN_KEY};
 
@@ -82,4 +82,65 @@ mod needs_docker {
         assert_eq!(claim.tier, AccountTier::Basic);
         assert_eq!(claim.limits.project_limit(), 3);
     }
+
+    #[tokio::test]
+    async fn convert_api_token_to_jwt() {
+        let app = app().await;
+
+        let response = app.post_user("test-user-ci", "basic").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let user: Value = serde_json::from_slice(&body).unwrap();
+        let user_key = user["key"].as_str().unwrap();
+
+        // POST /users/tokens with scopes for different projects.
+        let response = app
+            .post_token(
+                user_key,
+                json!({"name": "ci", "scopes": ["deploy:my-api", "logs:other"]}),
+            )
+            .await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app
+            .post_token(user_key, json!({"name": "ci", "scopes": ["deploy:my-api"]}))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let token: Value = serde_json::from_slice(&body).unwrap();
+        let token_id = token["id"].as_str().unwrap();
+        let token_key = token["key"].as_str().unwrap();
+
+        // Names are unique per user.
+        let response = app
+            .post_token(user_key, json!({"name": "ci", "scopes": ["read"]}))
+            .await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        // Tokens can not make tokens.
+        let response = app
+            .post_token(token_key, json!({"name": "ci-2", "scopes": ["read"]}))
+            .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let claim = app.get_claim(token_key).await;
+        assert_eq!(claim.sub, user["id"].as_str().unwrap());
+        assert!(claim.scopes.contains(&Scope::DeploymentPush));
+        assert!(!claim.scopes.contains(&Scope::SecretWrite));
+        assert!(claim.allows_project("my-api"));
+        assert!(!claim.allows_project("other"));
+
+        // DELETE /users/tokens/:token_id revokes the token.
+        let request = Request::builder()
+            .uri(format!("/users/tokens/{token_id}"))
+            .method("DELETE")
+            .header(AUTHORIZATION, format!("Bearer {user_key}"))
+            .body(Body::empty())
+            .unwrap();
+        let response = app.send_request(request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app.get_jwt_from_api_key(token_key, Some(ADMIN_KEY)).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
 }
@@ -395,4 +395,52 @@ mod needs_docker {
             ]
         );
     }
+
+    #[tokio::test]
+    async fn get_current_user() {
+        let app = app().await;
+
+        let user: user::Response = {
+            let response = app.post_user("test-user", "basic").await;
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            serde_json::from_slice(&body).unwrap()
+        };
+        let get_me = |key: &str| {
+            Request::builder()
+                .uri("/users/me")
+                .header(AUTHORIZATION, format!("Bearer {key}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.send_request(get_me(&user.key)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let account: user::AccountResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(account.id, user.id);
+        assert_eq!(account.name, "test-user");
+        assert!(account.token.is_none());
+
+        // With an API token of the user, which is shown with its scopes.
+        let response = app
+            .post_token(
+                &user.key,
+                serde_json::json!({"name": "ci", "scopes": ["read:my-api"]}),
+            )
+            .await;
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let token: Value = serde_json::from_slice(&body).unwrap();
+
+        let response = app
+            .send_request(get_me(token["key"].as_str().unwrap()))
+            .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let account: user::AccountResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(account.id, user.id);
+        assert_eq!(account.token.unwrap().name, "ci");
+
+        let response = app.send_request(get_me("0000000000000000")).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
 }
@@ -156,11 +156,22 @@ impl PermissionsDal for PermissionsMock {
     }
 
     async fn get_personal_projects(&self, user_id: &str) -> Result<Vec<String>> {
-        self.calls
-            .lock()
-            .await
-            .push(format!("get_personal_projects {user_id}"));
-        Ok(vec![])
+        let mut calls = self.calls.lock().await;
+
+        // The projects the user created that have not been deleted since
+        let mut projects = Vec::new();
+        for call in calls.iter() {
+            match call.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["create_project", user, project_id] if *user == user_id => {
+                    projects.push(project_id.to_string())
+                }
+                ["delete_project", project_id] => projects.retain(|p| p != project_id),
+                _ => {}
+            }
+        }
+
+        calls.push(format!("get_personal_projects {user_id}"));
+        Ok(projects)
     }
 
     async fn allowed(&self, user_id: &str, project_id: &str, action: &str) -> Result<bool> {
@@ -0,0 +1,96 @@
+//! Commands for the account of the API key in use
+
+use anyhow::{Context, Result};
+use crossterm::style::Stylize;
+use indoc::formatdoc;
+use shuttle_common::models::{organization, user};
+use tracing::debug;
+
+use crate::overview::ApiKeySource;
+use crate::{output, CommandOutcome, Shuttle};
+
+impl Shuttle {
+    pub(crate) async fn whoami(&self) -> Result<CommandOutcome> {
+        /// The account with its teams, for JSON output
+        #[derive(serde::Serialize)]
+        struct Whoami<'a> {
+            #[serde(flatten)]
+            account: &'a user::AccountResponse,
+            api_key: ApiKeySource,
+            /// The teams, when they could be fetched
+            teams: Option<&'a [organization::Response]>,
+        }
+
+        let client = self.client.as_ref().unwrap();
+        let api_key = ApiKeySource::current();
+        let (account, teams) =
+            tokio::join!(client.get_current_user(), client.get_organizations_list());
+        // Fetching the account is what checks the key
+        let account = account.context("failed to verify the API key")?;
+        let teams = teams
+            .map_err(|err| debug!(error = %err, "failed to get the teams"))
+            .ok();
+
+        let whoami = Whoami {
+            account: &account,
+            api_key,
+            teams: teams.as_deref(),
+        };
+        output::print(self.output, &whoami, |_| {
+            let api_key = match api_key {
+                ApiKeySource::Env => "from SHUTTLE_API_KEY",
+                ApiKeySource::Config => "from the global configuration (cargo shuttle login)",
+            };
+            let access = match &account.token {
+                Some(token) => {
+                    let expires = match token.expires_at {
+                        Some(expires_at) => {
+                            format!("expires at {}", expires_at.format("%Y-%m-%dT%H:%M:%SZ"))
+                        }
+                        None => "never expires".to_string(),
+                    };
+                    let scopes = token
+                        .scopes
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    format!(
+                        "API token {} ({}) with the scopes {scopes}, which {expires}",
+                        token.name, token.id
+                    )
+                }
+                None => "every scope of the account, with its API key".to_string(),
+            };
+            let teams = match &teams {
+                Some(teams) if teams.is_empty() => "none".to_string(),
+                Some(teams) => teams
+                    .iter()
+                    .map(|team| {
+                        let role = if team.is_admin {
+                            organization::MemberRole::Admin
+                        } else {
+                            organization::MemberRole::Member
+                        };
+                        format!("{} ({role})", team.display_name)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                None => "could not be fetched".to_string(),
+            };
+
+            formatdoc! {"
+                Account:  {} ({})
+                Tier:     {}
+                API key:  {api_key}
+                Access:   {access}
+                Teams:    {teams}",
+                account.name.clone().bold(),
+                account.id,
+                account.account_tier,
+            }
+        })?;
+
+        Ok(CommandOutcome::Ok)
+    }
+}
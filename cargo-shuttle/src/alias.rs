@@ -0,0 +1,230 @@
+//! Aliases for commands, like `d = "deploy --no-follow"` in the `[aliases]` table of the global
+//! configuration.
+//!
+//! Aliases are expanded before the arguments are parsed, and the arguments after an alias are
+//! passed on after its expansion. Built-in commands win over aliases of the same name, and the
+//! expansion of an alias is not expanded again.
+
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+
+use anyhow::{bail, Result};
+use clap::CommandFactory;
+
+use crate::args::ShuttleArgs;
+use crate::config::{Config, GlobalConfig, GlobalConfigManager};
+use crate::errors::{self, ErrorCode};
+use crate::{output, CommandOutcome, Shuttle};
+
+/// The aliases in the global configuration. A missing or broken configuration has none, and its
+/// errors are reported once the command runs.
+pub fn load() -> BTreeMap<String, String> {
+    let mut config: Config<_, GlobalConfig> = Config::new(GlobalConfigManager);
+    if !config.exists() || config.open().is_err() {
+        return BTreeMap::new();
+    }
+
+    config
+        .as_ref()
+        .and_then(|config| config.aliases.clone())
+        .unwrap_or_default()
+}
+
+/// Check that an alias can be added under `name`
+pub fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.starts_with('-') || name.contains(char::is_whitespace) {
+        bail!("invalid alias name `{name}`. Use a single word that does not start with a dash");
+    }
+    if is_builtin(name) {
+        bail!("`{name}` is a built-in command and cannot be an alias");
+    }
+
+    Ok(())
+}
+
+/// Join the words an alias stands for. They are split on spaces when expanded, so the words
+/// themselves cannot have any.
+pub fn join_command(command: &[String]) -> Result<String> {
+    if let Some(word) = command
+        .iter()
+        .find(|word| word.contains(char::is_whitespace))
+    {
+        bail!("the arguments of an alias cannot contain spaces, like `{word}` does");
+    }
+
+    Ok(command.join(" "))
+}
+
+fn is_builtin(name: &str) -> bool {
+    // `shuttle` is the name cargo passes in, and `help` is added by clap
+    name == "shuttle" || name == "help" || ShuttleArgs::command().find_subcommand(name).is_some()
+}
+
+/// Replace the alias in the command position of `args`, which start with the name of the binary
+pub fn expand(args: Vec<OsString>, aliases: &BTreeMap<String, String>) -> Vec<OsString> {
+    if aliases.is_empty() {
+        return args;
+    }
+    let Some(position) = command_position(&args) else {
+        return args;
+    };
+    let Some(expansion) = args[position]
+        .to_str()
+        .filter(|name| !is_builtin(name))
+        .and_then(|name| aliases.get(name))
+    else {
+        return args;
+    };
+
+    let mut expanded = args[..position].to_vec();
+    expanded.extend(expansion.split_whitespace().map(OsString::from));
+    expanded.extend(args[position + 1..].iter().cloned());
+
+    expanded
+}
+
+/// Where the command is, after the global options
+fn command_position(args: &[OsString]) -> Option<usize> {
+    let command = ShuttleArgs::command();
+    let takes_value = |flag: &str| {
+        command.get_arguments().any(|arg| {
+            let matches = match flag.strip_prefix("--") {
+                Some(long) => arg
+                    .get_long_and_visible_aliases()
+                    .is_some_and(|longs| longs.contains(&long)),
+                None => {
+                    flag.len() == 2 && arg.get_short().is_some_and(|short| flag.ends_with(short))
+                }
+            };
+
+            matches && arg.get_action().takes_values()
+        })
+    };
+
+    let mut position = 1;
+    while let Some(arg) = args.get(position) {
+        let arg = arg.to_str()?;
+        if arg == "--" {
+            return None;
+        }
+        if !arg.starts_with('-') {
+            return Some(position);
+        }
+        position += if !arg.contains('=') && takes_value(arg) {
+            2
+        } else {
+            1
+        };
+    }
+
+    None
+}
+
+impl Shuttle {
+    pub(crate) fn alias_list(&self) -> Result<CommandOutcome> {
+        let aliases = self.ctx.aliases();
+        if aliases.is_empty() {
+            println!(
+                "No aliases are set. Add one with `cargo shuttle alias add <NAME> <COMMAND>...`"
+            );
+            return Ok(CommandOutcome::Ok);
+        }
+
+        output::print(self.output, &aliases, |_| {
+            aliases
+                .iter()
+                .map(|(name, command)| format!("{name} = {command}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })?;
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) fn alias_add(
+        &mut self,
+        name: String,
+        command: Vec<String>,
+    ) -> Result<CommandOutcome> {
+        let command = validate_name(&name)
+            .and_then(|_| join_command(&command))
+            .map_err(|err| errors::with_code(err, ErrorCode::Validation))?;
+
+        match self.ctx.set_alias(name.clone(), command.clone())? {
+            Some(previous) => println!("Changed {name} from `{previous}` to `{command}`"),
+            None => println!("Added {name} for `{command}`"),
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) fn alias_remove(&mut self, name: String) -> Result<CommandOutcome> {
+        match self.ctx.remove_alias(&name)? {
+            Some(command) => println!("Removed {name}, which stood for `{command}`"),
+            None => println!("There is no alias named {name}"),
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_str(args: &str) -> String {
+        let aliases = BTreeMap::from([
+            ("d".to_string(), "deploy --no-follow".to_string()),
+            ("l".to_string(), "logs --follow --level info".to_string()),
+            ("deploy".to_string(), "status".to_string()),
+        ]);
+        let args = args.split(' ').map(OsString::from).collect();
+
+        expand(args, &aliases)
+            .into_iter()
+            .map(|arg| arg.into_string().unwrap())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn expands_aliases() {
+        assert_eq!(
+            expand_str("cargo-shuttle d"),
+            "cargo-shuttle deploy --no-follow"
+        );
+        assert_eq!(
+            expand_str("cargo-shuttle l --raw"),
+            "cargo-shuttle logs --follow --level info --raw"
+        );
+        // Global options before the alias, with and without their values
+        assert_eq!(
+            expand_str("cargo-shuttle --name d -q d"),
+            "cargo-shuttle --name d -q deploy --no-follow"
+        );
+        assert_eq!(
+            expand_str("cargo-shuttle --wd=d d"),
+            "cargo-shuttle --wd=d deploy --no-follow"
+        );
+
+        // Built-in commands win, and only the command position is expanded
+        assert_eq!(expand_str("cargo-shuttle deploy"), "cargo-shuttle deploy");
+        assert_eq!(expand_str("cargo-shuttle init d"), "cargo-shuttle init d");
+        assert_eq!(expand_str("cargo-shuttle -- d"), "cargo-shuttle -- d");
+    }
+
+    #[test]
+    fn alias_names() {
+        validate_name("d").unwrap();
+        assert!(validate_name("deploy").is_err());
+        assert!(validate_name("help").is_err());
+        assert!(validate_name("-d").is_err());
+        assert!(validate_name("my alias").is_err());
+
+        assert_eq!(
+            join_command(&["logs".to_string(), "--follow".to_string()]).unwrap(),
+            "logs --follow"
+        );
+        assert!(join_command(&["logs".to_string(), "--grep=a b".to_string()]).is_err());
+    }
+}
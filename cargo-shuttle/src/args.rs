@@ -1,31 +1,34 @@
 use std::{
+    collections::BTreeMap,
     ffi::OsString,
     fs::create_dir_all,
     io::{self, ErrorKind},
+    net::IpAddr,
     path::PathBuf,
+    time::Duration,
 };
 
 use anyhow::{bail, Context};
-use cargo_metadata::MetadataCommand;
+use chrono::{DateTime, Utc};
 use clap::{
-    builder::{OsStringValueParser, PossibleValue, TypedValueParser},
+    builder::{OsStringValueParser, TypedValueParser},
     Parser, ValueEnum,
 };
 use clap_complete::Shell;
+use regex::Regex;
 use shuttle_common::constants::{DEFAULT_IDLE_MINUTES, EXAMPLES_REPO};
+use shuttle_common::database::{self, SharedEngine};
+use shuttle_common::models::token;
 use shuttle_common::resource;
 use uuid::Uuid;
 
+use crate::config::Origin;
+use crate::logs::{file::parse_size, parse_time_format, parse_time_spec};
+use crate::secrets::import::ImportSource;
+use crate::workspace;
+
 #[derive(Parser)]
-#[command(
-    version,
-    // Cargo passes in the subcommand name to the invoked executable. Use a
-    // hidden, optional positional argument to deal with it.
-    arg(clap::Arg::new("dummy")
-        .value_parser([PossibleValue::new("shuttle")])
-        .required(false)
-        .hide(true))
-)]
+#[command(version)]
 pub struct ShuttleArgs {
     #[command(flatten)]
     pub project_args: ProjectArgs,
@@ -34,21 +37,71 @@ pub struct ShuttleArgs {
     #[arg(long, env = "SHUTTLE_API")]
     pub api_url: Option<String>,
     /// Disable network requests that are not strictly necessary. Limits some features.
-    #[arg(long, env = "SHUTTLE_OFFLINE")]
+    /// Local runs then only use crates and container images that are already downloaded.
+    #[arg(global = true, long, env = "SHUTTLE_OFFLINE")]
     pub offline: bool,
-    /// Turn on tracing output for cargo-shuttle and shuttle libraries.
-    #[arg(long, env = "SHUTTLE_DEBUG")]
+    /// Run commands against the API even when it does not support this version of cargo-shuttle
+    #[arg(long, env = "SHUTTLE_FORCE")]
+    pub force: bool,
+    /// Fetch lists like the ones of projects and deployments again instead of revalidating the
+    /// ones in the response cache, and run `cargo metadata` again instead of reusing its results
+    #[arg(global = true, long, env = "SHUTTLE_NO_CACHE")]
+    pub no_cache: bool,
+    /// Turn on tracing output for cargo-shuttle and shuttle libraries. The same as `-vv`.
+    #[arg(global = true, long, env = "SHUTTLE_DEBUG")]
     pub debug: bool,
+    /// Print more: `-v` adds debug logs, `-vv` adds traces of the HTTP requests
+    #[arg(global = true, short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+    /// Only print the result of the command, like the ID and URL of a deployment
+    #[arg(global = true, short, long, conflicts_with_all = ["verbose", "debug"])]
+    pub quiet: bool,
+    /// How results are printed: tables for people, JSON for scripts, or plain text without
+    /// borders and colors
+    #[arg(
+        global = true,
+        long = "output-format",
+        value_enum,
+        default_value_t = OutputMode::Table,
+        env = "SHUTTLE_OUTPUT"
+    )]
+    pub output: OutputMode,
+    /// When to color the output. `auto` colors terminals, unless NO_COLOR is set or
+    /// CLICOLOR_FORCE asks for colors anyway.
+    #[arg(global = true, long, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+    /// Where the values of the flags above came from, by their ID
+    #[arg(skip)]
+    pub arg_origins: BTreeMap<String, Origin>,
 
     #[command(subcommand)]
     pub cmd: Command,
 }
 
+impl ShuttleArgs {
+    pub fn verbosity(&self) -> Verbosity {
+        match (self.quiet, self.verbose, self.debug) {
+            (true, _, _) => Verbosity::Quiet,
+            (false, 0, false) => Verbosity::Normal,
+            (false, 1, false) => Verbosity::Verbose,
+            _ => Verbosity::Trace,
+        }
+    }
+}
+
 // Common args for subcommands that deal with projects.
 #[derive(Parser, Clone, Debug)]
 pub struct ProjectArgs {
-    /// Specify the working directory
-    #[arg(global = true, long, visible_alias = "wd", default_value = ".", value_parser = OsStringValueParser::new().try_map(parse_path))]
+    /// Specify the working directory: the crate or workspace to run the command on, instead of
+    /// the current directory. Any directory in a workspace stands for the whole workspace.
+    #[arg(
+        global = true,
+        long,
+        visible_alias = "wd",
+        default_value = ".",
+        env = "SHUTTLE_WORKING_DIRECTORY",
+        value_parser = OsStringValueParser::new().try_map(parse_working_directory)
+    )]
     pub working_directory: PathBuf,
     /// Specify the name of the project (overrides crate name)
     #[arg(global = true, long)]
@@ -57,12 +110,16 @@ pub struct ProjectArgs {
 
 impl ProjectArgs {
     pub fn workspace_path(&self) -> anyhow::Result<PathBuf> {
-        // NOTE: If crates cache is missing this blocks for several seconds during download
-        let path = MetadataCommand::new()
-            .current_dir(&self.working_directory)
-            .exec()
-            .context("failed to get cargo metadata")?
+        let path = workspace::metadata(&self.working_directory)
+            .with_context(|| {
+                format!(
+                    "{} is not in a Cargo crate or workspace. Run the command from the crate, \
+                    or pass its path with --working-directory.",
+                    self.working_directory.display()
+                )
+            })?
             .workspace_root
+            .clone()
             .into();
 
         Ok(path)
@@ -71,11 +128,7 @@ impl ProjectArgs {
     pub fn project_name(&self) -> anyhow::Result<String> {
         let workspace_path = self.workspace_path()?;
 
-        // NOTE: If crates cache is missing this blocks for several seconds during download
-        let meta = MetadataCommand::new()
-            .current_dir(&workspace_path)
-            .exec()
-            .context("failed to get cargo metadata")?;
+        let meta = workspace::metadata(&workspace_path)?;
         let package_name = if let Some(root_package) = meta.root_package() {
             root_package.name.clone()
         } else {
@@ -95,7 +148,8 @@ impl ProjectArgs {
 ///
 /// See the CLI docs (https://docs.shuttle.rs/getting-started/shuttle-commands)
 /// for more information.
-#[derive(Parser)]
+#[derive(Parser, strum::IntoStaticStr)]
+#[strum(serialize_all = "kebab-case")]
 pub enum Command {
     /// Create a new Shuttle project
     Init(InitArgs),
@@ -106,29 +160,89 @@ pub enum Command {
     /// Manage deployments of a Shuttle service
     #[command(subcommand)]
     Deployment(DeploymentCommand),
-    /// View the status of a Shuttle service
-    Status,
+    /// View the account, the project, its latest deployment and resources, and the health of the
+    /// platform
+    Status(StatusArgs),
     /// Stop this Shuttle service
     Stop,
     /// View the logs of a deployment in this Shuttle service
     Logs(LogsArgs),
+    /// Open the deployed service in the browser, or its page in the Shuttle console
+    Open(OpenArgs),
     /// List or manage projects on Shuttle
     #[command(subcommand)]
     Project(ProjectCommand),
+    /// Manage the teams (organizations) you are in and their members
+    #[command(subcommand)]
+    Team(TeamCommand),
+    /// Manage API tokens with limited rights, for CI systems
+    #[command(subcommand)]
+    Token(TokenCommand),
     /// Manage resources of a Shuttle project
     #[command(subcommand)]
     Resource(ResourceCommand),
+    /// Show and renew the TLS certificates of the custom domains of a Shuttle project
+    #[command(subcommand)]
+    Certificate(CertificateCommand),
+    /// Check the DNS records of the custom domains of a Shuttle project
+    #[command(subcommand)]
+    Domain(DomainCommand),
+    /// Run the sqlx, diesel or refinery migrations of the project on its database
+    Migrate(MigrateArgs),
+    /// Manage the secrets of a Shuttle project without deploying
+    #[command(subcommand)]
+    Secrets(SecretsCommand),
+    /// Manage the plain environment variables of the service in Shuttle.toml
+    #[command(subcommand)]
+    Env(EnvCommand),
+    /// Manage the jobs in Shuttle.toml to run on a schedule, once deployments support them
+    #[command(subcommand)]
+    Cron(CronCommand),
+    /// Manage what local runs keep on this machine
+    #[command(subcommand)]
+    Local(LocalCommand),
+    /// Manage the caches that speed up commands
+    #[command(subcommand)]
+    Cache(CacheCommand),
     /// Remove cargo build artifacts in the Shuttle environment
     Clean,
     /// Login to the Shuttle platform
     Login(LoginArgs),
     /// Log out of the Shuttle platform
     Logout(LogoutArgs),
-    /// Generate shell completions and man page
+    /// Show the account of the API key in use, with its teams, and the scopes and expiry of API
+    /// tokens
+    Whoami,
+    /// Manage aliases for commands, which are kept in the global configuration
+    #[command(subcommand)]
+    Alias(AliasCommand),
+    /// Turn anonymous usage telemetry on or off
+    #[command(subcommand)]
+    Telemetry(TelemetryCommand),
+    /// Show the settings in effect, from the flags, environment variables, Shuttle.toml and the
+    /// global configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Upgrade cargo-shuttle to the latest release, or to the one given with --version
+    Upgrade(UpgradeArgs),
+    /// Upgrade the Shuttle crates of the project to the version deployments support, with the
+    /// highlights of the changelog
+    UpgradeDeps(UpgradeDepsArgs),
+    /// Generate shell completions, man page and other files
     #[command(subcommand)]
     Generate(GenerateCommand),
-    /// Open an issue on GitHub and provide feedback
-    Feedback,
+    /// Open an issue on GitHub filled in with the version, the OS, rustc, the last command and,
+    /// if allowed, the recent errors, with secrets redacted
+    Feedback(FeedbackArgs),
+    /// Check the toolchain, Docker, the connection to the API, the configuration and the disk
+    /// space, and tell how to fix what is wrong
+    Doctor,
+    /// Serve the status, deployments, logs and errors of this project to editor extensions, over
+    /// JSON-RPC framed like the Language Server Protocol
+    Lsp(LspArgs),
+    /// Any other name runs the `cargo-shuttle-<name>` plugin on PATH
+    #[command(external_subcommand)]
+    External(Vec<OsString>),
 }
 
 #[derive(Parser)]
@@ -138,11 +252,140 @@ pub enum GenerateCommand {
         /// The shell to generate shell completion for
         shell: Shell,
         /// Output to a file (stdout by default)
-        #[arg(short, long)]
-        output: Option<PathBuf>,
+        #[arg(short, long = "output")]
+        out: Option<PathBuf>,
     },
     /// Generate man page to the standard output
     Manpage,
+    /// Generate a man page for every command into a directory, for installing them where `man`
+    /// finds them
+    Manpages {
+        /// The directory to write the pages to. It is created if it does not exist.
+        dir: PathBuf,
+    },
+    /// Generate a CI workflow that builds, tests and deploys the project
+    Ci {
+        /// The CI service to generate the workflow for
+        #[arg(value_enum)]
+        provider: CiProvider,
+        /// Output to a file (the place the CI service looks in by default)
+        #[arg(short, long = "output")]
+        out: Option<PathBuf>,
+        /// Overwrite the file if it exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Generate a Dockerfile that builds and runs the project like deployments do
+    Dockerfile {
+        /// Output to a file (Dockerfile in the project directory by default)
+        #[arg(short, long = "output")]
+        out: Option<PathBuf>,
+        /// Overwrite the file if it exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Generate a docker-compose.yml with the resources the project uses, for running them
+    /// yourself
+    Compose {
+        /// Output to a file (docker-compose.yml in the project directory by default)
+        #[arg(short, long = "output")]
+        out: Option<PathBuf>,
+        /// Overwrite the file if it exists
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum CiProvider {
+    /// GitHub Actions
+    Github,
+    /// GitLab CI/CD
+    Gitlab,
+    /// CircleCI
+    Circleci,
+}
+
+#[derive(Parser)]
+pub struct StatusArgs {
+    /// Refresh the overview until stopped with Ctrl-C
+    #[arg(long, short)]
+    pub watch: bool,
+    /// How often to refresh with --watch
+    #[arg(long, value_name = "DURATION", default_value = "5s", value_parser = humantime::parse_duration)]
+    pub interval: Duration,
+}
+
+#[derive(Parser, Debug)]
+pub struct FeedbackArgs {
+    /// The title of the issue
+    #[arg(long)]
+    pub title: Option<String>,
+    /// Include the recent errors of the error log without asking
+    #[arg(long)]
+    pub include_errors: bool,
+}
+
+#[derive(Parser, Debug)]
+pub struct LspArgs {
+    /// Listen on this Unix socket, for several editors to share, instead of serving over stdin and
+    /// stdout
+    #[arg(long, value_name = "PATH")]
+    pub socket: Option<PathBuf>,
+}
+
+#[derive(Parser)]
+pub struct OpenArgs {
+    /// Open the page of the project in the Shuttle console instead
+    #[arg(long, conflicts_with = "logs")]
+    pub console: bool,
+    /// Open the logs of the latest deployment in the Shuttle console instead
+    #[arg(long)]
+    pub logs: bool,
+}
+
+#[derive(Parser)]
+pub enum AliasCommand {
+    /// List the aliases
+    List,
+    /// Add an alias, or change what an existing one stands for
+    Add {
+        /// Name of the alias, like 'd'
+        name: String,
+        /// The command and arguments it stands for, like 'deploy --no-follow'
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// Remove an alias
+    Rm {
+        /// Name of the alias
+        name: String,
+    },
+}
+
+#[derive(Parser)]
+pub enum TelemetryCommand {
+    /// Send the names of the commands run, how long they took and how they failed. Arguments
+    /// and code are never sent.
+    Enable,
+    /// Stop sending telemetry, and drop what was not sent yet
+    Disable,
+    /// Show whether telemetry is on
+    Status,
+}
+
+#[derive(Parser)]
+pub enum ConfigCommand {
+    /// Show every setting in effect
+    Show(ConfigShowArgs),
+}
+
+#[derive(Parser)]
+pub struct ConfigShowArgs {
+    /// Show where each setting came from: a flag, an environment variable, Shuttle.toml, the
+    /// global configuration or the default
+    #[arg(long)]
+    pub origin: bool,
 }
 
 #[derive(Parser)]
@@ -183,7 +426,32 @@ pub enum ResourceCommand {
         )]
         show_secrets: bool,
     },
-    /// Delete a resource
+    /// Print the connection details of a database in the shape other tools read
+    Show {
+        /// Type of the database, like 'postgres'. Can be left out when there is only one.
+        #[arg(value_parser = parse_database_type)]
+        resource_type: Option<resource::Type>,
+        #[arg(long, value_enum, default_value_t = ConnectionFormat::Uri)]
+        /// Output format of the connection details
+        format: ConnectionFormat,
+        /// Variable to put the connection string in, for the env and dotenv formats
+        #[arg(long, default_value = "DATABASE_URL")]
+        var: String,
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Show the password instead of hiding it"
+        )]
+        show_secrets: bool,
+    },
+    /// Open an interactive session with a database, in its command line client if it is
+    /// installed
+    Connect {
+        /// Type of the database, like 'postgres'. Can be left out when there is only one.
+        #[arg(value_parser = parse_database_type)]
+        resource_type: Option<resource::Type>,
+    },
+    /// Delete a resource along with its data, after showing what is destroyed
     Delete {
         /// Type of the resource to delete.
         /// Use the string in the 'Type' column as displayed in the `resource list` command.
@@ -194,6 +462,259 @@ pub enum ResourceCommand {
     },
 }
 
+#[derive(Parser)]
+pub enum TeamCommand {
+    /// List the teams you are in, with the projects each of them owns
+    List {
+        #[arg(long, default_value_t = false)]
+        /// Output table in `raw` format
+        raw: bool,
+    },
+    /// Create a team, which you become the admin of
+    Create {
+        /// Name of the team
+        name: String,
+    },
+    /// List the members of a team
+    Members {
+        #[command(flatten)]
+        team: TeamArgs,
+        #[arg(long, default_value_t = false)]
+        /// Output table in `raw` format
+        raw: bool,
+    },
+    /// Add a user to a team as a member
+    Invite {
+        /// ID of the user to add
+        user_id: String,
+        #[command(flatten)]
+        team: TeamArgs,
+    },
+    /// Remove a member from a team
+    #[command(visible_alias = "rm")]
+    Remove {
+        /// ID of the user to remove
+        user_id: String,
+        #[command(flatten)]
+        team: TeamArgs,
+        #[command(flatten)]
+        confirmation: ConfirmationArgs,
+    },
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct TeamArgs {
+    /// ID or name of the team. Can be left out when you are in only one.
+    #[arg(long)]
+    pub team: Option<String>,
+}
+
+#[derive(Parser)]
+pub enum TokenCommand {
+    /// Create an API token. Its key is only shown this once.
+    Create {
+        /// Name of the token, like 'ci'
+        #[arg(long)]
+        name: String,
+        /// What the token can do, as `<read|logs|deploy>[:<project>]`. Can be given more than
+        /// once, for the same project.
+        #[arg(long = "scope", value_name = "SCOPE", required = true)]
+        scopes: Vec<token::Scope>,
+        /// How long until the token expires, like '90d'. Never expires when left out.
+        #[arg(long, value_name = "DURATION", value_parser = humantime::parse_duration)]
+        expires: Option<Duration>,
+    },
+    /// List your API tokens
+    List {
+        #[arg(long, default_value_t = false)]
+        /// Output table in `raw` format
+        raw: bool,
+    },
+    /// Revoke an API token, which stops it from working
+    Revoke {
+        /// ID of the token
+        id: String,
+        #[command(flatten)]
+        confirmation: ConfirmationArgs,
+    },
+}
+
+#[derive(Parser)]
+pub enum DomainCommand {
+    /// Check that the DNS records of a custom domain point at the project, and tell how to fix
+    /// the ones that do not
+    Check {
+        /// The custom domain, like 'www.example.com'
+        domain: String,
+        /// The domain it should point at, instead of the default domain of the project
+        #[arg(long)]
+        target: Option<String>,
+    },
+}
+
+#[derive(Parser)]
+pub enum CertificateCommand {
+    /// List the certificates of the custom domains of a project, with when they expire and
+    /// whether they are due for renewal
+    List {
+        #[arg(long, default_value_t = false)]
+        /// Output table in `raw` format
+        raw: bool,
+    },
+    /// Show the certificate of a custom domain
+    Status {
+        /// The custom domain, like 'www.example.com'
+        domain: String,
+    },
+    /// Issue a new certificate for a custom domain now, even when the current one is still valid
+    Renew {
+        /// The custom domain, like 'www.example.com'
+        domain: String,
+    },
+}
+
+#[derive(Parser)]
+pub enum SecretsCommand {
+    /// List the secrets of a project (names and metadata only)
+    List {
+        #[arg(long, default_value_t = false)]
+        /// Output table in `raw` format
+        raw: bool,
+    },
+    /// Set the value of a secret
+    Set {
+        /// Name of the secret
+        key: String,
+        /// Value of the secret. Prompted for if not given, which keeps it out of the shell history
+        #[arg(conflicts_with = "generate")]
+        value: Option<String>,
+        #[arg(long, value_name = "BYTES", num_args = 0..=1, default_missing_value = "32")]
+        /// Generate a cryptographically random value of this many bytes (32 if not given)
+        generate: Option<usize>,
+        #[arg(long, value_enum, default_value_t = GeneratedSecretFormat::Hex, requires = "generate")]
+        /// Encoding of the generated value. A uuid is always 16 bytes.
+        format: GeneratedSecretFormat,
+    },
+    /// Show the value of a secret
+    Get {
+        /// Name of the secret
+        key: String,
+        #[arg(long)]
+        /// Print the secret value to the terminal
+        reveal: bool,
+    },
+    /// Remove a secret
+    #[command(visible_alias = "rm")]
+    Remove {
+        /// Name of the secret
+        key: String,
+        #[command(flatten)]
+        confirmation: ConfirmationArgs,
+    },
+    /// Remove all secrets of a project
+    Clear(ConfirmationArgs),
+    /// Update the local Secrets.toml to match the contents of a dotenv file
+    Sync {
+        #[arg(long, value_parser = OsStringValueParser::new().try_map(parse_path))]
+        /// The dotenv file to read the secrets from
+        from: PathBuf,
+        #[command(flatten)]
+        confirmation: ConfirmationArgs,
+    },
+    /// Compare the local secrets file with the secrets of the running service
+    Diff(SecretsArgs),
+    /// Replace a secret with a new generated value
+    Rotate {
+        /// Name of the secret
+        key: String,
+        #[arg(long, value_name = "BYTES", default_value_t = 32)]
+        /// Number of random bytes in the new value
+        length: usize,
+        #[arg(long, value_enum, default_value_t = GeneratedSecretFormat::Hex)]
+        /// Encoding of the new value
+        format: GeneratedSecretFormat,
+        #[command(flatten)]
+        confirmation: ConfirmationArgs,
+    },
+    /// Import secrets from external secret managers, using their CLIs and ambient credentials
+    Import {
+        #[arg(long, required = true)]
+        /// Where to import from: `aws-ssm:<path>` (Parameter Store), `aws-sm:<secret id>`
+        /// (Secrets Manager) or `vault:<path>`. Can be given multiple times, later sources win.
+        from: Vec<ImportSource>,
+        #[command(flatten)]
+        confirmation: ConfirmationArgs,
+    },
+}
+
+#[derive(Parser)]
+pub enum EnvCommand {
+    /// List the environment variables in Shuttle.toml
+    List,
+    /// Set an environment variable. Use `secrets set` for sensitive values.
+    Set {
+        /// Name of the variable
+        name: String,
+        /// Value of the variable
+        value: String,
+    },
+    /// Remove an environment variable
+    #[command(visible_alias = "rm")]
+    Remove {
+        /// Name of the variable
+        name: String,
+    },
+}
+
+#[derive(Parser)]
+pub enum CronCommand {
+    /// List the scheduled jobs in Shuttle.toml
+    List,
+    /// Add a scheduled job, or replace the one with the same name
+    Add {
+        /// Name of the job, like 'nightly-cleanup'
+        name: String,
+        /// When the job runs, as a cron expression in UTC, like '0 3 * * *'
+        #[arg(long)]
+        schedule: String,
+        /// Path of the service to send a POST request to, like '/tasks/cleanup'
+        #[arg(long, conflicts_with = "command", required_unless_present = "command")]
+        path: Option<String>,
+        /// Command to run in the container of the service
+        #[arg(long)]
+        command: Option<String>,
+    },
+    /// Remove a scheduled job
+    #[command(visible_alias = "rm")]
+    Remove {
+        /// Name of the job
+        name: String,
+    },
+}
+
+#[derive(Parser)]
+pub enum LocalCommand {
+    /// Show what local runs keep on disk, and how large it is
+    Status,
+    /// Remove the data of local resources, like databases and shuttle-persist files, along
+    /// with their containers
+    Clean(ConfirmationArgs),
+    /// Show the local runs going on on this machine, of any project
+    Ps,
+    /// Stop the local run of a project that is going on in another terminal
+    Stop {
+        /// Name of the project, as shown by `local ps`
+        project: String,
+    },
+}
+
+#[derive(Parser)]
+pub enum CacheCommand {
+    /// Remove the file hashes of the project, and the API responses and cargo metadata cached for
+    /// every project
+    Clear,
+}
+
 #[derive(Parser)]
 pub enum ProjectCommand {
     /// Create an environment for this project on Shuttle
@@ -224,6 +745,75 @@ pub enum ProjectCommand {
     },
     /// Delete a project and all linked data
     Delete(ConfirmationArgs),
+    /// Write a backup of this project, with what is needed to recreate it elsewhere
+    Export(ProjectExportArgs),
+    /// Block deployments of this project until it is unlocked
+    Lock {
+        #[arg(long)]
+        /// Why deployments are blocked, shown to anyone trying to deploy
+        reason: String,
+    },
+    /// Allow deployments of this project again
+    Unlock,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum GeneratedSecretFormat {
+    Hex,
+    Base64,
+    Uuid,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputMode {
+    #[default]
+    Table,
+    /// JSON documents, or one object per line for streams like logs
+    Json,
+    /// Text without borders or colors
+    #[value(alias = "text")]
+    Plain,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// How much is printed, from the global `-q` and `-v` flags
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet,
+    #[default]
+    Normal,
+    Verbose,
+    Trace,
+}
+
+impl Verbosity {
+    /// The tracing filter, for when `RUST_LOG` does not set one
+    pub fn tracing_filter(&self) -> &'static str {
+        match self {
+            Self::Quiet => "off",
+            Self::Normal => "error",
+            Self::Verbose => "info,cargo_shuttle=debug,shuttle=debug",
+            Self::Trace => "info,cargo_shuttle=trace,shuttle=trace",
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ConnectionFormat {
+    /// The connection string alone
+    Uri,
+    /// A `DATABASE_URL=` line
+    Env,
+    /// A .env snippet with the connection string and its parts
+    Dotenv,
+    Json,
 }
 
 #[derive(Parser, Debug)]
@@ -241,6 +831,20 @@ pub struct ProjectStartArgs {
     pub idle_minutes: u64,
 }
 
+#[derive(Parser, Debug)]
+pub struct ProjectExportArgs {
+    #[arg(long, short)]
+    /// Where to write the backup, a `.tar.zst` archive (defaults to `<project>-backup.tar.zst`)
+    pub out: Option<PathBuf>,
+    #[arg(long, value_name = "RECIPIENT")]
+    /// Include the secret values, encrypted with age for this public key. Only the names of the
+    /// secrets are included otherwise
+    pub secrets_recipient: Option<String>,
+    #[arg(long)]
+    /// Leave out the executable of the latest deployment
+    pub no_artifact: bool,
+}
+
 #[derive(Parser, Clone, Debug, Default)]
 pub struct LoginArgs {
     /// API key for the Shuttle platform
@@ -248,6 +852,28 @@ pub struct LoginArgs {
     pub api_key: Option<String>,
 }
 
+#[derive(Parser, Clone, Debug)]
+pub struct UpgradeArgs {
+    /// The version to install, which can be older than the current one
+    #[arg(long, value_parser = crate::upgrade::parse_version)]
+    pub version: Option<semver::Version>,
+}
+
+#[derive(Parser, Debug)]
+pub struct UpgradeDepsArgs {
+    /// The version to upgrade to, instead of the one deployments support
+    #[arg(long, value_parser = crate::upgrade::parse_version)]
+    pub version: Option<semver::Version>,
+    /// Show what would change without editing the manifests
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Run `cargo check` after the upgrade
+    #[arg(long)]
+    pub check: bool,
+    #[command(flatten)]
+    pub confirmation: ConfirmationArgs,
+}
+
 #[derive(Parser, Clone, Debug)]
 pub struct LogoutArgs {
     /// Reset the API key before logging out
@@ -265,6 +891,10 @@ pub struct DeployArgs {
     /// Don't display timestamps and log origin tags
     #[arg(long)]
     pub raw: bool,
+    /// Refuse to deploy if the runtime, builder image, resources or Shuttle.toml changed since
+    /// Shuttle.lock was written
+    #[arg(long)]
+    pub locked: bool,
 
     #[command(flatten)]
     pub secret_args: SecretsArgs,
@@ -272,9 +902,13 @@ pub struct DeployArgs {
 
 #[derive(Parser, Debug)]
 pub struct RunArgs {
-    /// Port to start service on
-    #[arg(long, short = 'p', env, default_value = "8000")]
-    pub port: u16,
+    /// Port to start service on (8000 by default, or `port` under `[local]` in Shuttle.toml).
+    /// Use 0 to pick a free port.
+    #[arg(long, short = 'p', env)]
+    pub port: Option<u16>,
+    /// Address to bind to (localhost by default, or `host` under `[local]` in Shuttle.toml)
+    #[arg(long, conflicts_with = "external")]
+    pub host: Option<IpAddr>,
     /// Use 0.0.0.0 instead of localhost (for usage with local external devices)
     #[arg(long)]
     pub external: bool,
@@ -284,7 +918,88 @@ pub struct RunArgs {
     /// Don't display timestamps and log origin tags
     #[arg(long)]
     pub raw: bool,
+    /// Rebuild and restart the service when files in the project change
+    #[arg(long)]
+    pub watch: bool,
+    /// Start all the services of the workspace at the same time, with their output prefixed by
+    /// the service name
+    #[arg(long)]
+    pub all: bool,
+    /// Serve over HTTPS, with a certificate from a local certificate authority
+    #[arg(long)]
+    pub tls: bool,
+    /// Restart services that crash, waiting a little longer after each crash
+    #[arg(long, conflicts_with = "watch")]
+    pub restart_on_crash: bool,
+    /// Poll this path of each service after it starts, like /health, and report when it is ready
+    #[arg(long, value_name = "PATH")]
+    pub health_check: Option<String>,
+    /// Build with debug info and print the process ID of every service, for attaching a debugger
+    #[arg(long)]
+    pub debugger: bool,
+    /// Wait for a debugger to be attached before starting each service
+    #[arg(long, requires = "debugger")]
+    pub wait_for_debugger: bool,
+    /// Set an environment variable on the services, like RUST_LOG=debug. Can be given multiple
+    /// times, and takes priority over Shuttle.toml and the secrets.
+    #[arg(long = "env-var", value_name = "NAME=VALUE", value_parser = parse_env_var)]
+    pub env_vars: Vec<(String, String)>,
+    /// Build and run the services in the image that deployments are built in, to catch what
+    /// only breaks in production. Needs Docker with host networking.
+    #[arg(long, conflicts_with = "debugger")]
+    pub container: bool,
+    /// Record the requests to the services and their responses to this file
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["tls", "replay"])]
+    pub record: Option<PathBuf>,
+    /// Send the requests recorded in this file once the services are up, report the responses
+    /// that changed, and stop
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["watch", "restart_on_crash"])]
+    pub replay: Option<PathBuf>,
+    /// How long the services and the resource containers they started get to stop by themselves
+    /// when the run ends, before they are killed
+    #[arg(long, value_name = "DURATION", default_value = "10s", value_parser = humantime::parse_duration)]
+    pub grace_period: Duration,
+    /// Fill the databases with the fixtures of `[local.seed]` in Shuttle.toml again, even if
+    /// they were seeded before
+    #[arg(long)]
+    pub reseed: bool,
+
+    #[command(flatten)]
+    pub build_args: BuildArgs,
+    #[command(flatten)]
+    pub secret_args: SecretsArgs,
+    /// Don't load any secrets file
+    #[arg(long, conflicts_with_all = ["secrets", "env"])]
+    pub no_secrets: bool,
+}
+
+/// Options passed on to `cargo build`
+#[derive(Parser, Debug, Default)]
+pub struct BuildArgs {
+    /// Build with this cargo profile
+    #[arg(long, conflicts_with = "release")]
+    pub profile: Option<String>,
+    /// Features to activate, separated by spaces or commas
+    #[arg(long, short = 'F')]
+    pub features: Vec<String>,
+    /// Don't activate the default features
+    #[arg(long)]
+    pub no_default_features: bool,
+    /// Directory for all generated artifacts
+    #[arg(long)]
+    pub target_dir: Option<PathBuf>,
+    /// Other arguments for `cargo build`, like "--locked --offline".
+    /// Arguments that change where the binary ends up, like `--target`, are not supported.
+    #[arg(long, allow_hyphen_values = true)]
+    pub cargo_args: Option<String>,
+}
 
+#[derive(Parser, Debug)]
+pub struct MigrateArgs {
+    /// Type of the database, like 'postgres'. Can be left out when there is only one.
+    #[arg(long = "type", value_parser = parse_database_type)]
+    pub resource_type: Option<resource::Type>,
+    /// Secrets with a DATABASE_URL, like those of `--env staging`, migrate that database instead
     #[command(flatten)]
     pub secret_args: SecretsArgs,
 }
@@ -294,6 +1009,10 @@ pub struct SecretsArgs {
     /// Use this secrets file instead
     #[arg(long, value_parser = OsStringValueParser::new().try_map(parse_path))]
     pub secrets: Option<PathBuf>,
+    /// Use the secrets file of this environment (Secrets.<ENV>.toml). The environment has to be
+    /// listed under `environments` in Shuttle.toml
+    #[arg(long, conflicts_with = "secrets")]
+    pub env: Option<String>,
 }
 
 #[derive(Parser, Clone, Debug, Default)]
@@ -321,6 +1040,15 @@ pub struct InitArgs {
     /// Don't initialize a new git repository
     #[arg(long)]
     pub no_git: bool,
+    /// Download the template again instead of using the one cached by an earlier init
+    #[arg(long)]
+    pub refresh: bool,
+    /// Answer a prompt of the template, like db=postgres. Can be given multiple times.
+    #[arg(long = "var", value_name = "NAME=VALUE", value_parser = parse_env_var)]
+    pub vars: Vec<(String, String)>,
+    /// Run the commands the template runs once the project is generated, without asking
+    #[arg(long)]
+    pub allow_hooks: bool,
 
     #[command(flatten)]
     pub login_args: LoginArgs,
@@ -406,6 +1134,8 @@ impl InitTemplateArg {
 
 #[derive(Parser, Clone, Debug, Default)]
 pub struct LogsArgs {
+    #[command(subcommand)]
+    pub cmd: Option<LogsCommand>,
     /// Deployment ID to get logs for. Defaults to currently running deployment
     pub id: Option<Uuid>,
     #[arg(short, long)]
@@ -417,6 +1147,145 @@ pub struct LogsArgs {
     /// Don't display timestamps and log origin tags
     #[arg(long)]
     pub raw: bool,
+    #[arg(long, value_parser = parse_time_spec)]
+    /// Only show logs from after this time. An RFC 3339 timestamp, or a duration like '2h'
+    pub since: Option<DateTime<Utc>>,
+    #[arg(long, value_parser = parse_time_spec, conflicts_with = "follow")]
+    /// Only show logs from before this time. An RFC 3339 timestamp, or a duration like '10m'
+    pub until: Option<DateTime<Utc>>,
+    #[arg(long, value_name = "REGEX")]
+    /// Only show lines matching this regular expression
+    pub grep: Option<Regex>,
+    #[arg(long, requires = "grep")]
+    /// Show the lines not matching `--grep` instead
+    pub invert_match: bool,
+    #[arg(long, value_enum)]
+    /// Only show lines of this level or more severe. Lines without a level count as info.
+    pub level: Option<LogLevel>,
+    #[arg(long, value_enum, value_delimiter = ',', conflicts_with = "raw")]
+    /// Comma separated fields to show, for example 'ts,level,message'
+    pub fields: Vec<LogField>,
+    #[arg(long, conflicts_with_all = ["id", "latest", "follow"])]
+    /// Show the logs of all deployments of the project, interleaved by time
+    pub all_deployments: bool,
+    #[arg(long = "service", value_name = "NAME")]
+    /// Only show the logs of this service in a workspace. Can be repeated to interleave the
+    /// logs of several services
+    pub services: Vec<String>,
+    #[arg(long, value_name = "PATH")]
+    /// Also write the logs to this file
+    pub out: Option<PathBuf>,
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, requires = "out")]
+    /// Start a new file once the current one reaches this size, for example '50MB'. Full
+    /// files are kept as '<PATH>.1', '<PATH>.2', ...
+    pub rotate: Option<u64>,
+    #[arg(long, requires = "rotate")]
+    /// Compress the full files with gzip
+    pub gzip: bool,
+    #[arg(long, value_name = "N", conflicts_with = "head")]
+    /// Only show the last N lines. Without --follow, the last 1000 lines are shown by default
+    pub tail: Option<usize>,
+    #[arg(long, value_name = "N", conflicts_with = "follow")]
+    /// Only show the first N lines
+    pub head: Option<usize>,
+    #[arg(long, conflicts_with_all = ["head", "tail"])]
+    /// Show all lines instead of the last 1000
+    pub no_limit: bool,
+    #[arg(long, conflicts_with = "local_time")]
+    /// Show timestamps in UTC
+    pub utc: bool,
+    #[arg(long)]
+    /// Show timestamps in the local time zone
+    pub local_time: bool,
+    #[arg(long, value_name = "FORMAT", value_parser = parse_time_format)]
+    /// strftime-style format of the timestamps, for example '%H:%M:%S%.3f'
+    pub time_format: Option<String>,
+    #[arg(long)]
+    /// Remember the given --utc, --local-time and --time-format as the defaults
+    pub save_defaults: bool,
+    #[arg(long, conflicts_with_all = ["id", "latest", "all_deployments", "follow"])]
+    /// Show the logs captured from `cargo shuttle run` instead of a deployment
+    pub local: bool,
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "local")]
+    /// Which local run to show, 1 being the most recent
+    pub session: usize,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub enum LogsCommand {
+    /// Search the logs of the most recent deployments
+    Search(LogsSearchArgs),
+    /// Manage forwarding of the runtime logs to an external endpoint
+    #[command(subcommand)]
+    Forward(LogsForwardCommand),
+}
+
+#[derive(Parser, Clone, Debug)]
+pub enum LogsForwardCommand {
+    /// Show the [log-forwarding] configuration in Shuttle.toml
+    Show,
+    /// Validate the configuration and send a test line to the endpoint
+    Test,
+}
+
+#[derive(Parser, Clone, Debug)]
+pub struct LogsSearchArgs {
+    /// Regular expression to search for
+    pub pattern: String,
+    #[arg(long, default_value_t = 10)]
+    /// How many of the most recent deployments to search
+    pub deployments: u32,
+    #[arg(short, long)]
+    /// Ignore case when matching
+    pub ignore_case: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum LogField {
+    /// Timestamp in RFC 3339
+    Ts,
+    Level,
+    /// Module path the line was logged from, if it has one
+    Target,
+    Message,
+    /// Shuttle component the line is from
+    Origin,
+    /// Deployment ID
+    Deployment,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// Helper function to split a `NAME=VALUE` environment variable
+fn parse_env_var(var: &str) -> Result<(String, String), String> {
+    match var.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => Err(format!("'{var}' is not in the `NAME=VALUE` format")),
+    }
+}
+
+/// Helper function to parse a database type, with `postgres` and `mongodb` short for the shared
+/// ones
+fn parse_database_type(s: &str) -> Result<resource::Type, String> {
+    let resource_type = match s {
+        "postgres" => resource::Type::Database(database::Type::Shared(SharedEngine::Postgres)),
+        "mongodb" => resource::Type::Database(database::Type::Shared(SharedEngine::MongoDb)),
+        _ => s.parse()?,
+    };
+
+    match resource_type {
+        resource::Type::Database(_) => Ok(resource_type),
+        _ => Err(format!(
+            "'{s}' is not a database. Other resources are provisioned on deployment."
+        )),
+    }
 }
 
 /// Helper function to parse and return the absolute path
@@ -429,6 +1298,34 @@ fn parse_path(path: OsString) -> Result<PathBuf, io::Error> {
     })
 }
 
+/// Parse the working directory, which has to be an existing directory
+fn parse_working_directory(path: OsString) -> Result<PathBuf, io::Error> {
+    let real_path = match dunce::canonicalize(&path) {
+        Ok(real_path) => real_path,
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "the working directory {} does not exist",
+                    PathBuf::from(&path).display()
+                ),
+            ))
+        }
+        Err(_) => return parse_path(path),
+    };
+    if !real_path.is_dir() {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "the working directory {} is a file, not a directory",
+                real_path.display()
+            ),
+        ));
+    }
+
+    Ok(real_path)
+}
+
 /// Helper function to parse, create if not exists, and return the absolute path
 pub(crate) fn create_and_parse_path(path: OsString) -> Result<PathBuf, io::Error> {
     // Create the directory if does not exist
@@ -447,12 +1344,81 @@ mod tests {
     use super::*;
     use crate::tests::path_from_workspace_root;
     use clap::CommandFactory;
+    use shuttle_common::database::AwsRdsEngine;
 
     #[test]
     fn test_shuttle_args() {
         ShuttleArgs::command().debug_assert();
     }
 
+    #[test]
+    fn global_output_format() {
+        let args = ShuttleArgs::try_parse_from([
+            "cargo-shuttle",
+            "resource",
+            "list",
+            "--output-format",
+            "json",
+        ])
+        .unwrap();
+        assert_eq!(args.output, OutputMode::Json);
+
+        // `text` was the name of the plain log output
+        let args =
+            ShuttleArgs::try_parse_from(["cargo-shuttle", "logs", "--output-format", "text"])
+                .unwrap();
+        assert_eq!(args.output, OutputMode::Plain);
+
+        // `--output` of the generate commands is where to write to, whatever its value
+        let args = ShuttleArgs::try_parse_from([
+            "cargo-shuttle",
+            "generate",
+            "shell",
+            "bash",
+            "--output",
+            "json",
+        ])
+        .unwrap();
+        assert_eq!(args.output, OutputMode::Table);
+        assert!(matches!(
+            args.cmd,
+            Command::Generate(GenerateCommand::Shell { out: Some(path), .. }) if path == PathBuf::from("json")
+        ));
+    }
+
+    #[test]
+    fn verbosity() {
+        let verbosity = |args: &[&str]| {
+            ShuttleArgs::try_parse_from(["cargo-shuttle"].iter().chain(args))
+                .map(|args| args.verbosity())
+        };
+
+        assert_eq!(verbosity(&["status"]).unwrap(), Verbosity::Normal);
+        assert_eq!(verbosity(&["status", "-q"]).unwrap(), Verbosity::Quiet);
+        assert_eq!(verbosity(&["-v", "status"]).unwrap(), Verbosity::Verbose);
+        assert_eq!(verbosity(&["status", "-vv"]).unwrap(), Verbosity::Trace);
+        assert_eq!(verbosity(&["--debug", "status"]).unwrap(), Verbosity::Trace);
+        assert!(verbosity(&["status", "-q", "-v"]).is_err());
+    }
+
+    #[test]
+    fn external_subcommands() {
+        let args = ShuttleArgs::try_parse_from([
+            "cargo-shuttle",
+            "--name",
+            "my-app",
+            "preview",
+            "--pr",
+            "12",
+        ])
+        .unwrap();
+        assert_eq!(args.project_args.name.as_deref(), Some("my-app"));
+        let Command::External(plugin_args) = args.cmd else {
+            panic!("not parsed as a plugin subcommand");
+        };
+        assert_eq!(plugin_args, ["preview", "--pr", "12"]);
+    }
+
     #[test]
     fn test_init_args_framework() {
         // pre-defined template (only hello world)
@@ -538,6 +1504,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn working_directory() {
+        let crate_dir = path_from_workspace_root("examples/axum/hello-world");
+        assert_eq!(
+            parse_working_directory(crate_dir.join("src").into()).unwrap(),
+            dunce::canonicalize(crate_dir.join("src")).unwrap()
+        );
+
+        let err = parse_working_directory(crate_dir.join("missing").into()).unwrap_err();
+        assert!(err.to_string().contains("does not exist"));
+        let err = parse_working_directory(crate_dir.join("Cargo.toml").into()).unwrap_err();
+        assert!(err.to_string().contains("is a file"));
+    }
+
     #[test]
     fn project_name() {
         let project_args = ProjectArgs {
@@ -565,4 +1545,32 @@ mod tests {
             "workspace"
         );
     }
+
+    #[test]
+    fn env_var() {
+        assert_eq!(
+            parse_env_var("RUST_LOG=debug,hyper=info").unwrap(),
+            ("RUST_LOG".to_string(), "debug,hyper=info".to_string())
+        );
+        assert_eq!(
+            parse_env_var("EMPTY=").unwrap(),
+            ("EMPTY".to_string(), String::new())
+        );
+        assert!(parse_env_var("RUST_LOG").is_err());
+        assert!(parse_env_var("=debug").is_err());
+    }
+
+    #[test]
+    fn database_type() {
+        assert_eq!(
+            parse_database_type("postgres").unwrap(),
+            resource::Type::Database(database::Type::Shared(SharedEngine::Postgres))
+        );
+        assert_eq!(
+            parse_database_type("database::aws_rds::mysql").unwrap(),
+            resource::Type::Database(database::Type::AwsRds(AwsRdsEngine::MySql))
+        );
+        assert!(parse_database_type("secrets").is_err());
+        assert!(parse_database_type("redis").is_err());
+    }
 }
@@ -0,0 +1,86 @@
+//! A cache of the responses to read requests, like the lists of projects and deployments, so that
+//! repeated checks are quick.
+//!
+//! Responses are stored with their ETag, and revalidated with `If-None-Match` on the next request:
+//! the API then answers with an empty `304 Not Modified` when nothing changed. The cache is keyed
+//! by the API key too, so that accounts do not see each other's responses. `--no-cache` skips it.
+
+use std::fs;
+use std::path::PathBuf;
+
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+/// A response as it was last received
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct Entry {
+    pub etag: String,
+    pub body: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+impl ResponseCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// The key of the response to a request for `url`, made with `api_key`
+    pub fn key(url: &str, api_key: Option<&str>) -> String {
+        let request = format!("{url}\n{}", api_key.unwrap_or_default());
+
+        digest(&SHA256, request.as_bytes())
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    pub fn load(&self, key: &str) -> Option<Entry> {
+        let entry = fs::read_to_string(self.dir.join(key)).ok()?;
+
+        serde_json::from_str(&entry).ok()
+    }
+
+    /// Store the response for `key`. The cache only saves time, so failing to write it is not an
+    /// error.
+    pub fn store(&self, key: &str, entry: &Entry) {
+        let stored = fs::create_dir_all(&self.dir).and_then(|_| {
+            fs::write(
+                self.dir.join(key),
+                serde_json::to_string(entry).expect("to serialize a cache entry"),
+            )
+        });
+        if let Err(err) = stored {
+            debug!(error = %err, "failed to store a response in the cache");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_responses() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ResponseCache::new(dir.path().join("cache"));
+        let key = ResponseCache::key("https://api.shuttle.rs/projects", Some("key-one"));
+        assert_ne!(
+            key,
+            ResponseCache::key("https://api.shuttle.rs/projects", Some("key-two"))
+        );
+        assert_eq!(cache.load(&key), None);
+
+        let entry = Entry {
+            etag: "\"33a64df5\"".to_string(),
+            body: "[]".to_string(),
+        };
+        cache.store(&key, &entry);
+        assert_eq!(cache.load(&key), Some(entry));
+    }
+}
@@ -0,0 +1,105 @@
+//! Commands for the certificates of custom domains
+
+use anyhow::{bail, Context, Result};
+use crossterm::style::Stylize;
+use indoc::formatdoc;
+use shuttle_common::models::certificate::{self, get_certificates_table, RenewalState};
+
+use crate::progress::Progress;
+use crate::{output, CommandOutcome, Shuttle};
+
+impl Shuttle {
+    pub(crate) async fn certificates_list(&self, raw: bool) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let certificates = client
+            .get_certificates(self.ctx.project_name())
+            .await
+            .context("failed to get the certificates of the project")?;
+
+        output::print(self.output, &certificates, |plain| {
+            get_certificates_table(&certificates, self.ctx.project_name(), raw || plain)
+        })?;
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn certificate_status(&self, domain: &str) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let project_name = self.ctx.project_name();
+        let certificates = client
+            .get_certificates(project_name)
+            .await
+            .context("failed to get the certificates of the project")?;
+        let Some(certificate) = certificates
+            .into_iter()
+            .find(|certificate| certificate.domain.eq_ignore_ascii_case(domain))
+        else {
+            bail!(
+                "{project_name} has no custom domain {domain}. \
+                See `cargo shuttle certificate list` for the ones it has."
+            );
+        };
+
+        output::print(self.output, &certificate, |_| {
+            format_certificate(&certificate)
+        })?;
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn certificate_renew(&self, domain: &str) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+
+        let progress = Progress::spinner(format!("Issuing a new certificate for {domain}"));
+        let certificate = client
+            .renew_certificate(self.ctx.project_name(), domain)
+            .await;
+        progress.finish_and_clear();
+        let certificate = match certificate {
+            Ok(certificate) => certificate,
+            Err(err) => {
+                output::hint(
+                    self.output,
+                    format!("Check the DNS records of {domain} with `cargo shuttle domain check {domain}`")
+                        .dim(),
+                );
+                return Err(err.context("failed to renew the certificate"));
+            }
+        };
+
+        output::hint(self.output, format!("Renewed the certificate of {domain}"));
+        output::print(self.output, &certificate, |_| {
+            format_certificate(&certificate)
+        })?;
+
+        Ok(CommandOutcome::Ok)
+    }
+}
+
+/// The certificate of a custom domain, with what to do about its renewal state
+fn format_certificate(certificate: &certificate::Response) -> String {
+    let days_left = (certificate.expires_at - chrono::Utc::now()).num_days();
+    let renewal = match certificate.renewal {
+        RenewalState::Valid => "valid".green().to_string(),
+        RenewalState::Due => format!(
+            "{}, it expires in {days_left} days. Run `cargo shuttle certificate renew {}` to renew it now.",
+            "due".yellow(),
+            certificate.domain
+        ),
+        RenewalState::Expired => format!(
+            "{}. Run `cargo shuttle certificate renew {}` to issue a new one.",
+            "expired".red(),
+            certificate.domain
+        ),
+    };
+
+    formatdoc! {"
+        Certificate of {}
+            Issued:  {}
+            Expires: {}
+            Renewal: {renewal}",
+        certificate.domain.as_str().bold(),
+        certificate.issued_at.format("%Y-%m-%dT%H:%M:%SZ"),
+        certificate.expires_at.format("%Y-%m-%dT%H:%M:%SZ"),
+    }
+}
@@ -0,0 +1,199 @@
+//! Generation of CI workflows that build, test and deploy a project, for `cargo shuttle generate
+//! ci`.
+//!
+//! The workflows install the version of cargo-shuttle that generated them, and read the API key
+//! from a `SHUTTLE_API_KEY` secret that has to be set up in the CI service.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use indoc::formatdoc;
+use shuttle_common::constants::SHUTTLE_LOGIN_URL;
+
+use crate::args::CiProvider;
+use crate::{prompt, CommandOutcome, Shuttle, VERSION};
+
+/// Installs cargo-binstall, which installs cargo-shuttle without building it
+const INSTALL_BINSTALL: &str = "curl -L --proto '=https' --tlsv1.2 -sSf https://raw.githubusercontent.com/cargo-bins/cargo-binstall/main/install-from-binstall-release.sh | bash";
+
+/// Where the CI service looks for the workflow
+pub fn default_path(provider: CiProvider, working_directory: &Path) -> PathBuf {
+    match provider {
+        CiProvider::Github => working_directory
+            .join(".github")
+            .join("workflows")
+            .join("shuttle-deploy.yml"),
+        CiProvider::Gitlab => working_directory.join(".gitlab-ci.yml"),
+        CiProvider::Circleci => working_directory.join(".circleci").join("config.yml"),
+    }
+}
+
+/// How to give the workflow the API key
+pub fn secret_instructions(provider: CiProvider) -> &'static str {
+    match provider {
+        CiProvider::Github => {
+            "Add your API key as a repository secret named SHUTTLE_API_KEY, \
+            in Settings > Secrets and variables > Actions."
+        }
+        CiProvider::Gitlab => {
+            "Add your API key as a masked CI/CD variable named SHUTTLE_API_KEY, \
+            in Settings > CI/CD > Variables."
+        }
+        CiProvider::Circleci => {
+            "Add your API key as an environment variable named SHUTTLE_API_KEY, \
+            in Project Settings > Environment Variables."
+        }
+    }
+}
+
+pub fn generate(provider: CiProvider, project_name: &str, version: &str) -> String {
+    let install = format!("cargo binstall -y --locked cargo-shuttle@{version}");
+    let deploy = format!("cargo shuttle deploy --name {project_name}");
+
+    match provider {
+        CiProvider::Github => formatdoc! {"
+            name: Deploy {project_name} to Shuttle
+
+            on:
+              push:
+                branches:
+                  - main
+              workflow_dispatch:
+
+            jobs:
+              deploy:
+                runs-on: ubuntu-latest
+                steps:
+                  - uses: actions/checkout@v4
+                  - uses: dtolnay/rust-toolchain@stable
+                  - uses: Swatinem/rust-cache@v2
+                  - name: Build
+                    run: cargo build --locked
+                  - name: Test
+                    run: cargo test --locked
+                  - uses: cargo-bins/cargo-binstall@main
+                  - name: Install cargo-shuttle
+                    run: {install}
+                  - name: Deploy
+                    run: {deploy}
+                    env:
+                      SHUTTLE_API_KEY: ${{{{ secrets.SHUTTLE_API_KEY }}}}
+        "},
+        CiProvider::Gitlab => formatdoc! {"
+            stages:
+              - test
+              - deploy
+
+            default:
+              image: rust:latest
+
+            test:
+              stage: test
+              script:
+                - cargo build --locked
+                - cargo test --locked
+
+            deploy:
+              stage: deploy
+              rules:
+                - if: $CI_COMMIT_BRANCH == $CI_DEFAULT_BRANCH
+              script:
+                - {INSTALL_BINSTALL}
+                - {install}
+                # Reads the SHUTTLE_API_KEY variable
+                - {deploy}
+        "},
+        CiProvider::Circleci => formatdoc! {"
+            version: 2.1
+
+            jobs:
+              test:
+                docker:
+                  - image: cimg/rust:1.76
+                steps:
+                  - checkout
+                  - run: cargo build --locked
+                  - run: cargo test --locked
+              deploy:
+                docker:
+                  - image: cimg/rust:1.76
+                steps:
+                  - checkout
+                  - run: {INSTALL_BINSTALL}
+                  - run: {install}
+                  # Reads the SHUTTLE_API_KEY environment variable
+                  - run: {deploy}
+
+            workflows:
+              deploy:
+                jobs:
+                  - test
+                  - deploy:
+                      requires:
+                        - test
+                      filters:
+                        branches:
+                          only: main
+        "},
+    }
+}
+
+impl Shuttle {
+    pub(crate) fn generate_ci(
+        &self,
+        provider: CiProvider,
+        output: Option<PathBuf>,
+        force: bool,
+    ) -> Result<CommandOutcome> {
+        let path = output.unwrap_or_else(|| default_path(provider, self.ctx.working_directory()));
+        if path.exists()
+            && !force
+            && !prompt::confirm(
+                format!("{} exists. Overwrite it?", path.display()),
+                false,
+                "Pass --force to overwrite it.",
+            )?
+        {
+            bail!(
+                "{} already exists. Use `--force` to overwrite it.",
+                path.display()
+            );
+        }
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        std::fs::write(&path, generate(provider, self.ctx.project_name(), VERSION))
+            .with_context(|| format!("failed to write {}", path.display()))?;
+
+        println!("Wrote {}", path.display());
+        println!();
+        println!("{}", secret_instructions(provider));
+        println!("Get an API key at {SHUTTLE_LOGIN_URL} if you need one for CI.");
+
+        Ok(CommandOutcome::Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workflows() {
+        for provider in [CiProvider::Github, CiProvider::Gitlab, CiProvider::Circleci] {
+            let workflow = generate(provider, "my-app", "0.43.0");
+            assert!(workflow.contains("cargo binstall -y --locked cargo-shuttle@0.43.0"));
+            assert!(workflow.contains("cargo shuttle deploy --name my-app"));
+            assert!(workflow.contains("cargo test --locked"));
+        }
+
+        assert!(generate(CiProvider::Github, "my-app", "0.43.0")
+            .contains("SHUTTLE_API_KEY: ${{ secrets.SHUTTLE_API_KEY }}"));
+        assert_eq!(
+            default_path(CiProvider::Github, Path::new("app")),
+            Path::new("app/.github/workflows/shuttle-deploy.yml")
+        );
+    }
+}
@@ -1,51 +1,89 @@
 use std::collections::HashMap;
 use std::time::Duration;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use futures::stream::{self, StreamExt, TryStreamExt};
 use headers::{Authorization, HeaderMapExt};
 use percent_encoding::utf8_percent_encode;
-use reqwest::header::HeaderMap;
+use reqwest::header::{
+    self, HeaderMap, HeaderValue, AUTHORIZATION, COOKIE, ETAG, IF_NONE_MATCH, SET_COOKIE,
+};
 use reqwest::RequestBuilder;
 use reqwest::Response;
+use reqwest::StatusCode;
+use serde::de::IgnoredAny;
 use serde::{Deserialize, Serialize};
 use shuttle_common::constants::headers::X_CARGO_SHUTTLE_VERSION;
 use shuttle_common::models::deployment::DeploymentRequest;
 use shuttle_common::models::organization;
-use shuttle_common::models::{deployment, project, service, ToJson};
+use shuttle_common::models::{
+    certificate, deployment, project, secret, service, token, user, ToJson,
+};
 use shuttle_common::secrets::Secret;
 use shuttle_common::{resource, ApiKey, ApiUrl, LogItem, VersionInfo};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
-use tracing::error;
+use tokio_util::io::ReaderStream;
+use tracing::{error, trace, warn};
 use uuid::Uuid;
 
+use crate::cache::{Entry, ResponseCache};
+use crate::overview::ComponentStatus;
+
+/// Exports download whole executables, which takes longer than other requests
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// How many times idempotent requests are sent before their failure is returned
+const ATTEMPTS: u32 = 3;
+/// How long to wait before the first retry, doubled for each one after it
+const RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// The HTTP client shared by all the requests of a command, so that connections to the API are
+/// reused
+pub fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .default_headers(
+            HeaderMap::try_from(&HashMap::from([(
+                X_CARGO_SHUTTLE_VERSION.clone(),
+                crate::VERSION.to_owned(),
+            )]))
+            .unwrap(),
+        )
+        .user_agent(format!("cargo-shuttle/{}", crate::VERSION))
+        .gzip(true)
+        .pool_idle_timeout(Duration::from_secs(90))
+        .timeout(Duration::from_secs(60))
+        .build()
+        .unwrap()
+}
+
 #[derive(Clone)]
 pub struct Client {
     api_url: ApiUrl,
     api_key: Option<Secret<ApiKey>>,
     client: reqwest::Client,
+    cache: Option<ResponseCache>,
 }
 
 impl Client {
-    pub fn new(api_url: ApiUrl) -> Self {
+    /// A client for the API at `api_url`, making its requests with `client`
+    pub fn new(api_url: ApiUrl, client: reqwest::Client) -> Self {
         Self {
             api_url,
             api_key: None,
-            client: reqwest::Client::builder()
-                .default_headers(
-                    HeaderMap::try_from(&HashMap::from([(
-                        X_CARGO_SHUTTLE_VERSION.clone(),
-                        crate::VERSION.to_owned(),
-                    )]))
-                    .unwrap(),
-                )
-                .timeout(Duration::from_secs(60))
-                .build()
-                .unwrap(),
+            client,
+            cache: None,
         }
     }
 
+    /// Keep the responses to read requests in `cache`, and revalidate them instead of fetching
+    /// them again
+    pub fn set_cache(&mut self, cache: ResponseCache) {
+        self.cache = Some(cache);
+    }
+
     pub fn set_api_key(&mut self, api_key: ApiKey) {
         self.api_key = Some(Secret::new(api_key));
     }
@@ -53,21 +91,22 @@ impl Client {
     pub async fn get_api_versions(&self) -> Result<VersionInfo> {
         let url = format!("{}/versions", self.api_url);
 
-        self.client
-            .get(url)
-            .send()
+        self.send(self.client.get(url))
             .await?
             .json()
             .await
             .context("parsing API version info")
     }
 
+    /// The health of the components of the platform
+    pub async fn get_platform_status(&self) -> Result<Vec<(String, ComponentStatus)>> {
+        self.get("/".to_string()).await
+    }
+
     pub async fn check_project_name(&self, project_name: &str) -> Result<bool> {
         let url = format!("{}/projects/name/{project_name}", self.api_url);
 
-        self.client
-            .get(url)
-            .send()
+        self.send(self.client.get(url))
             .await
             .context("failed to check project name availability")?
             .to_json()
@@ -75,23 +114,34 @@ impl Client {
             .context("parsing name check response")
     }
 
+    /// Upload a deployment with its archive, which is streamed from the file. `uploaded` is told
+    /// how many more bytes of the archive were sent.
     pub async fn deploy(
         &self,
         project: &str,
         deployment_req: DeploymentRequest,
+        (archive, archive_len): (std::fs::File, u64),
+        uploaded: impl Fn(u64) + Send + Sync + 'static,
     ) -> Result<deployment::Response> {
         let path = format!("/projects/{project}/services/{project}");
-        let deployment_req = rmp_serde::to_vec(&deployment_req)
-            .context("serialize DeploymentRequest as a MessagePack byte vector")?;
+        let (head, tail) = deployment_request_framing(deployment_req, archive_len)?;
+        let archive = ReaderStream::new(tokio::fs::File::from_std(archive)).map_ok(move |chunk| {
+            uploaded(chunk.len() as u64);
+            encode_archive_chunk(&chunk)
+        });
+        let body = stream::once(async move { Ok::<_, std::io::Error>(head) })
+            .chain(archive)
+            .chain(stream::once(async move { Ok(tail) }));
 
         let url = format!("{}{}", self.api_url, path);
         let mut builder = self.client.post(url);
         builder = self.set_builder_auth(builder);
 
-        builder
+        builder = builder
             .header("Transfer-Encoding", "chunked")
-            .body(deployment_req)
-            .send()
+            .body(reqwest::Body::wrap_stream(body));
+
+        self.send(builder)
             .await
             .context("failed to send deployment to the Shuttle server")?
             .to_json()
@@ -123,15 +173,74 @@ impl Client {
     ) -> Result<()> {
         let path = format!(
             "/projects/{project}/services/{project}/resources/{}",
-            utf8_percent_encode(
-                &resource_type.to_string(),
-                percent_encoding::NON_ALPHANUMERIC
-            ),
+            encode_path_segment(&resource_type.to_string()),
         );
 
         self.delete(path).await
     }
 
+    pub async fn get_certificates(&self, project: &str) -> Result<Vec<certificate::Response>> {
+        let path = format!("/projects/{project}/certificates");
+
+        self.get(path).await
+    }
+
+    pub async fn renew_certificate(
+        &self,
+        project: &str,
+        domain: &str,
+    ) -> Result<certificate::Response> {
+        let path = format!(
+            "/projects/{project}/certificates/{}/renew",
+            encode_path_segment(domain)
+        );
+
+        self.post(path, Option::<String>::None)
+            .await
+            .context("failed to make renew certificate request")?
+            .to_json()
+            .await
+    }
+
+    pub async fn get_secrets(&self, project: &str) -> Result<Vec<secret::Response>> {
+        let path = format!("/projects/{project}/secrets");
+
+        self.get(path).await
+    }
+
+    pub async fn get_secret(&self, project: &str, key: &str) -> Result<secret::ValueResponse> {
+        let path = format!("/projects/{project}/secrets/{}", encode_path_segment(key));
+
+        self.get(path).await
+    }
+
+    pub async fn set_secret(
+        &self,
+        project: &str,
+        key: &str,
+        request: &secret::SetRequest,
+    ) -> Result<secret::Response> {
+        let path = format!("/projects/{project}/secrets/{}", encode_path_segment(key));
+
+        self.put(path, Some(request))
+            .await
+            .context("failed to make set secret request")?
+            .to_json()
+            .await
+    }
+
+    pub async fn delete_secret(&self, project: &str, key: &str) -> Result<()> {
+        let path = format!("/projects/{project}/secrets/{}", encode_path_segment(key));
+
+        self.delete(path).await
+    }
+
+    pub async fn clear_secrets(&self, project: &str) -> Result<()> {
+        let path = format!("/projects/{project}/secrets");
+
+        self.delete(path).await
+    }
+
     pub async fn create_project(
         &self,
         project: &str,
@@ -165,7 +274,7 @@ impl Client {
     pub async fn get_projects_list(&self, page: u32, limit: u32) -> Result<Vec<project::Response>> {
         let path = format!("/projects?page={}&limit={}", page.saturating_sub(1), limit);
 
-        self.get(path).await
+        self.get_cached(path).await
     }
 
     pub async fn stop_project(&self, project: &str) -> Result<project::Response> {
@@ -180,6 +289,32 @@ impl Client {
         self.delete(path).await
     }
 
+    pub async fn get_project_lock(&self, project: &str) -> Result<Option<project::Lock>> {
+        let path = format!("/projects/{project}/lock");
+
+        self.get(path).await
+    }
+
+    pub async fn lock_project(
+        &self,
+        project: &str,
+        request: &project::LockRequest,
+    ) -> Result<project::Lock> {
+        let path = format!("/projects/{project}/lock");
+
+        self.put(path, Some(request))
+            .await
+            .context("failed to make lock project request")?
+            .to_json()
+            .await
+    }
+
+    pub async fn unlock_project(&self, project: &str) -> Result<()> {
+        let path = format!("/projects/{project}/lock");
+
+        self.delete(path).await
+    }
+
     pub async fn get_organizations_list(&self) -> Result<Vec<organization::Response>> {
         self.get("/organizations".to_string()).await
     }
@@ -190,9 +325,59 @@ impl Client {
     ) -> Result<Vec<project::Response>> {
         let path = format!("/organizations/{org_id}/projects");
 
+        self.get_cached(path).await
+    }
+
+    pub async fn create_organization(&self, name: &str) -> Result<()> {
+        let path = format!("/organizations/name/{}", encode_path_segment(name));
+
+        let response = self
+            .post(path, Option::<String>::None)
+            .await
+            .context("failed to make create organization request")?;
+
+        check_status(response).await
+    }
+
+    pub async fn get_organization_members(
+        &self,
+        org_id: &str,
+    ) -> Result<Vec<organization::MemberResponse>> {
+        let path = format!("/organizations/{org_id}/members");
+
         self.get(path).await
     }
 
+    pub async fn add_organization_member(&self, org_id: &str, user_id: &str) -> Result<()> {
+        let path = format!(
+            "/organizations/{org_id}/members/{}",
+            encode_path_segment(user_id)
+        );
+
+        let response = self
+            .post(path, Option::<String>::None)
+            .await
+            .context("failed to make add member request")?;
+
+        check_status(response).await
+    }
+
+    pub async fn remove_organization_member(&self, org_id: &str, user_id: &str) -> Result<()> {
+        let url = format!(
+            "{}/organizations/{org_id}/members/{}",
+            self.api_url,
+            encode_path_segment(user_id)
+        );
+
+        let builder = self.set_builder_auth(self.client.delete(url));
+        let response = self
+            .send(builder)
+            .await
+            .context("failed to make remove member request")?;
+
+        check_status(response).await
+    }
+
     pub async fn get_logs(&self, project: &str, deployment_id: &Uuid) -> Result<Vec<LogItem>> {
         let path = format!("/projects/{project}/deployments/{deployment_id}/logs");
 
@@ -223,7 +408,7 @@ impl Client {
             limit,
         );
 
-        self.get(path).await
+        self.get_cached(path).await
     }
 
     pub async fn get_deployment_details(
@@ -236,11 +421,85 @@ impl Client {
         self.get(path).await
     }
 
+    /// Stream the executable built for a deployment into `out`, returning how many bytes were
+    /// written
+    pub async fn download_deployment_artifact(
+        &self,
+        project: &str,
+        deployment_id: &Uuid,
+        out: &mut (impl AsyncWrite + Unpin),
+    ) -> Result<u64> {
+        let url = format!(
+            "{}/projects/{project}/deployments/{deployment_id}/artifact",
+            self.api_url
+        );
+
+        self.download_to(url, out)
+            .await
+            .context("failed to download the artifact")
+    }
+
+    /// Stream the body of a GET of `url` into `out`, returning how many bytes were written
+    async fn download_to(&self, url: String, out: &mut (impl AsyncWrite + Unpin)) -> Result<u64> {
+        let builder = self.client.get(url).timeout(TRANSFER_TIMEOUT);
+        let mut response = self.send(self.set_builder_auth(builder)).await?;
+        let status = response.status();
+        if status != StatusCode::OK {
+            // The body of failed requests is the error
+            response.to_json::<IgnoredAny>().await?;
+            bail!("unexpected response: {status}");
+        }
+
+        let mut written = 0;
+        while let Some(chunk) = response.chunk().await? {
+            out.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+        }
+        out.flush().await?;
+
+        Ok(written)
+    }
+
     pub async fn reset_api_key(&self) -> Result<Response> {
         self.put("/users/reset-api-key".into(), Option::<()>::None)
             .await
     }
 
+    pub async fn get_current_user(&self) -> Result<user::AccountResponse> {
+        self.get("/users/me".into()).await
+    }
+
+    pub async fn create_token(
+        &self,
+        request: &token::CreateRequest,
+    ) -> Result<token::CreatedResponse> {
+        self.post("/users/tokens".into(), Some(request))
+            .await
+            .context("failed to make create token request")?
+            .to_json()
+            .await
+    }
+
+    pub async fn get_tokens(&self) -> Result<Vec<token::Response>> {
+        self.get("/users/tokens".into()).await
+    }
+
+    pub async fn revoke_token(&self, token_id: &str) -> Result<()> {
+        let url = format!(
+            "{}/users/tokens/{}",
+            self.api_url,
+            encode_path_segment(token_id)
+        );
+
+        let builder = self.set_builder_auth(self.client.delete(url));
+        let response = self
+            .send(builder)
+            .await
+            .context("failed to make revoke token request")?;
+
+        check_status(response).await
+    }
+
     async fn ws_get(&self, path: String) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
         let ws_scheme = self.api_url.clone().replace("http", "ws");
         let url = format!("{ws_scheme}{path}");
@@ -269,14 +528,61 @@ impl Client {
 
         builder = self.set_builder_auth(builder);
 
-        builder
-            .send()
+        self.send(builder)
             .await
             .context("failed to make get request")?
             .to_json()
             .await
     }
 
+    /// Like [`Client::get`], but revalidating the response in the cache when there is one
+    async fn get_cached<M>(&self, path: String) -> Result<M>
+    where
+        M: for<'de> Deserialize<'de>,
+    {
+        let Some(cache) = &self.cache else {
+            return self.get(path).await;
+        };
+        let url = format!("{}{}", self.api_url, path);
+        let key = ResponseCache::key(
+            &url,
+            self.api_key
+                .as_ref()
+                .map(|api_key| api_key.expose().as_ref()),
+        );
+        let cached = cache.load(&key);
+
+        let mut builder = self.client.get(url);
+        builder = self.set_builder_auth(builder);
+        if let Some(cached) = &cached {
+            builder = builder.header(IF_NONE_MATCH, &cached.etag);
+        }
+        let response = self
+            .send(builder)
+            .await
+            .context("failed to make get request")?;
+
+        if let (StatusCode::NOT_MODIFIED, Some(cached)) = (response.status(), cached) {
+            trace!(%path, "cached response is still valid");
+            return serde_json::from_str(&cached.body).context("failed to parse a cached response");
+        }
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(str::to_string);
+        let (StatusCode::OK, Some(etag)) = (response.status(), etag) else {
+            return response.to_json().await;
+        };
+
+        let body = response.text().await?;
+        let parsed =
+            serde_json::from_str(&body).context("failed to parse a successful response")?;
+        cache.store(&key, &Entry { etag, body });
+
+        Ok(parsed)
+    }
+
     async fn post<T: Serialize>(&self, path: String, body: Option<T>) -> Result<Response> {
         let url = format!("{}{}", self.api_url, path);
 
@@ -290,7 +596,7 @@ impl Client {
             builder = builder.header("Content-Type", "application/json");
         }
 
-        Ok(builder.send().await?)
+        Ok(self.send(builder).await?)
     }
 
     async fn put<T: Serialize>(&self, path: String, body: Option<T>) -> Result<Response> {
@@ -306,7 +612,7 @@ impl Client {
             builder = builder.header("Content-Type", "application/json");
         }
 
-        Ok(builder.send().await?)
+        Ok(self.send(builder).await?)
     }
 
     async fn delete<M>(&self, path: String) -> Result<M>
@@ -319,14 +625,59 @@ impl Client {
 
         builder = self.set_builder_auth(builder);
 
-        builder
-            .send()
+        self.send(builder)
             .await
             .context("failed to make delete request")?
             .to_json()
             .await
     }
 
+    /// Send a request, tracing it with its credentials left out. Idempotent ones are retried when
+    /// the API could not be reached or was briefly unavailable.
+    async fn send(&self, builder: RequestBuilder) -> reqwest::Result<Response> {
+        let request = builder.build()?;
+        trace!(
+            method = %request.method(),
+            url = %request.url(),
+            headers = ?redacted_headers(request.headers()),
+            "sending request"
+        );
+
+        let mut attempt = 1;
+        let response = loop {
+            // Streamed bodies, like the ones of deployments, cannot be sent again
+            let retry = match request.try_clone() {
+                Some(retry) if attempt < ATTEMPTS && request.method().is_idempotent() => retry,
+                _ => break self.client.execute(request).await?,
+            };
+            let delay = RETRY_DELAY * 2u32.pow(attempt - 1);
+            match self.client.execute(retry).await {
+                Ok(response) if !is_transient(response.status()) => break response,
+                Ok(response) => warn!(
+                    url = %request.url(),
+                    status = %response.status(),
+                    "request failed, retrying in {delay:?}"
+                ),
+                Err(err) if err.is_connect() || err.is_timeout() => warn!(
+                    url = %request.url(),
+                    error = %err,
+                    "request failed, retrying in {delay:?}"
+                ),
+                Err(err) => return Err(err),
+            }
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        };
+        trace!(
+            status = %response.status(),
+            url = %response.url(),
+            headers = ?redacted_headers(response.headers()),
+            "got response"
+        );
+
+        Ok(response)
+    }
+
     fn set_builder_auth(&self, builder: RequestBuilder) -> RequestBuilder {
         if let Some(ref api_key) = self.api_key {
             builder.bearer_auth(api_key.expose().as_ref())
@@ -335,3 +686,131 @@ impl Client {
         }
     }
 }
+
+/// Headers with the values of the ones that carry credentials replaced, for tracing
+fn redacted_headers(headers: &HeaderMap) -> HeaderMap {
+    let mut headers = headers.clone();
+    for name in [AUTHORIZATION, COOKIE, SET_COOKIE] {
+        if let header::Entry::Occupied(mut entry) = headers.entry(name) {
+            entry.insert(HeaderValue::from_static("[REDACTED]"));
+        }
+    }
+
+    headers
+}
+
+/// The MessagePack encoding of a deployment request before and after its archive, which is streamed
+/// in between. The request is encoded as an array with the archive first, and the archive as an
+/// array of integers, so only the length of the archive is needed up front.
+fn deployment_request_framing(
+    mut deployment_req: DeploymentRequest,
+    archive_len: u64,
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    /// The marker of an empty array
+    const EMPTY_ARRAY: u8 = 0x90;
+    /// The marker of an array with a 32 bit length
+    const ARRAY_32: u8 = 0xdd;
+
+    deployment_req.data = Vec::new();
+    let encoded = rmp_serde::to_vec(&deployment_req)
+        .context("serialize DeploymentRequest as a MessagePack byte vector")?;
+    // A fixarray marker for the fields, then the empty archive
+    if encoded.len() < 2 || encoded[0] & 0xf0 != 0x90 || encoded[1] != EMPTY_ARRAY {
+        bail!("unexpected encoding of the deployment request");
+    }
+    let archive_len = u32::try_from(archive_len).context("the archive is too large")?;
+
+    let mut head = vec![encoded[0], ARRAY_32];
+    head.extend(archive_len.to_be_bytes());
+
+    Ok((head, encoded[2..].to_vec()))
+}
+
+/// A chunk of an archive as the MessagePack integers of its bytes
+fn encode_archive_chunk(chunk: &[u8]) -> Vec<u8> {
+    /// The marker of an 8 bit unsigned integer, for the ones that do not fit in the marker itself
+    const UINT_8: u8 = 0xcc;
+
+    let mut encoded = Vec::with_capacity(chunk.len() * 2);
+    for &byte in chunk {
+        if byte >= 0x80 {
+            encoded.push(UINT_8);
+        }
+        encoded.push(byte);
+    }
+
+    encoded
+}
+
+/// Check the status of a response with a plain text body, which the API gives to some requests
+/// that change things
+async fn check_status(response: Response) -> Result<()> {
+    if response.status().is_success() {
+        return Ok(());
+    }
+
+    // Errors are JSON like the ones of other requests
+    response.to_json::<IgnoredAny>().await.map(|_| ())
+}
+
+/// Whether a response says the API is briefly unable to handle requests
+fn is_transient(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+fn encode_path_segment(segment: &str) -> String {
+    utf8_percent_encode(segment, percent_encoding::NON_ALPHANUMERIC).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn streamed_deployment_request() {
+        let archive = (0..=255).collect::<Vec<u8>>();
+        let deployment_req = DeploymentRequest {
+            no_test: true,
+            git_branch: Some("main".to_string()),
+            ..Default::default()
+        };
+
+        let (head, tail) =
+            deployment_request_framing(deployment_req, archive.len() as u64).unwrap();
+        let mut encoded = head;
+        for chunk in archive.chunks(100) {
+            encoded.extend(encode_archive_chunk(chunk));
+        }
+        encoded.extend(tail);
+
+        let decoded: DeploymentRequest = rmp_serde::from_slice(&encoded).unwrap();
+        assert_eq!(decoded.data, archive);
+        assert!(decoded.no_test);
+        assert_eq!(decoded.git_branch.as_deref(), Some("main"));
+    }
+
+    #[test]
+    fn transient_statuses() {
+        assert!(is_transient(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_transient(StatusCode::BAD_GATEWAY));
+        assert!(!is_transient(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(!is_transient(StatusCode::NOT_FOUND));
+    }
+
+    #[test]
+    fn redacts_credentials() {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer hunter2"));
+        headers.insert(COOKIE, HeaderValue::from_static("session=hunter2"));
+        headers.insert("Content-Type", HeaderValue::from_static("application/json"));
+
+        let redacted = redacted_headers(&headers);
+        assert_eq!(redacted[AUTHORIZATION], "[REDACTED]");
+        assert_eq!(redacted[COOKIE], "[REDACTED]");
+        assert_eq!(redacted["Content-Type"], "application/json");
+        assert!(!redacted.contains_key(SET_COOKIE));
+    }
+}
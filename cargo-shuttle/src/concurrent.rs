@@ -0,0 +1,67 @@
+//! Requests for many entities at once, like the projects of every organization or the logs of
+//! several deployments.
+//!
+//! The requests are made concurrently to cut the time commands take for accounts with many of
+//! them, with a cap on how many are in flight so that the API is not flooded. The `concurrency`
+//! key of the global configuration changes the cap.
+
+use std::future::Future;
+
+use futures::stream::{self, StreamExt};
+
+/// How many requests are in flight at once, unless configured otherwise
+pub const DEFAULT_LIMIT: usize = 8;
+
+/// Run `f` on every item with at most `limit` of them in flight, and return the outputs in the
+/// order of the items
+pub async fn map<I, F, Fut>(items: I, limit: usize, f: F) -> Vec<Fut::Output>
+where
+    I: IntoIterator,
+    F: Fn(I::Item) -> Fut,
+    Fut: Future,
+{
+    let mut outputs = stream::iter(items.into_iter().enumerate())
+        .map(|(idx, item)| {
+            let fut = f(item);
+            async move { (idx, fut.await) }
+        })
+        .buffer_unordered(limit.max(1))
+        .collect::<Vec<_>>()
+        .await;
+    outputs.sort_by_key(|(idx, _)| *idx);
+
+    outputs.into_iter().map(|(_, output)| output).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn bounded_and_ordered() {
+        let in_flight = AtomicUsize::new(0);
+        let most = AtomicUsize::new(0);
+
+        let outputs = map(0..20u64, 3, |n| {
+            let (in_flight, most) = (&in_flight, &most);
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                most.fetch_max(now, Ordering::SeqCst);
+                // The first ones finish last
+                tokio::time::sleep(Duration::from_millis(20 - n)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                n * 2
+            }
+        })
+        .await;
+
+        assert_eq!(outputs, (0..20).map(|n| n * 2).collect::<Vec<_>>());
+        assert_eq!(most.load(Ordering::SeqCst), 3);
+
+        // A limit of 0 would never make progress
+        assert_eq!(map([1], 0, |n| async move { n }).await, [1]);
+    }
+}
@@ -1,13 +1,22 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
 use std::fs::File;
 use std::io::{Read, Write};
+use std::net::IpAddr;
 use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, Context, Result};
-use serde::{Deserialize, Serialize};
-use shuttle_common::{constants::API_URL_DEFAULT, ApiKey, ApiUrl};
+use serde::{Deserialize, Serialize, Serializer};
+use shuttle_common::{
+    constants::API_URL_DEFAULT,
+    models::deployment::{CronJobs, Domains, ExternalResources, LogForwarding, ResourceOverrides},
+    ApiKey, ApiUrl,
+};
 use tracing::trace;
 
 use crate::args::ProjectArgs;
+use crate::client::{self, Client};
+use crate::logs::Timezone;
 
 /// Helper trait for dispatching fs ops for different config files
 pub trait ConfigManager: Sized {
@@ -123,6 +132,48 @@ impl ConfigManager for LocalConfigManager {
 pub struct GlobalConfig {
     api_key: Option<String>,
     pub api_url: Option<ApiUrl>,
+    /// Defaults for `cargo shuttle logs`
+    pub logs: Option<LogsConfig>,
+    /// Aliases for commands, like `d = "deploy --no-follow"`
+    pub aliases: Option<BTreeMap<String, String>>,
+    /// Colors and symbols of the output
+    pub theme: Option<ThemeConfig>,
+    /// Whether anonymous usage telemetry was turned on. It is off unless it was.
+    pub telemetry: Option<bool>,
+    /// Desktop notifications when long operations finish
+    pub notifications: Option<NotificationsConfig>,
+    /// How many requests commands that fetch many things make at once
+    pub concurrency: Option<usize>,
+}
+
+/// The `[logs]` table of the global configuration
+#[derive(Deserialize, Serialize, Default, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct LogsConfig {
+    pub timezone: Option<Timezone>,
+    /// strftime-style format of the timestamps
+    pub time_format: Option<String>,
+}
+
+/// The `[theme]` table of the global configuration
+#[derive(Deserialize, Serialize, Default, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct ThemeConfig {
+    /// Color of spinners, bars and highlights: a basic color like `cyan`, or a number of the
+    /// 256-color palette
+    pub accent: Option<String>,
+    /// Use ASCII instead of symbols like ●, for fonts that lack them
+    pub ascii: Option<bool>,
+}
+
+/// The `[notifications]` table of the global configuration
+#[derive(Deserialize, Serialize, Default, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct NotificationsConfig {
+    /// Notifications are on unless this is false
+    pub enabled: Option<bool>,
+    /// How long an operation has to take to be notified about, like `30s` or `2m`
+    pub after: Option<String>,
 }
 
 impl GlobalConfig {
@@ -148,6 +199,204 @@ impl GlobalConfig {
 pub struct ProjectConfig {
     pub name: Option<String>,
     pub assets: Option<Vec<String>>,
+    /// Names of the environments that can be selected with `--env`
+    pub environments: Option<Vec<String>>,
+    pub secrets: Option<SecretsConfig>,
+    /// Plain environment variables for the service, set with `cargo shuttle env`
+    #[serde(rename = "env-vars")]
+    pub env_vars: Option<BTreeMap<String, String>>,
+    #[serde(rename = "log-forwarding")]
+    pub log_forwarding: Option<LogForwarding>,
+    pub local: Option<LocalRunConfig>,
+    /// Settings to provision the databases with, not applied by deployments yet
+    pub resources: Option<ResourceOverrides>,
+    pub migrations: Option<MigrationsConfig>,
+    /// Resources outside of Shuttle whose connection strings are handed to the service
+    pub external: Option<ExternalResources>,
+    /// Custom domains the service is served on, and the ones that redirect to them
+    pub domains: Option<Domains>,
+    /// Jobs to run on a schedule, set with `cargo shuttle cron`
+    pub cron: Option<CronJobs>,
+}
+
+/// The `[local]` table of Shuttle.toml, used by `cargo shuttle run`
+#[derive(Deserialize, Serialize, Default, Clone, Debug)]
+pub struct LocalRunConfig {
+    /// Port to start the service on
+    pub port: Option<u16>,
+    /// Address to bind to, like `0.0.0.0`
+    pub host: Option<IpAddr>,
+    /// Where local runs keep the data of containers and of shuttle-persist, relative to the
+    /// project. Without it, they are kept in `.shuttle/data` and `.shuttle-storage`.
+    pub data_dir: Option<PathBuf>,
+    /// The container started for a shared Postgres database
+    pub postgres: Option<LocalContainerConfig>,
+    /// The container started for a shared MongoDB database
+    pub mongodb: Option<LocalContainerConfig>,
+    pub qdrant: Option<LocalContainerConfig>,
+    pub redis: Option<LocalContainerConfig>,
+    /// The S3 compatible container started for object storage
+    #[serde(rename = "object-storage")]
+    pub object_storage: Option<LocalContainerConfig>,
+    /// Fixtures to fill the databases with when they are first started
+    pub seed: Option<LocalSeedConfig>,
+}
+
+impl LocalRunConfig {
+    /// The configured containers, by the kind of container the local provisioner starts, with
+    /// the repository of their default image
+    pub fn containers(&self) -> Vec<(&'static str, &'static str, LocalContainerConfig)> {
+        [
+            (
+                "shared_postgres",
+                "docker.io/library/postgres",
+                &self.postgres,
+            ),
+            ("shared_mongodb", "docker.io/library/mongo", &self.mongodb),
+            ("qdrant", "docker.io/qdrant/qdrant", &self.qdrant),
+            ("redis", "docker.io/library/redis", &self.redis),
+            (
+                "object_storage",
+                "docker.io/minio/minio",
+                &self.object_storage,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(kind, repository, config)| {
+            config.clone().map(|config| (kind, repository, config))
+        })
+        .collect()
+    }
+
+    /// Settings of the container of a kind
+    pub fn container(&self, kind: &str) -> Option<(&'static str, LocalContainerConfig)> {
+        self.containers()
+            .into_iter()
+            .find(|(k, ..)| *k == kind)
+            .map(|(_, repository, config)| (repository, config))
+    }
+}
+
+/// A Docker container started for a resource of a local run
+#[derive(Deserialize, Serialize, Default, Clone, Debug)]
+pub struct LocalContainerConfig {
+    /// Image to use, like `docker.io/library/postgres:16`
+    pub image: Option<String>,
+    /// Tag of the default image to use, like `16`
+    pub version: Option<String>,
+    /// Keep the container and its data for the next run. Defaults to true.
+    pub keep: Option<bool>,
+}
+
+impl LocalContainerConfig {
+    /// The image to use instead of the default one, if any. `repository` is the default image
+    /// without its tag.
+    pub fn image(&self, repository: &str) -> Option<String> {
+        self.image.clone().or_else(|| {
+            self.version
+                .as_ref()
+                .map(|version| format!("{repository}:{version}"))
+        })
+    }
+
+    pub fn keep(&self) -> bool {
+        self.keep.unwrap_or(true)
+    }
+}
+
+/// The `[local.seed]` table of Shuttle.toml
+#[derive(Deserialize, Serialize, Default, Clone, Debug)]
+pub struct LocalSeedConfig {
+    /// SQL files to run on the database, in order, relative to the project
+    #[serde(default)]
+    pub sql: Vec<PathBuf>,
+    /// Binary of the project to run after the SQL files, with `DATABASE_URL` set
+    pub bin: Option<String>,
+}
+
+/// The `[migrations]` table of Shuttle.toml, used by `cargo shuttle migrate`
+#[derive(Deserialize, Serialize, Default, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub struct MigrationsConfig {
+    /// Directory of the migrations, relative to the project. `migrations` by default.
+    pub source: Option<PathBuf>,
+    /// Run the migrations after each deployment that enters the running state
+    #[serde(default)]
+    pub run_on_deploy: bool,
+}
+
+/// The `[secrets]` table of Shuttle.toml
+#[derive(Deserialize, Serialize, Default)]
+pub struct SecretsConfig {
+    /// Secrets the service needs to start. Deploys without them are rejected.
+    #[serde(default)]
+    pub required: Vec<String>,
+}
+
+/// Read the Shuttle.toml at `path` for editing, keeping its comments and layout. A missing file
+/// is read as an empty one.
+fn read_project_document(path: &Path) -> Result<toml_edit::Document> {
+    if !path.exists() {
+        return Ok(toml_edit::Document::new());
+    }
+
+    std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?
+        .parse::<toml_edit::Document>()
+        .with_context(|| format!("failed to parse {}", path.display()))
+}
+
+fn write_project_document(path: &Path, document: &toml_edit::Document) -> Result<()> {
+    std::fs::write(path, document.to_string())
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Set the name of the project in the Shuttle.toml at `path`, keeping the rest of the file as it
+/// is. The file is created if needed.
+pub fn save_project_name(path: &Path, name: &str) -> Result<()> {
+    let mut document = read_project_document(path)?;
+    document["name"] = toml_edit::value(name);
+
+    write_project_document(path, &document)
+}
+
+/// Set `key` in the `[table]` of the Shuttle.toml at `path`, creating the file and the table if
+/// needed. When the item is a table itself, only its `[table.key]` header is written. Returns
+/// whether the key was already set.
+pub fn insert_in_table(path: &Path, table: &str, key: &str, item: toml_edit::Item) -> Result<bool> {
+    let mut document = read_project_document(path)?;
+    let entries = document
+        .entry(table)
+        .or_insert(toml_edit::table())
+        .as_table_mut()
+        .with_context(|| format!("`{table}` in {} is not a table", path.display()))?;
+    if item.is_table() {
+        entries.set_implicit(true);
+    }
+    let replaced = entries.insert(key, item).is_some();
+
+    write_project_document(path, &document)?;
+
+    Ok(replaced)
+}
+
+/// Remove `key` from the `[table]` of the Shuttle.toml at `path`, and the table once it is empty.
+/// Returns whether the key was set.
+pub fn remove_from_table(path: &Path, table: &str, key: &str) -> Result<bool> {
+    let mut document = read_project_document(path)?;
+    let Some(entries) = document.get_mut(table).and_then(|item| item.as_table_mut()) else {
+        return Ok(false);
+    };
+    if entries.remove(key).is_none() {
+        return Ok(false);
+    }
+    if entries.is_empty() {
+        document.remove(table);
+    }
+
+    write_project_document(path, &document)?;
+
+    Ok(true)
 }
 
 /// A handler for configuration files. The type parameter `M` is the [`ConfigManager`] which handles
@@ -226,13 +475,70 @@ where
     }
 }
 
+/// Where the value of a setting in effect came from
+#[derive(Clone, Debug, PartialEq)]
+pub enum Origin {
+    /// A command-line flag, like `--api-url`
+    Flag(String),
+    /// An environment variable, like `SHUTTLE_API`
+    Env(String),
+    ProjectConfig,
+    GlobalConfig,
+    /// The name of the crate or workspace in Cargo.toml
+    Cargo,
+    Default,
+}
+
+impl Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Flag(flag) => write!(f, "flag {flag}"),
+            Self::Env(name) => write!(f, "env {name}"),
+            Self::ProjectConfig => f.write_str("Shuttle.toml"),
+            Self::GlobalConfig => f.write_str("global config"),
+            Self::Cargo => f.write_str("Cargo.toml"),
+            Self::Default => f.write_str("default"),
+        }
+    }
+}
+
+impl Serialize for Origin {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A setting in effect, for `cargo shuttle config show`
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Setting {
+    pub name: &'static str,
+    /// `None` when it is not set at all
+    pub value: Option<String>,
+    pub origin: Origin,
+}
+
+impl Setting {
+    pub fn new(name: &'static str, value: Option<impl ToString>, origin: Origin) -> Self {
+        Self {
+            name,
+            value: value.map(|value| value.to_string()),
+            origin,
+        }
+    }
+}
+
 /// A wrapper around our two sources of configuration and overrides:
 /// - Global config
 /// - Local config
 pub struct RequestContext {
     global: Config<GlobalConfigManager, GlobalConfig>,
     project: Option<Config<LocalConfigManager, ProjectConfig>>,
+    /// Where the project name came from, once the project configuration is loaded
+    project_name_origin: Option<Origin>,
     api_url: Option<String>,
+    /// Where the values of the global flags came from, by the ID of the flag
+    arg_origins: BTreeMap<String, Origin>,
+    http: reqwest::Client,
 }
 
 impl RequestContext {
@@ -248,7 +554,10 @@ impl RequestContext {
         Ok(Self {
             global,
             project: None,
+            project_name_origin: None,
             api_url: None,
+            arg_origins: BTreeMap::new(),
+            http: client::http_client(),
         })
     }
 
@@ -259,9 +568,10 @@ impl RequestContext {
     /// has `ProjectConfig.name = Some("crate-name")`.
     pub fn load_local(&mut self, project_args: &ProjectArgs) -> Result<()> {
         // Shuttle.toml
-        let project = Self::get_local_config(project_args)?;
+        let (project, name_origin) = Self::resolve_local_config(project_args)?;
 
         self.project = Some(project);
+        self.project_name_origin = Some(name_origin);
 
         Ok(())
     }
@@ -269,6 +579,13 @@ impl RequestContext {
     pub fn get_local_config(
         project_args: &ProjectArgs,
     ) -> Result<Config<LocalConfigManager, ProjectConfig>> {
+        Ok(Self::resolve_local_config(project_args)?.0)
+    }
+
+    /// The project configuration, and where its name came from
+    fn resolve_local_config(
+        project_args: &ProjectArgs,
+    ) -> Result<(Config<LocalConfigManager, ProjectConfig>, Origin)> {
         let workspace_path = project_args
             .workspace_path()
             .unwrap_or(project_args.working_directory.clone());
@@ -292,39 +609,184 @@ impl RequestContext {
         // 2. Name from Shuttle.toml file
         // 3. Name from Cargo.toml package if it's a crate
         // 3. Name from the workspace directory if it's a workspace
-        match (&project_args.name, &config.name) {
+        let origin = match (&project_args.name, &config.name) {
             // Command-line name parameter trumps everything
             (Some(name_from_args), _) => {
                 trace!("using command-line project name");
                 config.name = Some(name_from_args.clone());
+                Origin::Flag("--name".to_string())
             }
             // If key exists in config then keep it as it is
             (None, Some(_)) => {
                 trace!("using Shuttle.toml project name");
+                Origin::ProjectConfig
             }
             // If name key is not in project config, then we infer from crate name
             (None, None) => {
                 trace!("using crate name as project name");
                 config.name = Some(project_args.project_name()?);
+                Origin::Cargo
             }
         };
-        Ok(project)
+        Ok((project, origin))
     }
 
     pub fn set_api_url(&mut self, api_url: Option<String>) {
         self.api_url = api_url;
     }
 
+    /// Record where the values of the global flags came from, by the ID of the flag
+    pub fn set_arg_origins(&mut self, arg_origins: BTreeMap<String, Origin>) {
+        self.arg_origins = arg_origins;
+    }
+
+    /// Where the value of the global flag `id` came from. Flags that were not parsed from the
+    /// command line, like in tests, count as given when they are set.
+    pub fn arg_origin(&self, id: &str, flag: &str, given: bool) -> Origin {
+        match self.arg_origins.get(id) {
+            Some(origin) => origin.clone(),
+            None if given => Origin::Flag(flag.to_string()),
+            None => Origin::Default,
+        }
+    }
+
     pub fn api_url(&self) -> ApiUrl {
+        self.api_url_with_origin().0
+    }
+
+    fn api_url_with_origin(&self) -> (ApiUrl, Origin) {
         if let Some(api_url) = self.api_url.clone() {
-            api_url
+            (api_url, self.arg_origin("api_url", "--api-url", true))
         } else if let Some(api_url) = self.global.as_ref().unwrap().api_url() {
-            api_url
+            (api_url, Origin::GlobalConfig)
         } else {
-            API_URL_DEFAULT.to_string()
+            (API_URL_DEFAULT.to_string(), Origin::Default)
         }
     }
 
+    /// The settings that commands resolve from the flags, environment variables, Shuttle.toml
+    /// and the global configuration, with where each came from. The API key is masked, and the
+    /// project ones are only there once the project configuration is loaded.
+    pub fn settings(&self) -> Vec<Setting> {
+        let global = self.global.as_ref().unwrap();
+        let from_global = |set: bool| {
+            if set {
+                Origin::GlobalConfig
+            } else {
+                Origin::Default
+            }
+        };
+
+        let (api_url, api_url_origin) = self.api_url_with_origin();
+        let mut settings = vec![Setting::new("api-url", Some(api_url), api_url_origin)];
+        settings.push(if std::env::var_os("SHUTTLE_API_KEY").is_some() {
+            Setting::new(
+                "api-key",
+                Some("****"),
+                Origin::Env("SHUTTLE_API_KEY".to_string()),
+            )
+        } else {
+            let set = global.api_key.is_some();
+            Setting::new("api-key", set.then_some("****"), from_global(set))
+        });
+
+        if let (Some(project), Some(name_origin)) = (&self.project, &self.project_name_origin) {
+            settings.push(Setting::new(
+                "name",
+                Some(self.project_name()),
+                name_origin.clone(),
+            ));
+            settings.push(Setting::new(
+                "working-directory",
+                Some(self.working_directory().display()),
+                self.arg_origin("working_directory", "--working-directory", false),
+            ));
+            settings.push(Setting::new(
+                "project-config",
+                Some(project.manager.path().display()),
+                Origin::Default,
+            ));
+        }
+
+        let telemetry_origin = if self.telemetry() && !crate::telemetry::is_enabled(true) {
+            Origin::Env("DO_NOT_TRACK".to_string())
+        } else {
+            from_global(global.telemetry.is_some())
+        };
+        settings.push(Setting::new(
+            "telemetry",
+            Some(crate::telemetry::is_enabled(self.telemetry())),
+            telemetry_origin,
+        ));
+        settings.push(Setting::new(
+            "concurrency",
+            Some(self.concurrency()),
+            from_global(
+                global
+                    .concurrency
+                    .is_some_and(|concurrency| concurrency > 0),
+            ),
+        ));
+
+        let logs = self.logs_config();
+        let timezone = logs
+            .timezone
+            .map(|timezone| format!("{timezone:?}").to_lowercase());
+        settings.push(Setting::new(
+            "logs.timezone",
+            timezone.clone(),
+            from_global(timezone.is_some()),
+        ));
+        settings.push(Setting::new(
+            "logs.time-format",
+            logs.time_format.clone(),
+            from_global(logs.time_format.is_some()),
+        ));
+
+        let theme = self.theme_config();
+        settings.push(Setting::new(
+            "theme.accent",
+            theme.accent.clone(),
+            from_global(theme.accent.is_some()),
+        ));
+        settings.push(Setting::new(
+            "theme.ascii",
+            Some(theme.ascii.unwrap_or_default()),
+            from_global(theme.ascii.is_some()),
+        ));
+
+        let notifications = self.notifications_config();
+        settings.push(Setting::new(
+            "notifications.enabled",
+            Some(notifications.enabled.unwrap_or(true)),
+            from_global(notifications.enabled.is_some()),
+        ));
+        settings.push(Setting::new(
+            "notifications.after",
+            Some(notifications.after.clone().unwrap_or_else(|| {
+                humantime::format_duration(crate::notification::DEFAULT_AFTER).to_string()
+            })),
+            from_global(notifications.after.is_some()),
+        ));
+
+        settings
+    }
+
+    /// The HTTP client for the requests of the command, which shares its connections
+    pub fn http_client(&self) -> reqwest::Client {
+        self.http.clone()
+    }
+
+    /// Where the responses to read requests are cached
+    pub fn cache_dir(&self) -> PathBuf {
+        GlobalConfigManager.directory().join("cache")
+    }
+
+    /// A client for the API, without an API key
+    pub fn api_client(&self) -> Client {
+        Client::new(self.api_url(), self.http_client())
+    }
+
     /// Get the API key from the `SHUTTLE_API_KEY` env variable, or
     /// otherwise from the global configuration. Returns an error if
     /// an API key is not set.
@@ -360,6 +822,100 @@ impl RequestContext {
             .as_path()
     }
 
+    pub fn logs_config(&self) -> LogsConfig {
+        self.global
+            .as_ref()
+            .unwrap()
+            .logs
+            .clone()
+            .unwrap_or_default()
+    }
+
+    /// Set the defaults for `cargo shuttle logs` in the global configuration. Will persist the
+    /// file.
+    pub fn set_logs_config(&mut self, logs: LogsConfig) -> Result<()> {
+        self.global.as_mut().unwrap().logs = Some(logs);
+        self.global.save()
+    }
+
+    pub fn theme_config(&self) -> ThemeConfig {
+        self.global
+            .as_ref()
+            .unwrap()
+            .theme
+            .clone()
+            .unwrap_or_default()
+    }
+
+    pub fn notifications_config(&self) -> NotificationsConfig {
+        self.global
+            .as_ref()
+            .unwrap()
+            .notifications
+            .clone()
+            .unwrap_or_default()
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.global
+            .as_ref()
+            .unwrap()
+            .concurrency
+            .filter(|concurrency| *concurrency > 0)
+            .unwrap_or(crate::concurrent::DEFAULT_LIMIT)
+    }
+
+    pub fn telemetry(&self) -> bool {
+        self.global.as_ref().unwrap().telemetry.unwrap_or_default()
+    }
+
+    /// Turn telemetry on or off in the global configuration. Will persist the file.
+    pub fn set_telemetry(&mut self, enabled: bool) -> Result<()> {
+        self.global.as_mut().unwrap().telemetry = Some(enabled);
+        self.global.save()
+    }
+
+    pub fn aliases(&self) -> BTreeMap<String, String> {
+        self.global
+            .as_ref()
+            .unwrap()
+            .aliases
+            .clone()
+            .unwrap_or_default()
+    }
+
+    /// Add an alias to the global configuration, returning what it stood for before. Will
+    /// persist the file.
+    pub fn set_alias(&mut self, name: String, command: String) -> Result<Option<String>> {
+        let previous = self
+            .global
+            .as_mut()
+            .unwrap()
+            .aliases
+            .get_or_insert_with(Default::default)
+            .insert(name, command);
+        self.global.save()?;
+
+        Ok(previous)
+    }
+
+    /// Remove an alias from the global configuration, returning what it stood for. Will persist
+    /// the file.
+    pub fn remove_alias(&mut self, name: &str) -> Result<Option<String>> {
+        let removed = self
+            .global
+            .as_mut()
+            .unwrap()
+            .aliases
+            .as_mut()
+            .and_then(|aliases| aliases.remove(name));
+        if removed.is_some() {
+            self.global.save()?;
+        }
+
+        Ok(removed)
+    }
+
     /// Set the API key to the global configuration. Will persist the file.
     pub fn set_api_key(&mut self, api_key: ApiKey) -> Result<()> {
         self.global.as_mut().unwrap().set_api_key(api_key);
@@ -397,15 +953,156 @@ impl RequestContext {
             .assets
             .as_ref()
     }
+
+    /// # Panics
+    /// Panics if the project configuration has not been loaded.
+    pub fn environments(&self) -> &[String] {
+        self.project
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .environments
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    /// # Panics
+    /// Panics if the project configuration has not been loaded.
+    pub fn env_vars(&self) -> BTreeMap<String, String> {
+        self.project
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .env_vars
+            .clone()
+            .unwrap_or_default()
+    }
+
+    /// Path of the Shuttle.toml the project configuration is loaded from
+    ///
+    /// # Panics
+    /// Panics if the project configuration has not been loaded.
+    pub fn project_config_path(&self) -> PathBuf {
+        self.project.as_ref().unwrap().manager.path()
+    }
+
+    /// # Panics
+    /// Panics if the project configuration has not been loaded.
+    pub fn required_secrets(&self) -> &[String] {
+        self.project
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .secrets
+            .as_ref()
+            .map(|secrets| secrets.required.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// # Panics
+    /// Panics if the project configuration has not been loaded.
+    pub fn local_run_config(&self) -> LocalRunConfig {
+        self.project
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .local
+            .clone()
+            .unwrap_or_default()
+    }
+
+    /// # Panics
+    /// Panics if the project configuration has not been loaded.
+    pub fn log_forwarding(&self) -> Option<&LogForwarding> {
+        self.project
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .log_forwarding
+            .as_ref()
+    }
+
+    /// # Panics
+    /// Panics if the project configuration has not been loaded.
+    pub fn resource_overrides(&self) -> Option<&ResourceOverrides> {
+        self.project
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .resources
+            .as_ref()
+    }
+
+    /// # Panics
+    /// Panics if the project configuration has not been loaded.
+    pub fn migrations(&self) -> Option<&MigrationsConfig> {
+        self.project
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .migrations
+            .as_ref()
+    }
+
+    /// # Panics
+    /// Panics if the project configuration has not been loaded.
+    pub fn external_resources(&self) -> Option<&ExternalResources> {
+        self.project
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .external
+            .as_ref()
+    }
+
+    /// # Panics
+    /// Panics if the project configuration has not been loaded.
+    pub fn domains(&self) -> Option<&Domains> {
+        self.project
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .domains
+            .as_ref()
+    }
+
+    /// # Panics
+    /// Panics if the project configuration has not been loaded.
+    pub fn cron(&self) -> Option<&CronJobs> {
+        self.project
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .cron
+            .as_ref()
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+    use std::net::IpAddr;
     use std::path::PathBuf;
 
+    use shuttle_common::database::{self, AwsRdsEngine, SharedEngine};
+    use shuttle_common::models::deployment::DatabaseTier;
+
     use crate::{args::ProjectArgs, config::RequestContext};
 
-    use super::{Config, LocalConfigManager, ProjectConfig};
+    use super::{
+        client, insert_in_table, remove_from_table, save_project_name, Config, GlobalConfig,
+        GlobalConfigManager, LocalConfigManager, Origin, ProjectConfig, Setting,
+    };
 
     fn path_from_workspace_root(path: &str) -> PathBuf {
         PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap())
@@ -452,4 +1149,350 @@ mod tests {
 
         assert_eq!(unwrap_project_name(&local_config), "my-fancy-project-name");
     }
+
+    #[test]
+    fn settings_origins() {
+        let mut global = Config::new(GlobalConfigManager);
+        global.replace(GlobalConfig {
+            api_url: Some("https://api.example.com".to_string()),
+            concurrency: Some(4),
+            ..Default::default()
+        });
+        let mut ctx = RequestContext {
+            global,
+            project: None,
+            project_name_origin: None,
+            api_url: None,
+            arg_origins: BTreeMap::new(),
+            http: client::http_client(),
+        };
+        let setting = |ctx: &RequestContext, name: &str| {
+            ctx.settings()
+                .into_iter()
+                .find(|setting| setting.name == name)
+                .unwrap()
+        };
+
+        assert_eq!(
+            setting(&ctx, "api-url"),
+            Setting::new(
+                "api-url",
+                Some("https://api.example.com"),
+                Origin::GlobalConfig
+            )
+        );
+        assert_eq!(
+            setting(&ctx, "concurrency"),
+            Setting::new("concurrency", Some(4), Origin::GlobalConfig)
+        );
+        assert_eq!(
+            setting(&ctx, "logs.timezone"),
+            Setting::new("logs.timezone", None::<String>, Origin::Default)
+        );
+
+        // The flag wins over the global configuration
+        ctx.set_api_url(Some("http://localhost:8001".to_string()));
+        ctx.set_arg_origins(
+            [(
+                "api_url".to_string(),
+                Origin::Env("SHUTTLE_API".to_string()),
+            )]
+            .into(),
+        );
+        let api_url = setting(&ctx, "api-url");
+        assert_eq!(api_url.value.as_deref(), Some("http://localhost:8001"));
+        assert_eq!(api_url.origin.to_string(), "env SHUTTLE_API");
+    }
+
+    #[test]
+    fn local_run_containers() {
+        let config: ProjectConfig = toml::from_str(
+            r#"
+            [local.postgres]
+            version = "16"
+            keep = false
+
+            [local.object-storage]
+            image = "quay.io/minio/minio:latest"
+            "#,
+        )
+        .unwrap();
+        let local = config.local.unwrap();
+        let postgres = local.postgres.clone().unwrap();
+        assert_eq!(
+            postgres.image("docker.io/library/postgres").as_deref(),
+            Some("docker.io/library/postgres:16")
+        );
+        assert!(!postgres.keep());
+
+        let (repository, object_storage) = local.container("object_storage").unwrap();
+        assert_eq!(
+            object_storage.image(repository).as_deref(),
+            Some("quay.io/minio/minio:latest")
+        );
+        assert!(local.container("redis").is_none());
+
+        let default = super::LocalContainerConfig::default();
+        assert_eq!(default.image("docker.io/library/postgres"), None);
+        assert!(default.keep());
+    }
+
+    #[test]
+    fn local_run_address() {
+        let config: ProjectConfig = toml::from_str(
+            r#"
+            [local]
+            port = 8080
+            host = "0.0.0.0"
+            data_dir = "../data"
+            "#,
+        )
+        .unwrap();
+        let local = config.local.unwrap();
+        assert_eq!(local.port, Some(8080));
+        assert_eq!(local.host, Some(IpAddr::from([0, 0, 0, 0])));
+        assert_eq!(local.data_dir, Some(PathBuf::from("../data")));
+    }
+
+    #[test]
+    fn resource_overrides() {
+        let config: ProjectConfig = toml::from_str(
+            r#"
+            [resources.postgres]
+            version = "16"
+            tier = "small"
+            extensions = ["vector", "pg_trgm"]
+            backup-retention-days = 14
+            "#,
+        )
+        .unwrap();
+        let resources = config.resources.unwrap();
+        let postgres = resources.postgres.clone().unwrap();
+        assert_eq!(postgres.version.as_deref(), Some("16"));
+        assert_eq!(postgres.tier, Some(DatabaseTier::Small));
+        assert_eq!(postgres.extensions, vec!["vector", "pg_trgm"]);
+        assert_eq!(resources.validate(), Ok(()));
+
+        let invalid = |toml: &str| {
+            toml::from_str::<ProjectConfig>(toml)
+                .unwrap()
+                .resources
+                .unwrap()
+                .validate()
+                .unwrap_err()
+        };
+        assert!(invalid("[resources.postgres]\nversion = \"9\"").contains("not supported"));
+        assert!(invalid("[resources.mysql]\nextensions = [\"vector\"]").contains("extensions"));
+        assert!(invalid("[resources.postgres]\nbackup-retention-days = 90").contains("90"));
+
+        // Typos are caught when the file is read
+        assert!(
+            toml::from_str::<ProjectConfig>("[resources.postgress]\nversion = \"16\"").is_err()
+        );
+        assert!(toml::from_str::<ProjectConfig>("[resources.postgres]\ntier = \"huge\"").is_err());
+    }
+
+    #[test]
+    fn external_resources() {
+        let config: ProjectConfig = toml::from_str(
+            r#"
+            [external.analytics-db]
+            type = "database::aws_rds::postgres"
+            secret = "ANALYTICS_DATABASE_URL"
+
+            [external.payments]
+            secret = "PAYMENTS_API_URL"
+            env = "PAYMENTS_URL"
+            "#,
+        )
+        .unwrap();
+        let external = config.external.unwrap();
+        assert_eq!(external.validate(), Ok(()));
+        assert_eq!(
+            external.for_database(database::Type::AwsRds(AwsRdsEngine::Postgres)),
+            Some(("analytics-db", &external.0["analytics-db"]))
+        );
+        assert_eq!(
+            external.for_database(database::Type::Shared(SharedEngine::Postgres)),
+            None
+        );
+        assert_eq!(
+            external.env_vars(),
+            vec![
+                ("ANALYTICS_DB_URL".to_string(), "ANALYTICS_DATABASE_URL"),
+                ("PAYMENTS_URL".to_string(), "PAYMENTS_API_URL"),
+            ]
+        );
+
+        let invalid = |toml: &str| {
+            toml::from_str::<ProjectConfig>(toml)
+                .unwrap()
+                .external
+                .unwrap()
+                .validate()
+                .unwrap_err()
+        };
+        assert!(
+            invalid("[external.db]\ntype = \"secrets\"\nsecret = \"URL\"")
+                .contains("not a database")
+        );
+        assert!(invalid("[external.db]\nsecret = \"URL\"\nenv = \"1URL\"").contains("1URL"));
+        assert!(invalid(
+            "[external.a]\ntype = \"postgres\"\nsecret = \"A\"\n\
+            [external.b]\ntype = \"database::shared::postgres\"\nsecret = \"B\""
+        )
+        .contains("already stands in"));
+    }
+
+    #[test]
+    fn domains() {
+        let config: ProjectConfig = toml::from_str(
+            r#"
+            [domains]
+            aliases = ["www.example.com", "api.example.com"]
+            redirects = { "example.com" = "www.example.com" }
+            "#,
+        )
+        .unwrap();
+        let domains = config.domains.unwrap();
+        assert_eq!(domains.validate(), Ok(()));
+        assert_eq!(
+            domains.all().collect::<Vec<_>>(),
+            ["www.example.com", "api.example.com", "example.com"]
+        );
+
+        let invalid = |toml: &str| {
+            toml::from_str::<ProjectConfig>(toml)
+                .unwrap()
+                .domains
+                .unwrap()
+                .validate()
+                .unwrap_err()
+        };
+        assert!(invalid("[domains]\naliases = [\"example\"]").contains("not a valid domain"));
+        assert!(invalid("[domains]\naliases = [\"-a.example.com\"]").contains("not a valid"));
+        assert!(invalid(
+            "[domains]\naliases = [\"example.com\"]\nredirects = { \"example.com\" = \"example.com\" }"
+        )
+        .contains("more than once"));
+        assert!(
+            invalid("[domains]\nredirects = { \"example.com\" = \"www.example.com\" }")
+                .contains("not one of the `aliases`")
+        );
+    }
+
+    #[test]
+    fn cron() {
+        let config: ProjectConfig = toml::from_str(
+            r#"
+            [cron.nightly-cleanup]
+            schedule = "0 3 * * *"
+            path = "/tasks/cleanup"
+
+            [cron.report]
+            schedule = "*/15 9-17 * * mon-fri"
+            command = "report --email"
+            "#,
+        )
+        .unwrap();
+        let cron = config.cron.unwrap();
+        assert_eq!(cron.validate(), Ok(()));
+        assert_eq!(cron.0["nightly-cleanup"].target(), "POST /tasks/cleanup");
+
+        let invalid = |toml: &str| {
+            toml::from_str::<ProjectConfig>(toml)
+                .unwrap()
+                .cron
+                .unwrap()
+                .validate()
+                .unwrap_err()
+        };
+        assert!(invalid("[cron.a]\nschedule = \"0 3 * *\"\npath = \"/a\"").contains("five"));
+        assert!(
+            invalid("[cron.a]\nschedule = \"60 * * * *\"\npath = \"/a\"")
+                .contains("not between 0 and 59")
+        );
+        assert!(
+            invalid("[cron.a]\nschedule = \"0 0 * foo *\"\npath = \"/a\"")
+                .contains("not a valid month")
+        );
+        assert!(invalid("[cron.a]\nschedule = \"@daily\"").contains("either a `path`"));
+        assert!(invalid("[cron.a]\nschedule = \"@daily\"\npath = \"a\"").contains("start with"));
+        assert!(
+            invalid("[cron.Nightly]\nschedule = \"@daily\"\npath = \"/a\"")
+                .contains("not a valid job name")
+        );
+    }
+
+    #[test]
+    fn migrations() {
+        let config: ProjectConfig = toml::from_str(
+            r#"
+            [migrations]
+            source = "db/migrations"
+            run-on-deploy = true
+            "#,
+        )
+        .unwrap();
+        let migrations = config.migrations.unwrap();
+        assert_eq!(migrations.source, Some(PathBuf::from("db/migrations")));
+        assert!(migrations.run_on_deploy);
+
+        let config: ProjectConfig = toml::from_str("[migrations]").unwrap();
+        assert!(!config.migrations.unwrap().run_on_deploy);
+    }
+
+    #[test]
+    fn local_run_seed() {
+        let config: ProjectConfig = toml::from_str(
+            r#"
+            [local.seed]
+            sql = ["fixtures/schema.sql", "fixtures/data.sql"]
+            "#,
+        )
+        .unwrap();
+        let seed = config.local.unwrap().seed.unwrap();
+        assert_eq!(
+            seed.sql,
+            vec![
+                PathBuf::from("fixtures/schema.sql"),
+                PathBuf::from("fixtures/data.sql")
+            ]
+        );
+        assert_eq!(seed.bin, None);
+
+        let config: ProjectConfig = toml::from_str(
+            r#"
+            [local.seed]
+            bin = "seed"
+            "#,
+        )
+        .unwrap();
+        let seed = config.local.unwrap().seed.unwrap();
+        assert!(seed.sql.is_empty());
+        assert_eq!(seed.bin.as_deref(), Some("seed"));
+    }
+
+    #[test]
+    fn table_editing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Shuttle.toml");
+        std::fs::write(&path, "# my project\nname = \"my-api\"\n").unwrap();
+
+        let workers = |count: &str| toml_edit::value(count);
+        assert!(!insert_in_table(&path, "env-vars", "WORKERS", workers("4")).unwrap());
+        assert!(insert_in_table(&path, "env-vars", "WORKERS", workers("8")).unwrap());
+        save_project_name(&path, "my-other-api").unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with("# my project\n"));
+        let parsed: toml::Value = toml::from_str(&contents).unwrap();
+        assert_eq!(parsed["name"].as_str(), Some("my-other-api"));
+        assert_eq!(parsed["env-vars"]["WORKERS"].as_str(), Some("8"));
+
+        assert!(remove_from_table(&path, "env-vars", "WORKERS").unwrap());
+        assert!(!remove_from_table(&path, "env-vars", "WORKERS").unwrap());
+        assert!(!remove_from_table(&path, "cron", "cleanup").unwrap());
+        // The table is dropped once it is empty
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("env-vars"));
+    }
 }
@@ -0,0 +1,152 @@
+//! Editing of the scheduled jobs in the `[cron.<name>]` tables of Shuttle.toml
+
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use crossterm::style::Stylize;
+use shuttle_common::models::deployment::{validate_cron_job_name, CronJob};
+use toml_edit::{value, Item, Table};
+
+use crate::errors::{self, ErrorCode};
+use crate::{config, output, CommandOutcome, Shuttle};
+
+const TABLE: &str = "cron";
+
+/// Add a job to the Shuttle.toml at `path`, creating the file if needed. A job with the same name
+/// is replaced. Returns whether there was one.
+pub fn add_job(path: &Path, name: &str, job: &CronJob) -> Result<bool> {
+    validate_cron_job_name(name)
+        .and_then(|_| job.validate())
+        .map_err(|err| anyhow!(err))?;
+
+    let mut entry = Table::new();
+    entry["schedule"] = value(&job.schedule);
+    if let Some(job_path) = &job.path {
+        entry["path"] = value(job_path);
+    }
+    if let Some(command) = &job.command {
+        entry["command"] = value(command);
+    }
+
+    config::insert_in_table(path, TABLE, name, Item::Table(entry))
+}
+
+/// Remove a job from the Shuttle.toml at `path`. Returns whether it was there.
+pub fn remove_job(path: &Path, name: &str) -> Result<bool> {
+    config::remove_from_table(path, TABLE, name)
+}
+
+impl Shuttle {
+    pub(crate) fn cron_list(&self) -> Result<CommandOutcome> {
+        let Some(jobs) = self.ctx.cron().filter(|jobs| !jobs.0.is_empty()) else {
+            println!(
+                "No scheduled jobs are set in {}",
+                self.ctx.project_config_path().display()
+            );
+            return Ok(CommandOutcome::Ok);
+        };
+
+        output::print(self.output, jobs, |_| {
+            let width = jobs.0.keys().map(String::len).max().unwrap_or_default();
+            let schedule_width = jobs
+                .0
+                .values()
+                .map(|job| job.schedule.len())
+                .max()
+                .unwrap_or_default();
+            jobs.0
+                .iter()
+                .map(|(name, job)| {
+                    format!(
+                        "{name:width$}  {:schedule_width$}  {}",
+                        job.schedule,
+                        job.target()
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })?;
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) fn cron_add(&self, name: &str, job: CronJob) -> Result<CommandOutcome> {
+        let path = self.ctx.project_config_path();
+        let replaced = add_job(&path, name, &job)
+            .map_err(|err| errors::with_code(err, ErrorCode::Validation))?;
+
+        if replaced {
+            println!("Replaced {name} in {}", path.display());
+        } else {
+            println!("Added {name} to {}", path.display());
+        }
+        println!(
+            "{}",
+            "Deployments do not run scheduled jobs yet, and are refused while [cron] is set."
+                .yellow()
+        );
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) fn cron_remove(&self, name: &str) -> Result<CommandOutcome> {
+        let path = self.ctx.project_config_path();
+        if remove_job(&path, name)? {
+            println!("Removed {name} from {}", path.display());
+        } else {
+            println!("There is no job named {name} in {}", path.display());
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use super::*;
+
+    #[test]
+    fn add_and_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Shuttle.toml");
+        std::fs::write(&path, "# my project\nname = \"my-api\"\n").unwrap();
+
+        let cleanup = CronJob {
+            schedule: "0 3 * * *".to_string(),
+            path: Some("/tasks/cleanup".to_string()),
+            command: None,
+        };
+        assert!(!add_job(&path, "cleanup", &cleanup).unwrap());
+        let report = CronJob {
+            schedule: "@weekly".to_string(),
+            path: None,
+            command: Some("report".to_string()),
+        };
+        assert!(!add_job(&path, "report", &report).unwrap());
+        assert!(add_job(&path, "report", &report).unwrap());
+
+        let contents = read_to_string(&path).unwrap();
+        assert!(contents.starts_with("# my project\n"));
+        assert!(contents.contains("[cron.cleanup]\n"));
+        assert!(!contents.contains("[cron]\n"));
+        let parsed: toml::Value = toml::from_str(&contents).unwrap();
+        assert_eq!(
+            parsed[TABLE]["cleanup"]
+                .clone()
+                .try_into::<CronJob>()
+                .unwrap(),
+            cleanup
+        );
+
+        assert!(remove_job(&path, "report").unwrap());
+        assert!(!read_to_string(&path).unwrap().contains("[cron.report]"));
+
+        let invalid = CronJob {
+            schedule: "every day".to_string(),
+            ..cleanup
+        };
+        assert!(add_job(&path, "cleanup", &invalid).is_err());
+    }
+}
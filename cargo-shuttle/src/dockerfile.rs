@@ -0,0 +1,120 @@
+//! Generation of a Dockerfile that builds and runs a project like deployments do, for `cargo
+//! shuttle generate dockerfile`.
+//!
+//! The image is the one deployments are built in, with the same build tools, and the service is
+//! built in release mode like on the platform. Services are started by `cargo shuttle run` in the
+//! container, since the runtime in their binary waits to be told what to provision and when to
+//! start.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use indoc::formatdoc;
+use shuttle_service::builder::PRODUCTION_BUILD_IMAGE;
+
+use crate::{prompt, CommandOutcome, Shuttle, DEFAULT_LOCAL_PORT, VERSION};
+
+/// Keeps the secrets and the build artifacts of the host out of the image
+pub const DOCKERIGNORE: &str = "\
+/target
+.shuttle/
+.shuttle-storage
+Secrets*.toml
+";
+
+pub fn generate(project_name: &str, version: &str, port: u16) -> String {
+    formatdoc! {r#"
+        # Builds and runs {project_name} the way deployments on Shuttle do.
+        # Generated by cargo-shuttle {version}.
+        #
+        # Secrets are not part of the image. Mount them when running it:
+        #   docker build -t {project_name} .
+        #   docker run -p {port}:{port} -v "$PWD/Secrets.toml:/app/Secrets.toml:ro" {project_name}
+        #
+        # Databases and other resources are not started in the container. Point the service to
+        # ones that run elsewhere with the [external] table of Shuttle.toml.
+
+        FROM {PRODUCTION_BUILD_IMAGE}
+
+        # The build tools deployments have
+        RUN apt-get update \
+            && apt-get install -y llvm-dev libclang-dev clang cmake lld mold protobuf-compiler \
+            && rm -rf /var/lib/apt/lists/*
+        RUN rustup target add wasm32-unknown-unknown
+        RUN curl -L --proto '=https' --tlsv1.2 -sSf https://raw.githubusercontent.com/cargo-bins/cargo-binstall/main/install-from-binstall-release.sh | bash \
+            && cargo binstall -y --locked cargo-shuttle@{version}
+
+        WORKDIR /app
+        COPY . .
+        RUN cargo build --release --jobs 4
+
+        EXPOSE {port}
+        CMD ["cargo", "shuttle", "run", "--release", "--offline", "--external", "--port", "{port}"]
+    "#}
+}
+
+impl Shuttle {
+    pub(crate) fn generate_dockerfile(
+        &self,
+        output: Option<PathBuf>,
+        force: bool,
+    ) -> Result<CommandOutcome> {
+        let working_directory = self.ctx.working_directory();
+        let path = output.unwrap_or_else(|| working_directory.join("Dockerfile"));
+        if path.exists()
+            && !force
+            && !prompt::confirm(
+                format!("{} exists. Overwrite it?", path.display()),
+                false,
+                "Pass --force to overwrite it.",
+            )?
+        {
+            bail!(
+                "{} already exists. Use `--force` to overwrite it.",
+                path.display()
+            );
+        }
+
+        let port = self
+            .ctx
+            .local_run_config()
+            .port
+            .unwrap_or(DEFAULT_LOCAL_PORT);
+        std::fs::write(&path, generate(self.ctx.project_name(), VERSION, port))
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        println!("Wrote {}", path.display());
+
+        // The build context is the directory of the Dockerfile
+        let dockerignore = path
+            .parent()
+            .unwrap_or(working_directory)
+            .join(".dockerignore");
+        if !dockerignore.exists() {
+            std::fs::write(&dockerignore, DOCKERIGNORE)
+                .with_context(|| format!("failed to write {}", dockerignore.display()))?;
+            println!(
+                "Wrote {}, which keeps the secrets out of the image",
+                dockerignore.display()
+            );
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dockerfile() {
+        let dockerfile = generate("my-app", "0.43.0", 8000);
+        assert!(dockerfile.starts_with("# Builds and runs my-app"));
+        assert!(dockerfile.contains(&format!("FROM {PRODUCTION_BUILD_IMAGE}\n")));
+        assert!(dockerfile.contains("cargo binstall -y --locked cargo-shuttle@0.43.0"));
+        assert!(dockerfile.ends_with(
+            "CMD [\"cargo\", \"shuttle\", \"run\", \"--release\", \"--offline\", \"--external\", \"--port\", \"8000\"]\n"
+        ));
+        assert!(DOCKERIGNORE.contains("Secrets*.toml"));
+    }
+}
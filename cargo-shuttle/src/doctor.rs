@@ -0,0 +1,324 @@
+//! Checks of the environment cargo-shuttle runs in, for `cargo shuttle doctor`.
+//!
+//! Every check passes, warns about something that only limits some commands, or fails, and
+//! says how to fix what it found.
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use bollard::Docker;
+use serde::Serialize;
+
+use crate::args::ProjectArgs;
+use crate::client::Client;
+use crate::progress::Progress;
+use crate::style::{self, Tone};
+use crate::{output, CommandOutcome, Shuttle};
+
+/// The `rust-version` of the Shuttle crates
+const MIN_RUST_VERSION: semver::Version = semver::Version::new(1, 75, 0);
+/// Free space below which building and running projects starts failing
+const LOW_DISK_SPACE: u64 = 1024 * 1024 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+    pub fix: Option<String>,
+}
+
+impl Check {
+    pub fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Pass,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    pub fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    pub fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// The checks as lines with their status, and how to fix the ones that did not pass
+pub fn format_checks(checks: &[Check]) -> String {
+    checks
+        .iter()
+        .map(|check| {
+            let status = match check.status {
+                Status::Pass => style::status("Pass", Tone::Success),
+                Status::Warn => style::status("Warn", Tone::Warning),
+                Status::Fail => style::status("Fail", Tone::Failure),
+            };
+            match &check.fix {
+                Some(fix) => format!("{status} {}\n{:>12} {fix}", check.detail, "Fix:"),
+                None => format!("{status} {}", check.detail),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// How many of the checks failed
+pub fn failures(checks: &[Check]) -> usize {
+    checks
+        .iter()
+        .filter(|check| check.status == Status::Fail)
+        .count()
+}
+
+/// The version in the output of `rustc --version`, like `rustc 1.76.0 (07dca489a 2024-02-04)`
+fn parse_rustc_version(output: &str) -> Option<semver::Version> {
+    output.split_whitespace().nth(1)?.parse().ok()
+}
+
+pub async fn rust() -> Vec<Check> {
+    let rustc = tokio::process::Command::new("rustc")
+        .arg("--version")
+        .output()
+        .await;
+    let rustc = match rustc {
+        Ok(output) if output.status.success() => {
+            let output = String::from_utf8_lossy(&output.stdout).to_string();
+            match parse_rustc_version(&output) {
+                Some(version) if version < MIN_RUST_VERSION => Check::fail(
+                    "rustc",
+                    format!(
+                        "rustc {version} is older than {MIN_RUST_VERSION}, which Shuttle needs"
+                    ),
+                    "Run `rustup update stable`",
+                ),
+                Some(version) => Check::pass("rustc", format!("rustc {version}")),
+                None => Check::warn(
+                    "rustc",
+                    format!(
+                        "Could not tell the version of rustc from `{}`",
+                        output.trim()
+                    ),
+                    format!("Make sure rustc {MIN_RUST_VERSION} or newer is installed"),
+                ),
+            }
+        }
+        _ => Check::fail(
+            "rustc",
+            "rustc was not found",
+            "Install Rust from https://rustup.rs",
+        ),
+    };
+
+    let cargo = match tokio::process::Command::new("cargo")
+        .arg("--version")
+        .output()
+        .await
+    {
+        Ok(output) if output.status.success() => Check::pass(
+            "cargo",
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ),
+        _ => Check::fail(
+            "cargo",
+            "cargo was not found",
+            "Install Rust from https://rustup.rs",
+        ),
+    };
+
+    vec![rustc, cargo]
+}
+
+pub async fn docker() -> Check {
+    let version = match Docker::connect_with_local_defaults() {
+        Ok(docker) => tokio::time::timeout(Duration::from_secs(5), docker.version())
+            .await
+            .map_err(|_| "timed out".to_string())
+            .and_then(|version| version.map_err(|err| err.to_string())),
+        Err(err) => Err(err.to_string()),
+    };
+
+    match version {
+        Ok(version) => Check::pass(
+            "docker",
+            format!("Docker {}", version.version.unwrap_or_default()),
+        ),
+        Err(err) => Check::warn(
+            "docker",
+            format!("Docker is not reachable ({err}). Local runs cannot provision databases."),
+            "Start Docker, or install it from https://docs.docker.com/get-docker/",
+        ),
+    }
+}
+
+pub async fn api(client: &Client, api_url: &str) -> Check {
+    match client.get_api_versions().await {
+        Ok(versions) => Check::pass(
+            "api",
+            format!(
+                "{api_url} is reachable, the latest cargo-shuttle is {}",
+                versions.cargo_shuttle
+            ),
+        ),
+        Err(err) => Check::fail(
+            "api",
+            format!("{api_url} is not reachable: {err}"),
+            "Check the network connection and proxy settings, or the URL given with --api-url",
+        ),
+    }
+}
+
+/// The free space of the file system of `dir`, from the POSIX output of `df -Pk`
+fn parse_df_available(output: &str) -> Option<u64> {
+    let available = output.lines().nth(1)?.split_whitespace().nth(3)?;
+
+    available.parse::<u64>().ok().map(|kb| kb * 1024)
+}
+
+pub async fn disk_space(name: &'static str, dir: &Path) -> Check {
+    // The nearest directory that exists, for directories that were not created yet
+    let Some(dir) = dir.ancestors().find(|dir| dir.exists()) else {
+        return Check::warn(
+            name,
+            format!("{} does not exist", dir.display()),
+            "Check that the home directory exists",
+        );
+    };
+    let available = tokio::process::Command::new("df")
+        .arg("-Pk")
+        .arg(dir)
+        .output()
+        .await
+        .ok()
+        .and_then(|output| parse_df_available(&String::from_utf8_lossy(&output.stdout)));
+
+    match available {
+        Some(available) if available < LOW_DISK_SPACE => Check::fail(
+            name,
+            format!(
+                "Only {} MB is free for {}",
+                available / 1_000_000,
+                dir.display()
+            ),
+            "Free up some disk space, like with `cargo clean` or `cargo shuttle local clean`",
+        ),
+        Some(available) => Check::pass(
+            name,
+            format!(
+                "{:.1} GB is free for {}",
+                available as f64 / 1e9,
+                dir.display()
+            ),
+        ),
+        None => Check::warn(
+            name,
+            format!("Could not tell the free space for {}", dir.display()),
+            "Make sure there is at least 1 GB of free disk space",
+        ),
+    }
+}
+
+impl Shuttle {
+    pub(crate) async fn doctor(
+        &mut self,
+        project_args: ProjectArgs,
+        offline: bool,
+    ) -> Result<CommandOutcome> {
+        let progress = Progress::spinner("Checking the environment");
+        let mut checks = rust().await;
+        checks.push(docker().await);
+
+        let api_url = self.ctx.api_url();
+        checks.push(if offline {
+            Check::warn(
+                "api",
+                "Skipped because of --offline",
+                "Run the command without --offline to check it",
+            )
+        } else {
+            api(&self.ctx.api_client(), &api_url).await
+        });
+        checks.push(match self.ctx.api_key() {
+            Ok(_) => Check::pass("api-key", "An API key is set"),
+            Err(err) => Check::fail("api-key", format!("{err:#}"), "Run `cargo shuttle login`"),
+        });
+
+        checks.push(match self.load_project(&project_args) {
+            Ok(()) => match self.validate_project_config() {
+                Ok(()) => Check::pass(
+                    "config",
+                    format!("{} is valid", self.ctx.project_config_path().display()),
+                ),
+                Err(err) => Check::fail("config", err.to_string(), "Fix Shuttle.toml"),
+            },
+            Err(_) if project_args.workspace_path().is_err() => Check::warn(
+                "config",
+                "Not in a project, so there is no Shuttle.toml to check",
+                "Run the command in a project to check its Shuttle.toml",
+            ),
+            Err(err) => Check::fail("config", format!("{err:#}"), "Fix Shuttle.toml"),
+        });
+
+        if let Some(dir) = dirs::config_dir() {
+            checks.push(disk_space("config-disk-space", &dir.join("shuttle")).await);
+        }
+        if let Some(dir) = dirs::cache_dir() {
+            checks.push(disk_space("cache-disk-space", &dir).await);
+        }
+        progress.finish_and_clear();
+
+        output::print(self.output, &checks, |_| format_checks(&checks))?;
+
+        let failed = failures(&checks);
+        if failed > 0 {
+            bail!("{failed} of the checks failed");
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tool_output() {
+        assert_eq!(
+            parse_rustc_version("rustc 1.76.0 (07dca489a 2024-02-04)\n"),
+            Some(semver::Version::new(1, 76, 0))
+        );
+        assert_eq!(
+            parse_rustc_version("rustc 1.78.0-nightly (ef324565d 2024-02-27)"),
+            Some(semver::Version::parse("1.78.0-nightly").unwrap())
+        );
+        assert_eq!(parse_rustc_version("rustc"), None);
+
+        let df = "Filesystem     1024-blocks      Used Available Capacity Mounted on\n\
+                  /dev/nvme0n1p2   490617784 312345678 153285468      68% /\n";
+        assert_eq!(parse_df_available(df), Some(153285468 * 1024));
+        assert_eq!(parse_df_available(""), None);
+    }
+}
@@ -0,0 +1,257 @@
+//! Checks of the DNS records of a custom domain, for `cargo shuttle domain check`.
+//!
+//! A custom domain works when it is a CNAME of the default domain of its project, or when its A
+//! and AAAA records have the addresses of that domain. The records are resolved from this
+//! machine, so changes can take until the TTL of the old records runs out to show.
+
+use std::collections::BTreeSet;
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use anyhow::{bail, Context, Result};
+use trust_dns_resolver::error::{ResolveError, ResolveErrorKind};
+use trust_dns_resolver::proto::rr::{RData, RecordType};
+use trust_dns_resolver::TokioAsyncResolver;
+
+use crate::doctor::{self, Check};
+use crate::progress::Progress;
+use crate::{output, CommandOutcome, Shuttle};
+
+/// The domain projects are served on by default, as `<project>.shuttleapp.rs`
+pub const DEFAULT_DOMAIN: &str = "shuttleapp.rs";
+
+/// The records of a domain that decide where it points
+#[derive(Debug, Default, PartialEq)]
+pub struct Records {
+    pub cname: Option<String>,
+    pub a: BTreeSet<Ipv4Addr>,
+    pub aaaa: BTreeSet<Ipv6Addr>,
+}
+
+pub async fn resolve(resolver: &TokioAsyncResolver, domain: &str) -> Result<Records> {
+    let cname = found(resolver.lookup(domain, RecordType::CNAME).await)?.and_then(|lookup| {
+        lookup.iter().find_map(|record| match record {
+            RData::CNAME(name) => Some(normalize(&name.0.to_string())),
+            _ => None,
+        })
+    });
+    // These follow the CNAME, if any
+    let a = found(resolver.ipv4_lookup(domain).await)?
+        .map(|lookup| lookup.iter().map(|a| a.0).collect())
+        .unwrap_or_default();
+    let aaaa = found(resolver.ipv6_lookup(domain).await)?
+        .map(|lookup| lookup.iter().map(|aaaa| aaaa.0).collect())
+        .unwrap_or_default();
+
+    Ok(Records { cname, a, aaaa })
+}
+
+pub fn resolver() -> Result<TokioAsyncResolver> {
+    TokioAsyncResolver::tokio_from_system_conf().context("failed to read the DNS configuration")
+}
+
+/// A lookup that found no records, as `None`
+fn found<T>(lookup: Result<T, ResolveError>) -> Result<Option<T>> {
+    match lookup {
+        Ok(lookup) => Ok(Some(lookup)),
+        Err(err) if matches!(err.kind(), ResolveErrorKind::NoRecordsFound { .. }) => Ok(None),
+        Err(err) => Err(err).context("failed to look up DNS records"),
+    }
+}
+
+/// Names without the trailing dot of fully qualified ones, in lowercase
+pub fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_ascii_lowercase()
+}
+
+fn join<T: ToString>(items: impl IntoIterator<Item = T>) -> String {
+    items
+        .into_iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Compare the records of `domain` with the ones of `target`, which it should point at
+pub fn check(domain: &str, records: &Records, target: &str, expected: &Records) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let points_at_target = match &records.cname {
+        Some(cname) if *cname == normalize(target) => {
+            checks.push(Check::pass("cname", format!("CNAME {domain} is {target}")));
+            true
+        }
+        Some(cname) => {
+            checks.push(Check::fail(
+                "cname",
+                format!("CNAME {domain} is {cname}, not {target}"),
+                format!("Change the CNAME record of {domain} to {target}"),
+            ));
+            false
+        }
+        None => false,
+    };
+    if points_at_target {
+        return checks;
+    }
+
+    // The addresses, whether a CNAME led to them or not
+    let stray_a = records.a.difference(&expected.a).collect::<Vec<_>>();
+    if records.a.is_empty() && records.cname.is_none() {
+        checks.push(Check::fail(
+            "a",
+            format!("{domain} has no CNAME or A records"),
+            format!(
+                "Add a CNAME record for {domain} with the value {target}, or A records with {}",
+                join(&expected.a)
+            ),
+        ));
+    } else if !stray_a.is_empty() {
+        checks.push(Check::fail(
+            "a",
+            format!("A {domain} is {}, which is not Shuttle", join(&stray_a)),
+            if records.cname.is_some() {
+                format!("Change the CNAME record of {domain} to {target}")
+            } else {
+                format!(
+                    "Replace the A records of {domain} with ones for {}",
+                    join(&expected.a)
+                )
+            },
+        ));
+    } else if !records.a.is_empty() {
+        checks.push(Check::pass(
+            "a",
+            format!("A {domain} is {}", join(&records.a)),
+        ));
+    }
+
+    // Clients that prefer IPv6 use these over the A records
+    let stray_aaaa = records.aaaa.difference(&expected.aaaa).collect::<Vec<_>>();
+    if !stray_aaaa.is_empty() {
+        checks.push(Check::fail(
+            "aaaa",
+            format!(
+                "AAAA {domain} is {}, which is not Shuttle",
+                join(&stray_aaaa)
+            ),
+            if expected.aaaa.is_empty() {
+                format!("Remove the AAAA records of {domain}")
+            } else {
+                format!(
+                    "Replace the AAAA records of {domain} with ones for {}",
+                    join(&expected.aaaa)
+                )
+            },
+        ));
+    } else if !records.aaaa.is_empty() {
+        checks.push(Check::pass(
+            "aaaa",
+            format!("AAAA {domain} is {}", join(&records.aaaa)),
+        ));
+    }
+
+    checks
+}
+
+impl Shuttle {
+    pub(crate) async fn domain_check(
+        &self,
+        domain: &str,
+        target: Option<String>,
+    ) -> Result<CommandOutcome> {
+        let domain = normalize(domain);
+        let target = match target {
+            Some(target) => normalize(&target),
+            None => format!("{}.{}", self.ctx.project_name(), DEFAULT_DOMAIN),
+        };
+
+        let resolver = resolver()?;
+        let progress = Progress::spinner(format!("Resolving the DNS records of {domain}"));
+        let records = resolve(&resolver, &domain).await;
+        let expected = resolve(&resolver, &target).await;
+        progress.finish_and_clear();
+        let records = records.with_context(|| format!("failed to resolve {domain}"))?;
+        let expected = expected.with_context(|| format!("failed to resolve {target}"))?;
+
+        let checks = check(&domain, &records, &target, &expected);
+        output::print(self.output, &checks, |_| doctor::format_checks(&checks))?;
+
+        let failed = doctor::failures(&checks);
+        if failed > 0 {
+            bail!("{failed} of the DNS records of {domain} need fixing to point at {target}");
+        }
+        output::hint(
+            self.output,
+            format!("{domain} points at {target}. DNS changes can take a while to reach everyone."),
+        );
+
+        Ok(CommandOutcome::Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::doctor::Status;
+
+    fn statuses(checks: &[Check]) -> Vec<(&str, Status)> {
+        checks
+            .iter()
+            .map(|check| (check.name, check.status))
+            .collect()
+    }
+
+    #[test]
+    fn checks_records() {
+        let target = "my-project.shuttleapp.rs";
+        let expected = Records {
+            cname: None,
+            a: [Ipv4Addr::new(203, 0, 113, 7)].into(),
+            aaaa: BTreeSet::new(),
+        };
+
+        let cname = Records {
+            cname: Some("my-project.shuttleapp.rs".to_string()),
+            ..Default::default()
+        };
+        let checks = check("www.example.com", &cname, target, &expected);
+        assert_eq!(statuses(&checks), [("cname", Status::Pass)]);
+
+        let elsewhere = Records {
+            cname: Some("example.netlify.app".to_string()),
+            a: [Ipv4Addr::new(198, 51, 100, 1)].into(),
+            aaaa: BTreeSet::new(),
+        };
+        let checks = check("www.example.com", &elsewhere, target, &expected);
+        assert_eq!(
+            statuses(&checks),
+            [("cname", Status::Fail), ("a", Status::Fail)]
+        );
+
+        let a = Records {
+            cname: None,
+            a: expected.a.clone(),
+            aaaa: [Ipv6Addr::LOCALHOST].into(),
+        };
+        let checks = check("example.com", &a, target, &expected);
+        assert_eq!(
+            statuses(&checks),
+            [("a", Status::Pass), ("aaaa", Status::Fail)]
+        );
+        assert_eq!(
+            checks[1].fix.as_deref(),
+            Some("Remove the AAAA records of example.com")
+        );
+
+        let checks = check("example.com", &Records::default(), target, &expected);
+        assert_eq!(statuses(&checks), [("a", Status::Fail)]);
+    }
+
+    #[test]
+    fn normalizes_names() {
+        assert_eq!(
+            normalize("My-Project.shuttleapp.rs."),
+            "my-project.shuttleapp.rs"
+        );
+    }
+}
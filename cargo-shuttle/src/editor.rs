@@ -0,0 +1,424 @@
+//! The JSON-RPC server of `cargo shuttle lsp`, for editor extensions to show the state of a
+//! project without scraping the output of other commands.
+//!
+//! Messages are framed like in the Language Server Protocol, with a `Content-Length` header, so
+//! the JSON-RPC clients of VS Code and Neovim talk to it like to a language server. It serves over
+//! stdin and stdout, or over a Unix socket that several editors can share. The requests of a
+//! connection are answered in order.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::args::LspArgs;
+use crate::client::Client;
+use crate::errors::ErrorCode;
+use crate::redact::redact;
+use crate::{error_log, CommandOutcome, Shuttle};
+
+/// Error codes of JSON-RPC
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The start of the range JSON-RPC leaves to servers
+const SERVER_ERROR: i64 = -32000;
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    jsonrpc: String,
+    /// Not set for notifications, which get no response
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct RpcError {
+    code: i64,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+/// A failed request, with the code `cargo shuttle` would have exited with in its data
+impl From<anyhow::Error> for RpcError {
+    fn from(err: anyhow::Error) -> Self {
+        let code = ErrorCode::of(&err);
+
+        Self {
+            code: SERVER_ERROR,
+            message: redact(&format!("{err:#}")),
+            data: Some(json!({ "code": code, "exit_code": code.exit_code() })),
+        }
+    }
+}
+
+/// Answers the requests of a connection
+#[async_trait]
+pub trait Handler {
+    async fn handle(&self, method: &str, params: Value) -> Result<Value, RpcError>;
+}
+
+/// Read the body of the next message, or `None` once the other side is done
+async fn read_message(reader: &mut (impl AsyncBufRead + Unpin)) -> Result<Option<Vec<u8>>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 {
+            if length.is_some() {
+                bail!("the connection closed in the headers of a message");
+            }
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        // Other headers, like Content-Type, have nothing to tell
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                length = Some(
+                    value
+                        .trim()
+                        .parse::<usize>()
+                        .context("invalid Content-Length header")?,
+                );
+            }
+        }
+    }
+
+    let length = length.context("a message has no Content-Length header")?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+
+    Ok(Some(body))
+}
+
+async fn write_message(writer: &mut (impl AsyncWrite + Unpin), response: &Response) -> Result<()> {
+    let body = serde_json::to_vec(response)?;
+    writer
+        .write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes())
+        .await?;
+    writer.write_all(&body).await?;
+    writer.flush().await?;
+
+    Ok(())
+}
+
+/// Answer the requests read from `reader` until the `exit` notification, or until the other side
+/// closes the connection
+pub async fn serve(
+    mut reader: impl AsyncBufRead + Unpin,
+    mut writer: impl AsyncWrite + Unpin,
+    handler: &(impl Handler + Sync),
+) -> Result<()> {
+    while let Some(body) = read_message(&mut reader).await? {
+        let request = match serde_json::from_slice::<Request>(&body) {
+            Ok(request) if request.jsonrpc == "2.0" => request,
+            Ok(request) => {
+                let error = RpcError::new(INVALID_REQUEST, "only JSON-RPC 2.0 is supported");
+                respond(&mut writer, request.id.unwrap_or_default(), Err(error)).await?;
+                continue;
+            }
+            Err(err) => {
+                let error = RpcError::new(PARSE_ERROR, err.to_string());
+                respond(&mut writer, Value::Null, Err(error)).await?;
+                continue;
+            }
+        };
+
+        let Some(id) = request.id else {
+            // Notifications, like `initialized`, only matter for the one that ends the connection
+            if request.method == "exit" {
+                break;
+            }
+            debug!(method = %request.method, "ignoring a notification");
+            continue;
+        };
+        let result = handler.handle(&request.method, request.params).await;
+        respond(&mut writer, id, result).await?;
+    }
+
+    Ok(())
+}
+
+async fn respond(
+    writer: &mut (impl AsyncWrite + Unpin),
+    id: Value,
+    result: Result<Value, RpcError>,
+) -> Result<()> {
+    let (result, error) = match result {
+        Ok(result) => (Some(result), None),
+        Err(error) => (None, Some(error)),
+    };
+    let response = Response {
+        jsonrpc: "2.0",
+        id,
+        result,
+        error,
+    };
+
+    write_message(writer, &response).await
+}
+
+fn params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    // Methods without params take no params as an empty object
+    let params = if params.is_null() { json!({}) } else { params };
+
+    serde_json::from_value(params).map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))
+}
+
+#[derive(Deserialize)]
+struct DeploymentsParams {
+    #[serde(default = "default_limit")]
+    limit: u32,
+}
+
+#[derive(Deserialize)]
+struct LogsParams {
+    /// The latest deployment when not set
+    deployment_id: Option<Uuid>,
+}
+
+#[derive(Deserialize)]
+struct ErrorsParams {
+    #[serde(default = "default_limit")]
+    limit: u32,
+}
+
+#[derive(Deserialize)]
+struct ExplainParams {
+    code: String,
+}
+
+fn default_limit() -> u32 {
+    10
+}
+
+/// The methods of the server, on the project it was started for
+pub struct Methods {
+    pub client: Client,
+    pub project: String,
+}
+
+#[async_trait]
+impl Handler for Methods {
+    async fn handle(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        let project = self.project.as_str();
+        let result = match method {
+            "initialize" => json!({
+                "serverInfo": { "name": "cargo-shuttle", "version": crate::VERSION },
+                "project": project,
+            }),
+            // The connection stays up until `exit`, like for language servers
+            "shutdown" => Value::Null,
+            "shuttle/status" => json!(self.client.get_service(project).await?),
+            "shuttle/deployments" => {
+                let DeploymentsParams { limit } = self::params(params)?;
+                json!(self.client.get_deployments(project, 1, limit).await?)
+            }
+            "shuttle/logs" => {
+                let LogsParams { deployment_id } = self::params(params)?;
+                let deployment_id = match deployment_id {
+                    Some(deployment_id) => deployment_id,
+                    None => {
+                        self.client
+                            .get_deployments(project, 1, 1)
+                            .await?
+                            .first()
+                            .with_context(|| format!("{project} has no deployments"))?
+                            .id
+                    }
+                };
+                json!(self.client.get_logs(project, &deployment_id).await?)
+            }
+            "shuttle/errors" => {
+                let ErrorsParams { limit } = self::params(params)?;
+                json!(error_log::recent(limit as usize)?)
+            }
+            "shuttle/explain" => {
+                let ExplainParams { code } = self::params(params)?;
+                explain(&code)?
+            }
+            _ => {
+                return Err(RpcError::new(
+                    METHOD_NOT_FOUND,
+                    format!("unknown method {method}"),
+                ))
+            }
+        };
+
+        Ok(result)
+    }
+}
+
+/// What an error code means, and what to do about it
+fn explain(code: &str) -> Result<Value, RpcError> {
+    let code = ErrorCode::from_str(code)
+        .map_err(|_| RpcError::new(INVALID_PARAMS, format!("unknown error code {code}")))?;
+
+    Ok(json!({
+        "code": code,
+        "exit_code": code.exit_code(),
+        "explanation": code.explanation(),
+    }))
+}
+
+impl Shuttle {
+    pub(crate) async fn lsp(&self, args: LspArgs) -> Result<CommandOutcome> {
+        let methods = Methods {
+            client: self.client.clone().unwrap(),
+            project: self.ctx.project_name().to_string(),
+        };
+
+        match args.socket {
+            Some(socket) => Self::lsp_socket(socket, methods).await,
+            None => {
+                // Nothing else may be printed to stdout from here on
+                serve(
+                    BufReader::new(tokio::io::stdin()),
+                    tokio::io::stdout(),
+                    &methods,
+                )
+                .await?;
+
+                Ok(CommandOutcome::Ok)
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    async fn lsp_socket(socket: PathBuf, methods: Methods) -> Result<CommandOutcome> {
+        use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+        // Left behind by a server that did not stop cleanly
+        if std::fs::metadata(&socket).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+            std::fs::remove_file(&socket)?;
+        }
+        let listener = tokio::net::UnixListener::bind(&socket)
+            .with_context(|| format!("failed to listen on {}", socket.display()))?;
+        // The server answers with the API key of whoever started it
+        std::fs::set_permissions(&socket, std::fs::Permissions::from_mode(0o600))?;
+        eprintln!("Listening on {}", socket.display());
+
+        let methods = Arc::new(methods);
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, _) = accepted?;
+                    let methods = methods.clone();
+                    tokio::spawn(async move {
+                        let (reader, writer) = stream.into_split();
+                        if let Err(err) =
+                            serve(BufReader::new(reader), writer, &*methods).await
+                        {
+                            debug!(error = %err, "an editor connection failed");
+                        }
+                    });
+                }
+                _ = tokio::signal::ctrl_c() => break,
+            }
+        }
+        let _ = std::fs::remove_file(&socket);
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    #[cfg(not(unix))]
+    async fn lsp_socket(_socket: PathBuf, _methods: Methods) -> Result<CommandOutcome> {
+        bail!("--socket is only supported on Unix, leave it out to serve over stdin and stdout")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::BufReader;
+
+    use super::*;
+
+    struct Echo;
+
+    #[async_trait]
+    impl Handler for Echo {
+        async fn handle(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+            match method {
+                "echo" => Ok(params),
+                "shuttle/explain" => explain(&super::params::<ExplainParams>(params)?.code),
+                _ => Err(RpcError::new(METHOD_NOT_FOUND, "unknown")),
+            }
+        }
+    }
+
+    fn frame(body: &str) -> String {
+        format!("Content-Length: {}\r\n\r\n{body}", body.len())
+    }
+
+    #[tokio::test]
+    async fn serves_requests() {
+        let input = [
+            frame(r#"{"jsonrpc":"2.0","id":1,"method":"echo","params":{"a":1}}"#),
+            frame(r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#),
+            frame(r#"{"jsonrpc":"2.0","id":2,"method":"nope"}"#),
+            frame("not json"),
+            frame(
+                r#"{"jsonrpc":"2.0","id":3,"method":"shuttle/explain","params":{"code":"auth"}}"#,
+            ),
+            frame(r#"{"jsonrpc":"2.0","method":"exit"}"#),
+            // Never read
+            frame(r#"{"jsonrpc":"2.0","id":4,"method":"echo"}"#),
+        ]
+        .concat();
+
+        let mut output = Vec::new();
+        serve(BufReader::new(input.as_bytes()), &mut output, &Echo)
+            .await
+            .unwrap();
+
+        let mut reader = BufReader::new(output.as_slice());
+        let mut responses = Vec::new();
+        while let Some(body) = read_message(&mut reader).await.unwrap() {
+            responses.push(serde_json::from_slice::<Value>(&body).unwrap());
+        }
+
+        assert_eq!(responses.len(), 4);
+        assert_eq!(responses[0]["id"], 1);
+        assert_eq!(responses[0]["result"], json!({ "a": 1 }));
+        assert_eq!(responses[1]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(responses[2]["id"], Value::Null);
+        assert_eq!(responses[2]["error"]["code"], PARSE_ERROR);
+        assert_eq!(responses[3]["result"]["code"], "auth");
+        assert_eq!(responses[3]["result"]["exit_code"], 3);
+    }
+}
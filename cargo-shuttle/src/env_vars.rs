@@ -0,0 +1,122 @@
+//! Editing of the plain (non-secret) environment variables in the `[env-vars]` table of
+//! Shuttle.toml
+
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use toml_edit::value;
+
+use crate::{config, output, CommandOutcome, Shuttle};
+
+const TABLE: &str = "env-vars";
+
+fn validate_name(name: &str) -> Result<()> {
+    let mut chars = name.chars();
+    let valid_start = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    if !valid_start || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        bail!(
+            "invalid environment variable name `{name}`. \
+            Use letters, digits and underscores, not starting with a digit"
+        );
+    }
+
+    Ok(())
+}
+
+/// Set an environment variable in the Shuttle.toml at `path`, creating the file if needed
+pub fn set_env_var(path: &Path, name: &str, env_value: &str) -> Result<()> {
+    validate_name(name)?;
+    config::insert_in_table(path, TABLE, name, value(env_value))?;
+
+    Ok(())
+}
+
+/// Remove an environment variable from the Shuttle.toml at `path`. Returns whether it was set.
+pub fn remove_env_var(path: &Path, name: &str) -> Result<bool> {
+    config::remove_from_table(path, TABLE, name)
+}
+
+impl Shuttle {
+    pub(crate) fn env_list(&self) -> Result<CommandOutcome> {
+        let env_vars = self.ctx.env_vars();
+        if env_vars.is_empty() {
+            println!(
+                "No environment variables are set in {}",
+                self.ctx.project_config_path().display()
+            );
+            return Ok(CommandOutcome::Ok);
+        }
+
+        output::print(self.output, &env_vars, |_| {
+            env_vars
+                .iter()
+                .map(|(name, value)| format!("{name}={value}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })?;
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) fn env_set(&self, name: String, value: String) -> Result<CommandOutcome> {
+        let path = self.ctx.project_config_path();
+        set_env_var(&path, &name, &value)?;
+
+        println!("Set {name} in {}", path.display());
+        println!("It is used from the next deployment or local run.");
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) fn env_remove(&self, name: String) -> Result<CommandOutcome> {
+        let path = self.ctx.project_config_path();
+        if remove_env_var(&path, &name)? {
+            println!("Removed {name} from {}", path.display());
+        } else {
+            println!("{name} is not set in {}", path.display());
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::read_to_string;
+
+    use super::*;
+
+    #[test]
+    fn set_and_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Shuttle.toml");
+        std::fs::write(&path, "# my project\nname = \"my-api\"\n").unwrap();
+
+        set_env_var(&path, "LOG_FORMAT", "json").unwrap();
+        set_env_var(&path, "WORKERS", "4").unwrap();
+        set_env_var(&path, "LOG_FORMAT", "pretty").unwrap();
+        let contents = read_to_string(&path).unwrap();
+        assert!(contents.starts_with("# my project\n"));
+        let parsed: toml::Value = toml::from_str(&contents).unwrap();
+        assert_eq!(parsed["name"].as_str(), Some("my-api"));
+        assert_eq!(parsed[TABLE]["LOG_FORMAT"].as_str(), Some("pretty"));
+        assert_eq!(parsed[TABLE]["WORKERS"].as_str(), Some("4"));
+
+        assert!(remove_env_var(&path, "WORKERS").unwrap());
+        let parsed: toml::Value = toml::from_str(&read_to_string(&path).unwrap()).unwrap();
+        assert!(parsed[TABLE].get("WORKERS").is_none());
+
+        assert!(set_env_var(&path, "NOT VALID", "1").is_err());
+    }
+
+    #[test]
+    fn names() {
+        assert!(validate_name("DATABASE_POOL_SIZE").is_ok());
+        assert!(validate_name("_private").is_ok());
+        assert!(validate_name("1ST").is_err());
+        assert!(validate_name("WITH-DASH").is_err());
+        assert!(validate_name("").is_err());
+    }
+}
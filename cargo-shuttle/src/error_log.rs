@@ -0,0 +1,200 @@
+//! A log of the errors, crashes and panics seen by the CLI on this machine, so that they can be
+//! looked at and reported after the fact.
+//!
+//! Entries are kept as JSON lines in the Shuttle config directory, with secret values redacted.
+
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::panic;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::errors::ErrorCode;
+use crate::feedback;
+use crate::redact::redact;
+
+/// Entries kept once the log is pruned
+const MAX_ENTRIES: usize = 100;
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ErrorLogEntry {
+    /// RFC 3339 time of the error
+    pub timestamp: String,
+    /// What ran into the error, like `local-run`
+    pub source: String,
+    pub message: String,
+    /// Supporting lines, like the output leading up to a crash
+    #[serde(default)]
+    pub details: Vec<String>,
+    /// Version of cargo-shuttle
+    pub version: String,
+}
+
+impl ErrorLogEntry {
+    pub fn new(source: &str, message: impl Into<String>, details: Vec<String>) -> Self {
+        Self {
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            source: source.to_string(),
+            message: message.into(),
+            details,
+            version: crate::VERSION.to_string(),
+        }
+    }
+}
+
+/// Where the error log is kept
+pub fn path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir().context(
+        "Could not find a configuration directory. Your operating system may not be supported.",
+    )?;
+
+    Ok(config_dir.join("shuttle").join("error-log.jsonl"))
+}
+
+/// Add an entry to the error log. Failing to do so is not an error of its own.
+pub fn record(entry: ErrorLogEntry) -> Option<PathBuf> {
+    match path().and_then(|path| record_to(&path, entry).map(|_| path)) {
+        Ok(path) => Some(path),
+        Err(err) => {
+            debug!(error = %err, "failed to write to the error log");
+            None
+        }
+    }
+}
+
+fn record_to(path: &Path, mut entry: ErrorLogEntry) -> Result<()> {
+    entry.message = redact(&entry.message);
+    entry.details = entry.details.iter().map(|line| redact(line)).collect();
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+
+    // Prune every now and then, rather than rewriting the file on every entry
+    let contents = fs::read_to_string(path)?;
+    let lines = contents.lines().collect::<Vec<_>>();
+    if lines.len() > MAX_ENTRIES * 2 {
+        let mut kept = lines[lines.len() - MAX_ENTRIES..].join("\n");
+        kept.push('\n');
+        fs::write(path, kept)?;
+    }
+
+    Ok(())
+}
+
+/// The last `count` entries of the error log, oldest first
+pub fn recent(count: usize) -> Result<Vec<ErrorLogEntry>> {
+    recent_from(&path()?, count)
+}
+
+fn recent_from(path: &Path, count: usize) -> Result<Vec<ErrorLogEntry>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).context("failed to read the error log"),
+    };
+    let entries = contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect::<Vec<ErrorLogEntry>>();
+
+    Ok(entries[entries.len().saturating_sub(count)..].to_vec())
+}
+
+/// Record panics in the error log, with the command that was running and a backtrace, and ask
+/// for a bug report. The panic message and location are always printed, the backtrace only with
+/// `RUST_BACKTRACE` set.
+pub fn install_panic_hook(command: &'static str) {
+    panic::set_hook(Box::new(move |info| {
+        let message = panic_message(info.payload());
+        let backtrace = Backtrace::force_capture().to_string();
+
+        match info.location() {
+            Some(location) => eprintln!("cargo-shuttle panicked at {location}:\n{message}"),
+            None => eprintln!("cargo-shuttle panicked:\n{message}"),
+        }
+        if std::env::var_os("RUST_BACKTRACE").is_some_and(|value| value != "0") {
+            eprintln!("stack backtrace:\n{backtrace}");
+        }
+
+        let mut details = vec![format!("command: cargo shuttle {command}")];
+        if let Some(location) = info.location() {
+            details.push(format!("at {location}"));
+        }
+        details.extend(backtrace.lines().map(ToString::to_string));
+        let path = record(ErrorLogEntry::new("panic", message, details));
+        feedback::record_last_command(command, Some(ErrorCode::Unknown));
+
+        eprintln!("\ncargo-shuttle crashed. This is a bug in cargo-shuttle, not in your project.");
+        if let Some(path) = path {
+            eprintln!(
+                "The details were saved to the error log in {}",
+                path.display()
+            );
+        }
+        eprintln!(
+            "Please report it with `cargo shuttle feedback --include-errors`, which fills in an \
+            issue with them."
+        );
+    }));
+}
+
+/// The message a panic was raised with
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "panicked without a message".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recording() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("error-log.jsonl");
+        assert!(recent_from(&path, 5).unwrap().is_empty());
+
+        for i in 0..MAX_ENTRIES * 2 + 1 {
+            record_to(
+                &path,
+                ErrorLogEntry::new("test", format!("error {i}"), vec!["detail".to_string()]),
+            )
+            .unwrap();
+        }
+
+        let entries = recent_from(&path, 2).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].message, format!("error {}", MAX_ENTRIES * 2 - 1));
+        assert_eq!(entries[1].message, format!("error {}", MAX_ENTRIES * 2));
+        assert_eq!(entries[1].details, vec!["detail".to_string()]);
+        // The log was pruned
+        assert_eq!(recent_from(&path, usize::MAX).unwrap().len(), MAX_ENTRIES);
+    }
+
+    #[test]
+    fn panic_messages() {
+        let payload = panic::catch_unwind(|| panic!("static message")).unwrap_err();
+        assert_eq!(panic_message(&*payload), "static message");
+
+        let value = 42;
+        let payload = panic::catch_unwind(|| panic!("formatted {value}")).unwrap_err();
+        assert_eq!(panic_message(&*payload), "formatted 42");
+
+        let payload = panic::catch_unwind(|| panic::panic_any(42)).unwrap_err();
+        assert_eq!(panic_message(&*payload), "panicked without a message");
+    }
+}
@@ -0,0 +1,212 @@
+//! What kind of failure a command ran into, for exit codes and JSON output that CI pipelines can
+//! branch on.
+//!
+//! The codes and exit codes are stable. Errors that were not marked with a code where they come
+//! from are classified by their causes, like the status code of an API error.
+
+use serde::{Deserialize, Serialize};
+use shuttle_common::models::error::ApiError;
+
+use crate::args::OutputMode;
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize, strum::Display, strum::EnumString,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ErrorCode {
+    /// Anything that is not one of the others
+    Unknown,
+    /// Invalid arguments or configuration, like Shuttle.toml, or a missing flag
+    Validation,
+    /// A missing, invalid or revoked API key, or a project of someone else
+    Auth,
+    /// The API could not be reached
+    Network,
+    /// The API failed to handle a request
+    Server,
+    /// The project failed to build, or its deployment crashed
+    Build,
+    /// A resource of the project could not be provisioned
+    Provisioning,
+}
+
+impl ErrorCode {
+    /// Validation errors share 2 with the usage errors of clap
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Unknown => 1,
+            Self::Validation => 2,
+            Self::Auth => 3,
+            Self::Network => 4,
+            Self::Server => 5,
+            Self::Build => 6,
+            Self::Provisioning => 7,
+        }
+    }
+
+    /// What the code means and what to do about it, for editors to show next to errors
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            Self::Unknown => {
+                "The error is not one of the known kinds. Its message and causes tell what went \
+                wrong, and running the command again with --debug tells more."
+            }
+            Self::Validation => {
+                "An argument or the configuration is invalid, like a field of Shuttle.toml or a \
+                missing flag, or the API refused a request as invalid. Fix what the message points \
+                at and try again."
+            }
+            Self::Auth => {
+                "The API key is missing, invalid or revoked, or the project belongs to someone \
+                else. Run `cargo shuttle login` to set a new key, or check the name of the project."
+            }
+            Self::Network => {
+                "The API could not be reached. Check the connection and any proxy in the way, then \
+                try again."
+            }
+            Self::Server => {
+                "The API failed to handle the request. This is usually temporary, so try again in \
+                a little while, and report it if it keeps happening."
+            }
+            Self::Build => {
+                "The project failed to build, or its deployment crashed while starting. The logs \
+                of the deployment, from `cargo shuttle logs`, tell why."
+            }
+            Self::Provisioning => {
+                "A resource of the project, like a database, could not be provisioned. Check its \
+                configuration, then deploy again."
+            }
+        }
+    }
+
+    /// Classify an error by the first of its causes that tells what kind it is
+    pub fn of(err: &anyhow::Error) -> Self {
+        err.chain()
+            .find_map(|cause| {
+                if let Some(coded) = cause.downcast_ref::<CodedError>() {
+                    Some(coded.code)
+                } else if let Some(api_error) = cause.downcast_ref::<ApiError>() {
+                    Some(Self::from_status(api_error.status_code))
+                } else if let Some(err) = cause.downcast_ref::<reqwest::Error>() {
+                    (err.is_connect() || err.is_timeout() || err.is_request())
+                        .then_some(Self::Network)
+                } else {
+                    cause
+                        .downcast_ref::<tokio_tungstenite::tungstenite::Error>()
+                        .map(|_| Self::Network)
+                }
+            })
+            .unwrap_or(Self::Unknown)
+    }
+
+    fn from_status(status_code: u16) -> Self {
+        match status_code {
+            401 | 403 => Self::Auth,
+            400..=499 => Self::Validation,
+            _ => Self::Server,
+        }
+    }
+}
+
+/// An error marked with its code where it comes from. It reads as the error it marks.
+#[derive(Debug)]
+struct CodedError {
+    code: ErrorCode,
+    source: anyhow::Error,
+}
+
+impl std::fmt::Display for CodedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.source, f)
+    }
+}
+
+impl std::error::Error for CodedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.source()
+    }
+}
+
+/// Mark an error with its code, which wins over the ones of its causes
+pub fn with_code(err: anyhow::Error, code: ErrorCode) -> anyhow::Error {
+    CodedError { code, source: err }.into()
+}
+
+#[derive(Serialize)]
+struct ErrorOutput {
+    error: ErrorBody,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    code: ErrorCode,
+    exit_code: i32,
+    message: String,
+    /// The messages of the causes, outermost first
+    causes: Vec<String>,
+}
+
+/// Print the error a command failed with, and return the exit code for it
+pub fn report(err: &anyhow::Error, output: OutputMode) -> i32 {
+    let code = ErrorCode::of(err);
+
+    if output == OutputMode::Json {
+        let mut chain = err.chain().map(|cause| cause.to_string());
+        let body = ErrorOutput {
+            error: ErrorBody {
+                code,
+                exit_code: code.exit_code(),
+                message: chain.next().unwrap_or_default(),
+                causes: chain.collect(),
+            },
+        };
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&body).expect("error output to serialize")
+        );
+    } else {
+        eprintln!("Error: {err:?}");
+    }
+
+    code.exit_code()
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+
+    use super::*;
+
+    #[test]
+    fn classify() {
+        let api_error = |status_code| {
+            anyhow::Error::new(ApiError {
+                message: "no".to_string(),
+                status_code,
+            })
+        };
+        assert_eq!(ErrorCode::of(&api_error(401)), ErrorCode::Auth);
+        assert_eq!(ErrorCode::of(&api_error(404)), ErrorCode::Validation);
+        assert_eq!(ErrorCode::of(&api_error(502)), ErrorCode::Server);
+        assert_eq!(
+            ErrorCode::of(&api_error(403).context("failed to make get request")),
+            ErrorCode::Auth
+        );
+
+        let marked = with_code(anyhow!("cargo build failed"), ErrorCode::Build);
+        assert_eq!(ErrorCode::of(&marked), ErrorCode::Build);
+        assert_eq!(
+            ErrorCode::of(&marked.context("the services failed to start")),
+            ErrorCode::Build
+        );
+        // The marked code wins over the causes
+        assert_eq!(
+            ErrorCode::of(&with_code(api_error(500), ErrorCode::Provisioning)),
+            ErrorCode::Provisioning
+        );
+
+        assert_eq!(ErrorCode::of(&anyhow!("something")), ErrorCode::Unknown);
+        assert_eq!(ErrorCode::Build.to_string(), "build");
+    }
+}
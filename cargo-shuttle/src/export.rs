@@ -0,0 +1,381 @@
+//! Backups of a project for `cargo shuttle project export`, to recreate it elsewhere.
+//!
+//! A backup is a tar archive compressed with zstd. It holds a `manifest.json` describing the
+//! project, its domains, resources and the names of its secrets, next to its Shuttle.toml. The
+//! secret values and the executable of the latest deployment are optional. Secret values are only
+//! ever written encrypted with age, so a backup without them can be shared freely.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+use shuttle_common::deployment::State;
+use shuttle_common::resource;
+use tar::{Builder, Header};
+use uuid::Uuid;
+
+use crate::args::ProjectExportArgs;
+use crate::style::Tone;
+use crate::{output, secrets, style, suggestions, CommandOutcome, Shuttle};
+
+/// Changed when the layout of backups changes
+pub const FORMAT: u32 = 1;
+
+const MANIFEST: &str = "manifest.json";
+const PROJECT_CONFIG: &str = "Shuttle.toml";
+/// The secret values as a `Secrets.toml`, encrypted with age
+const SECRETS: &str = "Secrets.toml.age";
+/// The directory of the executable of the latest deployment, which is named after its ID
+const ARTIFACT_DIR: &str = "artifact";
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct Manifest {
+    pub format: u32,
+    pub project: String,
+    pub exported_at: DateTime<Utc>,
+    pub idle_minutes: Option<u64>,
+    pub deployment: Option<Deployment>,
+    /// The custom domains of the project
+    pub domains: Vec<String>,
+    pub resources: Vec<Resource>,
+    /// The names of the secrets, whether their values are in the backup or not
+    pub secrets: Vec<String>,
+}
+
+/// The latest deployment of a project
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct Deployment {
+    pub id: Uuid,
+    pub state: State,
+    pub last_update: DateTime<Utc>,
+    pub git_commit_id: Option<String>,
+}
+
+/// How a resource was provisioned. Its data is left out, since it holds connection strings and
+/// passwords that only work for the old project.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct Resource {
+    pub r#type: resource::Type,
+    pub config: serde_json::Value,
+}
+
+impl From<resource::Response> for Resource {
+    fn from(resource: resource::Response) -> Self {
+        Self {
+            r#type: resource.r#type,
+            config: resource.config,
+        }
+    }
+}
+
+pub struct Bundle {
+    pub manifest: Manifest,
+    pub project_config: Option<Vec<u8>>,
+    /// Already encrypted
+    pub secrets: Option<Vec<u8>>,
+    pub artifact: Option<PathBuf>,
+}
+
+/// Write `bundle` to `out`. It is written next to it first, so that an interrupted export never
+/// leaves a partial backup behind.
+pub fn write(out: &Path, bundle: &Bundle) -> Result<()> {
+    let partial = out.with_extension("partial");
+    let written = write_archive(&partial, bundle).and_then(|_| {
+        fs::rename(&partial, out).with_context(|| format!("failed to write {}", out.display()))
+    });
+    if written.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+
+    written
+}
+
+fn write_archive(path: &Path, bundle: &Bundle) -> Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("failed to create {}", path.display()))?;
+    let mut archive = Builder::new(zstd::Encoder::new(file, 0)?);
+
+    let manifest = serde_json::to_vec_pretty(&bundle.manifest)?;
+    append_bytes(&mut archive, MANIFEST, 0o644, &manifest)?;
+    if let Some(project_config) = &bundle.project_config {
+        append_bytes(&mut archive, PROJECT_CONFIG, 0o644, project_config)?;
+    }
+    if let Some(secrets) = &bundle.secrets {
+        append_bytes(&mut archive, SECRETS, 0o600, secrets)?;
+    }
+    if let (Some(artifact), Some(deployment)) = (&bundle.artifact, &bundle.manifest.deployment) {
+        let file = File::open(artifact)
+            .with_context(|| format!("failed to open {}", artifact.display()))?;
+        let size = file.metadata()?.len();
+        append(
+            &mut archive,
+            &format!("{ARTIFACT_DIR}/{}", deployment.id),
+            0o755,
+            size,
+            file,
+        )?;
+    }
+
+    archive
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .and_then(|file| file.sync_all())
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn append<W: std::io::Write>(
+    archive: &mut Builder<W>,
+    name: &str,
+    mode: u32,
+    size: u64,
+    data: impl Read,
+) -> Result<()> {
+    let mut header = Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(mode);
+    header.set_mtime(Utc::now().timestamp() as u64);
+    archive
+        .append_data(&mut header, name, data)
+        .with_context(|| format!("failed to add {name} to the backup"))
+}
+
+fn append_bytes<W: std::io::Write>(
+    archive: &mut Builder<W>,
+    name: &str,
+    mode: u32,
+    data: &[u8],
+) -> Result<()> {
+    append(archive, name, mode, data.len() as u64, data)
+}
+
+impl Shuttle {
+    pub(crate) async fn project_export(&self, args: ProjectExportArgs) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let project_name = self.ctx.project_name();
+        let ProjectExportArgs {
+            out,
+            secrets_recipient,
+            no_artifact,
+        } = args;
+        let out = out.unwrap_or_else(|| PathBuf::from(format!("{project_name}-backup.tar.zst")));
+
+        let project = client.get_project(project_name).await.map_err(|err| {
+            suggestions::project::project_request_failure(
+                err,
+                "Getting the project failed",
+                false,
+                "getting the project fails repeatedly",
+            )
+        })?;
+        let resources = client
+            .get_service_resources(project_name)
+            .await
+            .map_err(suggestions::resources::get_service_resources_failure)?;
+        let secret_names = client
+            .get_secrets(project_name)
+            .await
+            .map_err(|err| {
+                suggestions::secrets::secrets_request_failure(err, "Fetching the secrets failed")
+            })?
+            .into_iter()
+            .map(|secret| secret.key)
+            .collect::<Vec<_>>();
+        let domains = client
+            .get_certificates(project_name)
+            .await
+            .context("failed to get the custom domains of the project")?
+            .into_iter()
+            .map(|certificate| certificate.domain)
+            .collect();
+        let deployment = client
+            .get_deployments(project_name, 1, 1)
+            .await
+            .context("failed to get the latest deployment")?
+            .into_iter()
+            .next();
+
+        // The values are only ever held in memory before they are encrypted
+        let secrets = match &secrets_recipient {
+            Some(recipient) if !secret_names.is_empty() => {
+                let mut values = BTreeMap::new();
+                for key in &secret_names {
+                    let secret = client.get_secret(project_name, key).await.map_err(|err| {
+                        suggestions::secrets::secrets_request_failure(
+                            err,
+                            "Fetching the secret values failed",
+                        )
+                    })?;
+                    values.insert(secret.key, secret.value);
+                }
+                Some(secrets::encrypt_for(recipient, &toml::to_string(&values)?)?)
+            }
+            _ => None,
+        };
+
+        let artifact = match &deployment {
+            Some(deployment) if !no_artifact => {
+                let temp = tempfile::NamedTempFile::new()?;
+                let mut file = tokio::fs::File::from_std(temp.reopen()?);
+                println!("Downloading the executable of deployment {}", deployment.id);
+                match client
+                    .download_deployment_artifact(project_name, &deployment.id, &mut file)
+                    .await
+                {
+                    Ok(_) => Some(temp),
+                    // The rest of the backup is still enough to redeploy from the source
+                    Err(err) => {
+                        println!(
+                            "{}",
+                            format!(
+                                "The executable of deployment {} could not be downloaded ({err}), \
+                                the backup does not include it",
+                                deployment.id
+                            )
+                            .yellow()
+                        );
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+
+        let project_config_path = self.ctx.project_config_path();
+        let project_config = if project_config_path.exists() {
+            Some(
+                std::fs::read(&project_config_path)
+                    .with_context(|| format!("failed to read {}", project_config_path.display()))?,
+            )
+        } else {
+            None
+        };
+
+        let manifest = Manifest {
+            format: FORMAT,
+            project: project.name,
+            exported_at: chrono::Utc::now(),
+            idle_minutes: project.idle_minutes,
+            deployment: deployment.map(|deployment| Deployment {
+                id: deployment.id,
+                state: deployment.state,
+                last_update: deployment.last_update,
+                git_commit_id: deployment.git_commit_id,
+            }),
+            domains,
+            resources: resources
+                .into_iter()
+                // Local provisioner only, nothing to recreate remotely
+                .filter(|resource| resource.r#type != resource::Type::Container)
+                .map(Resource::from)
+                .collect(),
+            secrets: secret_names,
+        };
+        let bundle = Bundle {
+            manifest,
+            project_config,
+            secrets,
+            artifact: artifact.as_ref().map(|temp| temp.path().to_path_buf()),
+        };
+        write(&out, &bundle)?;
+
+        let size = std::fs::metadata(&out)?.len();
+        println!(
+            "{} {project_name} to {} ({:.1} MB)",
+            style::status("Exported", Tone::Success),
+            out.display(),
+            size as f64 / 1_000_000.0
+        );
+        let manifest = &bundle.manifest;
+        println!(
+            "  {} resources, {} custom domains, {} secrets",
+            manifest.resources.len(),
+            manifest.domains.len(),
+            manifest.secrets.len()
+        );
+        if bundle.project_config.is_none() {
+            output::hint(
+                self.output,
+                format!(
+                    "No Shuttle.toml was found at {}",
+                    project_config_path.display()
+                ),
+            );
+        }
+        if bundle.secrets.is_none() && !manifest.secrets.is_empty() {
+            output::hint(
+                self.output,
+                "Only the names of the secrets are included. Pass --secrets-recipient with an age \
+                public key to include their values, encrypted.",
+            );
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let artifact = dir.path().join("executable");
+        fs::write(&artifact, b"\x7fELF").unwrap();
+        let id = Uuid::new_v4();
+
+        let bundle = Bundle {
+            manifest: Manifest {
+                format: FORMAT,
+                project: "my-api".to_string(),
+                exported_at: Utc::now(),
+                idle_minutes: Some(30),
+                deployment: Some(Deployment {
+                    id,
+                    state: State::Running,
+                    last_update: Utc::now(),
+                    git_commit_id: None,
+                }),
+                domains: vec!["api.example.com".to_string()],
+                resources: Vec::new(),
+                secrets: vec!["API_KEY".to_string()],
+            },
+            project_config: Some(b"name = \"my-api\"\n".to_vec()),
+            secrets: None,
+            artifact: Some(artifact),
+        };
+        let out = dir.path().join("my-api-backup.tar.zst");
+        write(&out, &bundle).unwrap();
+        assert!(!out.with_extension("partial").exists());
+
+        let decoder = zstd::Decoder::new(File::open(&out).unwrap()).unwrap();
+        let mut archive = tar::Archive::new(decoder);
+        let mut entries = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let name = entry.path().unwrap().to_string_lossy().to_string();
+            let mode = entry.header().mode().unwrap();
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents).unwrap();
+            if name == MANIFEST {
+                let manifest: Manifest = serde_json::from_slice(&contents).unwrap();
+                assert_eq!(manifest, bundle.manifest);
+            }
+            entries.push((name, mode));
+        }
+
+        assert_eq!(
+            entries,
+            [
+                (MANIFEST.to_string(), 0o644),
+                (PROJECT_CONFIG.to_string(), 0o644),
+                (format!("{ARTIFACT_DIR}/{id}"), 0o755),
+            ]
+        );
+    }
+}
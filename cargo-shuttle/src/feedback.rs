@@ -0,0 +1,220 @@
+//! Bug reports for `cargo shuttle feedback`, which open a GitHub issue filled in with the
+//! environment, the last command that ran and, when allowed, the recent entries of the error log.
+//!
+//! The name and outcome of every command are kept in the Shuttle config directory for this. The
+//! report is redacted before it is shown: secret values and the API key are masked, and the home
+//! directory is shortened to `~`.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use chrono::{SecondsFormat, Utc};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::error_log::ErrorLogEntry;
+use crate::errors::ErrorCode;
+use crate::redact::redact;
+
+const NEW_ISSUE_URL: &str = "https://github.com/shuttle-hq/shuttle/issues/new";
+/// GitHub refuses longer URLs
+const MAX_URL_LEN: usize = 8000;
+
+/// The last command that ran on this machine
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct LastCommand {
+    /// RFC 3339 time the command ended
+    pub timestamp: String,
+    /// The top-level command, like `deploy`
+    pub command: String,
+    pub error: Option<ErrorCode>,
+}
+
+fn last_command_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir().context(
+        "Could not find a configuration directory. Your operating system may not be supported.",
+    )?;
+
+    Ok(config_dir.join("shuttle").join("last-command.json"))
+}
+
+/// Keep the outcome of a command for the next bug report. Failing to is not an error of its own.
+pub fn record_last_command(command: &str, error: Option<ErrorCode>) {
+    // Reporting a bug is not what the report is about
+    if command == "feedback" {
+        return;
+    }
+
+    let last = LastCommand {
+        timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        command: command.to_string(),
+        error,
+    };
+    let written = last_command_path().and_then(|path| {
+        fs::create_dir_all(path.parent().expect("the file to be in a directory"))?;
+        fs::write(path, serde_json::to_vec(&last)?)?;
+        Ok(())
+    });
+    if let Err(err) = written {
+        debug!(error = %err, "failed to record the last command");
+    }
+}
+
+pub fn last_command() -> Option<LastCommand> {
+    let last = fs::read(last_command_path().ok()?).ok()?;
+
+    serde_json::from_slice(&last).ok()
+}
+
+pub struct Report {
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+    /// The output of `rustc --version`, if it ran
+    pub rustc: Option<String>,
+    pub last_command: Option<LastCommand>,
+    /// Oldest first
+    pub errors: Vec<ErrorLogEntry>,
+}
+
+impl Report {
+    /// The report for this machine, without the error log
+    pub fn collect() -> Self {
+        let rustc = std::process::Command::new("rustc")
+            .arg("--version")
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+
+        Self {
+            version: crate::VERSION.to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            rustc,
+            last_command: last_command(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// The body of the issue, in Markdown
+    pub fn to_markdown(&self) -> String {
+        let mut body = String::from(
+            "### What happened\n\n\
+            <!-- What did you run, what did you expect, and what happened instead? -->\n\n\
+            ### Environment\n\n",
+        );
+        body.push_str(&format!("- cargo-shuttle: {}\n", self.version));
+        body.push_str(&format!("- OS: {} ({})\n", self.os, self.arch));
+        body.push_str(&format!(
+            "- rustc: {}\n",
+            self.rustc.as_deref().unwrap_or("not found")
+        ));
+        if let Some(last) = &self.last_command {
+            let outcome = match last.error {
+                Some(code) => format!("failed with `{code}`"),
+                None => "succeeded".to_string(),
+            };
+            body.push_str(&format!(
+                "- Last command: `cargo shuttle {}`, {outcome} at {}\n",
+                last.command, last.timestamp
+            ));
+        }
+
+        if !self.errors.is_empty() {
+            body.push_str("\n### Recent errors\n");
+        }
+        for entry in &self.errors {
+            body.push_str(&format!(
+                "\n`{}` {} (cargo-shuttle {})\n\n{}\n",
+                entry.timestamp, entry.source, entry.version, entry.message
+            ));
+            if !entry.details.is_empty() {
+                body.push_str(&format!("\n```text\n{}\n```\n", entry.details.join("\n")));
+            }
+        }
+
+        scrub(&body)
+    }
+
+    /// The URL of a new issue filled in with the report. The oldest errors are left out until it
+    /// is short enough for GitHub, which is returned with how many were left out.
+    pub fn issue_url(mut self, title: &str) -> (String, usize) {
+        let mut left_out = 0;
+        loop {
+            let url = format!(
+                "{NEW_ISSUE_URL}?title={}&body={}",
+                utf8_percent_encode(title, NON_ALPHANUMERIC),
+                utf8_percent_encode(&self.to_markdown(), NON_ALPHANUMERIC)
+            );
+            if url.len() <= MAX_URL_LEN || self.errors.is_empty() {
+                return (url, left_out);
+            }
+            self.errors.remove(0);
+            left_out += 1;
+        }
+    }
+}
+
+/// Mask the registered secrets, and the home directory which usually has the name of the user
+fn scrub(text: &str) -> String {
+    let text = redact(text);
+    match dirs::home_dir() {
+        Some(home) if home.as_os_str().len() > 1 => text.replace(&*home.to_string_lossy(), "~"),
+        _ => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(errors: usize) -> Report {
+        Report {
+            version: "0.43.0".to_string(),
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            rustc: Some("rustc 1.76.0 (07dca489a 2024-02-04)".to_string()),
+            last_command: Some(LastCommand {
+                timestamp: "2024-03-01T10:00:00.000Z".to_string(),
+                command: "deploy".to_string(),
+                error: Some(ErrorCode::Build),
+            }),
+            errors: (0..errors)
+                .map(|i| ErrorLogEntry {
+                    timestamp: "2024-03-01T09:00:00.000Z".to_string(),
+                    source: "local-run".to_string(),
+                    message: format!("error {i}"),
+                    details: vec!["x".repeat(500)],
+                    version: "0.43.0".to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn markdown() {
+        let markdown = report(1).to_markdown();
+        assert!(markdown.contains("- OS: linux (x86_64)\n"));
+        assert!(markdown.contains(
+            "- Last command: `cargo shuttle deploy`, failed with `build` at 2024-03-01T10:00:00.000Z"
+        ));
+        assert!(markdown.contains("`2024-03-01T09:00:00.000Z` local-run (cargo-shuttle 0.43.0)"));
+        assert!(!report(0).to_markdown().contains("Recent errors"));
+    }
+
+    #[test]
+    fn issue_urls_fit() {
+        let (url, left_out) = report(1).issue_url("deploy fails");
+        assert!(url.starts_with(&format!("{NEW_ISSUE_URL}?title=deploy%20fails&body=")));
+        assert_eq!(left_out, 0);
+
+        let (url, left_out) = report(20).issue_url("deploy fails");
+        assert!(url.len() <= MAX_URL_LEN);
+        assert!(left_out > 0);
+        // The newest errors are kept
+        assert!(url.contains("error%2019"));
+    }
+}
@@ -0,0 +1,252 @@
+//! The hashes of the files of a project, kept in `.shuttle/cache/` so that deploys only hash the
+//! files that changed since the last one.
+//!
+//! The hashes make up the digest of what a deploy packs, which tells whether anything changed
+//! since the last deploy from this machine. A file is hashed again when its size or modification
+//! time changed, or when it was modified so close to when the cache was written that a change
+//! could have gone unnoticed. A cache that does not match its checksum is thrown away.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use ring::digest::{Context, SHA256};
+use serde::{Deserialize, Serialize};
+use shuttle_common::constants::LOCAL_STATE_DIRNAME;
+use tracing::debug;
+use uuid::Uuid;
+
+const FILE_NAME: &str = "file-hashes.json";
+const LAST_DEPLOY_FILE_NAME: &str = "last-deploy.json";
+/// Changed when the format of the cache changes, to throw away the caches of older versions
+const FORMAT: u32 = 1;
+/// Files modified this close to when the cache was written are hashed again, since file systems
+/// can round modification times
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+/// The directory of the caches of a project
+pub fn cache_dir(project_dir: &Path) -> PathBuf {
+    project_dir.join(LOCAL_STATE_DIRNAME).join("cache")
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+struct Entry {
+    size: u64,
+    modified: SystemTime,
+    sha256: String,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Stored {
+    format: u32,
+    written: SystemTime,
+    entries: BTreeMap<PathBuf, Entry>,
+    /// The SHA-256 of the entries
+    checksum: String,
+}
+
+pub struct FileHashes {
+    path: PathBuf,
+    written: SystemTime,
+    entries: BTreeMap<PathBuf, Entry>,
+    /// The entries of the files hashed this time, which replace the others when saved
+    seen: BTreeMap<PathBuf, Entry>,
+}
+
+impl FileHashes {
+    /// The cache of the project in `project_dir`, or an empty one when there is none that can be
+    /// trusted
+    pub fn load(project_dir: &Path) -> Self {
+        let path = cache_dir(project_dir).join(FILE_NAME);
+        let stored = fs::read(&path)
+            .ok()
+            .and_then(|stored| serde_json::from_slice::<Stored>(&stored).ok())
+            .filter(|stored| {
+                let valid = stored.format == FORMAT && stored.checksum == checksum(&stored.entries);
+                if !valid {
+                    debug!("throwing away the file hash cache, which is outdated or corrupted");
+                }
+                valid
+            });
+
+        match stored {
+            Some(stored) => Self {
+                path,
+                written: stored.written,
+                entries: stored.entries,
+                seen: BTreeMap::new(),
+            },
+            None => Self {
+                path,
+                written: SystemTime::UNIX_EPOCH,
+                entries: BTreeMap::new(),
+                seen: BTreeMap::new(),
+            },
+        }
+    }
+
+    /// The SHA-256 of `file`, from the cache when the file did not change
+    pub fn hash(&mut self, file: &Path) -> io::Result<String> {
+        let metadata = fs::metadata(file)?;
+        let (size, modified) = (metadata.len(), metadata.modified()?);
+
+        let racy = modified + RACY_WINDOW >= self.written;
+        let cached = self
+            .entries
+            .get(file)
+            .filter(|entry| !racy && entry.size == size && entry.modified == modified);
+        let entry = match cached {
+            Some(entry) => entry.clone(),
+            None => Entry {
+                size,
+                modified,
+                sha256: hash_file(file)?,
+            },
+        };
+        let sha256 = entry.sha256.clone();
+        self.seen.insert(file.to_path_buf(), entry);
+
+        Ok(sha256)
+    }
+
+    /// Save the hashes of the files hashed since the cache was loaded. The cache only saves time,
+    /// so failing to write it is not an error.
+    pub fn save(self) {
+        let stored = Stored {
+            format: FORMAT,
+            written: SystemTime::now(),
+            checksum: checksum(&self.seen),
+            entries: self.seen,
+        };
+        let stored = serde_json::to_vec(&stored).expect("to serialize the file hashes");
+
+        // Written next to it first, so that an interrupted write leaves the old cache in place
+        let partial = self.path.with_extension("json.partial");
+        let saved = fs::create_dir_all(self.path.parent().expect("the cache to be in a directory"))
+            .and_then(|_| fs::write(&partial, stored))
+            .and_then(|_| fs::rename(&partial, &self.path));
+        if let Err(err) = saved {
+            debug!(error = %err, "failed to save the file hash cache");
+        }
+    }
+}
+
+/// What the last deploy from this machine packed
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct LastDeploy {
+    pub digest: String,
+    pub deployment_id: Uuid,
+}
+
+pub fn last_deploy(project_dir: &Path) -> Option<LastDeploy> {
+    let last = fs::read(cache_dir(project_dir).join(LAST_DEPLOY_FILE_NAME)).ok()?;
+
+    serde_json::from_slice(&last).ok()
+}
+
+pub fn set_last_deploy(project_dir: &Path, last: &LastDeploy) {
+    let saved = fs::create_dir_all(cache_dir(project_dir)).and_then(|_| {
+        fs::write(
+            cache_dir(project_dir).join(LAST_DEPLOY_FILE_NAME),
+            serde_json::to_vec(last).expect("to serialize the last deploy"),
+        )
+    });
+    if let Err(err) = saved {
+        debug!(error = %err, "failed to save the last deploy");
+    }
+}
+
+/// The digest of the files packed for a deploy, from their names in the archive and their hashes
+pub fn digest<'a>(files: impl IntoIterator<Item = (&'a Path, &'a str)>) -> String {
+    let mut context = Context::new(&SHA256);
+    for (name, sha256) in files {
+        context.update(name.to_string_lossy().as_bytes());
+        context.update(b"\0");
+        context.update(sha256.as_bytes());
+        context.update(b"\n");
+    }
+
+    hex(context.finish().as_ref())
+}
+
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    hex(ring::digest::digest(&SHA256, bytes).as_ref())
+}
+
+fn hash_file(file: &Path) -> io::Result<String> {
+    let mut file = File::open(file)?;
+    let mut context = Context::new(&SHA256);
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        context.update(&buf[..read]);
+    }
+
+    Ok(hex(context.finish().as_ref()))
+}
+
+fn checksum(entries: &BTreeMap<PathBuf, Entry>) -> String {
+    hash_bytes(&serde_json::to_vec(entries).expect("to serialize the file hashes"))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caches_hashes() {
+        let project = tempfile::tempdir().unwrap();
+        let file = project.path().join("main.rs");
+        fs::write(&file, "fn main() {}").unwrap();
+
+        let mut hashes = FileHashes::load(project.path());
+        let sha256 = hashes.hash(&file).unwrap();
+        assert_eq!(sha256, hash_bytes(b"fn main() {}"));
+        hashes.save();
+
+        // The file changed right before the cache was written, so it would be hashed again unless
+        // the cache was written later. A wrong cached hash shows when it is used.
+        let mut hashes = FileHashes::load(project.path());
+        hashes.written += Duration::from_secs(60);
+        hashes.entries.get_mut(&file).unwrap().sha256 = "cached".to_string();
+        assert_eq!(hashes.hash(&file).unwrap(), "cached");
+
+        hashes.entries.get_mut(&file).unwrap().size += 1;
+        assert_eq!(hashes.hash(&file).unwrap(), sha256);
+    }
+
+    #[test]
+    fn throws_away_corrupted_caches() {
+        let project = tempfile::tempdir().unwrap();
+        let file = project.path().join("main.rs");
+        fs::write(&file, "fn main() {}").unwrap();
+
+        let mut hashes = FileHashes::load(project.path());
+        hashes.hash(&file).unwrap();
+        hashes.save();
+
+        let path = cache_dir(project.path()).join(FILE_NAME);
+        let stored = fs::read_to_string(&path).unwrap();
+        fs::write(&path, stored.replace("\"size\":12", "\"size\":13")).unwrap();
+        assert!(FileHashes::load(project.path()).entries.is_empty());
+    }
+
+    #[test]
+    fn digests() {
+        let files = [(Path::new("app/src/main.rs"), "abc")];
+        assert_eq!(digest(files), digest(files));
+        assert_ne!(
+            digest(files),
+            digest([(Path::new("app/src/lib.rs"), "abc")])
+        );
+    }
+}
@@ -1,12 +1,15 @@
+use std::collections::BTreeMap;
 use std::fs::{self, read_to_string};
 use std::num::NonZeroU32;
 use std::sync::atomic::AtomicBool;
+use std::time::{Duration, SystemTime};
 use std::{
     fmt::Write,
     path::{Path, PathBuf},
 };
 
 use anyhow::{Context, Result};
+use crossterm::style::Stylize;
 use gix::clone::PrepareFetch;
 use gix::create::{self, Kind};
 use gix::remote::fetch::Shallow;
@@ -15,19 +18,38 @@ use regex::Regex;
 use shuttle_common::constants::EXAMPLES_README;
 use tempfile::{Builder, TempDir};
 use toml_edit::{value, Document};
+use tracing::{debug, warn};
 use url::Url;
 
 use crate::args::TemplateLocation;
+use crate::progress::Progress;
+use crate::prompt;
+
+use self::template::TemplateConfig;
+
+mod template;
+
+/// How templates from git repositories are fetched
+pub struct TemplateFetch {
+    /// Where fetched templates are kept, by repository and commit
+    pub cache_dir: PathBuf,
+    pub offline: bool,
+    /// Download the template even when a recent one is cached
+    pub refresh: bool,
+}
 
 pub fn generate_project(
     dest: PathBuf,
     name: &str,
     temp_loc: TemplateLocation,
     no_git: bool,
+    fetch: &TemplateFetch,
+    vars: BTreeMap<String, String>,
+    allow_hooks: bool,
 ) -> Result<()> {
     println!(r#"Creating project "{name}" in "{}""#, dest.display());
 
-    let temp_dir: TempDir = setup_template(&temp_loc.auto_path)
+    let temp_dir: TempDir = setup_template(&temp_loc.auto_path, fetch)
         .context("Failed to setup template generation directory")?;
 
     let path = match temp_loc.subfolder {
@@ -54,6 +76,33 @@ pub fn generate_project(
     create_ignore_file(&path, if no_git { ".ignore" } else { ".gitignore" })
         .context("Failed to create .gitignore file")?;
 
+    if let Some(config) = TemplateConfig::take(&path)? {
+        let vars = template::answer(&config.prompts, name, vars)?;
+        template::substitute(&path, &vars).context("Failed to fill in the template")?;
+
+        let hooks = &config.hooks.post_generate;
+        if !hooks.is_empty() {
+            println!("The template runs these commands once the project is generated:");
+            for hook in hooks {
+                println!("  {hook}");
+            }
+            let run = allow_hooks
+                || (prompt::is_interactive()
+                    && prompt::confirm("Run them?", false, "Pass --allow-hooks to run them.")?);
+            if run {
+                template::run_hooks(&path, hooks, &vars)
+                    .context("A command of the template failed")?;
+            } else {
+                println!("Skipped the commands, pass --allow-hooks to run them");
+            }
+        }
+    } else if !vars.is_empty() {
+        anyhow::bail!(
+            "--var was given, but the template has no {}",
+            template::FILE_NAME
+        );
+    }
+
     copy_dirs(&path, &dest, GitDir::Ignore)
         .context("Failed to copy the prepared template to the destination")?;
 
@@ -81,7 +130,7 @@ const GIT_PATTERN: &str = "^(?:(?<vendor>gh|gl|bb):)?(?<owner>[^/.:]+)/(?<name>[
 /// `auto_path` into this directory. On success, a handle to this
 /// directory is returned. It can then be used to modify the
 /// template and lastly copy it to the actual destination.
-fn setup_template(auto_path: &str) -> Result<TempDir> {
+fn setup_template(auto_path: &str, fetch: &TemplateFetch) -> Result<TempDir> {
     let temp_dir = Builder::new()
         .prefix("cargo-shuttle-init")
         .tempdir()
@@ -101,8 +150,7 @@ fn setup_template(auto_path: &str) -> Result<TempDir> {
         // `owner` and `name` are required for the regex to
         // match. Thus, we don't need to check if they exist.
         let url = format!("{vendor}{}/{}.git", &caps["owner"], &caps["name"]);
-        println!(r#"Cloning from "{}"..."#, url);
-        gix_clone(&url, temp_dir.path()).context("Failed to clone git repository")?;
+        fetch_template(&url, temp_dir.path(), fetch)?;
     } else if Path::new(auto_path).is_absolute() || auto_path.starts_with('.') {
         if Path::new(auto_path).exists() {
             copy_dirs(Path::new(auto_path), temp_dir.path(), GitDir::Copy)?;
@@ -113,8 +161,7 @@ fn setup_template(auto_path: &str) -> Result<TempDir> {
         }
     } else if let Ok(url) = auto_path.parse::<Url>() {
         if url.scheme() == "http" || url.scheme() == "https" {
-            gix_clone(auto_path, temp_dir.path())
-                .with_context(|| format!("Failed to clone Git repository at {url}"))?;
+            fetch_template(auto_path, temp_dir.path(), fetch)?;
         } else {
             println!(
                 "URL scheme is not supported. Please use HTTP of HTTPS for URLs, \
@@ -132,9 +179,161 @@ fn setup_template(auto_path: &str) -> Result<TempDir> {
     Ok(temp_dir)
 }
 
+/// How long a cached template is used before it is fetched again
+const TEMPLATE_FRESH_FOR: Duration = Duration::from_secs(24 * 60 * 60);
+/// The templates of repositories that were not fetched for this long are removed
+const TEMPLATE_KEPT_FOR: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Put the template of the repository at `url` in `to_path`, from the cache when it has a recent
+/// one, when offline, or when the repository cannot be reached. Templates that are not cached are
+/// cloned even when offline, since there is no other way to get them.
+fn fetch_template(url: &str, to_path: &Path, fetch: &TemplateFetch) -> Result<()> {
+    let cached = cached_template(&fetch.cache_dir, url);
+    let use_cached = |cached: &CachedTemplate| -> Result<()> {
+        copy_dirs(&cached.path, to_path, GitDir::Ignore)?;
+        println!(
+            r#"Using the template from "{url}" cached at {}"#,
+            &cached.commit[..cached.commit.len().min(7)]
+        );
+        Ok(())
+    };
+
+    match &cached {
+        Some(cached) if fetch.offline => return use_cached(cached),
+        Some(cached)
+            if !fetch.refresh
+                && cached
+                    .fetched
+                    .elapsed()
+                    .is_ok_and(|age| age < TEMPLATE_FRESH_FOR) =>
+        {
+            return use_cached(cached)
+        }
+        _ => {}
+    }
+
+    let cloning = Progress::spinner(format!(r#"Cloning from "{url}""#));
+    match gix_clone(url, to_path) {
+        Ok(commit) => {
+            cloning.finish(format!(r#"Cloned "{url}""#));
+            if let Err(err) = cache_template(&fetch.cache_dir, url, &commit, to_path) {
+                debug!(error = %err, "failed to cache the template");
+            }
+            Ok(())
+        }
+        Err(err) => {
+            cloning.finish_and_clear();
+            match &cached {
+                Some(cached) => {
+                    warn!(error = %err, "failed to clone the template, using the cached one");
+                    println!(
+                        "{}",
+                        format!(r#"Could not clone "{url}", using the cached template instead"#)
+                            .yellow()
+                    );
+                    // The clone can leave files behind
+                    fs::remove_dir_all(to_path)?;
+                    use_cached(cached)
+                }
+                None => Err(err.context(format!("Failed to clone Git repository at {url}"))),
+            }
+        }
+    }
+}
+
+/// A template in the cache, which is a checkout of its repository without the `.git` directory
+struct CachedTemplate {
+    commit: String,
+    path: PathBuf,
+    fetched: SystemTime,
+}
+
+/// The directory of the templates of the repository at `url`, and the file that names the commit
+/// of its latest one
+fn repository_cache(cache_dir: &Path, url: &str) -> (PathBuf, PathBuf) {
+    let name = ring::digest::digest(&ring::digest::SHA256, url.as_bytes())
+        .as_ref()
+        .iter()
+        .take(8)
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    let dir = cache_dir.join(name);
+    let latest = dir.join("latest");
+
+    (dir, latest)
+}
+
+fn cached_template(cache_dir: &Path, url: &str) -> Option<CachedTemplate> {
+    let (dir, latest) = repository_cache(cache_dir, url);
+    let commit = read_to_string(&latest).ok()?.trim().to_string();
+    let path = dir.join(&commit);
+    if commit.is_empty() || !path.is_dir() {
+        return None;
+    }
+
+    Some(CachedTemplate {
+        commit,
+        path,
+        fetched: fs::metadata(&latest).and_then(|m| m.modified()).ok()?,
+    })
+}
+
+/// Keep the checkout at `checkout` as the latest template of the repository at `url`, and remove
+/// the templates that are no longer needed
+fn cache_template(cache_dir: &Path, url: &str, commit: &str, checkout: &Path) -> Result<()> {
+    let (dir, latest) = repository_cache(cache_dir, url);
+    let path = dir.join(commit);
+    if !path.exists() {
+        // Copied next to it first, so that an interrupted copy is never used
+        let partial = dir.join(format!("{commit}.partial"));
+        if partial.exists() {
+            fs::remove_dir_all(&partial)?;
+        }
+        copy_dirs(checkout, &partial, GitDir::Ignore)?;
+        fs::rename(&partial, &path)?;
+    }
+    fs::write(&latest, commit)?;
+
+    prune_templates(cache_dir, &dir);
+
+    Ok(())
+}
+
+/// Remove the older templates of the repository cached in `current`, and the templates of the
+/// repositories that were not fetched for a while
+fn prune_templates(cache_dir: &Path, current: &Path) {
+    let Ok(repositories) = fs::read_dir(cache_dir) else {
+        return;
+    };
+    for repository in repositories.flatten().map(|entry| entry.path()) {
+        let latest = read_to_string(repository.join("latest")).unwrap_or_default();
+        let removed = if repository == current {
+            fs::read_dir(&repository).and_then(|entries| {
+                for entry in entries.flatten() {
+                    let name = entry.file_name();
+                    if entry.path().is_dir() && name.to_string_lossy() != latest.trim() {
+                        fs::remove_dir_all(entry.path())?;
+                    }
+                }
+                Ok(())
+            })
+        } else {
+            let fetched = fs::metadata(repository.join("latest")).and_then(|m| m.modified());
+            match fetched.map(|fetched| fetched.elapsed()) {
+                Ok(Ok(age)) if age < TEMPLATE_KEPT_FOR => Ok(()),
+                _ => fs::remove_dir_all(&repository),
+            }
+        };
+        if let Err(err) = removed {
+            debug!(error = %err, repository = %repository.display(), "failed to prune templates");
+        }
+    }
+}
+
 /// Mimic the behavior of `git clone`, cloning the Git repository found at
 /// `from_url` into a directory `to_path`, using the API exposed by `gix`.
-fn gix_clone(from_url: &str, to_path: &Path) -> Result<()> {
+/// Returns the commit that was checked out.
+fn gix_clone(from_url: &str, to_path: &Path) -> Result<String> {
     let mut fetch = PrepareFetch::new(
         from_url,
         to_path,
@@ -156,7 +355,7 @@ fn gix_clone(from_url: &str, to_path: &Path) -> Result<()> {
         .fetch_then_checkout(progress::Discard, &AtomicBool::new(false))
         .with_context(|| format!("Failed to fetch repository '{from_url}'"))?;
 
-    let (_repo, _outcome) = prepare
+    let (repo, _outcome) = prepare
         .main_worktree(progress::Discard, &AtomicBool::new(false))
         .with_context(|| {
             format!(
@@ -164,8 +363,11 @@ fn gix_clone(from_url: &str, to_path: &Path) -> Result<()> {
                 to_path.display()
             )
         })?;
+    let commit = repo
+        .head_id()
+        .with_context(|| format!("Failed to find the commit checked out from '{from_url}'"))?;
 
-    Ok(())
+    Ok(commit.to_string())
 }
 
 /// Recursively copy all files and directories from `src` to `dest`. If
@@ -278,7 +480,7 @@ fn create_ignore_file(path: &Path, name: &str) -> Result<()> {
     let path = path.join(name);
     let mut contents = std::fs::read_to_string(&path).unwrap_or_default();
 
-    for rule in ["/target", ".shuttle-storage", "Secrets*.toml"] {
+    for rule in ["/target", ".shuttle-storage", ".shuttle/", "Secrets*.toml"] {
         if !contents.lines().any(|l| l == rule) {
             writeln!(&mut contents, "{rule}")?;
         }
@@ -309,6 +511,35 @@ mod tests {
         temp_dir.close().unwrap();
     }
 
+    #[test]
+    fn caches_templates() {
+        let cache = tempfile::tempdir().unwrap();
+        let checkout = tempfile::tempdir().unwrap();
+        fs::write(checkout.path().join("Cargo.toml"), "[package]").unwrap();
+        fs::create_dir(checkout.path().join(".git")).unwrap();
+        let url = "https://github.com/shuttle-hq/shuttle-examples.git";
+
+        assert!(cached_template(cache.path(), url).is_none());
+        cache_template(cache.path(), url, "1111111", checkout.path()).unwrap();
+        cache_template(cache.path(), url, "2222222", checkout.path()).unwrap();
+
+        let cached = cached_template(cache.path(), url).unwrap();
+        assert_eq!(cached.commit, "2222222");
+        assert!(cached.path.join("Cargo.toml").exists());
+        assert!(!cached.path.join(".git").exists());
+        // The older template is pruned
+        assert!(!cached.path.with_file_name("1111111").exists());
+
+        let fetch = TemplateFetch {
+            cache_dir: cache.path().to_path_buf(),
+            offline: true,
+            refresh: false,
+        };
+        let to = tempfile::tempdir().unwrap();
+        fetch_template(url, to.path(), &fetch).unwrap();
+        assert!(to.path().join("Cargo.toml").exists());
+    }
+
     #[test]
     fn copy_dirs_works() {
         let temp_dir = Builder::new()
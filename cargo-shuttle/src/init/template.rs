@@ -0,0 +1,366 @@
+//! `template.toml`, in which templates for `cargo shuttle init` declare the questions to ask and
+//! the commands to run once the project is generated.
+//!
+//! The answers replace `{{<name>}}` in the text files of the template, and so does the name of the
+//! project for `{{project-name}}`. Templates without a `template.toml` are copied as they are.
+//!
+//! Hooks come from wherever the template came from, so they are shown and confirmed before they
+//! run. They run in the directory the project is generated in, before it is copied to its
+//! destination, without a shell and with only the environment variables tools need.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{anyhow, bail, Context, Result};
+use dialoguer::{theme::ColorfulTheme, Input};
+use serde::Deserialize;
+use walkdir::WalkDir;
+
+use crate::prompt;
+
+pub const FILE_NAME: &str = "template.toml";
+/// The variable of the name of the project, which is never asked for
+const PROJECT_NAME: &str = "project-name";
+/// Passed on to hooks, for tools like cargo to work
+const HOOK_ENV_VARS: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "SYSTEMROOT",
+    "CARGO_HOME",
+    "RUSTUP_HOME",
+];
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateConfig {
+    #[serde(default, rename = "prompt")]
+    pub prompts: Vec<Prompt>,
+    #[serde(default)]
+    pub hooks: Hooks,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Prompt {
+    /// The variable the answer is for
+    pub name: String,
+    pub message: String,
+    /// The only answers allowed, if set
+    #[serde(default)]
+    pub choices: Vec<String>,
+    pub default: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Hooks {
+    /// Commands to run once the files are generated. They are split on whitespace.
+    #[serde(default)]
+    pub post_generate: Vec<String>,
+}
+
+impl TemplateConfig {
+    /// The config of the template in `dir`, if it has one. It is removed from the template, so
+    /// that it does not end up in the project.
+    pub fn take(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(FILE_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents =
+            fs::read_to_string(&path).with_context(|| format!("failed to read {FILE_NAME}"))?;
+        let config: Self =
+            toml::from_str(&contents).with_context(|| format!("failed to parse {FILE_NAME}"))?;
+        config
+            .validate()
+            .map_err(|err| anyhow!("invalid {FILE_NAME}: {err}"))?;
+        fs::remove_file(&path)?;
+
+        Ok(Some(config))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let mut names = BTreeSet::new();
+        for prompt in &self.prompts {
+            let name = prompt.name.as_str();
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(format!(
+                    "prompt name `{name}` can only have letters, digits, '-' and '_'"
+                ));
+            }
+            if name == PROJECT_NAME {
+                return Err(format!(
+                    "`{PROJECT_NAME}` is the name of the project, not a prompt"
+                ));
+            }
+            if !names.insert(name) {
+                return Err(format!("there are several prompts named `{name}`"));
+            }
+            if let Some(default) = &prompt.default {
+                if !prompt.choices.is_empty() && !prompt.choices.contains(default) {
+                    return Err(format!("the default of `{name}` is not one of its choices"));
+                }
+            }
+        }
+        if self
+            .hooks
+            .post_generate
+            .iter()
+            .any(|hook| hook.trim().is_empty())
+        {
+            return Err("post-generate hooks can not be empty".to_string());
+        }
+
+        Ok(())
+    }
+}
+
+/// The values of the variables of the template, from `given` or else by asking. Without a
+/// terminal, prompts that were not answered take their defaults.
+pub fn answer(
+    prompts: &[Prompt],
+    project_name: &str,
+    mut given: BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>> {
+    if let Some(unknown) = given
+        .keys()
+        .find(|name| !prompts.iter().any(|prompt| &prompt.name == *name))
+    {
+        bail!(
+            "--var {unknown} is not a prompt of the template, which has: {}",
+            prompts
+                .iter()
+                .map(|prompt| prompt.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    let mut vars = BTreeMap::from([(PROJECT_NAME.to_string(), project_name.to_string())]);
+    for prompt in prompts {
+        let value = match given.remove(&prompt.name) {
+            Some(value) => {
+                if !prompt.choices.is_empty() && !prompt.choices.contains(&value) {
+                    bail!(
+                        "`{value}` is not a choice for {}, pick one of {}",
+                        prompt.name,
+                        prompt.choices.join(", ")
+                    );
+                }
+                value
+            }
+            None => ask(prompt)?,
+        };
+        vars.insert(prompt.name.clone(), value);
+    }
+
+    Ok(vars)
+}
+
+fn ask(prompt: &Prompt) -> Result<String> {
+    let instead = format!("Pass --var {}=<VALUE> to answer it.", prompt.name);
+    if !prompt::is_interactive() {
+        if let Some(default) = &prompt.default {
+            return Ok(default.clone());
+        }
+    }
+
+    if prompt.choices.is_empty() {
+        prompt::require(&instead)?;
+        let theme = ColorfulTheme::default();
+        let mut input = Input::<String>::with_theme(&theme).with_prompt(&prompt.message);
+        if let Some(default) = &prompt.default {
+            input = input.default(default.clone());
+        }
+
+        return input.interact_text().context("failed to read the answer");
+    }
+
+    // Choices are picked by typing, so the default goes first
+    let mut choices = prompt.choices.clone();
+    if let Some(position) = prompt
+        .default
+        .as_ref()
+        .and_then(|default| choices.iter().position(|choice| choice == default))
+    {
+        let default = choices.remove(position);
+        choices.insert(0, default);
+    }
+    let index = prompt::pick(&prompt.message, &choices, &instead)?;
+
+    Ok(choices.swap_remove(index))
+}
+
+/// Replace the variables in the text files under `dir`
+pub fn substitute(dir: &Path, vars: &BTreeMap<String, String>) -> Result<()> {
+    for entry in WalkDir::new(dir)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git")
+    {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        // Files that are not UTF-8, like images, are left alone
+        let Ok(contents) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        let substituted = substitute_str(&contents, vars);
+        if substituted != contents {
+            fs::write(entry.path(), substituted)
+                .with_context(|| format!("failed to write {}", entry.path().display()))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn substitute_str(contents: &str, vars: &BTreeMap<String, String>) -> String {
+    vars.iter()
+        .fold(contents.to_string(), |contents, (name, value)| {
+            contents.replace(&format!("{{{{{name}}}}}"), value)
+        })
+}
+
+/// Run the hooks in `dir`, with the variables as `SHUTTLE_TEMPLATE_<NAME>` environment variables
+pub fn run_hooks(dir: &Path, hooks: &[String], vars: &BTreeMap<String, String>) -> Result<()> {
+    for hook in hooks {
+        let mut words = hook.split_whitespace();
+        let program = words.next().expect("hooks to not be empty");
+
+        let mut command = Command::new(program);
+        command.args(words).current_dir(dir).env_clear();
+        for name in HOOK_ENV_VARS {
+            if let Some(value) = std::env::var_os(name) {
+                command.env(name, value);
+            }
+        }
+        for (name, value) in vars {
+            command.env(env_var_name(name), value);
+        }
+
+        println!("Running `{hook}`");
+        let status = command
+            .status()
+            .with_context(|| format!("failed to run `{hook}`"))?;
+        if !status.success() {
+            bail!("`{hook}` failed with {status}");
+        }
+    }
+
+    Ok(())
+}
+
+fn env_var_name(name: &str) -> String {
+    format!(
+        "SHUTTLE_TEMPLATE_{}",
+        name.to_ascii_uppercase().replace('-', "_")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_validates() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(FILE_NAME),
+            r#"
+            [[prompt]]
+            name = "db"
+            message = "Which database?"
+            choices = ["postgres", "none"]
+            default = "postgres"
+
+            [hooks]
+            post-generate = ["cargo fmt"]
+            "#,
+        )
+        .unwrap();
+
+        let config = TemplateConfig::take(dir.path()).unwrap().unwrap();
+        assert_eq!(config.prompts[0].choices, ["postgres", "none"]);
+        assert_eq!(config.hooks.post_generate, ["cargo fmt"]);
+        // Not copied into the project
+        assert!(!dir.path().join(FILE_NAME).exists());
+        assert_eq!(TemplateConfig::take(dir.path()).unwrap(), None);
+
+        let invalid = TemplateConfig {
+            prompts: vec![Prompt {
+                name: "db".to_string(),
+                message: "Which database?".to_string(),
+                choices: vec!["postgres".to_string()],
+                default: Some("mysql".to_string()),
+            }],
+            hooks: Hooks::default(),
+        };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn answers_from_vars() {
+        let prompts = [Prompt {
+            name: "auth".to_string(),
+            message: "Which auth provider?".to_string(),
+            choices: vec!["github".to_string(), "google".to_string()],
+            default: None,
+        }];
+
+        let vars = answer(
+            &prompts,
+            "my-api",
+            [("auth".to_string(), "google".to_string())].into(),
+        )
+        .unwrap();
+        assert_eq!(vars["auth"], "google");
+        assert_eq!(vars[PROJECT_NAME], "my-api");
+
+        assert!(answer(
+            &prompts,
+            "my-api",
+            [("auth".to_string(), "okta".to_string())].into()
+        )
+        .is_err());
+        assert!(answer(
+            &prompts,
+            "my-api",
+            [("db".to_string(), "postgres".to_string())].into()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn substitutes() {
+        let vars = BTreeMap::from([
+            ("project-name".to_string(), "my-api".to_string()),
+            ("db".to_string(), "postgres".to_string()),
+        ]);
+
+        assert_eq!(
+            substitute_str("name = \"{{project-name}}\"\n# {{db}} {{other}}", &vars),
+            "name = \"my-api\"\n# postgres {{other}}"
+        );
+        // Escaped braces of format strings stay
+        assert_eq!(
+            substitute_str("format!(\"{{}}\", db)", &vars),
+            "format!(\"{{}}\", db)"
+        );
+        assert_eq!(
+            env_var_name("project-name"),
+            "SHUTTLE_TEMPLATE_PROJECT_NAME"
+        );
+    }
+}
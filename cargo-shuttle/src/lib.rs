@@ -1,15 +1,59 @@
+mod account;
+mod alias;
 mod args;
+mod cache;
+mod certificate;
+mod ci;
 mod client;
+mod compose;
+mod concurrent;
 pub mod config;
+mod connect;
+mod cron;
+mod dockerfile;
+mod doctor;
+mod domain;
+mod editor;
+mod env_vars;
+pub mod error_log;
+pub mod errors;
+mod export;
+pub mod feedback;
+mod file_hashes;
 mod init;
+mod local;
+mod lockfile;
+mod logs;
+mod migrate;
+mod notification;
+mod output;
+mod overview;
+mod plugin;
+mod progress;
+mod projects;
+mod prompt;
 mod provisioner_server;
+mod record;
+mod redact;
+mod resources;
+mod secrets;
+mod seed;
+mod style;
 mod suggestions;
+mod team;
+pub mod telemetry;
+mod tls;
+mod token;
+mod upgrade;
+mod upgrade_deps;
+mod watch;
+mod workspace;
 
 use std::collections::{BTreeMap, HashMap};
 use std::ffi::OsString;
 use std::fmt::Write as FmtWrite;
-use std::fs::{read_to_string, File};
-use std::io::stdout;
+use std::fs::File;
+use std::io::{stdout, BufWriter, Seek, Write};
 use std::net::{Ipv4Addr, SocketAddr};
 use std::path::{Path, PathBuf};
 use std::process::exit;
@@ -17,15 +61,18 @@ use std::str::FromStr;
 
 use anyhow::{anyhow, bail, Context, Result};
 use args::{ConfirmationArgs, GenerateCommand};
-use clap::{parser::ValueSource, CommandFactory, FromArgMatches};
+use clap::{parser::ValueSource, ArgMatches, CommandFactory, FromArgMatches, ValueEnum};
 use clap_complete::{generate, Shell};
 use clap_mangen::Man;
-use config::RequestContext;
+use config::{LocalRunConfig, Origin, RequestContext, Setting};
 use crossterm::style::Stylize;
 use dialoguer::{theme::ColorfulTheme, Confirm, Input, Password, Select};
 use flate2::write::GzEncoder;
 use flate2::Compression;
-use futures::{StreamExt, TryFutureExt};
+use futures::{
+    future::{join_all, select_all},
+    StreamExt, TryFutureExt,
+};
 use git2::{Repository, StatusOptions};
 use globset::{Glob, GlobSetBuilder};
 use ignore::overrides::OverrideBuilder;
@@ -35,56 +82,84 @@ use indoc::{formatdoc, printdoc};
 use shuttle_common::{
     constants::{
         API_URL_DEFAULT, DEFAULT_IDLE_MINUTES, EXAMPLES_REPO, EXECUTABLE_DIRNAME,
-        RESOURCE_SCHEMA_VERSION, SHUTTLE_GH_ISSUE_URL, SHUTTLE_IDLE_DOCS_URL,
-        SHUTTLE_INSTALL_DOCS_URL, SHUTTLE_LOGIN_URL, STORAGE_DIRNAME, TEMPLATES_SCHEMA_VERSION,
+        LOCAL_STATE_DIRNAME, RESOURCE_SCHEMA_VERSION, SHUTTLE_CONSOLE_URL, SHUTTLE_GH_ISSUE_URL,
+        SHUTTLE_IDLE_DOCS_URL, SHUTTLE_INSTALL_DOCS_URL, SHUTTLE_LOGIN_URL, SHUTTLE_STATUS_URL,
+        STORAGE_DIRNAME, STORAGE_PATH_ENV, TEMPLATES_SCHEMA_VERSION,
     },
+    database::{self, AwsRdsEngine, SharedEngine},
     deployment::{DEPLOYER_END_MESSAGES_BAD, DEPLOYER_END_MESSAGES_GOOD},
     models::{
         deployment::{
-            get_deployments_table, DeploymentRequest, CREATE_SERVICE_BODY_LIMIT,
+            get_deployments_table, CronJob, DeploymentRequest, CREATE_SERVICE_BODY_LIMIT,
             GIT_STRINGS_MAX_LENGTH,
         },
         error::ApiError,
         project,
-        resource::get_resource_tables,
+        resource::{get_resource_summaries, get_resource_tables},
     },
     resource::{self, ResourceInput, ShuttleResourceOutput},
     semvers_are_compatible,
     templates::TemplatesSchema,
-    ApiKey, DatabaseResource, DbInput, LogItem, VersionInfo,
+    ApiKey, CliCompatibility, ContainerRequest, DatabaseInfo, DatabaseResource, DbInput, LogItem,
+    VersionInfo,
 };
 use shuttle_proto::{
     provisioner::{provisioner_server::Provisioner, DatabaseRequest},
     runtime::{self, LoadRequest, StartRequest, StopRequest},
 };
 use shuttle_service::{
-    builder::{build_workspace, BuiltService},
-    runner, Environment,
+    builder::{build_workspace_with_options, BuildOptions, BuiltService, PRODUCTION_BUILD_IMAGE},
+    runner, Environment, CONTAINER_WORKDIR,
 };
 use strum::{EnumMessage, VariantArray};
-use tar::Builder;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use tar::{Builder, Header};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
 use tokio::process::Child;
-use tokio::time::{sleep, Duration};
+use tokio::time::{sleep, Duration, Instant};
 use tonic::{Request, Status};
 use tracing::{debug, error, trace, warn};
 use uuid::Uuid;
 
-pub use crate::args::{Command, ProjectArgs, RunArgs, ShuttleArgs};
 use crate::args::{
-    DeployArgs, DeploymentCommand, InitArgs, LoginArgs, LogoutArgs, LogsArgs, ProjectCommand,
-    ProjectStartArgs, ResourceCommand, TemplateLocation,
+    AliasCommand, CacheCommand, CertificateCommand, ColorChoice, ConfigCommand, ConfigShowArgs,
+    CronCommand, DeployArgs, DeploymentCommand, DomainCommand, EnvCommand, FeedbackArgs, InitArgs,
+    LocalCommand, LoginArgs, LogoutArgs, LogsArgs, LogsCommand, LogsForwardCommand, OpenArgs,
+    OutputMode, ProjectCommand, ProjectStartArgs, ResourceCommand, SecretsArgs, SecretsCommand,
+    TeamCommand, TelemetryCommand, TemplateLocation, TokenCommand, Verbosity,
 };
+pub use crate::args::{Command, ProjectArgs, RunArgs, ShuttleArgs, StatusArgs};
+use crate::cache::ResponseCache;
 use crate::client::Client;
+use crate::error_log::ErrorLogEntry;
+use crate::errors::ErrorCode;
+use crate::file_hashes::{FileHashes, LastDeploy};
+use crate::init::TemplateFetch;
+use crate::local::{format_size, LocalRun, LocalServices};
+use crate::lockfile::Lockfile;
+use crate::logs::{interleave, LogCursor, LogFilter, LogLimit, LogWriter, Timezone};
+use crate::overview::ApiKeySource;
+use crate::progress::Progress;
 use crate::provisioner_server::LocalProvisioner;
+use crate::seed::{Seed, SeedTarget};
+use crate::style::Tone;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
+const DEFAULT_LOCAL_PORT: u16 = 8000;
 
 // Returns the args and whether the PATH arg of the init command was explicitly given
 pub fn parse_args() -> (ShuttleArgs, bool) {
-    let matches = ShuttleArgs::command().get_matches();
-    let args =
+    let mut args: Vec<OsString> = std::env::args_os().collect();
+    // Cargo passes in the subcommand name to the invoked executable. It is removed instead of
+    // being parsed as an optional positional argument, which would take the place of the names
+    // of plugins.
+    if args.get(1).is_some_and(|arg| arg == "shuttle") {
+        args.remove(1);
+    }
+    let args = alias::expand(args, &alias::load());
+    let matches = ShuttleArgs::command().get_matches_from(args);
+    let mut args =
         ShuttleArgs::from_arg_matches(&matches).expect("args to already be parsed successfully");
+    args.arg_origins = arg_origins(&matches);
     let provided_path_to_init = matches
         .subcommand_matches("init")
         .is_some_and(|init_matches| {
@@ -94,11 +169,34 @@ pub fn parse_args() -> (ShuttleArgs, bool) {
     (args, provided_path_to_init)
 }
 
+/// Where the values of the top-level flags came from, for `cargo shuttle config show --origin`
+fn arg_origins(matches: &ArgMatches) -> BTreeMap<String, Origin> {
+    ShuttleArgs::command()
+        .get_arguments()
+        .filter_map(|arg| {
+            let id = arg.get_id().as_str();
+            let origin = match matches.value_source(id)? {
+                ValueSource::CommandLine => Origin::Flag(format!("--{}", arg.get_long()?)),
+                ValueSource::EnvVariable => {
+                    Origin::Env(arg.get_env()?.to_string_lossy().into_owned())
+                }
+                _ => Origin::Default,
+            };
+
+            Some((id.to_string(), origin))
+        })
+        .collect()
+}
+
 pub struct Shuttle {
     ctx: RequestContext,
     client: Option<Client>,
     version_info: Option<VersionInfo>,
     version_warnings: Vec<String>,
+    /// How results are printed, from the global `--output-format` flag
+    output: OutputMode,
+    /// Only the results are printed, from the global `--quiet` flag
+    quiet: bool,
 }
 
 impl Shuttle {
@@ -109,33 +207,75 @@ impl Shuttle {
             client: None,
             version_info: None,
             version_warnings: vec![],
+            output: OutputMode::default(),
+            quiet: false,
         })
     }
 
-    fn find_available_port(run_args: &mut RunArgs, services_len: usize) {
-        let default_port = run_args.port;
-        'outer: for port in (run_args.port..=std::u16::MAX).step_by(services_len.max(10)) {
-            for inner_port in port..(port + services_len as u16) {
-                if !portpicker::is_free_tcp(inner_port) {
-                    continue 'outer;
+    /// Fill in the port and address the services of a local run bind to. An explicitly
+    /// requested port has to be free, while the default one moves to a free one after asking.
+    fn resolve_local_address(
+        run_args: &mut RunArgs,
+        config: &LocalRunConfig,
+        services_len: usize,
+    ) -> Result<()> {
+        let services_len = services_len.max(1) as u16;
+        let is_free = |port: u16| {
+            port.checked_add(services_len - 1).is_some()
+                && (port..port + services_len).all(portpicker::is_free_tcp)
+        };
+
+        let host = match (run_args.host, run_args.external) {
+            (Some(host), _) => host,
+            (None, true) => Ipv4Addr::UNSPECIFIED.into(),
+            (None, false) => config.host.unwrap_or(Ipv4Addr::LOCALHOST.into()),
+        };
+        run_args.host = Some(host);
+
+        let port = match run_args.port.or(config.port) {
+            Some(0) => {
+                let port = std::iter::from_fn(portpicker::pick_unused_port)
+                    .take(100)
+                    .find(|port| is_free(*port))
+                    .context("failed to find a free port")?;
+                println!("Picked port {port}");
+
+                port
+            }
+            Some(port) => {
+                if !is_free(port) {
+                    bail!(
+                        "Port {port} is already in use. Pick another one with `--port`, \
+                        or use `--port 0` to get a free one."
+                    );
+                }
+
+                port
+            }
+            None => {
+                let default_port = DEFAULT_LOCAL_PORT;
+                let port = (default_port..=u16::MAX)
+                    .step_by(services_len.max(10) as usize)
+                    .find(|port| is_free(*port))
+                    .context("failed to find a free port")?;
+                if port != default_port
+                    && !prompt::confirm(
+                        format!(
+                            "Port {default_port} is already in use. Would you like to continue on port {port}?"
+                        ),
+                        true,
+                        "Pass --port to pick the port, or --port 0 to get a free one.",
+                    )?
+                {
+                    exit(0);
                 }
+
+                port
             }
-            run_args.port = port;
-            break;
-        }
+        };
+        run_args.port = Some(port);
 
-        if run_args.port != default_port
-            && !Confirm::with_theme(&ColorfulTheme::default())
-                .with_prompt(format!(
-                    "Port {} is already in use. Would you like to continue on port {}?",
-                    default_port, run_args.port
-                ))
-                .default(true)
-                .interact()
-                .unwrap()
-        {
-            exit(0);
-        }
+        Ok(())
     }
 
     pub async fn run(
@@ -151,7 +291,13 @@ impl Shuttle {
                 eprintln!("WARNING: API URL is probably incorrect. Ends with '/': {url}");
             }
         }
+        self.quiet = args.verbosity() == Verbosity::Quiet;
+        output::set_quiet(self.quiet);
         self.ctx.set_api_url(args.api_url);
+        self.ctx.set_arg_origins(args.arg_origins);
+        self.output = args.output;
+        style::init(args.color, self.output, &self.ctx.theme_config());
+        workspace::set_disk_cache(!args.no_cache);
 
         // All commands that need to know which project is being handled
         if matches!(
@@ -159,6 +305,13 @@ impl Shuttle {
             Command::Deploy(..)
                 | Command::Deployment(..)
                 | Command::Resource(..)
+                | Command::Certificate(..)
+                | Command::Domain(..)
+                | Command::Migrate(..)
+                | Command::Secrets(..)
+                | Command::Env(..)
+                | Command::Cron(..)
+                | Command::Local(LocalCommand::Status | LocalCommand::Clean(..))
                 | Command::Project(
                     // ProjectCommand::List does not need to know which project we are in
                     ProjectCommand::Start { .. }
@@ -166,14 +319,55 @@ impl Shuttle {
                         | ProjectCommand::Restart { .. }
                         | ProjectCommand::Status { .. }
                         | ProjectCommand::Delete { .. }
+                        | ProjectCommand::Export(..)
+                        | ProjectCommand::Lock { .. }
+                        | ProjectCommand::Unlock
                 )
                 | Command::Stop
                 | Command::Clean
-                | Command::Status
+                | Command::Status(..)
+                | Command::Lsp(..)
+                | Command::Open(..)
                 | Command::Logs { .. }
                 | Command::Run(..)
+                | Command::UpgradeDeps(..)
+                | Command::Generate(
+                    GenerateCommand::Compose { .. }
+                        | GenerateCommand::Ci { .. }
+                        | GenerateCommand::Dockerfile { .. }
+                )
         ) {
-            self.load_project(&args.project_args)?;
+            match self.load_project(&args.project_args) {
+                // Outside of a crate, commands that only use the API can pick a project instead
+                Err(err)
+                    if args.project_args.name.is_none()
+                        && !args.offline
+                        && can_pick_project(&args.cmd)
+                        && prompt::is_interactive()
+                        && args.project_args.workspace_path().is_err() =>
+                {
+                    debug!(error = %err, "no project to infer, asking for one");
+                    let name = self.pick_project().await?;
+                    let project_args = ProjectArgs {
+                        name: Some(name.clone()),
+                        ..args.project_args.clone()
+                    };
+                    self.load_project(&project_args)?;
+
+                    let path = self.ctx.project_config_path();
+                    if prompt::confirm(
+                        format!(
+                            "Save {name} to {} for the next commands here?",
+                            path.display()
+                        ),
+                        false,
+                        "",
+                    )? {
+                        config::save_project_name(&path, &name)?;
+                    }
+                }
+                res => res?,
+            }
         }
 
         // All commands that call the API
@@ -181,23 +375,45 @@ impl Shuttle {
             args.cmd,
             Command::Init(..)
                 | Command::Deploy(..)
-                | Command::Status
-                | Command::Logs { .. }
+                | Command::Status(..)
+                | Command::Lsp(..)
+                | Command::Open(..)
+                // Local logs are read from disk
+                | Command::Logs(LogsArgs { local: false, .. })
                 | Command::Logout(..)
                 | Command::Deployment(..)
                 | Command::Resource(..)
+                | Command::Certificate(..)
+                | Command::Migrate(..)
+                | Command::Secrets(
+                    // SecretsCommand::Sync only works on local files
+                    SecretsCommand::List { .. }
+                        | SecretsCommand::Set { .. }
+                        | SecretsCommand::Get { .. }
+                        | SecretsCommand::Remove { .. }
+                        | SecretsCommand::Clear { .. }
+                        | SecretsCommand::Diff { .. }
+                        | SecretsCommand::Import { .. }
+                        | SecretsCommand::Rotate { .. }
+                )
                 | Command::Stop
                 | Command::Clean
                 | Command::Project(..)
+                | Command::Team(..)
+                | Command::Token(..)
+                | Command::Whoami
         ) {
-            let mut client = Client::new(self.ctx.api_url());
+            let mut client = self.ctx.api_client();
+            if !args.no_cache {
+                client.set_cache(ResponseCache::new(self.ctx.cache_dir()));
+            }
             if !matches!(args.cmd, Command::Init(..)) {
                 // init command will handle this by itself (log in and set key) if there is no key yet
                 client.set_api_key(self.ctx.api_key()?);
             }
             self.client = Some(client);
             if !args.offline {
-                self.check_api_versions().await?;
+                self.check_api_versions(args.force).await?;
             }
         }
 
@@ -212,15 +428,51 @@ impl Shuttle {
                 .await
             }
             Command::Generate(GenerateCommand::Manpage) => self.generate_manpage(),
-            Command::Generate(GenerateCommand::Shell { shell, output }) => {
-                self.complete(shell, output)
+            Command::Generate(GenerateCommand::Manpages { dir }) => self.generate_manpages(dir),
+            Command::Generate(GenerateCommand::Shell { shell, out }) => self.complete(shell, out),
+            Command::Generate(GenerateCommand::Ci {
+                provider,
+                out,
+                force,
+            }) => self.generate_ci(provider, out, force),
+            Command::Generate(GenerateCommand::Dockerfile { out, force }) => {
+                self.generate_dockerfile(out, force)
+            }
+            Command::Generate(GenerateCommand::Compose { out, force }) => {
+                self.generate_compose(out, force)
             }
             Command::Login(login_args) => self.login(login_args).await,
             Command::Logout(logout_args) => self.logout(logout_args).await,
-            Command::Feedback => self.feedback(),
-            Command::Run(run_args) => self.local_run(run_args).await,
-            Command::Deploy(deploy_args) => self.deploy(deploy_args).await,
-            Command::Status => self.status().await,
+            Command::Feedback(args) => self.feedback(args),
+            Command::Doctor => self.doctor(args.project_args, args.offline).await,
+            Command::External(plugin_args) => self.plugin(plugin_args, args.project_args).await,
+            Command::Telemetry(TelemetryCommand::Enable) => self.telemetry_set(true),
+            Command::Telemetry(TelemetryCommand::Disable) => self.telemetry_set(false),
+            Command::Telemetry(TelemetryCommand::Status) => self.telemetry_status(),
+            Command::Config(ConfigCommand::Show(show_args)) => self.config_show(
+                show_args,
+                args.project_args,
+                args.offline,
+                args.no_cache,
+                args.color,
+            ),
+            Command::Alias(AliasCommand::List) => self.alias_list(),
+            Command::Alias(AliasCommand::Add { name, command }) => self.alias_add(name, command),
+            Command::Alias(AliasCommand::Rm { name }) => self.alias_remove(name),
+            Command::Upgrade(upgrade_args) => self.upgrade(upgrade_args, args.offline).await,
+            Command::UpgradeDeps(upgrade_deps_args) => {
+                self.upgrade_deps(upgrade_deps_args, args.offline).await
+            }
+            Command::Run(run_args) => self.local_run(run_args, args.offline).await,
+            Command::Deploy(deploy_args) => {
+                let started = Instant::now();
+                let res = self.deploy(deploy_args).await;
+                self.notify_deployed(started, &res).await;
+                res
+            }
+            Command::Status(args) => self.status(args).await,
+            Command::Lsp(args) => self.lsp(args).await,
+            Command::Open(open_args) => self.open(open_args).await,
             Command::Logs(logs_args) => self.logs(logs_args).await,
             Command::Deployment(DeploymentCommand::List { page, limit, raw }) => {
                 self.deployments_list(page, limit, raw).await
@@ -231,10 +483,94 @@ impl Shuttle {
             }
             Command::Stop => self.stop().await,
             Command::Clean => self.clean().await,
+            Command::Resource(ResourceCommand::Show {
+                resource_type,
+                format,
+                var,
+                show_secrets,
+            }) => {
+                self.resource_show(resource_type, format, &var, show_secrets)
+                    .await
+            }
+            Command::Resource(ResourceCommand::Connect { resource_type }) => {
+                self.resource_connect(resource_type).await
+            }
+            Command::Migrate(migrate_args) => self.migrate(migrate_args).await,
             Command::Resource(ResourceCommand::Delete {
                 resource_type,
                 confirmation: ConfirmationArgs { yes },
             }) => self.resource_delete(&resource_type, yes).await,
+            Command::Domain(DomainCommand::Check { domain, target }) => {
+                self.domain_check(&domain, target).await
+            }
+            Command::Certificate(CertificateCommand::List { raw }) => {
+                self.certificates_list(raw).await
+            }
+            Command::Certificate(CertificateCommand::Status { domain }) => {
+                self.certificate_status(&domain).await
+            }
+            Command::Certificate(CertificateCommand::Renew { domain }) => {
+                self.certificate_renew(&domain).await
+            }
+            Command::Secrets(SecretsCommand::List { raw }) => self.secrets_list(raw).await,
+            Command::Secrets(SecretsCommand::Set {
+                key,
+                value,
+                generate,
+                format,
+            }) => self.secret_set(key, value, generate, format).await,
+            Command::Secrets(SecretsCommand::Get { key, reveal }) => {
+                self.secret_get(key, reveal).await
+            }
+            Command::Secrets(SecretsCommand::Remove {
+                key,
+                confirmation: ConfirmationArgs { yes },
+            }) => self.secret_remove(key, yes).await,
+            Command::Secrets(SecretsCommand::Clear(ConfirmationArgs { yes })) => {
+                self.secrets_clear(yes).await
+            }
+            Command::Secrets(SecretsCommand::Sync {
+                from,
+                confirmation: ConfirmationArgs { yes },
+            }) => self.secrets_sync(from, yes),
+            Command::Secrets(SecretsCommand::Diff(secret_args)) => {
+                self.secrets_diff(secret_args).await
+            }
+            Command::Secrets(SecretsCommand::Rotate {
+                key,
+                length,
+                format,
+                confirmation: ConfirmationArgs { yes },
+            }) => self.secret_rotate(key, length, format, yes).await,
+            Command::Secrets(SecretsCommand::Import {
+                from,
+                confirmation: ConfirmationArgs { yes },
+            }) => self.secrets_import(from, yes).await,
+            Command::Cron(CronCommand::List) => self.cron_list(),
+            Command::Cron(CronCommand::Add {
+                name,
+                schedule,
+                path,
+                command,
+            }) => self.cron_add(
+                &name,
+                CronJob {
+                    schedule,
+                    path,
+                    command,
+                },
+            ),
+            Command::Cron(CronCommand::Remove { name }) => self.cron_remove(&name),
+            Command::Env(EnvCommand::List) => self.env_list(),
+            Command::Env(EnvCommand::Set { name, value }) => self.env_set(name, value),
+            Command::Env(EnvCommand::Remove { name }) => self.env_remove(name),
+            Command::Local(LocalCommand::Status) => self.local_status(),
+            Command::Local(LocalCommand::Clean(ConfirmationArgs { yes })) => {
+                self.local_clean(yes).await
+            }
+            Command::Local(LocalCommand::Ps) => self.local_ps().await,
+            Command::Cache(CacheCommand::Clear) => self.cache_clear(&args.project_args),
+            Command::Local(LocalCommand::Stop { project }) => Shuttle::local_stop(&project).await,
             Command::Project(ProjectCommand::Start(ProjectStartArgs { idle_minutes })) => {
                 self.project_start(idle_minutes).await
             }
@@ -244,6 +580,28 @@ impl Shuttle {
             Command::Project(ProjectCommand::Status { follow }) => {
                 self.project_status(follow).await
             }
+            Command::Team(TeamCommand::List { raw }) => self.teams_list(raw).await,
+            Command::Team(TeamCommand::Create { name }) => self.team_create(&name).await,
+            Command::Team(TeamCommand::Members { team, raw }) => self.team_members(team, raw).await,
+            Command::Team(TeamCommand::Invite { user_id, team }) => {
+                self.team_invite(&user_id, team).await
+            }
+            Command::Team(TeamCommand::Remove {
+                user_id,
+                team,
+                confirmation: ConfirmationArgs { yes },
+            }) => self.team_remove(&user_id, team, yes).await,
+            Command::Whoami => self.whoami().await,
+            Command::Token(TokenCommand::Create {
+                name,
+                scopes,
+                expires,
+            }) => self.token_create(name, scopes, expires).await,
+            Command::Token(TokenCommand::List { raw }) => self.tokens_list(raw).await,
+            Command::Token(TokenCommand::Revoke {
+                id,
+                confirmation: ConfirmationArgs { yes },
+            }) => self.token_revoke(&id, yes).await,
             Command::Project(ProjectCommand::List { page, limit, raw }) => {
                 self.projects_list(page, limit, raw).await
             }
@@ -251,56 +609,78 @@ impl Shuttle {
             Command::Project(ProjectCommand::Delete(ConfirmationArgs { yes })) => {
                 self.project_delete(yes).await
             }
+            Command::Project(ProjectCommand::Export(export_args)) => {
+                self.project_export(export_args).await
+            }
+            Command::Project(ProjectCommand::Lock { reason }) => self.project_lock(reason).await,
+            Command::Project(ProjectCommand::Unlock) => self.project_unlock().await,
         };
 
         for w in self.version_warnings {
-            println!("{w}");
+            output::hint(self.output, w);
         }
 
         res
     }
 
-    async fn check_api_versions(&mut self) -> Result<()> {
+    /// Compare this version of cargo-shuttle with the ones the API supports. Versions older than
+    /// that range are refused unless forced, since the API may not understand them anymore, while
+    /// newer ones are only warned about.
+    async fn check_api_versions(&mut self, force: bool) -> Result<()> {
         let client = self.client.as_ref().unwrap();
         debug!("Checking API versions");
-        if let Ok(versions) = client.get_api_versions().await {
-            debug!("Got API versions: {versions:?}");
-            self.version_info = Some(versions);
-
-            // check cargo-shuttle version
-            // should always be a valid semver
-            let my_version = &semver::Version::from_str(VERSION).unwrap();
-            let latest_version = &self.version_info.as_ref().unwrap().cargo_shuttle;
-            if my_version != latest_version {
-                let newer_version_exists = my_version < latest_version;
-                let string = if semvers_are_compatible(my_version, latest_version) {
-                    newer_version_exists.then(|| {
-                        format!("Info: A newer version of cargo-shuttle exists ({latest_version}).")
-                    })
-                    // Having a newer but compatible version does not show warning
-                } else {
-                    newer_version_exists.then(||
-                        formatdoc! {"
-                            Warning:
-                                A newer version of cargo-shuttle exists ({latest_version}).
-                                It is recommended to upgrade.
-                                Refer to the upgrading docs: https://docs.shuttle.rs/configuration/shuttle-versions#upgrading-shuttle-version"
-                        }
-                    ).or_else(||
-                        Some(formatdoc! {"
-                            Warning:
-                                Your version of cargo-shuttle ({my_version}) is newer than what the API expects ({latest_version}).
-                                This means a new release is likely underway!
-                                Unexpected behavior can occur until the API is updated."
-                        })
-                    )
-                };
-                if let Some(s) = string {
-                    self.version_warnings.push(s.yellow().to_string());
-                }
-            }
-        } else {
+        let Ok(versions) = client.get_api_versions().await else {
             debug!("Failed to get API version info");
+            return Ok(());
+        };
+        debug!("Got API versions: {versions:?}");
+
+        // should always be a valid semver
+        let my_version = &semver::Version::from_str(VERSION).unwrap();
+        let latest_version = &versions.cargo_shuttle;
+        let unsupported = match versions.cli_compatibility(my_version) {
+            CliCompatibility::Latest => None,
+            CliCompatibility::Outdated => {
+                self.version_warnings.push(
+                    format!("Info: A newer version of cargo-shuttle exists ({latest_version}).")
+                        .yellow()
+                        .to_string(),
+                );
+                None
+            }
+            CliCompatibility::TooOld => Some(formatdoc! {"
+                This version of cargo-shuttle ({my_version}) is older than the API supports.
+                Upgrade to {latest_version} with `cargo shuttle upgrade`.
+                Refer to the upgrading docs: https://docs.shuttle.rs/configuration/shuttle-versions#upgrading-shuttle-version"
+            }),
+            CliCompatibility::TooNew => {
+                self.version_warnings.push(
+                    formatdoc! {"
+                        Warning:
+                            This version of cargo-shuttle ({my_version}) is newer than what the API expects ({latest_version}).
+                            This means a new release is likely underway!
+                            Unexpected behavior can occur until that is done."
+                    }
+                    .yellow()
+                    .to_string(),
+                );
+                None
+            }
+        };
+        self.version_info = Some(versions);
+
+        if let Some(message) = unsupported {
+            if !force {
+                return Err(errors::with_code(
+                    anyhow!("{message}\nPass --force before the command to run it anyway."),
+                    ErrorCode::Validation,
+                ));
+            }
+            self.version_warnings.push(
+                format!("Warning:\n{message}\nUnexpected behavior can occur.")
+                    .yellow()
+                    .to_string(),
+            );
         }
 
         Ok(())
@@ -328,6 +708,21 @@ impl Shuttle {
         let needs_path = !provided_path_to_init;
         let needs_login = unauthorized;
         let interactive = needs_name || needs_template || needs_path || needs_login;
+        if interactive {
+            let missing = [
+                (needs_name, "--name"),
+                (needs_template, "--template or --from"),
+                (needs_path, "the path"),
+                (needs_login, "--api-key"),
+            ]
+            .into_iter()
+            .filter_map(|(needed, flag)| needed.then_some(flag))
+            .collect::<Vec<_>>();
+            prompt::require(&format!(
+                "Pass {} to create the project without prompts.",
+                missing.join(", ")
+            ))?;
+        }
 
         let theme = ColorfulTheme::default();
 
@@ -575,6 +970,13 @@ impl Shuttle {
                 .expect("to have a project name provided"),
             template,
             no_git,
+            &TemplateFetch {
+                cache_dir: self.ctx.cache_dir().join("templates"),
+                offline,
+                refresh: args.refresh,
+            },
+            args.vars.iter().cloned().collect(),
+            args.allow_hooks,
         )?;
         println!();
 
@@ -678,10 +1080,92 @@ impl Shuttle {
         self.ctx.load_local(project_args)
     }
 
+    /// Let the user pick one of their projects, and the ones of their organizations
+    async fn pick_project(&self) -> Result<String> {
+        let mut client = self.ctx.api_client();
+        client.set_api_key(self.ctx.api_key()?);
+
+        let progress = Progress::spinner("Getting your projects");
+        let mut projects = client
+            .get_projects_list(1, 1000)
+            .await?
+            .into_iter()
+            .map(|project| (project.name.clone(), project.name))
+            .collect::<Vec<_>>();
+        let orgs = client.get_organizations_list().await?;
+        let org_projects = concurrent::map(&orgs, self.ctx.concurrency(), |org| {
+            client.get_organization_projects_list(&org.id)
+        })
+        .await;
+        for (org, org_projects) in orgs.iter().zip(org_projects) {
+            projects.extend(org_projects?.into_iter().map(|project| {
+                (
+                    format!("{} ({})", project.name, org.display_name),
+                    project.name,
+                )
+            }));
+        }
+        progress.finish_and_clear();
+
+        if projects.is_empty() {
+            bail!("There is no project to pick. Run this command in a project, or create one with `cargo shuttle init`.");
+        }
+
+        let items = projects
+            .iter()
+            .map(|(item, _)| item.clone())
+            .collect::<Vec<_>>();
+        let picked = prompt::pick(
+            "Which project? Type to search",
+            &items,
+            "Pass --name to pick the project.",
+        )?;
+
+        Ok(projects.swap_remove(picked).1)
+    }
+
     /// Provide feedback on GitHub.
-    fn feedback(&self) -> Result<CommandOutcome> {
-        let _ = webbrowser::open(SHUTTLE_GH_ISSUE_URL);
-        println!("If your browser did not open automatically, go to {SHUTTLE_GH_ISSUE_URL}");
+    fn feedback(&self, args: FeedbackArgs) -> Result<CommandOutcome> {
+        // The key is masked like the secrets, in case an error message contains it
+        if let Ok(api_key) = self.ctx.api_key() {
+            redact::register([api_key.as_ref().to_string()]);
+        }
+
+        let mut report = feedback::Report::collect();
+        let errors = error_log::recent(5)?;
+        let include_errors = !errors.is_empty()
+            && (args.include_errors
+                || (prompt::is_interactive()
+                    && prompt::confirm(
+                        format!(
+                            "Include the {} most recent errors of the error log in the report?",
+                            errors.len()
+                        ),
+                        false,
+                        "Pass --include-errors to include them.",
+                    )?));
+        if include_errors {
+            report.errors = errors;
+        }
+
+        println!("{}", report.to_markdown());
+        let title = args.title.as_deref().unwrap_or("");
+        let (url, left_out) = report.issue_url(title);
+        if left_out > 0 {
+            output::hint(
+                self.output,
+                format!(
+                    "The {left_out} oldest errors did not fit in the issue, add them from the \
+                    report above if they matter"
+                ),
+            );
+        }
+
+        let _ = webbrowser::open(&url);
+        println!(
+            "If your browser did not open automatically, go to {SHUTTLE_GH_ISSUE_URL} and paste \
+            the report above into a new issue"
+        );
 
         Ok(CommandOutcome::Ok)
     }
@@ -694,6 +1178,7 @@ impl Shuttle {
                 let _ = webbrowser::open(SHUTTLE_LOGIN_URL);
                 println!("If your browser did not automatically open, go to {SHUTTLE_LOGIN_URL}");
 
+                prompt::require("Pass --api-key to log in.")?;
                 Password::with_theme(&ColorfulTheme::default())
                     .with_prompt("API key")
                     .validate_with(|input: &String| ApiKey::parse(input).map(|_| ()))
@@ -811,11 +1296,95 @@ impl Shuttle {
         Ok(CommandOutcome::Ok)
     }
 
-    async fn status(&self) -> Result<CommandOutcome> {
+    fn generate_manpages(&self, dir: PathBuf) -> Result<CommandOutcome> {
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+
+        let mut app = ShuttleArgs::command()
+            .name("cargo-shuttle")
+            .bin_name("cargo shuttle");
+        app.build();
+        let pages = write_manpages(&app, "cargo-shuttle", &dir)?;
+
+        println!(
+            "{} {} man pages to {}",
+            style::status("Written", Tone::Success),
+            pages.len(),
+            dir.display()
+        );
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    async fn status(&self, args: StatusArgs) -> Result<CommandOutcome> {
         let client = self.client.as_ref().unwrap();
-        let summary = client.get_service(self.ctx.project_name()).await?;
+        let api_key = ApiKeySource::current();
+
+        loop {
+            let overview = overview::fetch(client, self.ctx.project_name(), api_key).await;
+            if args.watch && self.output == OutputMode::Table {
+                crossterm::execute!(
+                    stdout(),
+                    crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
+                    crossterm::cursor::MoveTo(0, 0)
+                )?;
+            }
+            output::print(self.output, &overview, |_| overview.to_string())?;
+            if !args.watch {
+                if overview.nothing_fetched() {
+                    bail!(
+                        "Could not fetch the status of {}. Check the network connection, or {SHUTTLE_STATUS_URL}.",
+                        self.ctx.project_name()
+                    );
+                }
+                break;
+            }
+
+            output::hint(
+                self.output,
+                format!(
+                    "Refreshing every {}, stop with Ctrl-C",
+                    humantime::format_duration(args.interval)
+                )
+                .dim(),
+            );
+            tokio::select! {
+                _ = sleep(args.interval) => {}
+                _ = tokio::signal::ctrl_c() => break,
+            }
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    async fn open(&self, args: OpenArgs) -> Result<CommandOutcome> {
+        let project_name = self.ctx.project_name();
+        let console_url = format!("{SHUTTLE_CONSOLE_URL}/project/{project_name}");
+
+        let url = if args.console {
+            console_url
+        } else {
+            let client = self.client.as_ref().unwrap();
+            let summary = client.get_service(project_name).await?;
+            let Some(deployment) = summary.deployment else {
+                bail!("{project_name} has not been deployed yet. Deploy it with `cargo shuttle deploy`.");
+            };
+
+            if args.logs {
+                format!("{console_url}/deployments/{}", deployment.id)
+            } else {
+                if deployment.state != shuttle_common::deployment::State::Running {
+                    output::hint(
+                        self.output,
+                        format!("The latest deployment is {}", deployment.state).yellow(),
+                    );
+                }
+                summary.uri
+            }
+        };
 
-        println!("{summary}");
+        let _ = webbrowser::open(&url);
+        println!("If your browser did not open automatically, go to {url}");
 
         Ok(CommandOutcome::Ok)
     }
@@ -838,8 +1407,76 @@ impl Shuttle {
         Ok(CommandOutcome::Ok)
     }
 
-    async fn logs(&self, args: LogsArgs) -> Result<CommandOutcome> {
+    async fn logs(&mut self, args: LogsArgs) -> Result<CommandOutcome> {
+        match args.cmd {
+            Some(LogsCommand::Search(search_args)) => return self.logs_search(search_args).await,
+            Some(LogsCommand::Forward(LogsForwardCommand::Show)) => {
+                return self.logs_forward_show()
+            }
+            Some(LogsCommand::Forward(LogsForwardCommand::Test)) => {
+                return self.logs_forward_test().await
+            }
+            None => {}
+        }
+
+        let mut defaults = self.ctx.logs_config();
+        if args.save_defaults {
+            if args.utc {
+                defaults.timezone = Some(Timezone::Utc);
+            } else if args.local_time {
+                defaults.timezone = Some(Timezone::Local);
+            }
+            if let Some(time_format) = &args.time_format {
+                defaults.time_format = Some(time_format.clone());
+            }
+            self.ctx.set_logs_config(defaults.clone())?;
+            eprintln!("Saved the timestamp display as the default for `cargo shuttle logs`");
+        }
+
+        // Only the plaintext file, reading logs should not need to decrypt anything
+        self.register_local_secrets(Some(&self.ctx.working_directory().join("Secrets.toml")));
+        let filter = LogFilter::new(&args);
+        let mut writer = LogWriter::new(&args, &defaults, self.output)?;
+        let limit = LogLimit::new(&args);
+        // Only point out lines that were left out when no limit was asked for
+        let hint_left_out = |left_out: usize| {
+            if left_out > 0 && args.head.is_none() && args.tail.is_none() {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "Showing the last {} lines, {left_out} earlier lines were left out. \
+                        Use '--tail', '--head' or '--no-limit' to see them.",
+                        LogLimit::DEFAULT_TAIL
+                    )
+                    .dim()
+                );
+            }
+        };
+
+        if args.local {
+            let sessions = logs::local::sessions(self.ctx.working_directory())?;
+            if sessions.is_empty() {
+                bail!("No local runs were captured yet. Start one with `cargo shuttle run`");
+            }
+            let Some(session) = sessions.get(args.session.saturating_sub(1)) else {
+                bail!("Only {} local runs were captured", sessions.len());
+            };
+            let captured = logs::local::read_session(session)?;
+            hint_left_out(writer.write_batch(captured, &filter, limit)?);
+
+            return Ok(CommandOutcome::Ok);
+        }
+
         let client = self.client.as_ref().unwrap();
+        if args.all_deployments {
+            let left_out = self
+                .all_deployments_logs(&filter, &mut writer, limit)
+                .await?;
+            hint_left_out(left_out);
+
+            return Ok(CommandOutcome::Ok);
+        }
+
         let id = if let Some(id) = args.id {
             id
         } else {
@@ -873,113 +1510,265 @@ impl Shuttle {
         };
 
         if args.follow {
-            let mut stream = client
-                .get_logs_ws(self.ctx.project_name(), &id)
-                .await
-                .map_err(|err| {
-                    suggestions::logs::get_logs_failure(err, "Connecting to the logs stream failed")
-                })?;
-
-            while let Some(Ok(msg)) = stream.next().await {
-                if let tokio_tungstenite::tungstenite::Message::Text(line) = msg {
-                    match serde_json::from_str::<shuttle_common::LogItem>(&line) {
-                        Ok(log) => {
-                            if args.raw {
-                                println!("{}", log.get_raw_line())
-                            } else {
-                                println!("{log}")
-                            }
-                        }
-                        Err(err) => {
-                            debug!(error = %err, "failed to parse message into log item");
-
-                            let message = if let Ok(err) = serde_json::from_str::<ApiError>(&line) {
-                                err.to_string()
-                            } else {
-                                "failed to parse logs, is your cargo-shuttle outdated?".to_string()
-                            };
-
-                            bail!(message);
-                        }
-                    }
-                }
-            }
+            self.follow_logs(&id, &filter, &mut writer, args.tail)
+                .await?;
         } else {
+            let fetching = Progress::spinner("Fetching the logs");
             let logs = client
                 .get_logs(self.ctx.project_name(), &id)
                 .await
                 .map_err(|err| {
                     suggestions::logs::get_logs_failure(err, "Fetching the deployment failed")
                 })?;
+            fetching.finish_and_clear();
 
-            for log in logs.into_iter() {
-                if args.raw {
-                    println!("{}", log.get_raw_line())
-                } else {
-                    println!("{log}")
-                }
-            }
+            hint_left_out(writer.write_batch(logs, &filter, limit)?);
         }
 
         Ok(CommandOutcome::Ok)
     }
 
-    async fn deployments_list(&self, page: u32, limit: u32, raw: bool) -> Result<CommandOutcome> {
-        let client = self.client.as_ref().unwrap();
-        if limit == 0 {
-            println!();
-            return Ok(CommandOutcome::Ok);
-        }
-        let limit = limit + 1;
+    /// Print the logs of every deployment of the project, interleaved by time. Returns how many
+    /// lines were left out by the limit.
+    async fn all_deployments_logs(
+        &self,
+        filter: &LogFilter,
+        writer: &mut LogWriter,
+        limit: LogLimit,
+    ) -> Result<usize> {
+        const PAGE_SIZE: u32 = 100;
 
+        let client = self.client.as_ref().unwrap();
         let proj_name = self.ctx.project_name();
-        let mut deployments = client
-            .get_deployments(proj_name, page, limit)
-            .await
-            .map_err(suggestions::deployment::get_deployments_list_failure)?;
-        let page_hint = if deployments.len() == limit as usize {
-            deployments.pop();
-            true
-        } else {
-            false
-        };
-        let table = get_deployments_table(&deployments, proj_name, page, raw, page_hint);
-
-        println!("{table}");
 
+        let mut deployments = Vec::new();
+        for page in 1.. {
+            let batch = client
+                .get_deployments(proj_name, page, PAGE_SIZE)
+                .await
+                .map_err(|err| {
+                    suggestions::logs::get_logs_failure(err, "Fetching the deployments failed")
+                })?;
+            let last_page = batch.len() < PAGE_SIZE as usize;
+            deployments.extend(batch);
+            if last_page {
+                break;
+            }
+        }
         if deployments.is_empty() {
-            println!("Run `cargo shuttle deploy` to deploy your project.");
-        } else {
-            println!("Run `cargo shuttle logs <id>` to get logs for a given deployment.");
+            bail!("Could not find any deployments for '{proj_name}'");
         }
 
-        Ok(CommandOutcome::Ok)
-    }
-
-    async fn deployment_get(&self, deployment_id: Uuid) -> Result<CommandOutcome> {
-        let client = self.client.as_ref().unwrap();
-        let deployment = client
-            .get_deployment_details(self.ctx.project_name(), &deployment_id)
-            .await
-            .map_err(suggestions::deployment::get_deployment_status_failure)?;
-
-        println!("{deployment}");
+        let fetching = Progress::count("Fetching the logs", deployments.len() as u64);
+        let mut streams = Vec::with_capacity(deployments.len());
+        for deployment in deployments {
+            let logs = client
+                .get_logs(proj_name, &deployment.id)
+                .await
+                .map_err(|err| {
+                    suggestions::logs::get_logs_failure(err, "Fetching the deployment failed")
+                })?;
+            streams.push(logs);
+            fetching.inc(1);
+        }
+        fetching.finish_and_clear();
 
-        Ok(CommandOutcome::Ok)
+        writer.write_batch(interleave(streams), filter, limit)
     }
 
-    async fn resources_list(&self, raw: bool, show_secrets: bool) -> Result<CommandOutcome> {
-        let client = self.client.as_ref().unwrap();
-        let resources = client
-            .get_service_resources(self.ctx.project_name())
-            .await
-            .map_err(suggestions::resources::get_service_resources_failure)?;
-        let table = get_resource_tables(&resources, self.ctx.project_name(), raw, show_secrets);
+    /// Stream the logs of a deployment until the server closes the stream. Dropped connections
+    /// are reopened with backoff, skipping the lines that were already shown. With `tail`, only
+    /// that many lines of the backlog are shown first.
+    async fn follow_logs(
+        &self,
+        id: &Uuid,
+        filter: &LogFilter,
+        writer: &mut LogWriter,
+        tail: Option<usize>,
+    ) -> Result<()> {
+        const MAX_ATTEMPTS: u32 = 10;
+        // 1s, 2s, 4s, ... up to 30s
+        let backoff = |attempt: u32| Duration::from_secs((1u64 << (attempt - 1).min(5)).min(30));
 
-        println!("{table}");
+        let client = self.client.as_ref().unwrap();
+        let mut cursor = LogCursor::default();
+        let mut attempt = 0;
 
-        Ok(CommandOutcome::Ok)
-    }
+        if let Some(tail) = tail {
+            let fetching = Progress::spinner("Fetching the logs");
+            let backlog = client
+                .get_logs(self.ctx.project_name(), id)
+                .await
+                .map_err(|err| {
+                    suggestions::logs::get_logs_failure(err, "Fetching the deployment failed")
+                })?;
+            fetching.finish_and_clear();
+            // The stream replays the backlog, which the cursor then skips
+            for log in &backlog {
+                cursor.advance(log);
+            }
+            writer.write_batch(backlog, filter, LogLimit::Tail(tail))?;
+        }
+
+        loop {
+            let mut stream = match client.get_logs_ws(self.ctx.project_name(), id).await {
+                Ok(stream) => stream,
+                // Failing to connect at all is reported right away, there is nothing to resume
+                Err(err) if cursor.position().is_none() && attempt == 0 => {
+                    return Err(suggestions::logs::get_logs_failure(
+                        err,
+                        "Connecting to the logs stream failed",
+                    ));
+                }
+                Err(err) => {
+                    attempt += 1;
+                    if attempt > MAX_ATTEMPTS {
+                        return Err(suggestions::logs::get_logs_failure(
+                            err,
+                            "Reconnecting to the logs stream failed",
+                        ));
+                    }
+                    debug!(error = %err, "failed to reconnect to the logs stream");
+                    sleep(backoff(attempt)).await;
+                    continue;
+                }
+            };
+            if attempt > 0 {
+                let position = cursor.position().map_or("the start".to_string(), |ts| {
+                    ts.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+                });
+                eprintln!(
+                    "{}",
+                    format!("Reconnected, resuming from {position}").yellow()
+                );
+            }
+
+            let mut closed = false;
+            while let Some(msg) = stream.next().await {
+                let msg = match msg {
+                    Ok(msg) => msg,
+                    Err(err) => {
+                        debug!(error = %err, "logs stream failed");
+                        break;
+                    }
+                };
+                // Only a successfully received message means the connection is healthy
+                attempt = 0;
+
+                let line = match msg {
+                    tokio_tungstenite::tungstenite::Message::Text(line) => line,
+                    tokio_tungstenite::tungstenite::Message::Close(_) => {
+                        closed = true;
+                        break;
+                    }
+                    _ => continue,
+                };
+                match serde_json::from_str::<shuttle_common::LogItem>(&line) {
+                    Ok(log) => {
+                        if !cursor.advance(&log) || !filter.matches(&log) {
+                            continue;
+                        }
+                        let log = redact::redact_log_item(log);
+                        writer.write(&log)?;
+                    }
+                    Err(err) => {
+                        debug!(error = %err, "failed to parse message into log item");
+
+                        let message = if let Ok(err) = serde_json::from_str::<ApiError>(&line) {
+                            err.to_string()
+                        } else {
+                            "failed to parse logs, is your cargo-shuttle outdated?".to_string()
+                        };
+
+                        bail!(message);
+                    }
+                }
+            }
+
+            if closed {
+                return Ok(());
+            }
+            attempt += 1;
+            if attempt > MAX_ATTEMPTS {
+                bail!("The logs stream keeps disconnecting, giving up");
+            }
+            eprintln!(
+                "{}",
+                "Lost the connection to the logs stream, reconnecting...".yellow()
+            );
+            sleep(backoff(attempt)).await;
+        }
+    }
+
+    async fn deployments_list(&self, page: u32, limit: u32, raw: bool) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        if limit == 0 {
+            println!();
+            return Ok(CommandOutcome::Ok);
+        }
+        let limit = limit + 1;
+
+        let proj_name = self.ctx.project_name();
+        let mut deployments = client
+            .get_deployments(proj_name, page, limit)
+            .await
+            .map_err(suggestions::deployment::get_deployments_list_failure)?;
+        let page_hint = if deployments.len() == limit as usize {
+            deployments.pop();
+            true
+        } else {
+            false
+        };
+        output::print(self.output, &deployments, |plain| {
+            get_deployments_table(&deployments, proj_name, page, raw || plain, page_hint)
+        })?;
+
+        if deployments.is_empty() {
+            output::hint(
+                self.output,
+                "Run `cargo shuttle deploy` to deploy your project.",
+            );
+        } else {
+            output::hint(
+                self.output,
+                "Run `cargo shuttle logs <id>` to get logs for a given deployment.",
+            );
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    async fn deployment_get(&self, deployment_id: Uuid) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let deployment = client
+            .get_deployment_details(self.ctx.project_name(), &deployment_id)
+            .await
+            .map_err(suggestions::deployment::get_deployment_status_failure)?;
+
+        output::print(self.output, &deployment, |_| deployment.to_string())?;
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    async fn resources_list(&self, raw: bool, show_secrets: bool) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let resources = client
+            .get_service_resources(self.ctx.project_name())
+            .await
+            .map_err(suggestions::resources::get_service_resources_failure)?;
+
+        let summaries = get_resource_summaries(&resources, self.ctx.project_name(), show_secrets);
+        output::print(self.output, &summaries, |plain| {
+            get_resource_tables(
+                &resources,
+                self.ctx.project_name(),
+                raw || plain,
+                show_secrets,
+            )
+        })?;
+
+        Ok(CommandOutcome::Ok)
+    }
 
     async fn resource_delete(
         &self,
@@ -987,6 +1776,51 @@ impl Shuttle {
         no_confirm: bool,
     ) -> Result<CommandOutcome> {
         let client = self.client.as_ref().unwrap();
+        let project_name = self.ctx.project_name();
+
+        let resources = client
+            .get_service_resources(project_name)
+            .await
+            .map_err(suggestions::resources::get_service_resources_failure)?;
+        let Some(summary) = get_resource_summaries(&resources, project_name, false)
+            .into_iter()
+            .find(|summary| summary.r#type == resource_type.to_string())
+        else {
+            bail!(
+                "{project_name} has no {resource_type} resource. \
+                See `cargo shuttle resource list` for the ones it has."
+            );
+        };
+        let destroyed = match resource_type {
+            resource::Type::Database(_) => {
+                format!("The database {} and all of its data", summary.identifier)
+            }
+            resource::Type::Secrets => format!(
+                "The secrets {}",
+                summary.keys.unwrap_or_default().join(", ")
+            ),
+            resource::Type::Persist => "Everything stored with shuttle-persist".to_string(),
+            resource::Type::Container => format!("The container {}", summary.identifier),
+        };
+        // The annotation provisions the resource again, empty, on the next deployment
+        let declared = compose::declares(self.ctx.working_directory(), resource_type)
+            .unwrap_or_else(|err| {
+                debug!(error = %err, "failed to look for the resource annotation");
+                false
+            });
+
+        if declared {
+            println!(
+                "{}",
+                formatdoc! {"
+                    Note:
+                        The #[shuttle_runtime::main] function still declares this resource.
+                        Unless its annotation is removed, an empty {resource_type} is provisioned
+                        on the next deployment."
+                }
+                .yellow(),
+            );
+        }
 
         if !no_confirm {
             println!(
@@ -995,74 +1829,277 @@ impl Shuttle {
                     "
                 WARNING:
                     Are you sure you want to delete this project's {}?
+                    This destroys:
+                    - {}
                     This action is permanent.",
-                    resource_type
+                    resource_type,
+                    destroyed
                 )
                 .bold()
                 .red()
             );
-            if !Confirm::with_theme(&ColorfulTheme::default())
-                .with_prompt("Are you sure?")
-                .default(false)
-                .interact()
-                .unwrap()
-            {
+            prompt::require("Pass --yes to delete the resource.")?;
+            let typed: String = Input::with_theme(&ColorfulTheme::default())
+                .with_prompt(format!("Type {resource_type} to confirm"))
+                .allow_empty(true)
+                .interact_text()
+                .context("failed to read the answer")?;
+            if typed.trim() != resource_type.to_string() {
+                println!("That is not {resource_type}, nothing was deleted.");
                 return Ok(CommandOutcome::Ok);
             }
         }
 
         client
-            .delete_service_resource(self.ctx.project_name(), resource_type)
+            .delete_service_resource(project_name, resource_type)
             .await?;
 
         println!("Deleted resource {resource_type}");
-        println!(
-            "{}",
-            formatdoc! {"
-                Note:
-                    Remember to remove the resource annotation from your #[shuttle_runtime::main] function.
-                    Otherwise, it will be provisioned again during the next deployment."
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    /// Fail before building when secrets declared as required in Shuttle.toml are neither in the
+    /// secrets file nor already set on the project
+    async fn check_required_secrets(&self, secrets_file: Option<&Path>) -> Result<()> {
+        let mut required = self.ctx.required_secrets().to_vec();
+        // The connection strings of external resources are read from the secrets
+        for external in self
+            .ctx
+            .external_resources()
+            .iter()
+            .flat_map(|e| e.0.values())
+        {
+            if !required.contains(&external.secret) {
+                required.push(external.secret.clone());
             }
-            .yellow(),
-        );
+        }
+        if required.is_empty() {
+            return Ok(());
+        }
+
+        let secrets_file = secrets_file
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| secrets::default_secrets_file(self.ctx.working_directory()));
+        let mut available = secrets::read_secrets_file(&secrets_file)?;
+        if !secrets::missing_secrets(&required, &available).is_empty() {
+            // Secrets that were set before are kept when a deployment has no value for them
+            match self
+                .client
+                .as_ref()
+                .unwrap()
+                .get_secrets(self.ctx.project_name())
+                .await
+            {
+                Ok(remote) => {
+                    available.extend(remote.into_iter().map(|secret| (secret.key, String::new())))
+                }
+                Err(err) => debug!(error = %err, "failed to get the secrets of the project"),
+            }
+        }
+
+        let missing = secrets::missing_secrets(&required, &available);
+        if !missing.is_empty() {
+            bail!(
+                "Missing required secrets declared in Shuttle.toml:\n{}\n\
+                Add them to {} or set them with `cargo shuttle secrets set <KEY>`.",
+                missing
+                    .iter()
+                    .map(|key| format!("  - {key}"))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                secrets_file.display()
+            );
+        }
+
+        Ok(())
+    }
+
+    fn cache_clear(&self, project_args: &ProjectArgs) -> Result<CommandOutcome> {
+        let mut dirs = Vec::new();
+        // Outside of a project, only the global caches are cleared
+        if let Ok(workspace) = project_args.workspace_path() {
+            dirs.push(file_hashes::cache_dir(&workspace));
+        }
+        dirs.push(self.ctx.cache_dir());
+
+        let mut removed = false;
+        for dir in dirs.into_iter().filter(|dir| dir.exists()) {
+            std::fs::remove_dir_all(&dir)
+                .with_context(|| format!("failed to remove {}", dir.display()))?;
+            println!("Removed {}", dir.display());
+            removed = true;
+        }
+        if !removed {
+            println!("There are no caches to clear");
+        }
 
         Ok(CommandOutcome::Ok)
     }
 
+    fn config_show(
+        &mut self,
+        args: ConfigShowArgs,
+        project_args: ProjectArgs,
+        offline: bool,
+        no_cache: bool,
+        color: ColorChoice,
+    ) -> Result<CommandOutcome> {
+        // The project settings are shown when there is a project here
+        if let Err(err) = self.load_project(&project_args) {
+            debug!(error = %err, "no project to show the settings of");
+        }
+
+        let mut settings = self.ctx.settings();
+        settings.extend([
+            Setting::new(
+                "output",
+                self.output
+                    .to_possible_value()
+                    .map(|value| value.get_name().to_string()),
+                self.ctx.arg_origin("output", "--output-format", false),
+            ),
+            Setting::new(
+                "color",
+                color
+                    .to_possible_value()
+                    .map(|value| value.get_name().to_string()),
+                self.ctx.arg_origin("color", "--color", false),
+            ),
+            Setting::new(
+                "offline",
+                Some(offline),
+                self.ctx.arg_origin("offline", "--offline", offline),
+            ),
+            Setting::new(
+                "no-cache",
+                Some(no_cache),
+                self.ctx.arg_origin("no_cache", "--no-cache", no_cache),
+            ),
+        ]);
+
+        let human = |settings: &[Setting]| {
+            let width = settings
+                .iter()
+                .map(|setting| setting.name.len())
+                .max()
+                .unwrap_or_default();
+            settings
+                .iter()
+                .map(|setting| {
+                    let value = setting.value.as_deref().unwrap_or("(not set)");
+                    if args.origin {
+                        format!(
+                            "{:width$}  {value}  {}",
+                            setting.name,
+                            format!("({})", setting.origin).dim()
+                        )
+                    } else {
+                        format!("{:width$}  {value}", setting.name)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        if args.origin {
+            output::print(self.output, &settings, |_| human(&settings))?;
+        } else {
+            let values = settings
+                .iter()
+                .map(|setting| (setting.name, setting.value.as_deref()))
+                .collect::<BTreeMap<_, _>>();
+            output::print(self.output, &values, |_| human(&settings))?;
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    /// Make the values of the local secrets file known to the redaction of output. Failing to
+    /// read the file is not an error here, the commands that need it report that themselves.
+    fn register_local_secrets(&self, secrets_file: Option<&Path>) {
+        let secrets_file = secrets_file
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| secrets::default_secrets_file(self.ctx.working_directory()));
+
+        match secrets::read_secrets_file(&secrets_file) {
+            Ok(secrets) => redact::register(secrets.into_values()),
+            Err(err) => debug!(error = %err, "failed to read secrets for redaction"),
+        }
+    }
+
+    /// The secrets file selected by `--secrets` or `--env`, if any
+    fn secrets_file(&self, secret_args: &SecretsArgs) -> Result<Option<PathBuf>> {
+        secrets::resolve_secrets_file(
+            self.ctx.working_directory(),
+            secret_args.secrets.clone(),
+            secret_args.env.as_deref(),
+            self.ctx.environments(),
+        )
+    }
+
     async fn spin_local_runtime(
         run_args: &RunArgs,
+        local_run: &LocalRun,
+        local_services: &LocalServices,
         service: &BuiltService,
         idx: u16,
     ) -> Result<Option<(Child, runtime::Client)>> {
-        let secrets_file = run_args.secret_args.secrets.clone().or_else(|| {
-            let crate_dir = service.crate_directory();
-            // Prioritise crate-local prod secrets over workspace dev secrets (in the rare case that both exist)
-            [
-                crate_dir.join("Secrets.dev.toml"),
-                crate_dir.join("Secrets.toml"),
-                service.workspace_path.join("Secrets.dev.toml"),
-                service.workspace_path.join("Secrets.toml"),
-            ]
-            .into_iter()
-            .find(|f| f.exists() && f.is_file())
-        });
+        let secrets_file = if run_args.no_secrets {
+            None
+        } else {
+            run_args.secret_args.secrets.clone().or_else(|| {
+                let crate_dir = service.crate_directory();
+                // Prioritise crate-local prod secrets over workspace dev secrets (in the rare case that both exist)
+                // Encrypted files are only used when there is no plaintext one at the same level
+                [
+                    crate_dir.join("Secrets.dev.toml"),
+                    crate_dir.join("Secrets.toml"),
+                    crate_dir.join("Secrets.enc.toml"),
+                    service.workspace_path.join("Secrets.dev.toml"),
+                    service.workspace_path.join("Secrets.toml"),
+                    service.workspace_path.join("Secrets.enc.toml"),
+                ]
+                .into_iter()
+                .find(|f| f.exists() && f.is_file())
+            })
+        };
         let secrets = if let Some(secrets_file) = secrets_file {
             trace!("Loading secrets from {}", secrets_file.display());
-            if let Ok(secrets_str) = read_to_string(secrets_file) {
-                let secrets = toml::from_str::<HashMap<String, String>>(&secrets_str)?;
-                trace!(keys = ?secrets.keys(), "available secrets");
-                secrets
-            } else {
-                trace!("No secrets were loaded");
-                Default::default()
+            let loaded = secrets::read_secrets_contents(&secrets_file).and_then(|secrets_str| {
+                toml::from_str::<HashMap<String, String>>(&secrets_str).with_context(|| {
+                    format!("failed to parse secrets file {}", secrets_file.display())
+                })
+            });
+            match loaded {
+                Ok(secrets) => {
+                    trace!(keys = ?secrets.keys(), "available secrets");
+                    redact::register(secrets.values().cloned());
+                    secrets
+                }
+                // A file given with --secrets has to be usable, while one that was only found
+                // lying around in the project should not stop the service from running
+                Err(err) if run_args.secret_args.secrets.is_some() => return Err(err),
+                Err(err) => {
+                    eprintln!("WARNING: Not loading any secrets: {err:#}");
+                    Default::default()
+                }
             }
+        } else if run_args.no_secrets {
+            trace!("Not loading secrets");
+            Default::default()
         } else {
             trace!("No secrets file was found");
             Default::default()
         };
 
         trace!(path = ?service.executable_path, "using alpha runtime");
-        if let Err(err) = check_version(&service.executable_path).await {
+        // Executables built in a container are not necessarily able to run on the host
+        let version_check = if run_args.container {
+            Ok(())
+        } else {
+            check_version(&service.executable_path).await
+        };
+        if let Err(err) = version_check {
             warn!("{}", err);
             if let Some(mismatch) = err.downcast_ref::<VersionMismatchError>() {
                 println!("Warning: {}.", mismatch);
@@ -1094,35 +2131,126 @@ impl Shuttle {
         let runtime_executable = service.executable_path.clone();
 
         // Child process and gRPC client for sending requests to it
-        let (mut runtime, mut runtime_client) = runner::start(
-            portpicker::pick_unused_port().expect("unable to find available port for gRPC server"),
-            runtime_executable,
-            service.workspace_path.as_path(),
-        )
-        .await?;
-
+        // The URLs of the other services, environment variables from Shuttle.toml and the secrets
+        // are set on the service process, in increasing order of priority.
+        let mut env = local_services
+            .discovery
+            .iter()
+            .chain(&local_run.env_vars)
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect::<HashMap<_, _>>();
+        if let Some(storage_dir) = &local_run.storage_dir {
+            env.insert(
+                STORAGE_PATH_ENV.to_string(),
+                storage_dir.display().to_string(),
+            );
+        }
+        env.extend(secrets.clone());
+        for (name, secret) in local_run.external.env_vars() {
+            if let Some(value) = secrets.get(secret) {
+                env.insert(name, value.clone());
+            }
+        }
+        env.extend(run_args.env_vars.iter().cloned());
         let service_name = service.service_name()?;
+        let port =
+            portpicker::pick_unused_port().expect("unable to find available port for gRPC server");
+        let (mut runtime, mut runtime_client) = if run_args.container {
+            let container_name = format!("shuttle_run_{service_name}");
+            // A container left behind by an earlier run would clash with this one
+            let docker = LocalProvisioner::new()?;
+            if docker.container_exists(&container_name).await {
+                docker.remove_container(&container_name).await?;
+            }
+            local_run.dispose_on_exit(container_name.clone());
+
+            let executable = runtime_executable
+                .strip_prefix(&service.workspace_path)
+                .context("the executable of a container build to be in the workspace")?;
+            runner::start_in_container(
+                port,
+                PRODUCTION_BUILD_IMAGE,
+                &container_name,
+                &format!("{CONTAINER_WORKDIR}/{}", executable.display()),
+                service.workspace_path.as_path(),
+                &env,
+            )
+            .await?
+        } else {
+            runner::start_capturing_stderr(
+                port,
+                runtime_executable,
+                service.workspace_path.as_path(),
+                &env,
+            )
+            .await?
+        };
         let deployment_id: Uuid = Default::default();
 
         let child_stdout = runtime
             .stdout
             .take()
             .context("child process did not have a handle to stdout")?;
-        let mut reader = BufReader::new(child_stdout).lines();
+        let child_stderr = runtime
+            .stderr
+            .take()
+            .context("child process did not have a handle to stderr")?;
+        // Panics end up on stderr, so it is shown along with stdout
+        let (line_tx, mut line_rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        for mut reader in [
+            BufReader::new(Box::new(child_stdout) as Box<dyn AsyncRead + Send + Unpin>).lines(),
+            BufReader::new(Box::new(child_stderr) as Box<dyn AsyncRead + Send + Unpin>).lines(),
+        ] {
+            let line_tx = line_tx.clone();
+            tokio::spawn(async move {
+                while let Ok(Some(line)) = reader.next_line().await {
+                    if line_tx.send(line).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
         let service_name_clone = service_name.clone();
+        let recent_output = local_run.recent_output.clone();
         let raw = run_args.raw;
+        let prefix = local_services.prefix.clone();
+        // Keep a copy for `cargo shuttle logs --local`
+        let mut session_file = match &local_run.session_log {
+            Some(path) => match tokio::fs::OpenOptions::new().append(true).open(path).await {
+                Ok(file) => Some(file),
+                Err(err) => {
+                    debug!(error = %err, "failed to open the log session file");
+                    None
+                }
+            },
+            None => None,
+        };
         tokio::spawn(async move {
-            while let Some(line) = reader.next_line().await.unwrap() {
+            while let Some(line) = line_rx.recv().await {
                 let log_item = LogItem::new(
                     deployment_id,
                     shuttle_common::log::Backend::Runtime(service_name_clone.clone()),
-                    line,
+                    redact::redact(&line),
                 );
+                recent_output.push(&service_name_clone, log_item.get_raw_line().to_string());
 
-                if raw {
-                    println!("{}", log_item.get_raw_line())
+                let line = if raw {
+                    log_item.get_raw_line().to_string()
                 } else {
-                    println!("{log_item}")
+                    log_item.to_string()
+                };
+                match &prefix {
+                    Some(prefix) => println!("{}", prefix.apply(&log_item, line)),
+                    None => println!("{line}"),
+                }
+
+                if let Some(file) = &mut session_file {
+                    let mut json = serde_json::to_string(&log_item).expect("log items serialize");
+                    json.push('\n');
+                    if let Err(err) = file.write_all(json.as_bytes()).await {
+                        debug!(error = %err, "failed to write to the log session file");
+                        session_file = None;
+                    }
                 }
             }
         });
@@ -1165,7 +2293,9 @@ impl Shuttle {
 
         let resources = response.resources;
         let (resources, mocked_responses) =
-            Shuttle::local_provision_phase(service_name.as_str(), resources, secrets).await?;
+            Shuttle::local_provision_phase(service_name.as_str(), resources, secrets, local_run)
+                .await
+                .map_err(|err| errors::with_code(err, ErrorCode::Provisioning))?;
 
         println!(
             "{}",
@@ -1176,25 +2306,31 @@ impl Shuttle {
         // START PHASE
         //
 
+        if run_args.debugger {
+            Shuttle::announce_debuggee(&service_name, &runtime, run_args.wait_for_debugger).await?;
+        }
+
         let addr = SocketAddr::new(
-            if run_args.external {
-                Ipv4Addr::UNSPECIFIED // 0.0.0.0
-            } else {
-                Ipv4Addr::LOCALHOST // 127.0.0.1
-            }
-            .into(),
-            run_args.port + idx,
+            run_args.host.expect("the local address to be resolved"),
+            run_args.port.expect("the local port to be resolved") + idx,
         );
+        // Behind a proxy, the service itself listens somewhere else
+        let scheme = if run_args.tls { "https" } else { "http" };
+        let bind_addr = local_services
+            .proxy_backends
+            .get(idx as usize)
+            .copied()
+            .unwrap_or(addr);
 
         println!(
-            "    {} {} on http://{}\n",
+            "    {} {} on {scheme}://{}\n",
             "Starting".bold().green(),
             service_name,
             addr
         );
 
         let start_request = StartRequest {
-            ip: addr.to_string(),
+            ip: bind_addr.to_string(),
             resources,
         };
 
@@ -1209,17 +2345,77 @@ impl Shuttle {
             .into_inner();
 
         trace!(response = ?response,  "client response: ");
+
+        if let Some(path) = &run_args.health_check {
+            // Checked directly, not through the HTTPS proxy
+            let host = if bind_addr.ip().is_unspecified() {
+                Ipv4Addr::LOCALHOST.into()
+            } else {
+                bind_addr.ip()
+            };
+            let url = format!(
+                "http://{}/{}",
+                SocketAddr::new(host, bind_addr.port()),
+                path.trim_start_matches('/')
+            );
+            tokio::spawn(local::report_readiness(service_name, url));
+        }
+
         Ok(Some((runtime, runtime_client)))
     }
 
+    /// Print the process ID of a service for attaching a debugger, and wait for one to be attached
+    /// before its code starts running if asked to
+    async fn announce_debuggee(service_name: &str, runtime: &Child, wait: bool) -> Result<()> {
+        let pid = runtime
+            .id()
+            .context("the service process exited before it could be debugged")?;
+        println!(
+            "{} {service_name} runs as process {pid}, attach with `lldb -p {pid}` or `gdb -p {pid}`",
+            style::status("Debugging", Tone::Accent),
+        );
+        if !wait {
+            return Ok(());
+        }
+
+        println!("Press Enter to start {service_name} once the debugger is attached");
+        tokio::task::spawn_blocking(|| std::io::stdin().read_line(&mut String::new()))
+            .await?
+            .context("failed to wait for the debugger")?;
+
+        Ok(())
+    }
+
     async fn local_provision_phase(
         project_name: &str,
         mut resources: Vec<Vec<u8>>,
         secrets: HashMap<String, String>,
+        local_run: &LocalRun,
     ) -> Result<(Vec<Vec<u8>>, Vec<resource::Response>)> {
         // for displaying the tables
         let mut mocked_responses: Vec<resource::Response> = Vec::new();
-        let prov = LocalProvisioner::new()?;
+        let mut prov = LocalProvisioner::new()?.with_data_dir(local_run.data_dir.clone());
+        if local_run.offline {
+            prov = prov.offline();
+        }
+        for (kind, repository, config) in local_run.config.containers() {
+            if let Some(image) = config.image(repository) {
+                prov = prov.with_image(kind, image);
+            }
+            if !config.keep() {
+                prov = prov.with_ephemeral(kind);
+            }
+        }
+        // Containers that are not kept are removed when the run ends
+        let dispose_if_not_kept = |kind: &str, container: String| {
+            if local_run
+                .config
+                .container(kind)
+                .is_some_and(|(_, config)| !config.keep())
+            {
+                local_run.dispose_on_exit(container);
+            }
+        };
 
         // Fail early if any bytes is invalid json
         let values = resources
@@ -1258,18 +2454,68 @@ impl Shuttle {
                 resource::Type::Database(db_type) => {
                     let config: DbInput = serde_json::from_value(shuttle_resource.config)
                         .context("deserializing resource config")?;
-                    let res = match config.local_uri {
-                        Some(local_uri) => DatabaseResource::ConnectionString(local_uri),
-                        None => DatabaseResource::Info(
-                            prov.provision_database(Request::new(DatabaseRequest {
-                                project_name: project_name.to_string(),
-                                db_type: Some(db_type.into()),
-                                db_name: config.db_name,
-                            }))
-                            .await?
-                            .into_inner()
-                            .into(),
-                        ),
+                    let external = local_run.external.for_database(db_type);
+                    let res = match (config.local_uri, external) {
+                        (Some(local_uri), _) => DatabaseResource::ConnectionString(local_uri),
+                        (None, Some((name, external))) => {
+                            let connection_string =
+                                secrets.get(&external.secret).cloned().with_context(|| {
+                                    format!(
+                                        "The external resource {name} stands in for {db_type}, \
+                                        but the secret {} with its connection string is not set",
+                                        external.secret
+                                    )
+                                })?;
+                            DatabaseResource::ConnectionString(connection_string)
+                        }
+                        (None, None) => {
+                            let container =
+                                LocalProvisioner::database_container_name(project_name, db_type);
+                            let existed = prov.container_exists(&container).await;
+                            if !existed && !Shuttle::confirm_database_container(db_type)? {
+                                bail!(
+                                    "A database is needed to run {project_name}. Start one yourself \
+                                    and pass its connection string with `local_uri` on the resource \
+                                    annotation, like `#[shuttle_shared_db::Postgres(local_uri = \"...\")]`"
+                                );
+                            }
+                            let was_running = prov.container_running(&container).await;
+                            let kind = LocalProvisioner::database_kind(db_type);
+                            dispose_if_not_kept(&kind, container.clone());
+
+                            let info: DatabaseInfo = prov
+                                .provision_database(Request::new(DatabaseRequest {
+                                    project_name: project_name.to_string(),
+                                    db_type: Some(db_type.into()),
+                                    db_name: config.db_name.clone(),
+                                }))
+                                .await?
+                                .into_inner()
+                                .into();
+                            if !was_running {
+                                local_run.stop_on_exit(container.clone());
+                            }
+
+                            if let Some(seed) = &local_run.seed {
+                                let target = SeedTarget {
+                                    project_name,
+                                    db_type,
+                                    db_name: config.db_name,
+                                    database_url: info.connection_string_public(true),
+                                    marker: seed::marker_path(&local_run.data_dir, &kind),
+                                    kept: local_run
+                                        .config
+                                        .container(&kind)
+                                        .map_or(true, |(_, config)| config.keep()),
+                                    existed,
+                                };
+                                if local_run.first_seed_check(&container) && seed.needed(&target) {
+                                    seed.run(&prov, &target, local_run.offline).await?;
+                                }
+                            }
+
+                            DatabaseResource::Info(info)
+                        }
                     };
                     mocked_responses.push(resource::Response {
                         r#type: shuttle_resource.r#type,
@@ -1304,9 +2550,16 @@ impl Shuttle {
                     });
                 }
                 resource::Type::Container => {
-                    let config = serde_json::from_value(shuttle_resource.config)
+                    let config: ContainerRequest = serde_json::from_value(shuttle_resource.config)
                         .context("deserializing resource config")?;
+                    let container =
+                        format!("shuttle_{}_{}", config.project_name, config.container_name);
+                    let was_running = prov.container_running(&container).await;
+                    dispose_if_not_kept(&config.container_name, container.clone());
                     let res = prov.start_container(config).await?;
+                    if !was_running {
+                        local_run.stop_on_exit(container);
+                    }
                     *bytes = serde_json::to_vec(&ShuttleResourceOutput {
                         output: res,
                         custom: shuttle_resource.custom,
@@ -1316,86 +2569,464 @@ impl Shuttle {
             }
         }
 
-        Ok((resources, mocked_responses))
+        Ok((resources, mocked_responses))
+    }
+
+    async fn stop_runtime(
+        runtime: &mut Child,
+        runtime_client: &mut runtime::Client,
+    ) -> Result<(), Status> {
+        let stop_request = StopRequest {};
+        trace!(?stop_request, "stopping service");
+        let response = runtime_client
+            .stop(tonic::Request::new(stop_request))
+            .or_else(|err| async {
+                runtime.kill().await?;
+                trace!(status = ?err, "killed the runtime by force because stopping it errored out");
+                Err(err)
+            })
+            .await?
+            .into_inner();
+        trace!(response = ?response, "client stop response: ");
+        Ok(())
+    }
+
+    async fn add_runtime_info(
+        runtime: Option<(Child, runtime::Client)>,
+        existing_runtimes: &mut Vec<(Child, runtime::Client)>,
+    ) -> Result<(), Status> {
+        match runtime {
+            Some(inner) => {
+                trace!("Adding runtime PID: {:?}", inner.0.id());
+                existing_runtimes.push(inner);
+            }
+            None => {
+                trace!("Runtime error: No runtime process. Crashed during startup?");
+                for rt_info in existing_runtimes {
+                    let mut errored_out = false;
+                    // Stopping all runtimes gracefully first, but if this errors out the function kills the runtime forcefully.
+                    Shuttle::stop_runtime(&mut rt_info.0, &mut rt_info.1)
+                        .await
+                        .unwrap_or_else(|_| {
+                            errored_out = true;
+                        });
+
+                    // If the runtime stopping is successful, we still need to kill it forcefully because we exit outside the loop
+                    // and destructors will not be guaranteed to run.
+                    if !errored_out {
+                        rt_info.0.kill().await?;
+                    }
+                }
+                exit(1);
+            }
+        };
+        Ok(())
+    }
+
+    async fn local_run(&self, run_args: RunArgs, offline: bool) -> Result<CommandOutcome> {
+        if let Some(Err(err)) = self
+            .ctx
+            .external_resources()
+            .map(|config| config.validate())
+        {
+            return Err(errors::with_code(
+                anyhow!("Invalid [external] configuration in Shuttle.toml: {err}"),
+                ErrorCode::Validation,
+            ));
+        }
+        let session_log = logs::local::new_session(self.ctx.working_directory())
+            .map_err(|err| debug!(error = %err, "failed to create a log session file"))
+            .ok();
+        let local_run = LocalRun::new(
+            self.ctx.env_vars(),
+            session_log,
+            self.ctx.local_run_config(),
+            self.local_data_dir(),
+            self.ctx
+                .local_run_config()
+                .data_dir
+                .is_some()
+                .then(|| self.local_storage_dir()),
+            offline,
+            self.ctx.local_run_config().seed.map(|config| Seed {
+                config,
+                project_dir: self.ctx.working_directory().to_path_buf(),
+                reseed: run_args.reseed,
+            }),
+        )
+        .with_external(self.ctx.external_resources().cloned().unwrap_or_default());
+
+        let grace_period = run_args.grace_period;
+        let res = self.run_local_services(run_args, &local_run).await;
+        local_run.tear_down_containers(grace_period).await;
+
+        res
+    }
+
+    /// Ask before starting a new database container. Runs without a terminal don't ask.
+    fn confirm_database_container(db_type: database::Type) -> Result<bool> {
+        if !prompt::is_interactive() {
+            return Ok(true);
+        }
+
+        let engine = match db_type {
+            database::Type::Shared(SharedEngine::Postgres) => "shared Postgres",
+            database::Type::Shared(SharedEngine::MongoDb) => "shared MongoDB",
+            database::Type::AwsRds(AwsRdsEngine::Postgres) => "RDS Postgres",
+            database::Type::AwsRds(AwsRdsEngine::MySql) => "RDS MySQL",
+            database::Type::AwsRds(AwsRdsEngine::MariaDB) => "RDS MariaDB",
+        };
+
+        Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!(
+                "This service needs a {engine} database. Start one in a Docker container?"
+            ))
+            .default(true)
+            .interact()
+            .context("failed to read the answer")
+    }
+
+    /// Stop the runtimes, making sure their processes are gone so that their ports are free.
+    /// Each runtime gets `grace` to stop by itself before it is killed.
+    async fn stop_runtimes(runtimes: &mut Vec<(Child, runtime::Client)>, grace: Duration) {
+        if runtimes.is_empty() {
+            return;
+        }
+
+        let shutdowns = join_all(
+            runtimes
+                .drain(..)
+                .map(|(rt, rt_client)| Shuttle::stop_runtime_gracefully(rt, rt_client, grace)),
+        )
+        .await;
+
+        let forced = shutdowns.iter().filter(|clean| !**clean).count();
+        if forced == 0 {
+            println!(
+                "{} {} cleanly",
+                style::status("Stopped", Tone::Success),
+                match shutdowns.len() {
+                    1 => "the service".to_string(),
+                    n => format!("all {n} services"),
+                }
+            );
+        } else {
+            println!(
+                "{} {forced} of {} services that did not stop within {}",
+                style::status("Killed", Tone::Failure),
+                shutdowns.len(),
+                humantime::format_duration(grace)
+            );
+        }
+    }
+
+    /// Ask the runtime to stop its service, then its process to exit, and kill it if it is not
+    /// gone after `grace`. Returns whether it stopped by itself.
+    async fn stop_runtime_gracefully(
+        mut rt: Child,
+        mut rt_client: runtime::Client,
+        grace: Duration,
+    ) -> bool {
+        let pid = rt.id();
+        if let Err(err) = Shuttle::stop_runtime(&mut rt, &mut rt_client).await {
+            // The runtime was killed already
+            trace!(status = ?err, "stopping the runtime errored out");
+            return false;
+        }
+        #[cfg(unix)]
+        if let Some(pid) = pid {
+            use nix::sys::signal::{kill, Signal};
+            use nix::unistd::Pid;
+
+            if let Err(err) = kill(Pid::from_raw(pid as i32), Signal::SIGTERM) {
+                trace!(error = %err, "sending SIGTERM to the runtime errored out");
+            }
+        }
+        // There is no SIGTERM to forward on other platforms, the runtime is only asked over gRPC
+        #[cfg(not(unix))]
+        if let Err(err) = rt.start_kill() {
+            trace!(error = %err, "killing the runtime errored out");
+        }
+
+        match tokio::time::timeout(grace, rt.wait()).await {
+            Ok(Ok(status)) => {
+                trace!(?pid, %status, "runtime exited");
+                true
+            }
+            Ok(Err(err)) => {
+                trace!(error = %err, "waiting for the runtime errored out");
+                false
+            }
+            Err(_) => {
+                if let Err(err) = rt.kill().await {
+                    trace!(error = %err, "killing the runtime errored out");
+                }
+                false
+            }
+        }
+    }
+
+    /// Restart the services that crash until they all exit by themselves, or until ctrl-c. The
+    /// output leading up to every crash is kept in the error log.
+    async fn supervise_local_run(
+        run_args: &RunArgs,
+        runtimes: Vec<(Child, runtime::Client)>,
+        started: Vec<u16>,
+        services: &[BuiltService],
+        local_run: &LocalRun,
+        local_services: &LocalServices,
+    ) -> Result<CommandOutcome> {
+        const MAX_BACKOFF: Duration = Duration::from_secs(30);
+        // Services that ran for this long before crashing start over with a short backoff
+        const STABLE_AFTER: Duration = Duration::from_secs(60);
+
+        // The runtime of every service, with when it was started and how often it crashed in a row
+        let mut supervised = runtimes
+            .into_iter()
+            .zip(started)
+            .map(|(runtime, idx)| (Some(runtime), idx, Instant::now(), 0u32))
+            .collect::<Vec<_>>();
+
+        loop {
+            let waits = supervised
+                .iter_mut()
+                .enumerate()
+                .filter_map(|(slot, (runtime, ..))| {
+                    let (child, _) = runtime.as_mut()?;
+                    Some(Box::pin(async move { (slot, child.wait().await) }))
+                })
+                .collect::<Vec<_>>();
+            if waits.is_empty() {
+                return Ok(CommandOutcome::Ok);
+            }
+
+            let exited = tokio::select! {
+                (exited, _, _) = select_all(waits) => Some(exited),
+                _ = tokio::signal::ctrl_c() => None,
+            };
+            let Some((slot, status)) = exited else {
+                println!("cargo-shuttle received ctrl-c. Stopping all the runtimes...");
+                let mut runtimes = supervised
+                    .iter_mut()
+                    .filter_map(|(runtime, ..)| runtime.take())
+                    .collect();
+                Shuttle::stop_runtimes(&mut runtimes, run_args.grace_period).await;
+                return Ok(CommandOutcome::Ok);
+            };
+
+            let (runtime, idx, started_at, crashes) = &mut supervised[slot];
+            *runtime = None;
+            let service = &services[*idx as usize];
+            let service_name = service.service_name()?;
+            let reason = match status {
+                Ok(status) if status.success() => {
+                    println!("{service_name} exited");
+                    continue;
+                }
+                Ok(status) => match status.code() {
+                    Some(code) => format!("exited with code {code}"),
+                    None => "was killed by a signal".to_string(),
+                },
+                Err(err) => format!("could not be waited for: {err}"),
+            };
+
+            if started_at.elapsed() > STABLE_AFTER {
+                *crashes = 0;
+            }
+            let backoff = Duration::from_secs(2u64.saturating_pow(*crashes)).min(MAX_BACKOFF);
+            *crashes += 1;
+
+            println!(
+                "{} {service_name} {reason}, restarting in {}s",
+                style::status("Crashed", Tone::Failure),
+                backoff.as_secs()
+            );
+            let report = error_log::record(ErrorLogEntry::new(
+                "local-run",
+                format!("{service_name} {reason}"),
+                local_run.recent_output.take(&service_name),
+            ));
+            if let Some(path) = report {
+                println!("Its last output was saved to {}", path.display());
+            }
+
+            tokio::select! {
+                _ = sleep(backoff) => {}
+                _ = tokio::signal::ctrl_c() => {
+                    println!("cargo-shuttle received ctrl-c. Stopping all the runtimes...");
+                    let mut runtimes = supervised
+                        .iter_mut()
+                        .filter_map(|(runtime, ..)| runtime.take())
+                        .collect();
+                    Shuttle::stop_runtimes(&mut runtimes, run_args.grace_period).await;
+                    return Ok(CommandOutcome::Ok);
+                }
+            }
+
+            let (runtime, idx, started_at, _) = &mut supervised[slot];
+            match Shuttle::spin_local_runtime(run_args, local_run, local_services, service, *idx)
+                .await
+            {
+                Ok(Some(restarted)) => {
+                    *runtime = Some(restarted);
+                    *started_at = Instant::now();
+                }
+                // The reason was already logged
+                Ok(None) => {}
+                Err(err) => println!("{service_name} could not be restarted: {err:?}"),
+            }
+        }
+    }
+
+    /// The services of a local run, once its address is resolved
+    fn local_services(
+        run_args: &RunArgs,
+        services: &[BuiltService],
+        proxy_backends: &[SocketAddr],
+    ) -> Result<LocalServices> {
+        let names = services
+            .iter()
+            .map(BuiltService::service_name)
+            .collect::<Result<Vec<_>>>()?;
+        let addr = SocketAddr::new(
+            run_args.host.expect("the local address to be resolved"),
+            run_args.port.expect("the local port to be resolved"),
+        );
+
+        let local_services =
+            LocalServices::new(names, addr, run_args.all).with_proxies(proxy_backends.to_vec());
+        if !run_args.tls {
+            return Ok(local_services);
+        }
+
+        Ok(local_services.with_https())
     }
 
-    async fn stop_runtime(
-        runtime: &mut Child,
-        runtime_client: &mut runtime::Client,
-    ) -> Result<(), Status> {
-        let stop_request = StopRequest {};
-        trace!(?stop_request, "stopping service");
-        let response = runtime_client
-            .stop(tonic::Request::new(stop_request))
-            .or_else(|err| async {
-                runtime.kill().await?;
-                trace!(status = ?err, "killed the runtime by force because stopping it errored out");
-                Err(err)
-            })
-            .await?
-            .into_inner();
-        trace!(response = ?response, "client stop response: ");
-        Ok(())
+    /// Register the run for `cargo shuttle local ps` until the registration is dropped. Failing to
+    /// do so only keeps the run out of the list.
+    fn register_local_run(&self, run_args: &RunArgs) -> Option<local::RunRegistration> {
+        let run = local::ActiveRun::new(
+            self.ctx.project_name().to_string(),
+            SocketAddr::new(
+                run_args.host.expect("the local address to be resolved"),
+                run_args.port.expect("the local port to be resolved"),
+            ),
+            self.ctx.working_directory().to_path_buf(),
+        );
+
+        local::runs_dir()
+            .and_then(|dir| local::RunRegistration::new(&dir, &run))
+            .map_err(|err| debug!(error = %err, "failed to register the local run"))
+            .ok()
     }
 
-    async fn add_runtime_info(
-        runtime: Option<(Child, runtime::Client)>,
-        existing_runtimes: &mut Vec<(Child, runtime::Client)>,
-    ) -> Result<(), Status> {
-        match runtime {
-            Some(inner) => {
-                trace!("Adding runtime PID: {:?}", inner.0.id());
-                existing_runtimes.push(inner);
-            }
-            None => {
-                trace!("Runtime error: No runtime process. Crashed during startup?");
-                for rt_info in existing_runtimes {
-                    let mut errored_out = false;
-                    // Stopping all runtimes gracefully first, but if this errors out the function kills the runtime forcefully.
-                    Shuttle::stop_runtime(&mut rt_info.0, &mut rt_info.1)
-                        .await
-                        .unwrap_or_else(|_| {
-                            errored_out = true;
-                        });
+    /// Services are started one after the other, or all at once with `--all`
+    fn start_batches(services: &[BuiltService], all: bool) -> Vec<Vec<(u16, &BuiltService)>> {
+        let indexed = services
+            .iter()
+            .enumerate()
+            .map(|(i, service)| (i as u16, service));
+        if all {
+            vec![indexed.collect()]
+        } else {
+            indexed.map(|service| vec![service]).collect()
+        }
+    }
 
-                    // If the runtime stopping is successful, we still need to kill it forcefully because we exit outside the loop
-                    // and destructors will not be guaranteed to run.
-                    if !errored_out {
-                        rt_info.0.kill().await?;
-                    }
-                }
-                exit(1);
-            }
-        };
-        Ok(())
+    /// Start a batch of services concurrently
+    async fn spin_local_runtimes(
+        run_args: &RunArgs,
+        local_run: &LocalRun,
+        local_services: &LocalServices,
+        batch: Vec<(u16, &BuiltService)>,
+    ) -> Vec<Result<Option<(Child, runtime::Client)>>> {
+        join_all(batch.into_iter().map(|(idx, service)| {
+            Shuttle::spin_local_runtime(run_args, local_run, local_services, service, idx)
+        }))
+        .await
     }
 
-    async fn pre_local_run(&self, run_args: &RunArgs) -> Result<Vec<BuiltService>> {
+    async fn pre_local_run(
+        &self,
+        run_args: &RunArgs,
+        local_run: &LocalRun,
+    ) -> Result<Vec<BuiltService>> {
         trace!("starting a local run for a service: {run_args:?}");
 
         let (tx, mut rx) = tokio::sync::mpsc::channel::<String>(256);
         tokio::task::spawn(async move {
             while let Some(line) = rx.recv().await {
-                println!("{line}");
+                println!("{}", redact::redact(&line));
             }
         });
 
         let working_directory = self.ctx.working_directory();
 
         trace!("building project");
+        let in_container = match run_args.container {
+            true => format!(" in {PRODUCTION_BUILD_IMAGE}"),
+            false => String::new(),
+        };
         println!(
-            "{} {}",
-            "    Building".bold().green(),
+            "{} {}{in_container}",
+            style::status("Building", Tone::Success),
             working_directory.display()
         );
 
-        build_workspace(working_directory, run_args.release, tx, false).await
+        let build_args = &run_args.build_args;
+        let options = BuildOptions {
+            release_mode: run_args.release,
+            profile: build_args.profile.clone(),
+            features: build_args.features.clone(),
+            no_default_features: build_args.no_default_features,
+            target_dir: build_args
+                .target_dir
+                .as_ref()
+                .map(|dir| std::env::current_dir().map(|cwd| cwd.join(dir)))
+                .transpose()?,
+            cargo_args: build_args
+                .cargo_args
+                .as_deref()
+                .unwrap_or_default()
+                .split_whitespace()
+                .map(str::to_string)
+                .collect(),
+            debug_info: run_args.debugger,
+            offline: local_run.offline,
+            container_image: run_args
+                .container
+                .then(|| PRODUCTION_BUILD_IMAGE.to_string()),
+        };
+
+        let started = Instant::now();
+        let built = build_workspace_with_options(working_directory, &options, tx, false).await;
+        if notification::is_due(&self.ctx.notifications_config(), started) {
+            let project_name = self.ctx.project_name();
+            let (summary, body) = match &built {
+                Ok(_) => (
+                    format!("Built {project_name}"),
+                    "Starting the services".to_string(),
+                ),
+                Err(err) => (format!("Build of {project_name} failed"), err.to_string()),
+            };
+            notification::show(summary, body).await;
+        }
+
+        built.map_err(|err| errors::with_code(err, ErrorCode::Build))
     }
 
     #[cfg(target_family = "unix")]
-    async fn local_run(&self, mut run_args: RunArgs) -> Result<CommandOutcome> {
+    async fn run_local_services(
+        &self,
+        mut run_args: RunArgs,
+        local_run: &LocalRun,
+    ) -> Result<CommandOutcome> {
         debug!("starting local run");
-        let services = self.pre_local_run(&run_args).await?;
+        run_args.secret_args.secrets = self.secrets_file(&run_args.secret_args)?;
+        if !run_args.no_secrets {
+            self.register_local_secrets(run_args.secret_args.secrets.as_deref());
+        }
+        let services = self.pre_local_run(&run_args, local_run).await?;
 
         let mut sigterm_notif =
             tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
@@ -1407,31 +3038,45 @@ impl Shuttle {
         // Start all the services.
         let mut runtimes: Vec<(Child, runtime::Client)> = Vec::new();
 
-        Shuttle::find_available_port(&mut run_args, services.len());
+        Shuttle::resolve_local_address(&mut run_args, &local_run.config, services.len())?;
+        let _registration = self.register_local_run(&run_args);
+
+        let tls_proxies = Shuttle::start_tls_proxies(&run_args, services.len()).await?;
+        let recording_proxies = Shuttle::start_recording_proxies(&run_args, &services).await?;
+        let proxy_backends = Shuttle::proxy_backends(&tls_proxies, &recording_proxies);
+        let local_services = Shuttle::local_services(&run_args, &services, &proxy_backends)?;
 
+        // Which service each of the runtimes runs
+        let mut started: Vec<u16> = Vec::new();
         let mut signal_received = false;
-        for (i, service) in services.iter().enumerate() {
+        for batch in Shuttle::start_batches(&services, run_args.all) {
+            let indices = batch.iter().map(|(idx, _)| *idx).collect::<Vec<_>>();
             // We must cover the case of starting multiple workspace services and receiving a signal in parallel.
             // This must stop all the existing runtimes and creating new ones.
             signal_received = tokio::select! {
-                res = Shuttle::spin_local_runtime(&run_args, service, i as u16) => {
-                    match res {
-                        Ok(runtime) => {
-                            Shuttle::add_runtime_info(runtime, &mut runtimes).await?;
-                        },
-                        Err(e) => println!("Runtime error: {e:?}"),
+                results = Shuttle::spin_local_runtimes(&run_args, local_run, &local_services, batch) => {
+                    for (idx, res) in indices.into_iter().zip(results) {
+                        match res {
+                            Ok(runtime) => {
+                                if runtime.is_some() {
+                                    started.push(idx);
+                                }
+                                Shuttle::add_runtime_info(runtime, &mut runtimes).await?;
+                            },
+                            Err(e) => println!("Runtime error: {e:?}"),
+                        }
                     }
                     false
                 },
                 _ = sigterm_notif.recv() => {
                     println!(
-                        "cargo-shuttle received SIGTERM. Killing all the runtimes..."
+                        "cargo-shuttle received SIGTERM. Stopping all the runtimes..."
                     );
                     true
                 },
                 _ = sigint_notif.recv() => {
                     println!(
-                        "cargo-shuttle received SIGINT. Killing all the runtimes..."
+                        "cargo-shuttle received SIGINT. Stopping all the runtimes..."
                     );
                     true
                 }
@@ -1445,31 +3090,42 @@ impl Shuttle {
         // If prior signal received is set to true we must stop all the existing runtimes and
         // exit the `local_run`.
         if signal_received {
-            for (mut rt, mut rt_client) in runtimes {
-                Shuttle::stop_runtime(&mut rt, &mut rt_client)
-                    .await
-                    .unwrap_or_else(|err| {
-                        trace!(status = ?err, "stopping the runtime errored out");
-                    });
-            }
+            Shuttle::stop_runtimes(&mut runtimes, run_args.grace_period).await;
             return Ok(CommandOutcome::Ok);
         }
 
+        if let Some(session) = &run_args.replay {
+            return Shuttle::replay_local_run(
+                session,
+                runtimes,
+                &local_services,
+                run_args.grace_period,
+            )
+            .await;
+        }
+
+        if run_args.watch {
+            return self
+                .watch_local_run(&run_args, runtimes, local_run, &proxy_backends)
+                .await;
+        }
+        if run_args.restart_on_crash {
+            return Shuttle::supervise_local_run(
+                &run_args,
+                runtimes,
+                started,
+                &services,
+                local_run,
+                &local_services,
+            )
+            .await;
+        }
+
         // If no signal was received during runtimes initialization, then we must handle each runtime until
         // completion and handle the signals during this time.
-        for (mut rt, mut rt_client) in runtimes {
-            // If we received a signal while waiting for any runtime we must stop the rest and exit
-            // the waiting loop.
-            if signal_received {
-                Shuttle::stop_runtime(&mut rt, &mut rt_client)
-                    .await
-                    .unwrap_or_else(|err| {
-                        trace!(status = ?err, "stopping the runtime errored out");
-                    });
-                continue;
-            }
-
-            // Receiving a signal will stop the current runtime we're waiting for.
+        let mut runtimes = runtimes.into_iter();
+        while let Some((mut rt, rt_client)) = runtimes.next() {
+            // Receiving a signal while waiting for any runtime stops it along with the rest.
             signal_received = tokio::select! {
                 res = rt.wait() => {
                     println!(
@@ -1480,23 +3136,23 @@ impl Shuttle {
                 },
                 _ = sigterm_notif.recv() => {
                     println!(
-                        "cargo-shuttle received SIGTERM. Killing all the runtimes..."
+                        "cargo-shuttle received SIGTERM. Stopping all the runtimes..."
                     );
-                    Shuttle::stop_runtime(&mut rt, &mut rt_client).await.unwrap_or_else(|err| {
-                        trace!(status = ?err, "stopping the runtime errored out");
-                    });
                     true
                 },
                 _ = sigint_notif.recv() => {
                     println!(
-                        "cargo-shuttle received SIGINT. Killing all the runtimes..."
+                        "cargo-shuttle received SIGINT. Stopping all the runtimes..."
                     );
-                    Shuttle::stop_runtime(&mut rt, &mut rt_client).await.unwrap_or_else(|err| {
-                        trace!(status = ?err, "stopping the runtime errored out");
-                    });
                     true
                 }
             };
+
+            if signal_received {
+                let mut rest = std::iter::once((rt, rt_client)).chain(runtimes).collect();
+                Shuttle::stop_runtimes(&mut rest, run_args.grace_period).await;
+                break;
+            }
         }
 
         println!(
@@ -1548,24 +3204,47 @@ impl Shuttle {
     }
 
     #[cfg(target_family = "windows")]
-    async fn local_run(&self, mut run_args: RunArgs) -> Result<CommandOutcome> {
-        let services = self.pre_local_run(&run_args).await?;
+    async fn run_local_services(
+        &self,
+        mut run_args: RunArgs,
+        local_run: &LocalRun,
+    ) -> Result<CommandOutcome> {
+        run_args.secret_args.secrets = self.secrets_file(&run_args.secret_args)?;
+        if !run_args.no_secrets {
+            self.register_local_secrets(run_args.secret_args.secrets.as_deref());
+        }
+        let services = self.pre_local_run(&run_args, local_run).await?;
 
         // Start all the services.
         let mut runtimes: Vec<(Child, runtime::Client)> = Vec::new();
 
-        Shuttle::find_available_port(&mut run_args, services.len());
+        Shuttle::resolve_local_address(&mut run_args, &local_run.config, services.len())?;
+        let _registration = self.register_local_run(&run_args);
+
+        let tls_proxies = Shuttle::start_tls_proxies(&run_args, services.len()).await?;
+        let recording_proxies = Shuttle::start_recording_proxies(&run_args, &services).await?;
+        let proxy_backends = Shuttle::proxy_backends(&tls_proxies, &recording_proxies);
+        let local_services = Shuttle::local_services(&run_args, &services, &proxy_backends)?;
 
+        // Which service each of the runtimes runs
+        let mut started: Vec<u16> = Vec::new();
         let mut signal_received = false;
-        for (i, service) in services.iter().enumerate() {
+        for batch in Shuttle::start_batches(&services, run_args.all) {
+            let indices = batch.iter().map(|(idx, _)| *idx).collect::<Vec<_>>();
             signal_received = tokio::select! {
-                res = Shuttle::spin_local_runtime(&run_args, service, i as u16) => {
-                    Shuttle::add_runtime_info(res.unwrap(), &mut runtimes).await?;
+                results = Shuttle::spin_local_runtimes(&run_args, local_run, &local_services, batch) => {
+                    for (idx, res) in indices.into_iter().zip(results) {
+                        let runtime = res.unwrap();
+                        if runtime.is_some() {
+                            started.push(idx);
+                        }
+                        Shuttle::add_runtime_info(runtime, &mut runtimes).await?;
+                    }
                     false
                 },
                 _ = Shuttle::handle_signals() => {
                     println!(
-                        "Killing all the runtimes..."
+                        "Stopping all the runtimes..."
                     );
                     true
                 }
@@ -1579,31 +3258,42 @@ impl Shuttle {
         // If prior signal received is set to true we must stop all the existing runtimes and
         // exit the `local_run`.
         if signal_received {
-            for (mut rt, mut rt_client) in runtimes {
-                Shuttle::stop_runtime(&mut rt, &mut rt_client)
-                    .await
-                    .unwrap_or_else(|err| {
-                        trace!(status = ?err, "stopping the runtime errored out");
-                    });
-            }
+            Shuttle::stop_runtimes(&mut runtimes, run_args.grace_period).await;
             return Ok(CommandOutcome::Ok);
         }
 
+        if let Some(session) = &run_args.replay {
+            return Shuttle::replay_local_run(
+                session,
+                runtimes,
+                &local_services,
+                run_args.grace_period,
+            )
+            .await;
+        }
+
+        if run_args.watch {
+            return self
+                .watch_local_run(&run_args, runtimes, local_run, &proxy_backends)
+                .await;
+        }
+        if run_args.restart_on_crash {
+            return Shuttle::supervise_local_run(
+                &run_args,
+                runtimes,
+                started,
+                &services,
+                local_run,
+                &local_services,
+            )
+            .await;
+        }
+
         // If no signal was received during runtimes initialization, then we must handle each runtime until
         // completion and handle the signals during this time.
-        for (mut rt, mut rt_client) in runtimes {
-            // If we received a signal while waiting for any runtime we must stop the rest and exit
-            // the waiting loop.
-            if signal_received {
-                Shuttle::stop_runtime(&mut rt, &mut rt_client)
-                    .await
-                    .unwrap_or_else(|err| {
-                        trace!(status = ?err, "stopping the runtime errored out");
-                    });
-                continue;
-            }
-
-            // Receiving a signal will stop the current runtime we're waiting for.
+        let mut runtimes = runtimes.into_iter();
+        while let Some((mut rt, rt_client)) = runtimes.next() {
+            // Receiving a signal while waiting for any runtime stops it along with the rest.
             signal_received = tokio::select! {
                 res = rt.wait() => {
                     println!(
@@ -1614,14 +3304,17 @@ impl Shuttle {
                 },
                 _ = Shuttle::handle_signals() => {
                     println!(
-                        "Killing all the runtimes..."
+                        "Stopping all the runtimes..."
                     );
-                    Shuttle::stop_runtime(&mut rt, &mut rt_client).await.unwrap_or_else(|err| {
-                        trace!(status = ?err, "stopping the runtime errored out");
-                    });
                     true
                 }
             };
+
+            if signal_received {
+                let mut rest = std::iter::once((rt, rt_client)).chain(runtimes).collect();
+                Shuttle::stop_runtimes(&mut rest, run_args.grace_period).await;
+                break;
+            }
         }
 
         println!(
@@ -1632,12 +3325,169 @@ impl Shuttle {
         Ok(CommandOutcome::Ok)
     }
 
+    /// Check the tables of Shuttle.toml that are sent with deployments
+    fn validate_project_config(&self) -> Result<()> {
+        if let Some(Err(err)) = self.ctx.log_forwarding().map(|config| config.validate()) {
+            return Err(errors::with_code(
+                anyhow!("Invalid [log-forwarding] configuration in Shuttle.toml: {err}"),
+                ErrorCode::Validation,
+            ));
+        }
+        if let Some(Err(err)) = self
+            .ctx
+            .resource_overrides()
+            .map(|config| config.validate())
+        {
+            return Err(errors::with_code(
+                anyhow!("Invalid resource configuration in Shuttle.toml: {err}"),
+                ErrorCode::Validation,
+            ));
+        }
+        if let Some(Err(err)) = self
+            .ctx
+            .external_resources()
+            .map(|config| config.validate())
+        {
+            return Err(errors::with_code(
+                anyhow!("Invalid [external] configuration in Shuttle.toml: {err}"),
+                ErrorCode::Validation,
+            ));
+        }
+        if let Some(Err(err)) = self.ctx.domains().map(|config| config.validate()) {
+            return Err(errors::with_code(
+                anyhow!("Invalid [domains] configuration in Shuttle.toml: {err}"),
+                ErrorCode::Validation,
+            ));
+        }
+        if let Some(Err(err)) = self.ctx.cron().map(|config| config.validate()) {
+            return Err(errors::with_code(
+                anyhow!("Invalid [cron] configuration in Shuttle.toml: {err}"),
+                ErrorCode::Validation,
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Fail when a domain in Shuttle.toml is already a custom domain of another project of the
+    /// account, since a domain can only be served by one project
+    async fn check_domain_collisions(&self) -> Result<()> {
+        let Some(domains) = self.ctx.domains() else {
+            return Ok(());
+        };
+        let client = self.client.as_ref().unwrap();
+        let project_name = self.ctx.project_name();
+
+        let projects = client
+            .get_projects_list(1, 1000)
+            .await
+            .context("failed to get your projects to check the domains against")?;
+        let others = projects
+            .iter()
+            .filter(|project| project.name != project_name)
+            .collect::<Vec<_>>();
+        let certificates = concurrent::map(&others, self.ctx.concurrency(), |project| {
+            client.get_certificates(&project.name)
+        })
+        .await;
+
+        for (project, certificates) in others.iter().zip(certificates) {
+            let certificates = match certificates {
+                Ok(certificates) => certificates,
+                // Projects without custom domains can fail to answer, like stopped ones
+                Err(err) => {
+                    debug!(error = %err, project = %project.name, "failed to get the certificates");
+                    continue;
+                }
+            };
+            if let Some(taken) = domains.all().find(|domain| {
+                certificates
+                    .iter()
+                    .any(|certificate| certificate.domain.eq_ignore_ascii_case(domain))
+            }) {
+                return Err(errors::with_code(
+                    anyhow!(
+                        "{taken} in the [domains] of Shuttle.toml is already a custom domain of \
+                        your project {}. Remove it from one of the projects.",
+                        project.name
+                    ),
+                    ErrorCode::Validation,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
     async fn deploy(&mut self, args: DeployArgs) -> Result<CommandOutcome> {
         let client = self.client.as_ref().unwrap();
         let working_directory = self.ctx.working_directory();
 
+        // The API rejects deploys to locked projects too, but checking first avoids
+        // building and uploading the archive for nothing
+        match client.get_project_lock(self.ctx.project_name()).await {
+            Ok(Some(lock)) => bail!(
+                "Deployments of \"{}\" are {lock}\n\
+                Run `cargo shuttle project unlock` to allow deployments again.",
+                self.ctx.project_name()
+            ),
+            Ok(None) => {}
+            Err(err) => eprintln!(
+                "WARNING: Could not check whether deployments of \"{}\" are locked, \
+                deploying anyway: {err:#}",
+                self.ctx.project_name()
+            ),
+        }
+
+        let secrets_file = self.secrets_file(&args.secret_args)?;
+        self.register_local_secrets(secrets_file.as_deref());
+        self.validate_project_config()?;
+        self.check_domain_collisions().await?;
+        self.check_required_secrets(secrets_file.as_deref()).await?;
+
+        let project_config_path = self.ctx.project_config_path();
+        let lockfile_path = project_config_path.with_file_name(lockfile::FILE_NAME);
+        let metadata = workspace::metadata(working_directory)?;
+        let resolved = Lockfile::resolve(
+            &metadata,
+            &project_config_path,
+            PRODUCTION_BUILD_IMAGE,
+            RESOURCE_SCHEMA_VERSION,
+        )?;
+        let locked = Lockfile::load(&lockfile_path)?;
+        if args.locked {
+            let Some(locked) = &locked else {
+                return Err(errors::with_code(
+                    anyhow!(
+                        "--locked was given, but there is no {}. \
+                        Deploy once without --locked to write it.",
+                        lockfile::FILE_NAME
+                    ),
+                    ErrorCode::Validation,
+                ));
+            };
+            let drift = locked.drift(&resolved);
+            if !drift.is_empty() {
+                return Err(errors::with_code(
+                    anyhow!(
+                        "--locked was given, but the deploy does not match {}:\n  {}\n\
+                        Deploy without --locked to update it.",
+                        lockfile::FILE_NAME,
+                        drift.join("\n  ")
+                    ),
+                    ErrorCode::Validation,
+                ));
+            }
+        }
+
         let mut deployment_req: DeploymentRequest = DeploymentRequest {
             no_test: args.no_test,
+            env_vars: self.ctx.env_vars(),
+            log_forwarding: self.ctx.log_forwarding().cloned(),
+            resource_overrides: self.ctx.resource_overrides().cloned(),
+            external_resources: self.ctx.external_resources().cloned(),
+            domains: self.ctx.domains().cloned(),
+            cron: self.ctx.cron().cloned(),
             ..Default::default()
         };
 
@@ -1670,21 +3520,80 @@ impl Shuttle {
             }
         }
 
-        deployment_req.data = self.make_archive(args.secret_args.secrets.clone())?;
-        if deployment_req.data.len() > CREATE_SERVICE_BODY_LIMIT {
+        let packing = Progress::spinner("Packing the project");
+        // The archive goes to a file that is removed once closed, and is streamed from there, so
+        // that large projects are not held in memory
+        let archive = tempfile::tempfile().context("failed to create the archive file")?;
+        let (archive, digest) = self.make_archive(secrets_file.clone(), BufWriter::new(archive))?;
+        let mut archive = archive
+            .into_inner()
+            .context("failed to write the archive")?;
+        let archive_len = archive.metadata()?.len();
+        archive.rewind()?;
+        debug!("Archive size: {archive_len} bytes");
+        packing.finish(format!(
+            "{} the project ({})",
+            style::status("Packed", Tone::Success),
+            format_size(archive_len)
+        ));
+        if archive_len > CREATE_SERVICE_BODY_LIMIT as u64 {
             bail!(
                 r#"The project is too large - the limit is {} MB. \
                 Your project archive is {:.1} MB. \
                 Run with `cargo shuttle --debug` to see which files are being packed."#,
                 CREATE_SERVICE_BODY_LIMIT / 1_000_000,
-                deployment_req.data.len() as f32 / 1_000_000f32,
+                archive_len as f32 / 1_000_000f32,
+            );
+        }
+
+        let last_deploy = file_hashes::last_deploy(working_directory);
+        if let Some(last_deploy) = last_deploy.filter(|last_deploy| last_deploy.digest == digest) {
+            output::hint(
+                self.output,
+                format!(
+                    "Nothing changed since the last deploy from here ({})",
+                    last_deploy.deployment_id
+                ),
             );
         }
 
+        let uploading = Progress::bytes("Uploading", archive_len);
+        let uploaded = uploading.bar();
         let deployment = client
-            .deploy(self.ctx.project_name(), deployment_req)
+            .deploy(
+                self.ctx.project_name(),
+                deployment_req,
+                (archive, archive_len),
+                move |n| uploaded.inc(n),
+            )
             .await
             .map_err(suggestions::deploy::deploy_request_failure)?;
+        uploading.finish(format!(
+            "{} deployment {}",
+            style::status("Uploaded", Tone::Success),
+            deployment.id
+        ));
+        file_hashes::set_last_deploy(
+            working_directory,
+            &LastDeploy {
+                digest,
+                deployment_id: deployment.id,
+            },
+        );
+        if locked.as_ref() != Some(&resolved) {
+            match resolved.save(&lockfile_path) {
+                Ok(()) => output::hint(
+                    self.output,
+                    format!(
+                        "Wrote {}, commit it to deploy with --locked",
+                        lockfile_path.display()
+                    ),
+                ),
+                Err(err) => warn!(error = %err, "failed to write the lockfile"),
+            }
+        }
+        // Cleared by the first line of the build logs
+        let mut building = Some(Progress::spinner("Waiting for the build to start"));
 
         let mut stream = client
             .get_logs_ws(self.ctx.project_name(), &deployment.id)
@@ -1702,7 +3611,7 @@ impl Shuttle {
             if let Some(Ok(msg)) = stream.next().await {
                 if let tokio_tungstenite::tungstenite::Message::Text(line) = msg {
                     let log_item = match serde_json::from_str::<shuttle_common::LogItem>(&line) {
-                        Ok(log_item) => log_item,
+                        Ok(log_item) => redact::redact_log_item(log_item),
                         Err(err) => {
                             debug!(error = %err, "failed to parse message into log item");
 
@@ -1716,10 +3625,16 @@ impl Shuttle {
                         }
                     };
 
-                    if args.raw {
-                        println!("{}", log_item.get_raw_line())
-                    } else {
-                        println!("{log_item}")
+                    if let Some(building) = building.take() {
+                        building.finish_and_clear();
+                    }
+                    // The build logs are left out of quiet output, which is only the result
+                    if !self.quiet {
+                        if args.raw {
+                            println!("{}", log_item.get_raw_line())
+                        } else {
+                            println!("{log_item}")
+                        }
                     }
 
                     // Detect versions of deployer and runtime, and print warnings of outdated.
@@ -1821,6 +3736,7 @@ impl Shuttle {
         // Temporary fix.
         // TODO: Make get_service_summary endpoint wait for a bit and see if it entered Running/Crashed state.
         // Note: Will otherwise be possible when health checks are supported
+        let checking = Progress::spinner("Checking the state of the deployment");
         sleep(Duration::from_millis(500)).await;
 
         let deployment = client
@@ -1832,6 +3748,7 @@ impl Shuttle {
                     "Assessing deployment state failed",
                 )
             })?;
+        checking.finish_and_clear();
 
         // A deployment will only exist if there is currently one in the running state
         if deployment.state != shuttle_common::deployment::State::Running {
@@ -1876,11 +3793,55 @@ impl Shuttle {
             .await?;
         let resources = get_resource_tables(&resources, self.ctx.project_name(), false, false);
 
-        println!("{resources}{service}");
+        if self.quiet {
+            println!("{}\n{}", deployment.id, service.uri);
+        } else {
+            println!("{resources}{service}");
+        }
+
+        if self
+            .ctx
+            .migrations()
+            .is_some_and(|config| config.run_on_deploy)
+        {
+            self.run_migrations(None, secrets_file.as_deref())
+                .await
+                .context("the deployment is running, but its migrations failed")?;
+        }
 
         Ok(CommandOutcome::Ok)
     }
 
+    /// Tell the desktop how a deploy went, with the URL of the service when it is running
+    async fn notify_deployed(&self, started: Instant, res: &Result<CommandOutcome>) {
+        if !notification::is_due(&self.ctx.notifications_config(), started) {
+            return;
+        }
+
+        let project_name = self.ctx.project_name();
+        let (summary, body) = match res {
+            Ok(CommandOutcome::Ok) => {
+                let client = self.client.as_ref().unwrap();
+                let uri = client
+                    .get_service(project_name)
+                    .await
+                    .map(|service| service.uri)
+                    .unwrap_or_default();
+                (format!("Deployed {project_name}"), uri)
+            }
+            Ok(_) => (
+                format!("Deployment of {project_name} failed"),
+                "Run `cargo shuttle logs` for more details".to_string(),
+            ),
+            Err(err) => (
+                format!("Deployment of {project_name} failed"),
+                err.to_string(),
+            ),
+        };
+
+        notification::show(summary, body).await;
+    }
+
     async fn project_start(&self, idle_minutes: u64) -> Result<CommandOutcome> {
         let client = self.client.as_ref().unwrap();
         let config = &project::Config { idle_minutes };
@@ -1968,37 +3929,57 @@ impl Shuttle {
         } else {
             false
         };
-        let projects_table = project::get_projects_table(&projects, page, raw, page_hint);
-
-        println!("{}", "Personal Projects".bold());
-        println!("{projects_table}");
-
         let orgs = client.get_organizations_list().await?;
 
-        for org in orgs {
-            let mut org_projects = client
-                .get_organization_projects_list(&org.id)
-                .await
-                .map_err(|err| {
-                    suggestions::project::project_request_failure(
-                        err,
-                        "Getting organization projects list failed",
-                        false,
-                        "getting the organization projects list fails repeatedly",
-                    )
-                })?;
+        let mut organizations = serde_json::Map::new();
+        let mut listed = serde_json::json!({ "personal": projects });
+        let mut tables = Vec::new();
+        tables.push(("Personal Projects".to_string(), projects, page_hint));
+
+        let orgs_projects = concurrent::map(&orgs, self.ctx.concurrency(), |org| {
+            client.get_organization_projects_list(&org.id)
+        })
+        .await;
+        for (org, org_projects) in orgs.iter().zip(orgs_projects) {
+            let mut org_projects = org_projects.map_err(|err| {
+                suggestions::project::project_request_failure(
+                    err,
+                    "Getting organization projects list failed",
+                    false,
+                    "getting the organization projects list fails repeatedly",
+                )
+            })?;
             let page_hint = if org_projects.len() == limit as usize {
                 org_projects.pop();
                 true
             } else {
                 false
             };
-            let org_projects_table =
-                project::get_projects_table(&org_projects, page, raw, page_hint);
-
-            println!("{}", format!("{}'s Projects", org.display_name).bold());
-            println!("{org_projects_table}");
+            organizations.insert(
+                org.display_name.clone(),
+                serde_json::to_value(&org_projects)?,
+            );
+            tables.push((
+                format!("{}'s Projects", org.display_name),
+                org_projects,
+                page_hint,
+            ));
         }
+        listed["organizations"] = organizations.into();
+
+        output::print(self.output, &listed, |plain| {
+            tables
+                .iter()
+                .map(|(title, projects, page_hint)| {
+                    format!(
+                        "{}\n{}",
+                        title.as_str().bold(),
+                        project::get_projects_table(projects, page, raw || plain, *page_hint)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })?;
 
         Ok(CommandOutcome::Ok)
     }
@@ -2041,13 +4022,18 @@ impl Shuttle {
                         "getting project status failed repeatedly",
                     )
                 })?;
-            println!(
-                "{project}\nIdle minutes: {}",
-                project
-                    .idle_minutes
-                    .map(|i| i.to_string())
-                    .unwrap_or("<unknown>".to_owned())
-            );
+            output::print(self.output, &project, |_| {
+                format!(
+                    "{project}\nIdle minutes: {}",
+                    project
+                        .idle_minutes
+                        .map(|i| i.to_string())
+                        .unwrap_or("<unknown>".to_owned())
+                )
+            })?;
+            if let Ok(Some(lock)) = client.get_project_lock(self.ctx.project_name()).await {
+                output::hint(self.output, format!("Deployments are {lock}").yellow());
+            }
         }
 
         Ok(CommandOutcome::Ok)
@@ -2115,12 +4101,7 @@ impl Shuttle {
                 .bold()
                 .red()
             );
-            if !Confirm::with_theme(&ColorfulTheme::default())
-                .with_prompt("Are you sure?")
-                .default(false)
-                .interact()
-                .unwrap()
-            {
+            if !prompt::confirm("Are you sure?", false, "Pass --yes to delete the project.")? {
                 return Ok(CommandOutcome::Ok);
             }
         }
@@ -2142,9 +4123,11 @@ impl Shuttle {
         Ok(CommandOutcome::Ok)
     }
 
-    fn make_archive(&self, secrets_file: Option<PathBuf>) -> Result<Vec<u8>> {
+    /// Pack the project into a compressed archive written to `out`, and return `out` with the
+    /// digest of the packed files
+    fn make_archive<W: Write>(&self, secrets_file: Option<PathBuf>, out: W) -> Result<(W, String)> {
         let include_patterns = self.ctx.assets();
-        let encoder = GzEncoder::new(Vec::new(), Compression::new(3));
+        let encoder = GzEncoder::new(out, Compression::new(3));
         let mut tar = Builder::new(encoder);
 
         let working_directory = self.ctx.working_directory();
@@ -2161,6 +4144,8 @@ impl Shuttle {
             .context("adding override `!.git/`")?
             .add("!target/")
             .context("adding override `!target/`")?
+            .add(&format!("!{LOCAL_STATE_DIRNAME}/"))
+            .context(format!("adding override `!{LOCAL_STATE_DIRNAME}/`"))?
             // these should always be ignored when unpacked in deployment, so ignore them here as well
             .add(&format!("!{EXECUTABLE_DIRNAME}/"))
             .context(format!("adding override `!{EXECUTABLE_DIRNAME}/`"))?
@@ -2178,6 +4163,20 @@ impl Shuttle {
 
         let mut globs = GlobSetBuilder::new();
 
+        // Fall back to an encrypted secrets file when there is no plaintext one
+        let secrets_file = secrets_file.or_else(|| {
+            let default = secrets::default_secrets_file(working_directory);
+            default.ends_with("Secrets.enc.toml").then_some(default)
+        });
+        // Encrypted secrets are decrypted in memory and packed as plaintext Secrets.toml,
+        // so the plaintext never touches the disk
+        let decrypted_secrets = match &secrets_file {
+            Some(secrets_file) if secrets::is_encrypted(secrets_file)? => {
+                Some(secrets::read_secrets_contents(secrets_file)?)
+            }
+            _ => None,
+        };
+
         if let Some(secrets_file) = secrets_file.clone() {
             entries.push(secrets_file);
         } else {
@@ -2233,16 +4232,47 @@ impl Shuttle {
         }
 
         // Append all the entries to the archive.
+        let mut hashes = FileHashes::load(working_directory);
+        let mut packed = Vec::with_capacity(archive_files.len());
         for (k, v) in archive_files {
             debug!("Packing {k:?}");
-            tar.append_path_with_name(k, v)?;
+            let sha256 = match &decrypted_secrets {
+                Some(contents) if secrets_file.as_ref() == Some(&k) => {
+                    file_hashes::hash_bytes(contents.as_bytes())
+                }
+                _ => hashes
+                    .hash(&k)
+                    .with_context(|| format!("failed to hash {}", k.display()))?,
+            };
+            packed.push((v.clone(), sha256));
+            match &decrypted_secrets {
+                Some(contents) if secrets_file.as_ref() == Some(&k) => {
+                    let mut header = Header::new_gnu();
+                    header.set_size(contents.len() as u64);
+                    header.set_mode(0o644);
+                    header.set_mtime(
+                        std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .map(|d| d.as_secs())
+                            .unwrap_or_default(),
+                    );
+                    tar.append_data(&mut header, v, contents.as_bytes())?;
+                }
+                _ => tar.append_path_with_name(k, v)?,
+            }
         }
 
+        hashes.save();
+        let digest = file_hashes::digest(
+            packed
+                .iter()
+                .map(|(name, sha256)| (name.as_path(), sha256.as_str())),
+        );
+
         let encoder = tar.into_inner().context("get encoder from tar archive")?;
-        let bytes = encoder.finish().context("finish up encoder")?;
-        debug!("Archive size: {} bytes", bytes.len());
+        let out = encoder.finish().context("finish up encoder")?;
 
-        Ok(bytes)
+        Ok((out, digest))
     }
 }
 
@@ -2298,6 +4328,49 @@ fn is_dirty(repo: &Repository) -> Result<()> {
     Ok(())
 }
 
+/// Whether a command only needs the name of the project, and no files of it
+fn can_pick_project(cmd: &Command) -> bool {
+    matches!(
+        cmd,
+        Command::Deployment(..)
+            | Command::Resource(..)
+            | Command::Certificate(..)
+            | Command::Secrets(
+                SecretsCommand::List { .. }
+                    | SecretsCommand::Set { .. }
+                    | SecretsCommand::Get { .. }
+                    | SecretsCommand::Remove { .. }
+            )
+            | Command::Project(..)
+            | Command::Stop
+            | Command::Status(..)
+            | Command::Open(..)
+            | Command::Logs(LogsArgs { local: false, .. })
+    )
+}
+
+/// Write the man page of a command, named `page`, and then the ones of its subcommands, named
+/// after it like `cargo-shuttle-resource-list`. Returns the paths of the pages.
+fn write_manpages(cmd: &clap::Command, page: &str, dir: &Path) -> Result<Vec<PathBuf>> {
+    let path = dir.join(format!("{page}.1"));
+    let mut file =
+        File::create(&path).with_context(|| format!("failed to create {}", path.display()))?;
+    Man::new(cmd.clone().display_name(page.to_string()))
+        .render(&mut file)
+        .with_context(|| format!("failed to write {}", path.display()))?;
+
+    let mut pages = vec![path];
+    for subcommand in cmd.get_subcommands() {
+        if subcommand.is_hide_set() || subcommand.get_name() == "help" {
+            continue;
+        }
+        let page = format!("{page}-{}", subcommand.get_name());
+        pages.extend(write_manpages(subcommand, &page, dir)?);
+    }
+
+    Ok(pages)
+}
+
 async fn check_version(runtime_path: &Path) -> Result<()> {
     debug!(
         "Checking version of runtime binary at {}",
@@ -2378,7 +4451,7 @@ where
     Fut: std::future::Future<Output = Result<Option<C>>>,
     C: FnOnce() -> O,
 {
-    let progress_bar = create_spinner();
+    let progress_bar = progress::spinner();
     let mut count = 0usize;
     let cleanup = loop {
         if let Some(cleanup) = f(count, progress_bar.clone()).await? {
@@ -2392,30 +4465,6 @@ where
     Ok(cleanup())
 }
 
-fn create_spinner() -> ProgressBar {
-    let pb = indicatif::ProgressBar::new_spinner();
-    pb.enable_steady_tick(std::time::Duration::from_millis(350));
-    pb.set_style(
-        indicatif::ProgressStyle::with_template("{spinner:.orange} {msg}")
-            .unwrap()
-            .tick_strings(&[
-                "( ●    )",
-                "(  ●   )",
-                "(   ●  )",
-                "(    ● )",
-                "(     ●)",
-                "(    ● )",
-                "(   ●  )",
-                "(  ●   )",
-                "( ●    )",
-                "(●     )",
-                "(●●●●●●)",
-            ]),
-    );
-
-    pb
-}
-
 #[derive(PartialEq)]
 pub enum CommandOutcome {
     Ok,
@@ -2444,8 +4493,8 @@ mod tests {
         let mut shuttle = Shuttle::new().unwrap();
         shuttle.load_project(&project_args).unwrap();
 
-        let archive = shuttle
-            .make_archive(deploy_args.secret_args.secrets)
+        let (archive, _) = shuttle
+            .make_archive(deploy_args.secret_args.secrets, Vec::new())
             .unwrap();
 
         let tar = GzDecoder::new(&archive[..]);
@@ -2519,6 +4568,7 @@ mod tests {
             DeployArgs {
                 secret_args: SecretsArgs {
                     secrets: Some(working_directory.join("Secrets.toml.example")),
+                    ..Default::default()
                 },
                 ..Default::default()
             },
@@ -2566,4 +4616,24 @@ mod tests {
             path_from_workspace_root("examples/axum/hello-world")
         );
     }
+
+    #[test]
+    fn writes_manpages() {
+        use clap::CommandFactory;
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = crate::args::ShuttleArgs::command()
+            .name("cargo-shuttle")
+            .bin_name("cargo shuttle");
+        app.build();
+        let pages = crate::write_manpages(&app, "cargo-shuttle", dir.path()).unwrap();
+
+        assert!(pages.contains(&dir.path().join("cargo-shuttle.1")));
+        assert!(pages.contains(&dir.path().join("cargo-shuttle-resource-list.1")));
+        assert!(!pages.contains(&dir.path().join("cargo-shuttle-help.1")));
+
+        let page = fs::read_to_string(dir.path().join("cargo-shuttle-deploy.1")).unwrap();
+        assert!(page.contains("cargo\\-shuttle\\-deploy"));
+        assert!(page.contains("cargo shuttle deploy"));
+    }
 }
@@ -0,0 +1,844 @@
+//! State shared by the services of a `cargo shuttle run`, and the `cargo shuttle local` commands
+//! that look after it
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use shuttle_common::constants::{LOCAL_STATE_DIRNAME, STORAGE_DIRNAME};
+use shuttle_common::models::deployment::ExternalResources;
+use tokio::time::sleep;
+use tracing::{debug, trace};
+
+use crate::args::OutputMode;
+use crate::config::LocalRunConfig;
+use crate::logs::SourcePrefix;
+use crate::provisioner_server::LocalProvisioner;
+use crate::seed::Seed;
+use crate::style::{self, Tone};
+use crate::{output, prompt, CommandOutcome, Shuttle};
+
+pub struct LocalRun {
+    /// Plain environment variables from Shuttle.toml
+    pub env_vars: BTreeMap<String, String>,
+    /// File the output of the services is captured in, for `cargo shuttle logs --local`
+    pub session_log: Option<PathBuf>,
+    pub config: LocalRunConfig,
+    /// Directory that containers of resources keep their data in
+    pub data_dir: PathBuf,
+    /// Where shuttle-persist keeps its files, when it is moved from the default
+    pub storage_dir: Option<PathBuf>,
+    /// The last lines of output of each service, for crash reports
+    pub recent_output: RecentOutput,
+    /// Nothing is downloaded, only what is already on this machine is used
+    pub offline: bool,
+    /// How the databases are filled with fixtures, if they are
+    pub seed: Option<Seed>,
+    /// Resources outside of Shuttle, handed to the services from their secrets
+    pub external: ExternalResources,
+    /// Containers of databases that were seeded in this run, or found to not need it
+    seeded: Mutex<Vec<String>>,
+    /// Containers to remove when the run ends
+    disposable: Mutex<Vec<String>>,
+    /// Containers that were started by this run, to stop when it ends
+    started: Mutex<Vec<String>>,
+}
+
+impl LocalRun {
+    pub fn new(
+        env_vars: BTreeMap<String, String>,
+        session_log: Option<PathBuf>,
+        config: LocalRunConfig,
+        data_dir: PathBuf,
+        storage_dir: Option<PathBuf>,
+        offline: bool,
+        seed: Option<Seed>,
+    ) -> Self {
+        Self {
+            env_vars,
+            session_log,
+            config,
+            data_dir,
+            storage_dir,
+            offline,
+            seed,
+            external: ExternalResources::default(),
+            seeded: Mutex::new(Vec::new()),
+            recent_output: RecentOutput::default(),
+            disposable: Mutex::new(Vec::new()),
+            started: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn with_external(mut self, external: ExternalResources) -> Self {
+        self.external = external;
+
+        self
+    }
+
+    /// Remove this container when the run ends, instead of keeping it for the next run
+    pub fn dispose_on_exit(&self, container_name: String) {
+        let mut disposable = self.disposable.lock().unwrap_or_else(|e| e.into_inner());
+        if !disposable.contains(&container_name) {
+            disposable.push(container_name);
+        }
+    }
+
+    /// Whether the database in this container is yet to be looked at for seeding in this run, so
+    /// that services sharing it and restarts don't seed it again
+    pub fn first_seed_check(&self, container_name: &str) -> bool {
+        let mut seeded = self.seeded.lock().unwrap_or_else(|e| e.into_inner());
+        if seeded.iter().any(|name| name == container_name) {
+            return false;
+        }
+        seeded.push(container_name.to_string());
+
+        true
+    }
+
+    /// Stop this container when the run ends, since it was not running before it
+    pub fn stop_on_exit(&self, container_name: String) {
+        let mut started = self.started.lock().unwrap_or_else(|e| e.into_inner());
+        if !started.contains(&container_name) {
+            started.push(container_name);
+        }
+    }
+
+    /// Stop the containers this run started, giving each `grace` to shut down, and remove the
+    /// disposable ones
+    pub async fn tear_down_containers(&self, grace: Duration) {
+        let disposable =
+            std::mem::take(&mut *self.disposable.lock().unwrap_or_else(|e| e.into_inner()));
+        let started = std::mem::take(&mut *self.started.lock().unwrap_or_else(|e| e.into_inner()));
+        if disposable.is_empty() && started.is_empty() {
+            return;
+        }
+
+        let provisioner = match LocalProvisioner::new() {
+            Ok(provisioner) => provisioner,
+            Err(err) => {
+                debug!(error = %err, "failed to connect to Docker to tear down containers");
+                return;
+            }
+        };
+        // Disposable containers are removed by force below, there is no data to shut down for
+        let to_stop = started
+            .into_iter()
+            .filter(|container_name| !disposable.contains(container_name))
+            .collect::<Vec<_>>();
+        let provisioner = &provisioner;
+        let stops = to_stop.iter().map(|container_name| async move {
+            trace!("stopping container {container_name}");
+            provisioner
+                .stop_container(container_name, grace)
+                .await
+                .map_err(|err| debug!(error = %err, "failed to stop container {container_name}"))
+                .is_ok()
+        });
+        let stopped = futures::future::join_all(stops)
+            .await
+            .into_iter()
+            .filter(|stopped| *stopped)
+            .count();
+        if stopped > 0 {
+            println!(
+                "{} {stopped} resource container{}",
+                style::status("Stopped", Tone::Success),
+                if stopped == 1 { "" } else { "s" }
+            );
+        }
+
+        for container_name in disposable {
+            trace!("removing container {container_name}");
+            if let Err(err) = provisioner.remove_container(&container_name).await {
+                debug!(error = %err, "failed to remove container {container_name}");
+            }
+        }
+    }
+}
+
+/// Poll the health check of a service until it answers with a success, and report when it does
+pub async fn report_readiness(service_name: String, url: String) {
+    const INTERVAL: Duration = Duration::from_millis(250);
+    const TIMEOUT: Duration = Duration::from_secs(60);
+
+    let client = reqwest::Client::new();
+    let start = Instant::now();
+    let mut last_problem = String::from("no response");
+    while start.elapsed() < TIMEOUT {
+        match client.get(&url).timeout(INTERVAL * 4).send().await {
+            Ok(response) if response.status().is_success() => {
+                println!(
+                    "{} {service_name} is ready after {}ms",
+                    style::status("Healthy", Tone::Success),
+                    start.elapsed().as_millis()
+                );
+                return;
+            }
+            Ok(response) => last_problem = format!("status {}", response.status()),
+            Err(err) => last_problem = err.to_string(),
+        }
+        tokio::time::sleep(INTERVAL).await;
+    }
+
+    println!(
+        "{} {service_name} did not pass its health check at {url} within {}s ({last_problem})",
+        style::status("Unhealthy", Tone::Failure),
+        TIMEOUT.as_secs()
+    );
+}
+
+/// The last lines of output of each service, by service name
+#[derive(Clone, Default)]
+pub struct RecentOutput(Arc<Mutex<HashMap<String, VecDeque<String>>>>);
+
+impl RecentOutput {
+    const LINES: usize = 50;
+
+    pub fn push(&self, service_name: &str, line: String) {
+        let mut output = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let lines = output.entry(service_name.to_string()).or_default();
+        if lines.len() == Self::LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// Take the lines of a service, leaving none behind for the next time it runs
+    pub fn take(&self, service_name: &str) -> Vec<String> {
+        let mut output = self.0.lock().unwrap_or_else(|e| e.into_inner());
+
+        output
+            .remove(service_name)
+            .map(Vec::from)
+            .unwrap_or_default()
+    }
+}
+
+/// The services of a workspace that run together
+pub struct LocalServices {
+    /// URL of every service, set as env vars on all of them so that they can call each other
+    pub discovery: BTreeMap<String, String>,
+    /// Labels the output of each service when they are multiplexed
+    pub prefix: Option<Arc<SourcePrefix>>,
+    /// Where the services listen when they are behind proxies, like the HTTPS ones, by index
+    pub proxy_backends: Vec<SocketAddr>,
+}
+
+impl LocalServices {
+    /// `addr` is the address of the first service. The others use the ports after it.
+    pub fn new(names: Vec<String>, addr: SocketAddr, prefixed: bool) -> Self {
+        // Services bound to all interfaces are still reached through localhost
+        let host = if addr.ip().is_unspecified() {
+            Ipv4Addr::LOCALHOST.into()
+        } else {
+            addr.ip()
+        };
+        let discovery = names
+            .iter()
+            .zip(addr.port()..)
+            .map(|(name, port)| {
+                (
+                    discovery_var(name),
+                    format!("http://{}", SocketAddr::new(host, port)),
+                )
+            })
+            .collect();
+        let prefix = if prefixed {
+            SourcePrefix::new(false, names).map(Arc::new)
+        } else {
+            None
+        };
+
+        Self {
+            discovery,
+            prefix,
+            proxy_backends: Vec::new(),
+        }
+    }
+
+    /// Serve the services through proxies, which forward to `backends`
+    pub fn with_proxies(mut self, backends: Vec<SocketAddr>) -> Self {
+        self.proxy_backends = backends;
+        self
+    }
+
+    /// The services are reached over HTTPS
+    pub fn with_https(mut self) -> Self {
+        for url in self.discovery.values_mut() {
+            *url = url.replacen("http://", "https://", 1);
+        }
+
+        self
+    }
+}
+
+/// Name of the env var with the URL of a service, like `SHUTTLE_SERVICE_MY_API_URL`
+pub fn discovery_var(service_name: &str) -> String {
+    let name: String = service_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    format!("SHUTTLE_SERVICE_{name}_URL")
+}
+
+/// A local run that is going on, as registered for `cargo shuttle local ps`
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ActiveRun {
+    pub project: String,
+    /// Process of the `cargo shuttle run`
+    pub pid: u32,
+    /// Where the first service listens
+    pub address: SocketAddr,
+    /// RFC 3339 time the run started
+    pub started_at: String,
+    pub working_directory: PathBuf,
+}
+
+impl ActiveRun {
+    pub fn new(project: String, address: SocketAddr, working_directory: PathBuf) -> Self {
+        Self {
+            project,
+            pid: std::process::id(),
+            address,
+            started_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            working_directory,
+        }
+    }
+
+    pub fn uptime(&self) -> Duration {
+        DateTime::parse_from_rfc3339(&self.started_at)
+            .ok()
+            .and_then(|started_at| (Utc::now() - started_at.with_timezone(&Utc)).to_std().ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Where local runs register themselves while they go on
+pub fn runs_dir() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir().context(
+        "Could not find a configuration directory. Your operating system may not be supported.",
+    )?;
+
+    Ok(config_dir.join("shuttle").join("runs"))
+}
+
+/// Keeps a local run registered until it is dropped
+pub struct RunRegistration(PathBuf);
+
+impl RunRegistration {
+    pub fn new(dir: &Path, run: &ActiveRun) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+        let path = dir.join(format!("{}.json", run.pid));
+        std::fs::write(&path, serde_json::to_vec(run)?)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+
+        Ok(Self(path))
+    }
+}
+
+impl Drop for RunRegistration {
+    fn drop(&mut self) {
+        if let Err(err) = std::fs::remove_file(&self.0) {
+            debug!(error = %err, "failed to remove the registration of the local run");
+        }
+    }
+}
+
+/// The local runs registered in `dir`, oldest first. Registrations of runs that ended without
+/// removing them are cleaned up.
+pub fn active_runs(dir: &Path) -> Vec<ActiveRun> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut runs = Vec::new();
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        let run = std::fs::read(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_slice::<ActiveRun>(&contents).ok());
+        match run {
+            Some(run) if process_alive(run.pid) => runs.push(run),
+            _ => {
+                trace!("removing stale run registration {}", path.display());
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+    }
+    runs.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+
+    runs
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    use nix::errno::Errno;
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+
+    // Signal 0 only checks that the process is there
+    matches!(
+        kill(Pid::from_raw(pid as i32), None),
+        Ok(()) | Err(Errno::EPERM)
+    )
+}
+
+/// There is no cheap check on other platforms, so runs are trusted to remove their registration
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
+
+/// Something that local runs keep on disk
+#[derive(Debug, PartialEq)]
+pub struct StoredData {
+    pub label: String,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// What local runs keep in `data_dir`, by kind of container, and in `storage_dir` for
+/// shuttle-persist. Missing directories are left out.
+pub fn stored_data(data_dir: &Path, storage_dir: &Path) -> Vec<StoredData> {
+    let mut stored = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(data_dir) {
+        let mut entries = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            // shuttle-persist may be kept in the data directory as well
+            .filter(|path| path.is_dir() && path != storage_dir)
+            .collect::<Vec<_>>();
+        entries.sort();
+        stored.extend(entries.into_iter().map(|path| {
+            StoredData {
+                label: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                size: dir_size(&path),
+                path,
+            }
+        }));
+    }
+    if storage_dir.is_dir() {
+        stored.push(StoredData {
+            label: "shuttle-persist".to_string(),
+            path: storage_dir.to_path_buf(),
+            size: dir_size(storage_dir),
+        });
+    }
+
+    stored
+}
+
+/// Total size of the files under `path`. Files that can't be read, like the ones owned by the
+/// users of containers, are not counted.
+fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// A size like `1.5 MB`
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+
+    if bytes < 1000 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for next in UNITS {
+        if size < 1000.0 {
+            break;
+        }
+        size /= 1000.0;
+        unit = next;
+    }
+
+    format!("{size:.1} {unit}")
+}
+
+impl Shuttle {
+    /// Where local runs keep the data of resource containers
+    pub(crate) fn local_data_dir(&self) -> PathBuf {
+        let working_directory = self.ctx.working_directory();
+        match self.ctx.local_run_config().data_dir {
+            Some(data_dir) => working_directory.join(data_dir),
+            None => working_directory.join(LOCAL_STATE_DIRNAME).join("data"),
+        }
+    }
+
+    /// Where shuttle-persist keeps its files on local runs
+    pub(crate) fn local_storage_dir(&self) -> PathBuf {
+        let working_directory = self.ctx.working_directory();
+        match self.ctx.local_run_config().data_dir {
+            Some(data_dir) => working_directory.join(data_dir).join("storage"),
+            None => working_directory.join(STORAGE_DIRNAME),
+        }
+    }
+
+    pub(crate) fn local_status(&self) -> Result<CommandOutcome> {
+        let project_name = self.ctx.project_name();
+        let stored = stored_data(&self.local_data_dir(), &self.local_storage_dir());
+        if stored.is_empty() {
+            println!("Local runs of {project_name} have not stored anything yet.");
+            return Ok(CommandOutcome::Ok);
+        }
+
+        println!("Stored by local runs of {project_name}:");
+        let working_directory = self.ctx.working_directory();
+        let width = stored
+            .iter()
+            .map(|data| data.label.len())
+            .max()
+            .unwrap_or(0);
+        for data in &stored {
+            let path = data
+                .path
+                .strip_prefix(working_directory)
+                .unwrap_or(&data.path);
+            println!(
+                "  {:width$}  {:>9}  {}",
+                data.label,
+                format_size(data.size),
+                path.display()
+            );
+        }
+        println!(
+            "Total: {}",
+            format_size(stored.iter().map(|data| data.size).sum())
+        );
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn local_clean(&self, yes: bool) -> Result<CommandOutcome> {
+        let project_name = self.ctx.project_name().to_string();
+        let stored = stored_data(&self.local_data_dir(), &self.local_storage_dir());
+        // The containers keep using the data, so they go too. They are started again on the next run.
+        let docker = LocalProvisioner::new()
+            .map_err(|err| debug!(error = %err, "failed to connect to Docker"))
+            .ok();
+        let containers = match &docker {
+            Some(docker) => docker
+                .project_containers(&project_name)
+                .await
+                .unwrap_or_else(|err| {
+                    debug!(error = %err, "failed to list the containers");
+                    Vec::new()
+                })
+                .into_iter()
+                .filter_map(|container| container.names?.into_iter().next())
+                .map(|name| name.trim_start_matches('/').to_string())
+                .collect(),
+            None => Vec::new(),
+        };
+
+        if stored.is_empty() && containers.is_empty() {
+            println!("Local runs of {project_name} have not stored anything.");
+            return Ok(CommandOutcome::Ok);
+        }
+
+        if !yes {
+            println!("This removes:");
+            for data in &stored {
+                println!("  {} ({})", data.label, format_size(data.size));
+            }
+            for name in &containers {
+                println!("  the container {name}");
+            }
+            if !prompt::confirm(
+                "Remove the local data?",
+                false,
+                "Pass --yes to remove the local data.",
+            )? {
+                return Ok(CommandOutcome::Ok);
+            }
+        }
+
+        if let Some(docker) = &docker {
+            for name in &containers {
+                docker
+                    .remove_container(name)
+                    .await
+                    .with_context(|| format!("failed to remove the container {name}"))?;
+            }
+        }
+        for data in &stored {
+            match std::fs::remove_dir_all(&data.path) {
+                Ok(()) => {}
+                Err(err) if err.kind() == std::io::ErrorKind::PermissionDenied => bail!(
+                    "Some files in {path} belong to the user of a container. \
+                    Remove them with `sudo rm -rf {path}`",
+                    path = data.path.display()
+                ),
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("failed to remove {}", data.path.display()))
+                }
+            }
+        }
+        println!("Removed the local data of {project_name}.");
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn local_ps(&self) -> Result<CommandOutcome> {
+        /// A local run with the containers it uses, for JSON output
+        #[derive(serde::Serialize)]
+        struct RunStatus<'a> {
+            #[serde(flatten)]
+            run: &'a ActiveRun,
+            containers: Vec<String>,
+        }
+
+        let runs = active_runs(&runs_dir()?);
+        if runs.is_empty() && self.output != OutputMode::Json {
+            println!("No local runs are going on.");
+            return Ok(CommandOutcome::Ok);
+        }
+
+        let docker = LocalProvisioner::new()
+            .map_err(|err| debug!(error = %err, "failed to connect to Docker"))
+            .ok();
+        let mut rows = Vec::new();
+        let mut statuses = Vec::new();
+        for run in &runs {
+            let containers = match &docker {
+                Some(docker) => docker
+                    .project_containers(&run.project)
+                    .await
+                    .unwrap_or_else(|err| {
+                        debug!(error = %err, "failed to list the containers");
+                        Vec::new()
+                    })
+                    .into_iter()
+                    .filter(|container| container.state.as_deref() == Some("running"))
+                    .filter_map(|container| container.names?.into_iter().next())
+                    .map(|name| name.trim_start_matches('/').to_string())
+                    .collect::<Vec<_>>(),
+                None => Vec::new(),
+            };
+            statuses.push(RunStatus {
+                run,
+                containers: containers.clone(),
+            });
+            let uptime = Duration::from_secs(run.uptime().as_secs());
+            rows.push([
+                run.project.clone(),
+                run.address.to_string(),
+                run.pid.to_string(),
+                humantime::format_duration(uptime).to_string(),
+                if containers.is_empty() {
+                    "-".to_string()
+                } else {
+                    containers.join(", ")
+                },
+            ]);
+        }
+
+        let header = ["PROJECT", "ADDRESS", "PID", "UPTIME", "CONTAINERS"];
+        let widths = (0..header.len())
+            .map(|i| {
+                rows.iter()
+                    .map(|row| row[i].len())
+                    .chain([header[i].len()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect::<Vec<_>>();
+        output::print(self.output, &statuses, |_| {
+            std::iter::once(header.map(|h| h.to_string()))
+                .chain(rows)
+                .map(|row| {
+                    row.iter()
+                        .zip(&widths)
+                        .map(|(cell, width)| format!("{cell:width$}"))
+                        .collect::<Vec<_>>()
+                        .join("  ")
+                        .trim_end()
+                        .to_string()
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })?;
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn local_stop(project: &str) -> Result<CommandOutcome> {
+        const TIMEOUT: Duration = Duration::from_secs(60);
+
+        let runs_dir = runs_dir()?;
+        let pids = active_runs(&runs_dir)
+            .into_iter()
+            .filter(|run| run.project == project)
+            .map(|run| run.pid)
+            .collect::<Vec<_>>();
+        if pids.is_empty() {
+            bail!("No local run of {project} is going on. See `cargo shuttle local ps`.");
+        }
+
+        for pid in &pids {
+            Shuttle::terminate_local_run(*pid)?;
+        }
+
+        // The runs stop their services and containers like on ctrl-c, and remove their
+        // registration when they are done
+        let start = Instant::now();
+        while active_runs(&runs_dir)
+            .iter()
+            .any(|run| pids.contains(&run.pid))
+        {
+            if start.elapsed() > TIMEOUT {
+                bail!(
+                    "The local run of {project} did not stop within {}s",
+                    TIMEOUT.as_secs()
+                );
+            }
+            sleep(Duration::from_millis(250)).await;
+        }
+        println!(
+            "{} the local run of {project}",
+            style::status("Stopped", Tone::Success)
+        );
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    #[cfg(unix)]
+    fn terminate_local_run(pid: u32) -> Result<()> {
+        use nix::sys::signal::{kill, Signal};
+        use nix::unistd::Pid;
+
+        // The same as ctrl-c in its terminal, which every mode of a local run handles
+        kill(Pid::from_raw(pid as i32), Signal::SIGINT)
+            .with_context(|| format!("failed to signal the local run with PID {pid}"))
+    }
+
+    #[cfg(not(unix))]
+    fn terminate_local_run(pid: u32) -> Result<()> {
+        bail!("The local run with PID {pid} can only be stopped from its terminal on this platform")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_dir = dir.path().join("data");
+        std::fs::create_dir_all(data_dir.join("shared_postgres")).unwrap();
+        std::fs::write(data_dir.join("shared_postgres/base"), [0; 1500]).unwrap();
+        std::fs::create_dir_all(data_dir.join("storage")).unwrap();
+        std::fs::write(data_dir.join("storage/key.bin"), [0; 10]).unwrap();
+
+        assert_eq!(
+            stored_data(&data_dir, &data_dir.join("storage")),
+            vec![
+                StoredData {
+                    label: "shared_postgres".to_string(),
+                    path: data_dir.join("shared_postgres"),
+                    size: 1500,
+                },
+                StoredData {
+                    label: "shuttle-persist".to_string(),
+                    path: data_dir.join("storage"),
+                    size: 10,
+                },
+            ]
+        );
+        assert!(stored_data(&dir.path().join("missing"), &dir.path().join("none")).is_empty());
+    }
+
+    #[test]
+    fn registered_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(active_runs(&dir.path().join("missing")).is_empty());
+
+        let run = ActiveRun::new(
+            "my-api".to_string(),
+            SocketAddr::from((Ipv4Addr::LOCALHOST, 8000)),
+            PathBuf::from("/projects/my-api"),
+        );
+        let registration = RunRegistration::new(dir.path(), &run).unwrap();
+        std::fs::write(dir.path().join("1.json"), "not a run").unwrap();
+
+        assert_eq!(active_runs(dir.path()), vec![run]);
+        // The unreadable registration was cleaned up
+        assert!(!dir.path().join("1.json").exists());
+
+        drop(registration);
+        assert!(active_runs(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(format_size(999), "999 B");
+        assert_eq!(format_size(1500), "1.5 KB");
+        assert_eq!(format_size(2_000_000), "2.0 MB");
+        assert_eq!(format_size(3_400_000_000), "3.4 GB");
+    }
+
+    #[test]
+    fn service_discovery() {
+        let services = LocalServices::new(
+            vec!["api".to_string(), "auth-worker".to_string()],
+            "0.0.0.0:8000".parse().unwrap(),
+            false,
+        );
+
+        assert_eq!(
+            services.discovery,
+            BTreeMap::from([
+                (
+                    "SHUTTLE_SERVICE_API_URL".to_string(),
+                    "http://127.0.0.1:8000".to_string()
+                ),
+                (
+                    "SHUTTLE_SERVICE_AUTH_WORKER_URL".to_string(),
+                    "http://127.0.0.1:8001".to_string()
+                ),
+            ])
+        );
+        assert!(services.prefix.is_none());
+    }
+
+    #[test]
+    fn recent_output() {
+        let output = RecentOutput::default();
+        for i in 0..RecentOutput::LINES + 2 {
+            output.push("api", format!("line {i}"));
+        }
+        output.push("worker", "other".to_string());
+
+        let lines = output.take("api");
+        assert_eq!(lines.len(), RecentOutput::LINES);
+        assert_eq!(lines[0], "line 2");
+        assert!(output.take("api").is_empty());
+        assert_eq!(output.take("worker"), vec!["other".to_string()]);
+    }
+}
@@ -0,0 +1,213 @@
+//! `Shuttle.lock`, which records what a deploy resolved to, for `cargo shuttle deploy --locked`.
+//!
+//! Like `Cargo.lock`, it is written next to the manifest on every deploy and meant to be committed.
+//! It holds the version of shuttle-runtime and of the resource crates in `Cargo.lock`, the image
+//! deployments are built in, the version of the format resources are provisioned with, and a hash
+//! of Shuttle.toml. A locked deploy refuses to go ahead when any of them changed since.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use cargo_metadata::Metadata;
+use serde::{Deserialize, Serialize};
+
+use crate::file_hashes::hash_bytes;
+
+pub const FILE_NAME: &str = "Shuttle.lock";
+/// Changed when the layout of the lockfile changes
+const FORMAT: u32 = 1;
+const HEADER: &str = "# This file is written by cargo-shuttle on every deploy.\n\
+                      # It is not meant to be edited by hand.\n";
+
+/// The crates of the resources that can be provisioned
+const RESOURCE_CRATES: &[&str] = &[
+    "shuttle-aws-rds",
+    "shuttle-opendal",
+    "shuttle-persist",
+    "shuttle-qdrant",
+    "shuttle-shared-db",
+    "shuttle-turso",
+];
+
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Lockfile {
+    pub format: u32,
+    /// The versions of shuttle-runtime in `Cargo.lock`, usually only one
+    pub runtime: Option<String>,
+    pub builder_image: String,
+    pub resource_schema: u32,
+    /// The SHA-256 of Shuttle.toml, or of nothing when there is none
+    pub config_hash: String,
+    /// The versions of the resource crates in `Cargo.lock`, by name. Last, since it is a table.
+    pub resources: BTreeMap<String, String>,
+}
+
+impl Lockfile {
+    /// What a deploy of the workspace in `metadata` with the config in `project_config` resolves to
+    pub fn resolve(
+        metadata: &Metadata,
+        project_config: &Path,
+        builder_image: &str,
+        resource_schema: u32,
+    ) -> Result<Self> {
+        let config = if project_config.exists() {
+            fs::read(project_config)
+                .with_context(|| format!("failed to read {}", project_config.display()))?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            format: FORMAT,
+            runtime: versions(metadata, "shuttle-runtime"),
+            builder_image: builder_image.to_string(),
+            resource_schema,
+            config_hash: hash_bytes(&config),
+            resources: RESOURCE_CRATES
+                .iter()
+                .filter_map(|name| Some((name.to_string(), versions(metadata, name)?)))
+                .collect(),
+        })
+    }
+
+    /// The lockfile at `path`, if there is one
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let lockfile = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+
+        Ok(Some(lockfile))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents = format!("{HEADER}\n{}", toml::to_string(self)?);
+
+        fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// What changed from this lockfile to `current`, one line per change
+    pub fn drift(&self, current: &Self) -> Vec<String> {
+        let mut changes = Vec::new();
+        if self.format != current.format {
+            changes.push(format!(
+                "the lockfile format changed from {} to {}",
+                self.format, current.format
+            ));
+        }
+        if self.runtime != current.runtime {
+            changes.push(format!(
+                "shuttle-runtime changed from {} to {}",
+                self.runtime.as_deref().unwrap_or("none"),
+                current.runtime.as_deref().unwrap_or("none")
+            ));
+        }
+        if self.builder_image != current.builder_image {
+            changes.push(format!(
+                "the builder image changed from {} to {}",
+                self.builder_image, current.builder_image
+            ));
+        }
+        if self.resource_schema != current.resource_schema {
+            changes.push(format!(
+                "the resource schema changed from {} to {}",
+                self.resource_schema, current.resource_schema
+            ));
+        }
+        let names = self
+            .resources
+            .keys()
+            .chain(current.resources.keys())
+            .collect::<BTreeSet<_>>();
+        for name in names {
+            match (self.resources.get(name), current.resources.get(name)) {
+                (Some(locked), Some(version)) if locked != version => {
+                    changes.push(format!("{name} changed from {locked} to {version}"))
+                }
+                (Some(locked), None) => changes.push(format!("{name} {locked} was removed")),
+                (None, Some(version)) => changes.push(format!("{name} {version} was added")),
+                _ => {}
+            }
+        }
+        if self.config_hash != current.config_hash {
+            changes.push("Shuttle.toml changed".to_string());
+        }
+
+        changes
+    }
+}
+
+/// The versions of the package `name` in the dependency graph, if it is in it
+fn versions(metadata: &Metadata, name: &str) -> Option<String> {
+    let versions = metadata
+        .packages
+        .iter()
+        .filter(|package| package.name == name)
+        .map(|package| package.version.clone())
+        .collect::<BTreeSet<_>>();
+    if versions.is_empty() {
+        return None;
+    }
+
+    Some(
+        versions
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", "),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lockfile() -> Lockfile {
+        Lockfile {
+            format: FORMAT,
+            runtime: Some("0.43.0".to_string()),
+            builder_image: "docker.io/library/rust:1.76.0-bookworm".to_string(),
+            resource_schema: 1,
+            config_hash: hash_bytes(b"name = \"my-api\"\n"),
+            resources: [("shuttle-shared-db".to_string(), "0.43.0".to_string())].into(),
+        }
+    }
+
+    #[test]
+    fn drift() {
+        let locked = lockfile();
+        assert!(locked.drift(&lockfile()).is_empty());
+
+        let mut current = lockfile();
+        current.runtime = Some("0.44.0".to_string());
+        current.resources = [("shuttle-persist".to_string(), "0.44.0".to_string())].into();
+        current.config_hash = hash_bytes(b"");
+        assert_eq!(
+            locked.drift(&current),
+            [
+                "shuttle-runtime changed from 0.43.0 to 0.44.0",
+                "shuttle-persist 0.44.0 was added",
+                "shuttle-shared-db 0.43.0 was removed",
+                "Shuttle.toml changed",
+            ]
+        );
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(FILE_NAME);
+        assert_eq!(Lockfile::load(&path).unwrap(), None);
+
+        lockfile().save(&path).unwrap();
+        assert!(fs::read_to_string(&path).unwrap().starts_with(HEADER));
+        assert_eq!(Lockfile::load(&path).unwrap(), Some(lockfile()));
+    }
+}
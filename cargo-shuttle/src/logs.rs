@@ -0,0 +1,855 @@
+//! Client-side processing of the log stream of a deployment
+
+pub mod file;
+pub mod forward;
+pub mod local;
+mod search;
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::OnceLock;
+
+use anyhow::Result;
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use crossterm::style::{Color, Stylize};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use shuttle_common::{log::Backend, LogItem};
+
+use crate::args::{LogField, LogLevel, LogsArgs, OutputMode};
+use crate::config::LogsConfig;
+use crate::redact;
+
+use self::file::LogFile;
+
+/// Parse a point in time given either as an RFC 3339 timestamp, or as a duration in the past
+/// like `2h` or `1h 30m`
+pub fn parse_time_spec(spec: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(spec) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    let ago = humantime::parse_duration(spec).map_err(|_| {
+        format!("'{spec}' is neither an RFC 3339 timestamp nor a duration like '2h' or '10m'")
+    })?;
+    let ago = chrono::Duration::from_std(ago).map_err(|_| format!("'{spec}' is too long ago"))?;
+
+    Ok(Utc::now() - ago)
+}
+
+/// Remove the terminal color codes that log lines of services often contain
+pub fn strip_ansi(line: &str) -> std::borrow::Cow<'_, str> {
+    static ANSI: OnceLock<Regex> = OnceLock::new();
+
+    ANSI.get_or_init(|| Regex::new(r"\x1b\[[0-9;]*m").unwrap())
+        .replace_all(line, "")
+}
+
+/// Find the level of a log line, such as the one printed by `tracing` or `log` formatters
+pub fn detect_level(line: &str) -> Option<LogLevel> {
+    static LEVEL: OnceLock<Regex> = OnceLock::new();
+
+    let level = LEVEL
+        .get_or_init(|| Regex::new(r"\b(TRACE|DEBUG|INFO|WARN|WARNING|ERROR)\b").unwrap())
+        .find(line)?;
+
+    Some(match level.as_str() {
+        "TRACE" => LogLevel::Trace,
+        "DEBUG" => LogLevel::Debug,
+        "INFO" => LogLevel::Info,
+        "WARN" | "WARNING" => LogLevel::Warn,
+        _ => LogLevel::Error,
+    })
+}
+
+/// The parts of a log line printed by the common `tracing` and `log` formatters
+#[derive(Debug, PartialEq)]
+pub struct ParsedLine<'a> {
+    pub level: Option<LogLevel>,
+    pub target: Option<&'a str>,
+    pub message: &'a str,
+}
+
+impl<'a> ParsedLine<'a> {
+    /// Parse a line without color codes. Without a level, the whole line is the message.
+    pub fn parse(line: &'a str) -> Self {
+        static LINE: OnceLock<Regex> = OnceLock::new();
+
+        let Some(level) = detect_level(line) else {
+            return Self {
+                level: None,
+                target: None,
+                message: line.trim(),
+            };
+        };
+
+        let after_level = LINE
+            .get_or_init(|| Regex::new(r"\b(TRACE|DEBUG|INFO|WARN|WARNING|ERROR)\b\]?\s*").unwrap())
+            .find(line)
+            .map_or(line, |m| &line[m.end()..]);
+        // `my_crate::module: message`, as printed by tracing-subscriber and env_logger
+        let (target, message) = match after_level.split_once(": ") {
+            Some((target, message))
+                if !target.is_empty()
+                    && target
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':') =>
+            {
+                (Some(target), message)
+            }
+            _ => (None, after_level),
+        };
+
+        Self {
+            level: Some(level),
+            target,
+            message: message.trim(),
+        }
+    }
+}
+
+fn level_name(level: LogLevel) -> &'static str {
+    match level {
+        LogLevel::Trace => "trace",
+        LogLevel::Debug => "debug",
+        LogLevel::Info => "info",
+        LogLevel::Warn => "warn",
+        LogLevel::Error => "error",
+    }
+}
+
+/// Colors for the source prefixes of interleaved logs
+const SOURCE_COLORS: [Color; 6] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Yellow,
+    Color::Green,
+    Color::Blue,
+    Color::Red,
+];
+
+/// Labels every line with where it came from, when the logs of several deployments or services
+/// are interleaved
+#[derive(Debug)]
+pub struct SourcePrefix {
+    deployments: bool,
+    services: Vec<String>,
+    width: usize,
+}
+
+impl SourcePrefix {
+    /// Length of the shortened deployment IDs
+    const ID_LEN: usize = 8;
+
+    pub fn new(deployments: bool, services: Vec<String>) -> Option<Self> {
+        if !deployments && services.is_empty() {
+            return None;
+        }
+
+        let service_width = services.iter().map(String::len).max().unwrap_or_default();
+        let width = match (deployments, service_width) {
+            (true, 0) => Self::ID_LEN,
+            (true, service_width) => Self::ID_LEN + 1 + service_width,
+            (false, service_width) => service_width,
+        };
+
+        Some(Self {
+            deployments,
+            services,
+            width,
+        })
+    }
+
+    pub fn apply(&self, log: &LogItem, line: String) -> String {
+        let service = match &log.internal_origin {
+            Backend::Runtime(name) if !self.services.is_empty() => Some(name.as_str()),
+            _ => None,
+        };
+        let id = log.id.to_string();
+        let label = match (self.deployments, service) {
+            (true, Some(service)) => format!("{}/{service}", &id[..Self::ID_LEN]),
+            (true, None) => id[..Self::ID_LEN].to_string(),
+            (false, service) => service.unwrap_or_default().to_string(),
+        };
+
+        // Services keep the color of their position in `--service`, so that they don't clash
+        let index = match service.and_then(|s| self.services.iter().position(|x| x == s)) {
+            Some(position) if !self.deployments => position,
+            _ => {
+                let mut hasher = DefaultHasher::new();
+                label.hash(&mut hasher);
+                hasher.finish() as usize
+            }
+        };
+        let color = SOURCE_COLORS[index % SOURCE_COLORS.len()];
+
+        format!(
+            "{} | {line}",
+            format!("{label:<width$}", width = self.width).with(color)
+        )
+    }
+}
+
+/// Time zone to show log timestamps in
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Timezone {
+    Utc,
+    Local,
+}
+
+/// Check that a strftime-style format, like `%H:%M:%S%.3f`, is valid
+pub fn parse_time_format(format: &str) -> Result<String, String> {
+    if StrftimeItems::new(format).any(|item| item == Item::Error) {
+        return Err(format!("'{format}' is not a valid strftime format"));
+    }
+
+    Ok(format.to_string())
+}
+
+/// How timestamps are shown
+#[derive(Debug, Default)]
+struct TimeDisplay {
+    /// Without one, text output uses the local time zone and structured output uses UTC
+    timezone: Option<Timezone>,
+    format: Option<String>,
+}
+
+impl TimeDisplay {
+    fn format(&self, timestamp: DateTime<Utc>, default: Timezone) -> String {
+        let timezone = self.timezone.unwrap_or(default);
+        match (&self.format, timezone) {
+            (Some(format), Timezone::Utc) => timestamp.format(format).to_string(),
+            (Some(format), Timezone::Local) => {
+                timestamp.with_timezone(&Local).format(format).to_string()
+            }
+            (None, Timezone::Utc) => timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+            (None, Timezone::Local) => timestamp
+                .with_timezone(&Local)
+                .to_rfc3339_opts(SecondsFormat::Millis, false),
+        }
+    }
+}
+
+/// Turns log items into the lines to print
+#[derive(Debug)]
+pub struct LogFormatter {
+    output: OutputMode,
+    fields: Vec<LogField>,
+    raw: bool,
+    prefix: Option<SourcePrefix>,
+    time: TimeDisplay,
+}
+
+impl LogFormatter {
+    /// Flags given for the time display win over the saved `defaults`
+    pub fn new(args: &LogsArgs, defaults: &LogsConfig, output: OutputMode) -> Self {
+        let timezone = match (args.utc, args.local_time) {
+            (true, _) => Some(Timezone::Utc),
+            (_, true) => Some(Timezone::Local),
+            _ => defaults.timezone,
+        };
+
+        Self {
+            output,
+            fields: args.fields.clone(),
+            raw: args.raw,
+            prefix: SourcePrefix::new(args.all_deployments, args.services.clone()),
+            time: TimeDisplay {
+                timezone,
+                format: args
+                    .time_format
+                    .clone()
+                    .or_else(|| defaults.time_format.clone()),
+            },
+        }
+    }
+
+    pub fn format(&self, log: &LogItem) -> String {
+        let line = self.format_line(log);
+
+        match &self.prefix {
+            // JSON lines can say where they are from with the `origin` and `deployment` fields
+            Some(prefix) if self.output != OutputMode::Json => prefix.apply(log, line),
+            _ => line,
+        }
+    }
+
+    fn format_line(&self, log: &LogItem) -> String {
+        if self.output != OutputMode::Json && self.fields.is_empty() {
+            return if self.raw {
+                log.get_raw_line().to_string()
+            } else {
+                // The same layout as the `Display` of log items, with the chosen time display
+                format!(
+                    "{} [{}] {}",
+                    self.time.format(log.timestamp, Timezone::Local).dim(),
+                    log.internal_origin,
+                    log.line
+                )
+            };
+        }
+
+        let fields = if self.fields.is_empty() {
+            &[
+                LogField::Ts,
+                LogField::Level,
+                LogField::Target,
+                LogField::Message,
+            ][..]
+        } else {
+            &self.fields[..]
+        };
+        let line = strip_ansi(&log.line);
+        let parsed = ParsedLine::parse(&line);
+        let values = fields.iter().map(|field| {
+            let value = match field {
+                LogField::Ts => Some(self.time.format(log.timestamp, Timezone::Utc)),
+                LogField::Level => parsed.level.map(|level| level_name(level).to_string()),
+                LogField::Target => parsed.target.map(str::to_string),
+                LogField::Message => Some(parsed.message.to_string()),
+                LogField::Origin => Some(log.internal_origin.to_string()),
+                LogField::Deployment => Some(log.id.to_string()),
+            };
+            (field, value)
+        });
+
+        match self.output {
+            OutputMode::Json => serde_json::Value::Object(
+                values
+                    .map(|(field, value)| {
+                        (
+                            field_name(*field).to_string(),
+                            value.map_or(serde_json::Value::Null, serde_json::Value::String),
+                        )
+                    })
+                    .collect(),
+            )
+            .to_string(),
+            OutputMode::Table | OutputMode::Plain => values
+                .map(|(_, value)| value.unwrap_or_else(|| "-".to_string()))
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+}
+
+/// Prints the formatted log lines, and copies them to the `--out` file
+#[derive(Debug)]
+pub struct LogWriter {
+    formatter: LogFormatter,
+    file: Option<LogFile>,
+}
+
+impl LogWriter {
+    pub fn new(args: &LogsArgs, defaults: &LogsConfig, output: OutputMode) -> Result<Self> {
+        let file = args
+            .out
+            .as_deref()
+            .map(|path| LogFile::open(path, args.rotate, args.gzip))
+            .transpose()?;
+
+        Ok(Self {
+            formatter: LogFormatter::new(args, defaults, output),
+            file,
+        })
+    }
+
+    pub fn write(&mut self, log: &LogItem) -> Result<()> {
+        let line = self.formatter.format(log);
+        println!("{line}");
+        if let Some(file) = &mut self.file {
+            file.write_line(&line)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the matching lines of a batch of fetched logs. Returns how many of them were left
+    /// out by the limit.
+    pub fn write_batch(
+        &mut self,
+        logs: Vec<LogItem>,
+        filter: &LogFilter,
+        limit: LogLimit,
+    ) -> Result<usize> {
+        let (logs, left_out) = limit.apply(logs.into_iter().filter(|log| filter.matches(log)));
+        for log in logs.into_iter().map(redact::redact_log_item) {
+            self.write(&log)?;
+        }
+
+        Ok(left_out)
+    }
+}
+
+/// How many of the fetched lines to show
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LogLimit {
+    All,
+    Head(usize),
+    Tail(usize),
+}
+
+impl LogLimit {
+    /// Lines shown when no limit is asked for, so that a long backlog doesn't flood the terminal
+    pub const DEFAULT_TAIL: usize = 1000;
+
+    pub fn new(args: &LogsArgs) -> Self {
+        match (args.head, args.tail) {
+            (Some(head), _) => Self::Head(head),
+            (_, Some(tail)) => Self::Tail(tail),
+            // Following shows the whole backlog, as the stream replays it anyway
+            _ if args.no_limit || args.follow => Self::All,
+            _ => Self::Tail(Self::DEFAULT_TAIL),
+        }
+    }
+
+    /// Returns the lines to show and how many were left out. Only the lines to show are kept
+    /// while going through `logs`, so a filtered backlog is never held whole.
+    pub fn apply(self, logs: impl IntoIterator<Item = LogItem>) -> (Vec<LogItem>, usize) {
+        let mut total = 0;
+        let kept = match self {
+            Self::All => logs.into_iter().inspect(|_| total += 1).collect(),
+            Self::Head(head) => logs
+                .into_iter()
+                .inspect(|_| total += 1)
+                .enumerate()
+                .filter_map(|(idx, log)| (idx < head).then_some(log))
+                .collect(),
+            Self::Tail(tail) => {
+                // The last `tail` lines, in a ring buffer
+                let mut kept = VecDeque::with_capacity(tail.min(Self::DEFAULT_TAIL));
+                for log in logs {
+                    total += 1;
+                    if kept.len() == tail {
+                        kept.pop_front();
+                    }
+                    if tail > 0 {
+                        kept.push_back(log);
+                    }
+                }
+                Vec::from(kept)
+            }
+        };
+
+        let left_out = total - kept.len();
+        (kept, left_out)
+    }
+}
+
+fn field_name(field: LogField) -> &'static str {
+    match field {
+        LogField::Ts => "timestamp",
+        LogField::Level => "level",
+        LogField::Target => "target",
+        LogField::Message => "message",
+        LogField::Origin => "origin",
+        LogField::Deployment => "deployment_id",
+    }
+}
+
+/// Decides which log lines are shown
+#[derive(Debug, Default)]
+pub struct LogFilter {
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    grep: Option<Regex>,
+    invert_match: bool,
+    level: Option<LogLevel>,
+    services: Vec<String>,
+}
+
+impl LogFilter {
+    pub fn new(args: &LogsArgs) -> Self {
+        Self {
+            since: args.since,
+            until: args.until,
+            grep: args.grep.clone(),
+            invert_match: args.invert_match,
+            level: args.level,
+            services: args.services.clone(),
+        }
+    }
+
+    pub fn matches(&self, log: &LogItem) -> bool {
+        if !self.since.map_or(true, |since| log.timestamp >= since)
+            || !self.until.map_or(true, |until| log.timestamp <= until)
+        {
+            return false;
+        }
+        if !self.services.is_empty() {
+            match &log.internal_origin {
+                Backend::Runtime(name) if self.services.contains(name) => {}
+                _ => return false,
+            }
+        }
+
+        let line = strip_ansi(&log.line);
+        if let Some(grep) = &self.grep {
+            if grep.is_match(&line) == self.invert_match {
+                return false;
+            }
+        }
+        if let Some(level) = self.level {
+            if detect_level(&line).unwrap_or(LogLevel::Info) < level {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Merge the logs of several deployments into one stream ordered by time. Lines with the same
+/// timestamp keep their order.
+pub fn interleave(streams: Vec<Vec<LogItem>>) -> Vec<LogItem> {
+    let mut logs: Vec<_> = streams.into_iter().flatten().collect();
+    logs.sort_by_key(|log| log.timestamp);
+
+    logs
+}
+
+/// The lines of a deployment's logs that match a search, with the matches highlighted
+pub fn find_matches(logs: Vec<LogItem>, pattern: &Regex) -> Vec<LogItem> {
+    logs.into_iter()
+        .filter_map(|mut log| {
+            let line = strip_ansi(&log.line);
+            if !pattern.is_match(&line) {
+                return None;
+            }
+            let line = redact::redact(&line);
+            log.line = pattern
+                .replace_all(&line, |caps: &regex::Captures| {
+                    caps[0].to_string().red().bold().to_string()
+                })
+                .into_owned();
+
+            Some(log)
+        })
+        .collect()
+}
+
+/// Position in a log stream, used to skip the lines a reconnected stream replays
+#[derive(Debug, Default)]
+pub struct LogCursor {
+    last: Option<DateTime<Utc>>,
+    /// Lines seen with the `last` timestamp, since several lines can share one. Everything
+    /// before `last` is skipped by the timestamp alone, so this is only as big as the number of
+    /// lines sharing the newest timestamp.
+    seen_at_last: HashSet<u64>,
+}
+
+impl LogCursor {
+    pub fn position(&self) -> Option<DateTime<Utc>> {
+        self.last
+    }
+
+    /// Move the cursor past this log. Returns `false` if it was seen already.
+    pub fn advance(&mut self, log: &LogItem) -> bool {
+        let mut hasher = DefaultHasher::new();
+        log.id.hash(&mut hasher);
+        log.internal_origin.to_string().hash(&mut hasher);
+        log.line.hash(&mut hasher);
+        let fingerprint = hasher.finish();
+
+        match self.last {
+            Some(last) if log.timestamp < last => false,
+            Some(last) if log.timestamp == last => self.seen_at_last.insert(fingerprint),
+            _ => {
+                self.last = Some(log.timestamp);
+                self.seen_at_last.clear();
+                self.seen_at_last.insert(fingerprint)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn log_line(line: &str) -> LogItem {
+        LogItem::new(Uuid::nil(), Backend::Deployer, line)
+    }
+
+    fn log_at(timestamp: DateTime<Utc>) -> LogItem {
+        let mut log = LogItem::new(Uuid::nil(), Backend::Deployer, "line");
+        log.timestamp = timestamp;
+        log
+    }
+
+    #[test]
+    fn time_specs() {
+        assert_eq!(
+            parse_time_spec("2024-03-01T12:00:00+01:00").unwrap(),
+            Utc.with_ymd_and_hms(2024, 3, 1, 11, 0, 0).unwrap()
+        );
+
+        let two_hours_ago = parse_time_spec("2h").unwrap();
+        let expected = Utc::now() - chrono::Duration::hours(2);
+        assert!((expected - two_hours_ago).num_seconds().abs() < 5);
+        assert!(parse_time_spec("1h 30m").is_ok());
+
+        assert!(parse_time_spec("yesterday-ish").is_err());
+    }
+
+    #[test]
+    fn time_range() {
+        let at = |h, m, s| Utc.with_ymd_and_hms(2024, 3, 1, h, m, s).unwrap();
+        let filter = LogFilter {
+            since: Some(at(10, 0, 0)),
+            until: Some(at(11, 0, 0)),
+            ..Default::default()
+        };
+
+        assert!(filter.matches(&log_at(at(10, 30, 0))));
+        assert!(filter.matches(&log_at(at(10, 0, 0))));
+        assert!(!filter.matches(&log_at(at(9, 59, 59))));
+        assert!(!filter.matches(&log_at(at(11, 0, 1))));
+        assert!(LogFilter::default().matches(&log_at(Utc::now())));
+    }
+
+    #[test]
+    fn levels() {
+        assert_eq!(
+            detect_level("2024-03-01T10:00:00Z  WARN my_api: slow query"),
+            Some(LogLevel::Warn)
+        );
+        assert_eq!(
+            detect_level(&strip_ansi("\x1b[31mERROR\x1b[0m my_api: failed")),
+            Some(LogLevel::Error)
+        );
+        assert_eq!(detect_level("[INFO] started"), Some(LogLevel::Info));
+        assert_eq!(detect_level("listening on 8000"), None);
+        // Only whole words
+        assert_eq!(detect_level("INFORMATION"), None);
+    }
+
+    #[test]
+    fn grep_and_level() {
+        let filter = LogFilter {
+            grep: Some(Regex::new("GET /api").unwrap()),
+            ..Default::default()
+        };
+        assert!(filter.matches(&log_line(" INFO GET /api/users 200")));
+        assert!(!filter.matches(&log_line(" INFO POST /login 200")));
+
+        let filter = LogFilter {
+            grep: Some(Regex::new("healthz").unwrap()),
+            invert_match: true,
+            ..Default::default()
+        };
+        assert!(!filter.matches(&log_line(" INFO GET /healthz 200")));
+        assert!(filter.matches(&log_line(" INFO GET /api 200")));
+
+        let filter = LogFilter {
+            level: Some(LogLevel::Warn),
+            ..Default::default()
+        };
+        assert!(filter.matches(&log_line("ERROR db: connection lost")));
+        assert!(filter.matches(&log_line(" WARN db: slow")));
+        assert!(!filter.matches(&log_line(" INFO db: connected")));
+        assert!(!filter.matches(&log_line("plain println output")));
+    }
+
+    #[test]
+    fn line_parsing() {
+        assert_eq!(
+            ParsedLine::parse("2024-03-01T10:00:00.000Z  WARN my_api::db: slow query took 2s"),
+            ParsedLine {
+                level: Some(LogLevel::Warn),
+                target: Some("my_api::db"),
+                message: "slow query took 2s",
+            }
+        );
+        assert_eq!(
+            ParsedLine::parse("[INFO] server started: listening"),
+            ParsedLine {
+                level: Some(LogLevel::Info),
+                target: None,
+                message: "server started: listening",
+            }
+        );
+        assert_eq!(
+            ParsedLine::parse("  plain output "),
+            ParsedLine {
+                level: None,
+                target: None,
+                message: "plain output",
+            }
+        );
+    }
+
+    #[test]
+    fn json_output() {
+        let formatter = LogFormatter {
+            output: OutputMode::Json,
+            fields: vec![],
+            raw: false,
+            prefix: None,
+            time: TimeDisplay::default(),
+        };
+        let mut log = log_line("\x1b[32m INFO\x1b[0m my_api: started");
+        log.timestamp = Utc.with_ymd_and_hms(2024, 3, 1, 10, 0, 0).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&formatter.format(&log)).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "timestamp": "2024-03-01T10:00:00.000Z",
+                "level": "info",
+                "target": "my_api",
+                "message": "started",
+            })
+        );
+
+        let formatter = LogFormatter {
+            output: OutputMode::Plain,
+            fields: vec![LogField::Level, LogField::Message],
+            raw: false,
+            prefix: None,
+            time: TimeDisplay::default(),
+        };
+        assert_eq!(formatter.format(&log), "info started");
+        assert_eq!(formatter.format(&log_line("no level")), "- no level");
+    }
+
+    #[test]
+    fn cursor_skips_replayed_lines() {
+        let at = |s| Utc.with_ymd_and_hms(2024, 3, 1, 10, 0, s).unwrap();
+        let log = |s, line: &str| {
+            let mut log = log_line(line);
+            log.timestamp = at(s);
+            log
+        };
+        let mut cursor = LogCursor::default();
+
+        assert!(cursor.advance(&log(1, "a")));
+        assert!(cursor.advance(&log(2, "b")));
+        assert!(cursor.advance(&log(2, "c")));
+
+        // Replay after reconnecting
+        assert!(!cursor.advance(&log(1, "a")));
+        assert!(!cursor.advance(&log(2, "b")));
+        assert!(!cursor.advance(&log(2, "c")));
+        assert!(cursor.advance(&log(2, "d")));
+        assert!(cursor.advance(&log(3, "e")));
+        assert_eq!(cursor.position(), Some(at(3)));
+
+        // However many lines share one timestamp, none of them is shown twice
+        for n in 0..10_000 {
+            assert!(cursor.advance(&log(4, &n.to_string())));
+        }
+        for n in 0..10_000 {
+            assert!(!cursor.advance(&log(4, &n.to_string())));
+        }
+        assert!(cursor.advance(&log(5, "f")));
+        assert_eq!(cursor.seen_at_last.len(), 1);
+    }
+
+    #[test]
+    fn interleaved_sources() {
+        let at = |s| Utc.with_ymd_and_hms(2024, 3, 1, 10, 0, s).unwrap();
+        let runtime_log = |id: u128, service: &str, s, line: &str| {
+            let mut log = LogItem::new(
+                Uuid::from_u128(id),
+                Backend::Runtime(service.to_string()),
+                line,
+            );
+            log.timestamp = at(s);
+            log
+        };
+
+        let merged = interleave(vec![
+            vec![runtime_log(1, "api", 1, "a"), runtime_log(1, "api", 3, "c")],
+            vec![runtime_log(2, "api", 2, "b"), runtime_log(2, "api", 3, "d")],
+        ]);
+        let lines: Vec<_> = merged.iter().map(|log| log.line.as_str()).collect();
+        assert_eq!(lines, ["a", "b", "c", "d"]);
+
+        let filter = LogFilter {
+            services: vec!["api".to_string(), "worker".to_string()],
+            ..Default::default()
+        };
+        assert!(filter.matches(&runtime_log(1, "api", 1, "a")));
+        assert!(filter.matches(&runtime_log(1, "worker", 1, "a")));
+        assert!(!filter.matches(&runtime_log(1, "cron", 1, "a")));
+        assert!(!filter.matches(&log_line("building")));
+
+        let services =
+            SourcePrefix::new(false, vec!["api".to_string(), "worker".to_string()]).unwrap();
+        let api = services.apply(&runtime_log(1, "api", 1, "a"), "a".to_string());
+        let worker = services.apply(&runtime_log(1, "worker", 1, "b"), "b".to_string());
+        assert_eq!(strip_ansi(&api), "api    | a");
+        assert_eq!(strip_ansi(&worker), "worker | b");
+        // The color codes in front of the labels differ
+        assert_ne!(api.split("api").next(), worker.split("worker").next());
+
+        let deployments = SourcePrefix::new(true, vec!["api".to_string()]).unwrap();
+        assert_eq!(
+            strip_ansi(&deployments.apply(&runtime_log(1, "api", 1, "a"), "a".to_string())),
+            "00000000/api | a"
+        );
+        assert!(SourcePrefix::new(false, vec![]).is_none());
+    }
+
+    #[test]
+    fn limits() {
+        let logs = || (0..5).map(|i| log_line(&i.to_string())).collect::<Vec<_>>();
+        let lines = |(logs, left_out): (Vec<LogItem>, usize)| {
+            let lines: Vec<_> = logs.into_iter().map(|log| log.line).collect();
+            (lines.join(","), left_out)
+        };
+
+        assert_eq!(lines(LogLimit::All.apply(logs())), ("0,1,2,3,4".into(), 0));
+        assert_eq!(lines(LogLimit::Head(2).apply(logs())), ("0,1".into(), 3));
+        assert_eq!(lines(LogLimit::Tail(2).apply(logs())), ("3,4".into(), 3));
+        assert_eq!(
+            lines(LogLimit::Tail(10).apply(logs())),
+            ("0,1,2,3,4".into(), 0)
+        );
+        assert_eq!(lines(LogLimit::Head(0).apply(logs())), ("".into(), 5));
+    }
+
+    #[test]
+    fn searching() {
+        let pattern = Regex::new("panic(ked)?").unwrap();
+        let logs = vec![
+            log_line("starting"),
+            log_line("\x1b[31mthread 'main' panicked at src/main.rs\x1b[0m"),
+            log_line("done"),
+        ];
+
+        let found = find_matches(logs, &pattern);
+        assert_eq!(found.len(), 1);
+        assert_eq!(
+            strip_ansi(&found[0].line),
+            "thread 'main' panicked at src/main.rs"
+        );
+        assert!(found[0].line.contains(&"panicked".red().bold().to_string()));
+    }
+
+    #[test]
+    fn time_display() {
+        let at = Utc.with_ymd_and_hms(2024, 3, 1, 10, 0, 0).unwrap();
+        let utc = TimeDisplay {
+            timezone: Some(Timezone::Utc),
+            format: None,
+        };
+        assert_eq!(utc.format(at, Timezone::Local), "2024-03-01T10:00:00.000Z");
+
+        let formatted = TimeDisplay {
+            timezone: None,
+            format: Some("%d/%m %H:%M".to_string()),
+        };
+        assert_eq!(formatted.format(at, Timezone::Utc), "01/03 10:00");
+
+        assert!(parse_time_format("%H:%M:%S%.3f").is_ok());
+        assert!(parse_time_format("%Q").is_err());
+    }
+}
@@ -0,0 +1,185 @@
+//! Copying the log stream to a file, optionally rotated by size
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use super::strip_ansi;
+
+/// Parse a file size like `50MB`, `512KiB` or `1048576`. Decimal units are powers of 1000 and
+/// binary units powers of 1024.
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("'{size}' is not a size like '50MB'"))?;
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1000,
+        "kib" => 1 << 10,
+        "m" | "mb" => 1000 * 1000,
+        "mib" => 1 << 20,
+        "g" | "gb" => 1000 * 1000 * 1000,
+        "gib" => 1 << 30,
+        _ => {
+            return Err(format!(
+                "unknown unit in '{size}', use B, KB, MB, GB or KiB, MiB, GiB"
+            ))
+        }
+    };
+
+    match number.checked_mul(multiplier) {
+        Some(0) => Err("the size must be more than zero".to_string()),
+        Some(bytes) => Ok(bytes),
+        None => Err(format!("'{size}' is too large")),
+    }
+}
+
+/// A log file that is started over when it reaches a size limit. Full files are kept next to
+/// it as `<name>.1`, `<name>.2`, ... with the highest number being the most recent.
+#[derive(Debug)]
+pub struct LogFile {
+    path: PathBuf,
+    rotate: Option<u64>,
+    gzip: bool,
+    file: File,
+    size: u64,
+}
+
+impl LogFile {
+    /// Open the file for appending, creating it and its directory if needed
+    pub fn open(path: &Path, rotate: Option<u64>, gzip: bool) -> Result<Self> {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("failed to create {}", dir.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            rotate,
+            gzip,
+            file,
+            size,
+        })
+    }
+
+    /// Append a line, without its color codes
+    pub fn write_line(&mut self, line: &str) -> Result<()> {
+        let line = strip_ansi(line);
+        let len = line.len() as u64 + 1;
+
+        if let Some(max_size) = self.rotate {
+            if self.size > 0 && self.size + len > max_size {
+                self.roll()
+                    .with_context(|| format!("failed to rotate {}", self.path.display()))?;
+            }
+        }
+
+        writeln!(self.file, "{line}")
+            .with_context(|| format!("failed to write to {}", self.path.display()))?;
+        self.size += len;
+
+        Ok(())
+    }
+
+    /// Move the current file aside and start a new one
+    fn roll(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        let rolled = self.next_roll_path();
+        if self.gzip {
+            let mut encoder = GzEncoder::new(File::create(&rolled)?, Compression::default());
+            io::copy(&mut File::open(&self.path)?, &mut encoder)?;
+            encoder.finish()?;
+        } else {
+            fs::rename(&self.path, &rolled)?;
+        }
+
+        self.file = File::create(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+
+    fn next_roll_path(&self) -> PathBuf {
+        let with_suffix = |suffix: String| {
+            let mut path = OsString::from(self.path.as_os_str());
+            path.push(suffix);
+            PathBuf::from(path)
+        };
+
+        (1..)
+            .map(|n| {
+                (
+                    with_suffix(format!(".{n}")),
+                    with_suffix(format!(".{n}.gz")),
+                )
+            })
+            .find(|(plain, gzipped)| !plain.exists() && !gzipped.exists())
+            .map(|(plain, gzipped)| if self.gzip { gzipped } else { plain })
+            .expect("there is always a free number")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::GzDecoder;
+
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("50MB"), Ok(50_000_000));
+        assert_eq!(parse_size("512KiB"), Ok(512 * 1024));
+        assert_eq!(parse_size("1 GiB"), Ok(1 << 30));
+        assert_eq!(parse_size("1048576"), Ok(1_048_576));
+        assert!(parse_size("0MB").is_err());
+        assert!(parse_size("50XB").is_err());
+        assert!(parse_size("MB").is_err());
+    }
+
+    #[test]
+    fn rotation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logs").join("app.log");
+
+        // Room for two lines of 9 bytes per file
+        let mut file = LogFile::open(&path, Some(20), false).unwrap();
+        for line in ["line one", "line two", "\x1b[32mline 333\x1b[0m"] {
+            file.write_line(line).unwrap();
+        }
+        assert_eq!(
+            fs::read_to_string(dir.path().join("logs/app.log.1")).unwrap(),
+            "line one\nline two\n"
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "line 333\n");
+
+        let mut file = LogFile::open(&path, Some(20), true).unwrap();
+        for line in ["line 444", "line 555"] {
+            file.write_line(line).unwrap();
+        }
+        let mut rolled = String::new();
+        GzDecoder::new(File::open(dir.path().join("logs/app.log.2.gz")).unwrap())
+            .read_to_string(&mut rolled)
+            .unwrap();
+        assert_eq!(rolled, "line 333\nline 444\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "line 555\n");
+    }
+}
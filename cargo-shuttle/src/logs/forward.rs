@@ -0,0 +1,326 @@
+//! Checking a `[log-forwarding]` configuration from the CLI, by sending a test line to the
+//! endpoint in the format its kind expects
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use crossterm::style::Stylize;
+use indoc::printdoc;
+use serde_json::{json, Value};
+use shuttle_common::models::deployment::{LogForwarding, LogForwardingKind};
+
+use crate::{secrets, CommandOutcome, Shuttle};
+
+/// A request to the forwarding endpoint
+#[derive(Debug, PartialEq)]
+pub struct ForwardRequest {
+    pub url: String,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Value,
+}
+
+/// Build the request that ships one log line of `service` to the endpoint
+pub fn forward_request(
+    config: &LogForwarding,
+    service: &str,
+    timestamp: DateTime<Utc>,
+    line: &str,
+    api_key: Option<&str>,
+) -> ForwardRequest {
+    let endpoint = config.endpoint.trim_end_matches('/');
+    let bearer = || {
+        api_key
+            .map(|key| vec![("Authorization", format!("Bearer {key}"))])
+            .unwrap_or_default()
+    };
+
+    match config.kind {
+        LogForwardingKind::Loki => {
+            let mut stream = json!({ "service": service });
+            for (name, value) in &config.labels {
+                stream[name] = json!(value);
+            }
+            ForwardRequest {
+                url: format!("{endpoint}/loki/api/v1/push"),
+                headers: bearer(),
+                body: json!({
+                    "streams": [{
+                        "stream": stream,
+                        "values": [[timestamp_nanos(timestamp), line]],
+                    }]
+                }),
+            }
+        }
+        LogForwardingKind::Datadog => ForwardRequest {
+            url: endpoint.to_string(),
+            headers: api_key
+                .map(|key| vec![("DD-API-KEY", key.to_string())])
+                .unwrap_or_default(),
+            body: json!([{
+                "ddsource": "shuttle",
+                "service": service,
+                "ddtags": config
+                    .labels
+                    .iter()
+                    .map(|(name, value)| format!("{name}:{value}"))
+                    .collect::<Vec<_>>()
+                    .join(","),
+                "message": line,
+            }]),
+        },
+        LogForwardingKind::Http => ForwardRequest {
+            url: endpoint.to_string(),
+            headers: bearer(),
+            body: json!([{
+                "service": service,
+                "timestamp": timestamp.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                "labels": config.labels,
+                "line": line,
+            }]),
+        },
+        LogForwardingKind::Otlp => {
+            let attributes: Vec<_> = std::iter::once(("service.name", service))
+                .chain(
+                    config
+                        .labels
+                        .iter()
+                        .map(|(name, value)| (name.as_str(), value.as_str())),
+                )
+                .map(|(key, value)| json!({ "key": key, "value": { "stringValue": value } }))
+                .collect();
+            ForwardRequest {
+                url: format!("{endpoint}/v1/logs"),
+                headers: bearer(),
+                body: json!({
+                    "resourceLogs": [{
+                        "resource": { "attributes": attributes },
+                        "scopeLogs": [{
+                            "logRecords": [{
+                                "timeUnixNano": timestamp_nanos(timestamp),
+                                "body": { "stringValue": line },
+                            }]
+                        }]
+                    }]
+                }),
+            }
+        }
+    }
+}
+
+fn timestamp_nanos(timestamp: DateTime<Utc>) -> String {
+    (timestamp.timestamp() as i128 * 1_000_000_000 + timestamp.timestamp_subsec_nanos() as i128)
+        .to_string()
+}
+
+/// Validate the configuration and send a test line to the endpoint
+pub async fn send_test(config: &LogForwarding, service: &str, api_key: Option<&str>) -> Result<()> {
+    if let Err(err) = config.validate() {
+        bail!("Invalid [log-forwarding] configuration: {err}");
+    }
+
+    let request = forward_request(
+        config,
+        service,
+        Utc::now(),
+        "Test message from `cargo shuttle logs forward test`",
+        api_key,
+    );
+    let mut builder = reqwest::Client::new()
+        .post(&request.url)
+        .json(&request.body);
+    for (name, value) in request.headers {
+        builder = builder.header(name, value);
+    }
+
+    let response = builder
+        .send()
+        .await
+        .with_context(|| format!("failed to reach {}", request.url))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("{} responded with {status}: {}", request.url, body.trim());
+    }
+
+    Ok(())
+}
+
+impl Shuttle {
+    pub(crate) fn logs_forward_show(&self) -> Result<CommandOutcome> {
+        let Some(config) = self.ctx.log_forwarding() else {
+            printdoc! {"
+                No log forwarding is configured. Add it to Shuttle.toml, for example:
+
+                [log-forwarding]
+                type = \"loki\"  # or \"datadog\", \"http\", \"otlp\"
+                endpoint = \"https://logs.example.com\"
+                api-key-secret = \"LOGS_TOKEN\"  # name of a secret holding the token
+                labels = {{ env = \"prod\" }}
+            "};
+            return Ok(CommandOutcome::Ok);
+        };
+
+        println!(
+            "Runtime logs are configured to be forwarded to {} ({})",
+            config.endpoint, config.kind
+        );
+        if let Some(secret) = &config.api_key_secret {
+            println!("  API key from secret: {secret}");
+        }
+        for (name, value) in &config.labels {
+            println!("  {name} = {value}");
+        }
+        if let Err(err) = config.validate() {
+            println!("{}", format!("Invalid configuration: {err}").red());
+        }
+        println!(
+            "{}",
+            "Deployments do not forward logs yet, and are refused while [log-forwarding] is set."
+                .yellow()
+        );
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn logs_forward_test(&self) -> Result<CommandOutcome> {
+        let config = self
+            .ctx
+            .log_forwarding()
+            .context("No [log-forwarding] table found in Shuttle.toml")?;
+
+        let api_key = match &config.api_key_secret {
+            Some(name) => {
+                let secrets_file = secrets::default_secrets_file(self.ctx.working_directory());
+                let value = secrets::read_secrets_file(&secrets_file)?
+                    .remove(name)
+                    .or_else(|| std::env::var(name).ok())
+                    .with_context(|| {
+                        format!(
+                            "The secret '{name}' holding the API key was found neither in {} \
+                            nor in the environment",
+                            secrets_file.display()
+                        )
+                    })?;
+                Some(value)
+            }
+            None => None,
+        };
+
+        println!(
+            "Sending a test line to {} ({})...",
+            config.endpoint, config.kind
+        );
+        send_test(config, self.ctx.project_name(), api_key.as_deref()).await?;
+        println!("{}", "The endpoint accepted the test line.".green());
+
+        Ok(CommandOutcome::Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn config(kind: LogForwardingKind, endpoint: &str) -> LogForwarding {
+        LogForwarding {
+            kind,
+            endpoint: endpoint.to_string(),
+            api_key_secret: Some("LOGS_TOKEN".to_string()),
+            labels: BTreeMap::from([("env".to_string(), "prod".to_string())]),
+        }
+    }
+
+    #[test]
+    fn validation() {
+        assert!(config(LogForwardingKind::Loki, "https://loki.example.com")
+            .validate()
+            .is_ok());
+        assert!(config(LogForwardingKind::Http, "ftp://logs.example.com")
+            .validate()
+            .is_err());
+        assert!(config(LogForwardingKind::Http, "not a url")
+            .validate()
+            .is_err());
+
+        let mut datadog = config(LogForwardingKind::Datadog, "https://intake.example.com");
+        datadog.api_key_secret = None;
+        assert!(datadog.validate().is_err());
+
+        let mut loki = config(LogForwardingKind::Loki, "https://loki.example.com");
+        loki.labels.insert("not-valid".to_string(), "x".to_string());
+        assert!(loki.validate().is_err());
+    }
+
+    #[test]
+    fn parse_config() {
+        let config: LogForwarding = toml::from_str(
+            r#"
+            type = "otlp"
+            endpoint = "https://otel.example.com:4318"
+            api-key-secret = "OTEL_TOKEN"
+            labels = { env = "prod" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.kind, LogForwardingKind::Otlp);
+        assert_eq!(config.api_key_secret.as_deref(), Some("OTEL_TOKEN"));
+        assert_eq!(config.labels["env"], "prod");
+    }
+
+    #[test]
+    fn requests() {
+        let at = Utc.with_ymd_and_hms(2024, 3, 1, 10, 0, 0).unwrap();
+
+        let loki = forward_request(
+            &config(LogForwardingKind::Loki, "https://loki.example.com/"),
+            "my-api",
+            at,
+            "hello",
+            Some("token"),
+        );
+        assert_eq!(loki.url, "https://loki.example.com/loki/api/v1/push");
+        assert_eq!(
+            loki.headers,
+            vec![("Authorization", "Bearer token".to_string())]
+        );
+        assert_eq!(
+            loki.body,
+            json!({
+                "streams": [{
+                    "stream": { "service": "my-api", "env": "prod" },
+                    "values": [["1709287200000000000", "hello"]],
+                }]
+            })
+        );
+
+        let datadog = forward_request(
+            &config(
+                LogForwardingKind::Datadog,
+                "https://http-intake.logs.datadoghq.eu/api/v2/logs",
+            ),
+            "my-api",
+            at,
+            "hello",
+            Some("key"),
+        );
+        assert_eq!(datadog.headers, vec![("DD-API-KEY", "key".to_string())]);
+        assert_eq!(datadog.body[0]["ddtags"], "env:prod");
+
+        let otlp = forward_request(
+            &config(LogForwardingKind::Otlp, "https://otel.example.com:4318"),
+            "my-api",
+            at,
+            "hello",
+            None,
+        );
+        assert_eq!(otlp.url, "https://otel.example.com:4318/v1/logs");
+        assert!(otlp.headers.is_empty());
+        assert_eq!(
+            otlp.body["resourceLogs"][0]["resource"]["attributes"][0]["value"]["stringValue"],
+            "my-api"
+        );
+    }
+}
@@ -0,0 +1,123 @@
+//! Captured output of local runs, so that `cargo shuttle logs --local` can show it with the
+//! same filtering and formatting as the logs of deployments.
+//!
+//! Every `cargo shuttle run` session writes its log items as JSON lines to its own file under
+//! `.shuttle/logs/`.
+
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use shuttle_common::{constants::LOCAL_STATE_DIRNAME, LogItem};
+use tracing::debug;
+
+/// How many sessions are kept, older ones are removed when a new one starts
+const KEPT_SESSIONS: usize = 10;
+
+fn logs_dir(project_dir: &Path) -> PathBuf {
+    project_dir.join(LOCAL_STATE_DIRNAME).join("logs")
+}
+
+/// The session files of a project, most recent first
+pub fn sessions(project_dir: &Path) -> Result<Vec<PathBuf>> {
+    let dir = logs_dir(project_dir);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut sessions: Vec<_> = fs::read_dir(&dir)
+        .with_context(|| format!("failed to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("run-") && name.ends_with(".jsonl"))
+        })
+        .collect();
+    // The names contain the start time, so they sort chronologically
+    sessions.sort();
+    sessions.reverse();
+
+    Ok(sessions)
+}
+
+/// Create the file for a new session, removing the oldest sessions
+pub fn new_session(project_dir: &Path) -> Result<PathBuf> {
+    let dir = logs_dir(project_dir);
+    fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+
+    for old in sessions(project_dir)?.into_iter().skip(KEPT_SESSIONS - 1) {
+        if let Err(err) = fs::remove_file(&old) {
+            debug!(error = %err, path = %old.display(), "failed to remove an old log session");
+        }
+    }
+
+    let path = dir.join(format!(
+        "run-{}.jsonl",
+        Utc::now().format("%Y%m%dT%H%M%S%.3f")
+    ));
+    fs::File::create(&path).with_context(|| format!("failed to create {}", path.display()))?;
+
+    Ok(path)
+}
+
+/// Read the log items of a session. Lines that are not log items, for example a line cut
+/// short when the run was killed, are skipped.
+pub fn read_session(path: &Path) -> Result<Vec<LogItem>> {
+    let file =
+        fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+
+    Ok(BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use shuttle_common::log::Backend;
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn sessions_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(sessions(dir.path()).unwrap().is_empty());
+
+        let first = new_session(dir.path()).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let second = new_session(dir.path()).unwrap();
+        assert_eq!(sessions(dir.path()).unwrap(), [second.clone(), first]);
+
+        let mut file = fs::OpenOptions::new().append(true).open(&second).unwrap();
+        let log = LogItem::new(Uuid::nil(), Backend::Runtime("api".to_string()), "started");
+        writeln!(file, "{}", serde_json::to_string(&log).unwrap()).unwrap();
+        write!(file, "{{\"cut short").unwrap();
+
+        let logs = read_session(&second).unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].line, "started");
+    }
+
+    #[test]
+    fn old_sessions_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let logs = logs_dir(dir.path());
+        fs::create_dir_all(&logs).unwrap();
+        for i in 0..12 {
+            fs::write(logs.join(format!("run-20240301T1000{i:02}.000.jsonl")), "").unwrap();
+        }
+
+        let latest = new_session(dir.path()).unwrap();
+        let kept = sessions(dir.path()).unwrap();
+        assert_eq!(kept.len(), KEPT_SESSIONS);
+        assert_eq!(kept[0], latest);
+        assert!(kept[1].ends_with("run-20240301T100011.000.jsonl"));
+    }
+}
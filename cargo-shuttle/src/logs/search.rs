@@ -0,0 +1,77 @@
+//! Searching the logs of the recent deployments of a project
+
+use anyhow::{bail, Context, Result};
+use crossterm::style::Stylize;
+use regex::RegexBuilder;
+
+use crate::args::LogsSearchArgs;
+use crate::logs::find_matches;
+use crate::{concurrent, suggestions, CommandOutcome, Shuttle};
+
+impl Shuttle {
+    pub(crate) async fn logs_search(&self, args: LogsSearchArgs) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let proj_name = self.ctx.project_name();
+        self.register_local_secrets(Some(&self.ctx.working_directory().join("Secrets.toml")));
+        let pattern = RegexBuilder::new(&args.pattern)
+            .case_insensitive(args.ignore_case)
+            .build()
+            .context("invalid search pattern")?;
+
+        let deployments = client
+            .get_deployments(proj_name, 1, args.deployments)
+            .await
+            .map_err(|err| {
+                suggestions::logs::get_logs_failure(err, "Fetching the deployments failed")
+            })?;
+        if deployments.is_empty() {
+            bail!("Could not find any deployments for '{proj_name}'");
+        }
+
+        let mut total = 0;
+        let mut with_matches = 0;
+        let deployments_logs =
+            concurrent::map(&deployments, self.ctx.concurrency(), |deployment| {
+                client.get_logs(proj_name, &deployment.id)
+            })
+            .await;
+        for (deployment, logs) in deployments.iter().zip(deployments_logs) {
+            let logs = logs.map_err(|err| {
+                suggestions::logs::get_logs_failure(err, "Fetching the deployment failed")
+            })?;
+            let found = find_matches(logs, &pattern);
+            if found.is_empty() {
+                continue;
+            }
+            total += found.len();
+            with_matches += 1;
+
+            println!(
+                "{} {} ({}, last updated {})",
+                "Deployment".bold(),
+                deployment.id,
+                deployment.state,
+                deployment.last_update.format("%Y-%m-%dT%H:%M:%SZ")
+            );
+            for log in found {
+                println!("  {log}");
+            }
+            println!();
+        }
+
+        if total == 0 {
+            println!(
+                "No matches for '{}' in the last {} deployments",
+                args.pattern,
+                deployments.len()
+            );
+        } else {
+            println!(
+                "{total} matching lines in {with_matches} of the last {} deployments",
+                deployments.len()
+            );
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+}
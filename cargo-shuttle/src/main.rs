@@ -1,32 +1,45 @@
-use anyhow::Result;
-use cargo_shuttle::{parse_args, CommandOutcome, Shuttle};
+use cargo_shuttle::errors::{self, ErrorCode};
+use cargo_shuttle::{error_log, feedback, parse_args, telemetry, CommandOutcome, Shuttle};
 use tracing_subscriber::{fmt, prelude::*, registry, EnvFilter};
 
 #[tokio::main(flavor = "multi_thread")]
-async fn main() -> Result<()> {
+async fn main() {
     let (args, provided_path_to_init) = parse_args();
+    let command: &'static str = (&args.cmd).into();
+    error_log::install_panic_hook(command);
 
     registry()
-        .with(fmt::layer())
+        // Standard output is left to the results of commands
+        .with(fmt::layer().with_writer(std::io::stderr))
         .with(
             // let user set RUST_LOG if they want to
-            EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-                if args.debug {
-                    EnvFilter::new("info,cargo_shuttle=trace,shuttle=trace")
-                } else {
-                    EnvFilter::default()
-                }
-            }),
+            EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| EnvFilter::new(args.verbosity().tracing_filter())),
         )
         .init();
 
-    let outcome = Shuttle::new()?.run(args, provided_path_to_init).await?;
+    let output = args.output;
+    let telemetry = telemetry::Session::start(command, args.api_url.clone());
+    let outcome = match Shuttle::new() {
+        Ok(shuttle) => shuttle.run(args, provided_path_to_init).await,
+        Err(err) => Err(err),
+    };
 
-    if outcome == CommandOutcome::DeploymentFailure {
+    let error = match &outcome {
+        Ok(CommandOutcome::Ok) => None,
+        Ok(CommandOutcome::DeploymentFailure) => Some(ErrorCode::Build),
+        Err(err) => Some(ErrorCode::of(err)),
+    };
+    feedback::record_last_command(command, error);
+    if let Some(telemetry) = telemetry {
+        telemetry.finish(error).await;
+    }
+
+    match outcome {
+        Ok(CommandOutcome::Ok) => {}
         // Deployment failure results in a shell error exit code being returned (this allows
         // chaining of commands with `&&` for example to fail at the first deployment failure).
-        std::process::exit(1);
+        Ok(CommandOutcome::DeploymentFailure) => std::process::exit(ErrorCode::Build.exit_code()),
+        Err(err) => std::process::exit(errors::report(&err, output)),
     }
-
-    Ok(())
 }
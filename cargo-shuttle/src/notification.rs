@@ -0,0 +1,93 @@
+//! Desktop notifications when a deploy or a build finishes, for when it was left to run in another
+//! window.
+//!
+//! Only operations that took long enough are notified about, and only in interactive sessions. The
+//! `[notifications]` table of the global configuration turns them off or changes how long is long
+//! enough.
+
+use std::time::Duration;
+
+use notify_rust::Notification;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+use crate::config::NotificationsConfig;
+use crate::prompt;
+
+/// How long an operation takes before it is notified about, unless configured otherwise
+pub const DEFAULT_AFTER: Duration = Duration::from_secs(30);
+
+/// Whether an operation that took `elapsed` is worth a notification
+fn should_notify(config: &NotificationsConfig, elapsed: Duration) -> bool {
+    if !config.enabled.unwrap_or(true) {
+        return false;
+    }
+    let after = match config.after.as_deref().map(humantime::parse_duration) {
+        Some(Ok(after)) => after,
+        Some(Err(err)) => {
+            warn!(
+                after = ?config.after,
+                error = %err,
+                "invalid duration for notifications, using the default one"
+            );
+            DEFAULT_AFTER
+        }
+        None => DEFAULT_AFTER,
+    };
+
+    elapsed >= after
+}
+
+/// Whether an operation that started at `started` and just finished should be notified about
+pub fn is_due(config: &NotificationsConfig, started: Instant) -> bool {
+    should_notify(config, started.elapsed()) && prompt::is_interactive()
+}
+
+pub async fn show(summary: String, body: String) {
+    // Showing one can block on the notification service of the desktop
+    let shown = tokio::task::spawn_blocking(move || {
+        Notification::new()
+            .appname("cargo-shuttle")
+            .summary(&summary)
+            .body(&body)
+            .show()
+            .map(|_| ())
+    })
+    .await;
+    match shown {
+        Ok(Ok(())) => {}
+        Ok(Err(err)) => debug!(error = %err, "failed to show the notification"),
+        Err(err) => debug!(error = %err, "failed to show the notification"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notify_after() {
+        let config = NotificationsConfig::default();
+        assert!(!should_notify(&config, Duration::from_secs(5)));
+        assert!(should_notify(&config, Duration::from_secs(45)));
+
+        let config = NotificationsConfig {
+            enabled: Some(false),
+            after: None,
+        };
+        assert!(!should_notify(&config, Duration::from_secs(600)));
+
+        let config = NotificationsConfig {
+            enabled: None,
+            after: Some("2m".to_string()),
+        };
+        assert!(!should_notify(&config, Duration::from_secs(90)));
+        assert!(should_notify(&config, Duration::from_secs(120)));
+
+        let config = NotificationsConfig {
+            enabled: None,
+            after: Some("soon".to_string()),
+        };
+        assert!(should_notify(&config, DEFAULT_AFTER));
+    }
+}
@@ -0,0 +1,46 @@
+//! Printing the results of commands in the mode picked with the global `--output-format` flag.
+//!
+//! Results are the serde models of the API. The JSON mode prints them as they are, while the
+//! table and plain modes render them for people, the latter without borders and colors so that
+//! they can be piped into other tools.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::args::OutputMode;
+
+/// Set from the global `--quiet` flag, which leaves out everything but the results
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Print the result of a command. `human` renders it for the table and plain modes, and is told
+/// whether to leave out the borders.
+pub fn print<T>(mode: OutputMode, value: &T, human: impl FnOnce(bool) -> String) -> Result<()>
+where
+    T: Serialize + ?Sized,
+{
+    match mode {
+        OutputMode::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        OutputMode::Table => println!("{}", human(false)),
+        OutputMode::Plain => println!("{}", human(true)),
+    }
+
+    Ok(())
+}
+
+/// Hints and other messages meant for people, which JSON output leaves out so that it stays
+/// parseable
+pub fn hint(mode: OutputMode, message: impl std::fmt::Display) {
+    if mode != OutputMode::Json && !is_quiet() {
+        println!("{message}");
+    }
+}
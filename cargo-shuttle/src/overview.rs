@@ -0,0 +1,327 @@
+//! The overview of `cargo shuttle status`: the account, the project, its latest deployment, its
+//! resources, and the health of the platform.
+//!
+//! The parts are fetched at the same time. A part that cannot be fetched is reported in its place,
+//! so that the rest of the overview is still shown during an incident.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+use chrono::SecondsFormat;
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+use shuttle_common::constants::SHUTTLE_STATUS_URL;
+use shuttle_common::deployment::State as DeploymentState;
+use shuttle_common::models::{deployment, project};
+use shuttle_common::resource;
+
+use crate::client::Client;
+use crate::style::{self, Tone};
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Health {
+    Healthy,
+    Degraded,
+    Unhealthy,
+}
+
+impl Health {
+    fn tone(self) -> Tone {
+        match self {
+            Health::Healthy => Tone::Success,
+            Health::Degraded => Tone::Warning,
+            Health::Unhealthy => Tone::Failure,
+        }
+    }
+}
+
+impl Display for Health {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Health::Healthy => write!(f, "healthy"),
+            Health::Degraded => write!(f, "degraded"),
+            Health::Unhealthy => write!(f, "unhealthy"),
+        }
+    }
+}
+
+/// The status of a component of the platform, as reported by the root of the API
+#[derive(Debug, Deserialize)]
+pub struct ComponentStatus {
+    pub status: Health,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiKeySource {
+    /// The `SHUTTLE_API_KEY` variable
+    Env,
+    /// The global configuration, set by `cargo shuttle login`
+    Config,
+}
+
+impl ApiKeySource {
+    /// Where the API key in use comes from. It was found when the client was made, so it is set in
+    /// one of these.
+    pub fn current() -> Self {
+        if std::env::var("SHUTTLE_API_KEY").is_ok() {
+            Self::Env
+        } else {
+            Self::Config
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Account {
+    pub api_key: ApiKeySource,
+    /// Who owns the project, when it could be fetched
+    pub owner: Option<project::Owner>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Component {
+    pub name: String,
+    pub status: Health,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Overview {
+    pub account: Account,
+    pub project_name: String,
+    pub project: Option<project::Response>,
+    pub deployment: Option<deployment::Response>,
+    pub uri: Option<String>,
+    pub resources: Option<Vec<resource::Type>>,
+    pub platform: Option<Vec<Component>>,
+    /// Why the parts that are missing could not be fetched, by part
+    pub errors: BTreeMap<&'static str, String>,
+}
+
+pub async fn fetch(client: &Client, project_name: &str, api_key: ApiKeySource) -> Overview {
+    let (project, service, resources, platform) = tokio::join!(
+        client.get_project(project_name),
+        client.get_service(project_name),
+        client.get_service_resources(project_name),
+        client.get_platform_status(),
+    );
+
+    let mut errors = BTreeMap::new();
+    let project = part(&mut errors, "project", project);
+    let service = part(&mut errors, "deployment", service);
+    let (deployment, uri) = match service {
+        Some(service) => (service.deployment, Some(service.uri)),
+        None => (None, None),
+    };
+    let resources = part(&mut errors, "resources", resources).map(|resources| {
+        resources
+            .into_iter()
+            .map(|resource| resource.r#type)
+            .collect()
+    });
+    let platform = part(&mut errors, "platform", platform).map(|components| {
+        components
+            .into_iter()
+            .map(|(name, component)| Component {
+                name,
+                status: component.status,
+            })
+            .collect()
+    });
+
+    Overview {
+        account: Account {
+            api_key,
+            owner: project.as_ref().map(|project| project.owner.clone()),
+        },
+        project_name: project_name.to_string(),
+        project,
+        deployment,
+        uri,
+        resources,
+        platform,
+        errors,
+    }
+}
+
+/// The value of a part, or the reason it is missing in `errors`
+fn part<T>(
+    errors: &mut BTreeMap<&'static str, String>,
+    name: &'static str,
+    res: anyhow::Result<T>,
+) -> Option<T> {
+    match res {
+        Ok(value) => Some(value),
+        Err(err) => {
+            errors.insert(name, format!("{err:#}"));
+            None
+        }
+    }
+}
+
+impl Overview {
+    /// Whether every part failed, which points to a problem on this end, like the network
+    pub fn nothing_fetched(&self) -> bool {
+        self.project.is_none()
+            && self.uri.is_none()
+            && self.resources.is_none()
+            && self.platform.is_none()
+    }
+
+    fn failed(&self, part: &str) -> Option<String> {
+        self.errors
+            .get(part)
+            .map(|err| format!("could not be fetched: {err}").red().to_string())
+    }
+}
+
+impl Display for Overview {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let api_key = match self.account.api_key {
+            ApiKeySource::Env => "API key from SHUTTLE_API_KEY",
+            ApiKeySource::Config => "API key from `cargo shuttle login`",
+        };
+        let account = match &self.account.owner {
+            Some(project::Owner::User(id)) => format!("{id} ({api_key})"),
+            Some(project::Owner::Organization(id)) => {
+                format!("organization {id} ({api_key})")
+            }
+            None => api_key.to_string(),
+        };
+
+        let project = match &self.project {
+            Some(project) => format!(
+                "{} is {}",
+                project.name.as_str().bold(),
+                style::toned(project.state.to_string(), project_tone(&project.state))
+            ),
+            None => self.failed("project").unwrap_or_default(),
+        };
+
+        let deployment = match (&self.deployment, self.failed("deployment")) {
+            (Some(deployment), _) => format!(
+                "{} is {}, last updated {}",
+                deployment.id,
+                style::toned(
+                    deployment.state.to_string(),
+                    deployment_tone(&deployment.state)
+                ),
+                deployment
+                    .last_update
+                    .to_rfc3339_opts(SecondsFormat::Secs, true)
+            ),
+            (None, Some(err)) => err,
+            (None, None) => "not deployed yet".dim().to_string(),
+        };
+
+        let resources = match &self.resources {
+            Some(resources) if resources.is_empty() => "none".dim().to_string(),
+            Some(resources) => resources
+                .iter()
+                .map(|resource_type| resource_type.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            None => self.failed("resources").unwrap_or_default(),
+        };
+
+        let platform = match &self.platform {
+            Some(components) => {
+                let unwell = components
+                    .iter()
+                    .filter(|component| component.status != Health::Healthy)
+                    .map(|component| {
+                        format!(
+                            "{} is {}",
+                            component.name,
+                            style::toned(component.status, component.status.tone())
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                if unwell.is_empty() {
+                    style::toned("healthy", Tone::Success).to_string()
+                } else {
+                    format!("{}, see {SHUTTLE_STATUS_URL}", unwell.join(", "))
+                }
+            }
+            None => format!(
+                "{}, see {SHUTTLE_STATUS_URL}",
+                self.failed("platform").unwrap_or_default()
+            ),
+        };
+
+        writeln!(f, "Account:     {account}")?;
+        writeln!(f, "Project:     {project}")?;
+        writeln!(f, "Deployment:  {deployment}")?;
+        if let (Some(uri), Some(_)) = (&self.uri, &self.deployment) {
+            writeln!(f, "URI:         {uri}")?;
+        }
+        writeln!(f, "Resources:   {resources}")?;
+        writeln!(f, "Platform:    {platform}")
+    }
+}
+
+fn project_tone(state: &project::State) -> Tone {
+    match state {
+        project::State::Ready => Tone::Success,
+        project::State::Errored { .. } | project::State::Destroyed | project::State::Deleted => {
+            Tone::Failure
+        }
+        _ => Tone::Warning,
+    }
+}
+
+fn deployment_tone(state: &DeploymentState) -> Tone {
+    match state {
+        DeploymentState::Running | DeploymentState::Completed => Tone::Success,
+        DeploymentState::Crashed => Tone::Failure,
+        _ => Tone::Warning,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn platform_status() {
+        let components: Vec<(String, ComponentStatus)> = serde_json::from_str(
+            r#"[["shuttle-gateway",{"status":"healthy"}],["shuttle-auth",{"status":"degraded"}]]"#,
+        )
+        .unwrap();
+        assert_eq!(components[1].0, "shuttle-auth");
+        assert_eq!(components[1].1.status, Health::Degraded);
+    }
+
+    #[test]
+    fn partial_overview() {
+        let overview = Overview {
+            account: Account {
+                api_key: ApiKeySource::Env,
+                owner: None,
+            },
+            project_name: "my-app".to_string(),
+            project: None,
+            deployment: None,
+            uri: None,
+            resources: Some(Vec::new()),
+            platform: Some(vec![Component {
+                name: "shuttle-provisioner".to_string(),
+                status: Health::Unhealthy,
+            }]),
+            errors: BTreeMap::from([("project", "the gateway timed out".to_string())]),
+        };
+
+        let shown = overview.to_string();
+        assert!(shown.contains("Account:     API key from SHUTTLE_API_KEY\n"));
+        assert!(shown.contains("could not be fetched: the gateway timed out"));
+        assert!(shown.contains("not deployed yet"));
+        assert!(shown.contains("shuttle-provisioner is "));
+        assert!(shown.contains(", see https://status.shuttle.rs"));
+
+        let json = serde_json::to_value(&overview).unwrap();
+        assert_eq!(json["account"]["api_key"], "env");
+        assert_eq!(json["errors"]["project"], "the gateway timed out");
+        assert!(json["project"].is_null());
+    }
+}
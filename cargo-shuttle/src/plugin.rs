@@ -0,0 +1,181 @@
+//! Subcommands from plugins: `cargo shuttle <name>` runs the `cargo-shuttle-<name>` executable
+//! found on PATH, like cargo does with `cargo-<name>`.
+//!
+//! A plugin gets the arguments that follow its name, and the context of the command in
+//! environment variables, so that it can call the API or run `cargo shuttle` for the same
+//! project:
+//!
+//! - `SHUTTLE_API`: the URL of the API
+//! - `SHUTTLE_API_KEY`: the API key, when logged in. It can do anything the account can.
+//! - `SHUTTLE_OUTPUT`: the output mode, like `json`
+//! - `SHUTTLE_PROJECT_NAME` and `SHUTTLE_WORKING_DIRECTORY`: the project, when in one
+//! - `CARGO_SHUTTLE_VERSION`: the version of cargo-shuttle
+
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::{exit, ExitStatus};
+
+use anyhow::{anyhow, Context as _, Result};
+use tracing::debug;
+
+use crate::args::{OutputMode, ProjectArgs};
+use crate::errors::{self, ErrorCode};
+use crate::{CommandOutcome, Shuttle};
+
+/// Plugins are executables named with this and the name of their subcommand
+const PREFIX: &str = "cargo-shuttle-";
+
+/// What a plugin is told about the command it runs for
+pub struct Context {
+    pub api_url: String,
+    pub api_key: Option<String>,
+    pub output: OutputMode,
+    pub project_name: Option<String>,
+    pub working_directory: Option<PathBuf>,
+}
+
+impl Context {
+    fn envs(&self) -> Vec<(&'static str, OsString)> {
+        let output = match self.output {
+            OutputMode::Table => "table",
+            OutputMode::Json => "json",
+            OutputMode::Plain => "plain",
+        };
+        let mut envs = vec![
+            ("SHUTTLE_API", self.api_url.clone().into()),
+            ("SHUTTLE_OUTPUT", output.into()),
+            ("CARGO_SHUTTLE_VERSION", crate::VERSION.into()),
+        ];
+        if let Some(api_key) = &self.api_key {
+            envs.push(("SHUTTLE_API_KEY", api_key.into()));
+        }
+        if let Some(project_name) = &self.project_name {
+            envs.push(("SHUTTLE_PROJECT_NAME", project_name.into()));
+        }
+        if let Some(working_directory) = &self.working_directory {
+            envs.push(("SHUTTLE_WORKING_DIRECTORY", working_directory.into()));
+        }
+
+        envs
+    }
+}
+
+/// The executable of the plugin for `name`, in the directories of `path`
+pub fn find(name: &str, path: Option<OsString>) -> Option<PathBuf> {
+    let file_name = format!("{PREFIX}{name}{}", std::env::consts::EXE_SUFFIX);
+
+    std::env::split_paths(&path?)
+        .map(|dir| dir.join(&file_name))
+        .find(|executable| is_executable(executable))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Run a plugin with the arguments that followed its name, until it exits
+pub async fn run(executable: &Path, args: &[OsString], context: &Context) -> Result<ExitStatus> {
+    tokio::process::Command::new(executable)
+        .args(args)
+        .envs(context.envs())
+        .status()
+        .await
+        .with_context(|| format!("failed to run {}", executable.display()))
+}
+
+impl Shuttle {
+    /// Run the plugin of a subcommand that is not built in, and exit like it did
+    pub(crate) async fn plugin(
+        &mut self,
+        mut args: Vec<OsString>,
+        project_args: ProjectArgs,
+    ) -> Result<CommandOutcome> {
+        let name = args.remove(0).to_string_lossy().to_string();
+        let Some(executable) = find(&name, std::env::var_os("PATH")) else {
+            return Err(errors::with_code(
+                anyhow!(
+                    "no such command: `{name}`\n\n\
+                    Run `cargo shuttle --help` for the commands, \
+                    or install a plugin named cargo-shuttle-{name}"
+                ),
+                ErrorCode::Validation,
+            ));
+        };
+
+        // Plugins can run anywhere, so the project is only passed on when there is one
+        let in_project = match self.load_project(&project_args) {
+            Ok(()) => true,
+            Err(err) => {
+                debug!(error = %err, "no project to pass to the plugin");
+                false
+            }
+        };
+        let context = Context {
+            api_url: self.ctx.api_url(),
+            api_key: self.ctx.api_key().ok().map(|key| key.as_ref().to_string()),
+            output: self.output,
+            project_name: in_project.then(|| self.ctx.project_name().to_string()),
+            working_directory: in_project.then(|| self.ctx.working_directory().to_path_buf()),
+        };
+
+        let status = run(&executable, &args, &context).await?;
+        if !status.success() {
+            // Killed by a signal when there is no code
+            exit(status.code().unwrap_or(1));
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_plugins() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        let executable = second.path().join(format!(
+            "cargo-shuttle-preview{}",
+            std::env::consts::EXE_SUFFIX
+        ));
+        std::fs::write(&executable, "#!/bin/sh\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&executable, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let path = std::env::join_paths([first.path(), second.path()]).unwrap();
+        assert_eq!(find("preview", Some(path.clone())), Some(executable));
+        assert_eq!(find("missing", Some(path)), None);
+        assert_eq!(find("preview", None), None);
+    }
+
+    #[test]
+    fn plugin_context() {
+        let context = Context {
+            api_url: "https://api.shuttle.rs".to_string(),
+            api_key: None,
+            output: OutputMode::Json,
+            project_name: Some("my-app".to_string()),
+            working_directory: None,
+        };
+        let envs = context.envs();
+
+        assert!(envs.contains(&("SHUTTLE_API", "https://api.shuttle.rs".into())));
+        assert!(envs.contains(&("SHUTTLE_OUTPUT", "json".into())));
+        assert!(envs.contains(&("SHUTTLE_PROJECT_NAME", "my-app".into())));
+        assert!(!envs.iter().any(|(name, _)| *name == "SHUTTLE_API_KEY"));
+    }
+}
@@ -0,0 +1,146 @@
+//! Spinners and progress bars for the steps of commands that take a while.
+//!
+//! They are drawn on standard error when it is a terminal. Elsewhere, like in CI, every step is
+//! printed as a line of its own instead, and `--quiet` leaves them out altogether.
+
+use std::fmt::Display;
+use std::io::IsTerminal;
+use std::time::Duration;
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+use crate::{output, style};
+
+/// How steps are shown
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    Drawn,
+    Lines,
+    Hidden,
+}
+
+fn mode() -> Mode {
+    if output::is_quiet() {
+        Mode::Hidden
+    } else if std::io::stderr().is_terminal() {
+        Mode::Drawn
+    } else {
+        Mode::Lines
+    }
+}
+
+/// A step of a command, shown with a spinner or a bar
+pub struct Progress {
+    bar: ProgressBar,
+    mode: Mode,
+}
+
+impl Progress {
+    /// A step whose length is not known
+    pub fn spinner(message: impl Into<String>) -> Self {
+        let bar = spinner();
+        Self::start(bar, message.into())
+    }
+
+    /// A step of moving `len` bytes
+    pub fn bytes(message: impl Into<String>, len: u64) -> Self {
+        let bar = ProgressBar::new(len).with_style(
+            ProgressStyle::with_template(&format!(
+                "{{msg}} [{{bar:30.{}}}] {{bytes}}/{{total_bytes}} ({{bytes_per_sec}})",
+                style::accent_code()
+            ))
+            .unwrap()
+            .progress_chars("=> "),
+        );
+        Self::start(bar, message.into())
+    }
+
+    /// A step of going through `len` items, like the deployments of a project
+    pub fn count(message: impl Into<String>, len: u64) -> Self {
+        let bar = ProgressBar::new(len).with_style(
+            ProgressStyle::with_template(&format!(
+                "{{msg}} [{{bar:30.{}}}] {{pos}}/{{len}}",
+                style::accent_code()
+            ))
+            .unwrap()
+            .progress_chars("=> "),
+        );
+        Self::start(bar, message.into())
+    }
+
+    fn start(bar: ProgressBar, message: String) -> Self {
+        let mode = mode();
+        match mode {
+            Mode::Drawn => {}
+            Mode::Lines => {
+                eprintln!("{message}...");
+                bar.set_draw_target(ProgressDrawTarget::hidden());
+            }
+            Mode::Hidden => bar.set_draw_target(ProgressDrawTarget::hidden()),
+        }
+        bar.set_message(message);
+
+        Self { bar, mode }
+    }
+
+    /// A handle for updating the step from elsewhere, like the callbacks of uploads
+    pub fn bar(&self) -> ProgressBar {
+        self.bar.clone()
+    }
+
+    pub fn inc(&self, delta: u64) {
+        self.bar.inc(delta);
+    }
+
+    /// End the step, leaving a line that says how it went
+    pub fn finish(self, message: impl Display) {
+        self.bar.finish_and_clear();
+        if self.mode != Mode::Hidden {
+            eprintln!("{message}");
+        }
+    }
+
+    /// End the step without a trace, for steps whose result is printed next
+    pub fn finish_and_clear(self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+/// Steps that end early, like on errors, do not leave their spinner behind
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.bar.finish_and_clear();
+    }
+}
+
+/// A spinner for polling loops, whose changing messages are only drawn on terminals
+pub fn spinner() -> ProgressBar {
+    let pb = ProgressBar::new_spinner();
+    if mode() != Mode::Drawn {
+        pb.set_draw_target(ProgressDrawTarget::hidden());
+    }
+    pb.enable_steady_tick(Duration::from_millis(350));
+    let dot = if style::ascii() { "*" } else { "●" };
+    let ticks = [
+        "( ●    )",
+        "(  ●   )",
+        "(   ●  )",
+        "(    ● )",
+        "(     ●)",
+        "(    ● )",
+        "(   ●  )",
+        "(  ●   )",
+        "( ●    )",
+        "(●     )",
+        "(●●●●●●)",
+    ]
+    .map(|tick| tick.replace('●', dot));
+    let ticks = ticks.iter().map(String::as_str).collect::<Vec<_>>();
+    pb.set_style(
+        ProgressStyle::with_template(&format!("{{spinner:.{}}} {{msg}}", style::accent_code()))
+            .unwrap()
+            .tick_strings(&ticks),
+    );
+
+    pb
+}
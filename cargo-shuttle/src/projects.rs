@@ -0,0 +1,47 @@
+//! Commands for projects that go beyond starting and stopping them
+
+use anyhow::Result;
+use shuttle_common::models::project;
+
+use crate::{suggestions, CommandOutcome, Shuttle};
+
+impl Shuttle {
+    pub(crate) async fn project_lock(&self, reason: String) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let lock = client
+            .lock_project(self.ctx.project_name(), &project::LockRequest { reason })
+            .await
+            .map_err(|err| {
+                suggestions::project::project_request_failure(
+                    err,
+                    "Project lock failed",
+                    true,
+                    "locking the project fails repeatedly",
+                )
+            })?;
+
+        println!(r#"Project "{}" is {lock}"#, self.ctx.project_name());
+        println!("Run `cargo shuttle project unlock` to allow deployments again.");
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn project_unlock(&self) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        client
+            .unlock_project(self.ctx.project_name())
+            .await
+            .map_err(|err| {
+                suggestions::project::project_request_failure(
+                    err,
+                    "Project unlock failed",
+                    true,
+                    "unlocking the project fails repeatedly",
+                )
+            })?;
+
+        println!(r#"Project "{}" is unlocked"#, self.ctx.project_name());
+
+        Ok(CommandOutcome::Ok)
+    }
+}
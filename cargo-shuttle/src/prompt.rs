@@ -0,0 +1,81 @@
+//! Asking the user, and failing fast where nobody can answer.
+//!
+//! Prompts need a terminal on standard input. In CI, or with input piped in, commands that would
+//! prompt fail right away and name the flag that answers the question instead, rather than
+//! hanging until the job times out.
+
+use std::io::IsTerminal;
+
+use anyhow::{anyhow, Context, Result};
+use dialoguer::{theme::ColorfulTheme, Confirm, FuzzySelect};
+
+use crate::errors::{with_code, ErrorCode};
+
+/// Whether someone is there to answer prompts
+pub fn is_interactive() -> bool {
+    std::io::stdin().is_terminal() && !is_ci()
+}
+
+/// CI services set `CI`, some of them to `true` and others to `1`
+fn is_ci() -> bool {
+    is_ci_value(std::env::var("CI").ok().as_deref())
+}
+
+fn is_ci_value(value: Option<&str>) -> bool {
+    value.is_some_and(|value| !value.is_empty() && value != "false" && value != "0")
+}
+
+/// Fail unless someone can answer a prompt. `instead` tells how to answer it with flags, like
+/// "Pass --yes to delete the project."
+pub fn require(instead: &str) -> Result<()> {
+    if !is_interactive() {
+        return Err(with_code(
+            anyhow!(
+                "Cannot prompt without an interactive terminal{}. {instead}",
+                if is_ci() { " in CI" } else { "" }
+            ),
+            ErrorCode::Validation,
+        ));
+    }
+
+    Ok(())
+}
+
+/// Ask a yes or no question, see [`require`] for `instead`
+pub fn confirm(prompt: impl Into<String>, default: bool, instead: &str) -> Result<bool> {
+    require(instead)?;
+
+    Confirm::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .default(default)
+        .interact()
+        .context("failed to read the answer")
+}
+
+/// Pick one of `items` by typing parts of it, see [`require`] for `instead`
+pub fn pick(prompt: impl Into<String>, items: &[String], instead: &str) -> Result<usize> {
+    require(instead)?;
+
+    FuzzySelect::with_theme(&ColorfulTheme::default())
+        .with_prompt(prompt)
+        .items(items)
+        .default(0)
+        .interact()
+        .context("failed to read the answer")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ci_values() {
+        assert!(is_ci_value(Some("true")));
+        assert!(is_ci_value(Some("1")));
+        assert!(is_ci_value(Some("woodpecker")));
+        assert!(!is_ci_value(Some("false")));
+        assert!(!is_ci_value(Some("0")));
+        assert!(!is_ci_value(Some("")));
+        assert!(!is_ci_value(None));
+    }
+}
@@ -1,12 +1,20 @@
-use std::{collections::HashMap, io::stdout, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    io::stdout,
+    path::PathBuf,
+    time::Duration,
+};
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
 use bollard::{
-    container::{Config, CreateContainerOptions, StartContainerOptions},
+    container::{
+        Config, CreateContainerOptions, ListContainersOptions, RemoveContainerOptions,
+        StartContainerOptions, StopContainerOptions,
+    },
     exec::{CreateExecOptions, CreateExecResults},
     image::CreateImageOptions,
-    models::{CreateImageInfo, HostConfig, PortBinding, ProgressDetail},
+    models::{ContainerSummary, CreateImageInfo, HostConfig, PortBinding, ProgressDetail},
     service::ContainerInspectResponse,
     Docker,
 };
@@ -34,15 +42,202 @@ use tracing::{error, trace};
 /// It uses Docker to create Databases
 pub struct LocalProvisioner {
     docker: Docker,
+    /// Images to use instead of the default ones, by kind of container
+    images: HashMap<String, String>,
+    /// Directory to keep the data of containers in, so that it survives the containers
+    data_dir: Option<PathBuf>,
+    /// Kinds of containers whose data is thrown away with them
+    ephemeral: HashSet<String>,
+    /// Only use images that were pulled before
+    offline: bool,
 }
 
 impl LocalProvisioner {
     pub fn new() -> Result<Self> {
         Ok(Self {
             docker: Docker::connect_with_local_defaults()?,
+            images: HashMap::new(),
+            data_dir: None,
+            ephemeral: HashSet::new(),
+            offline: false,
         })
     }
 
+    /// Use another image for containers of this kind, like a different Postgres version
+    pub fn with_image(mut self, kind: &str, image: String) -> Self {
+        self.images.insert(kind.to_string(), image);
+        self
+    }
+
+    /// Keep the data of new containers in a directory per kind of container in `data_dir`
+    pub fn with_data_dir(mut self, data_dir: PathBuf) -> Self {
+        self.data_dir = Some(data_dir);
+        self
+    }
+
+    /// Don't keep the data of containers of this kind outside of them
+    pub fn with_ephemeral(mut self, kind: &str) -> Self {
+        self.ephemeral.insert(kind.to_string());
+        self
+    }
+
+    /// Don't pull images, and fail on the ones that are not on this machine yet
+    pub fn offline(mut self) -> Self {
+        self.offline = true;
+        self
+    }
+
+    /// The kind of container a database of this type runs in, like `shared_postgres`
+    pub fn database_kind(db_type: Type) -> String {
+        db_type_to_config(db_type, "").r#type
+    }
+
+    /// Name of the container a database of the project is kept in
+    pub fn database_container_name(project_name: &str, db_type: Type) -> String {
+        let EngineConfig { r#type, .. } = db_type_to_config(db_type, project_name);
+
+        format!("shuttle_{project_name}_{type}")
+    }
+
+    pub async fn container_exists(&self, container_name: &str) -> bool {
+        self.docker
+            .inspect_container(container_name, None)
+            .await
+            .is_ok()
+    }
+
+    pub async fn container_running(&self, container_name: &str) -> bool {
+        self.docker
+            .inspect_container(container_name, None)
+            .await
+            .ok()
+            .and_then(|container| container.state?.running)
+            .unwrap_or(false)
+    }
+
+    /// Run SQL on a database of the project, with the client that comes with its image
+    pub async fn run_sql(
+        &self,
+        project_name: &str,
+        db_type: Type,
+        db_name: Option<String>,
+        sql: &str,
+    ) -> Result<()> {
+        let database_name = database_name(project_name, db_type, db_name);
+        let EngineConfig {
+            username, password, ..
+        } = db_type_to_config(db_type, &database_name);
+        let cmd = match db_type {
+            Type::Shared(SharedEngine::Postgres) | Type::AwsRds(AwsRdsEngine::Postgres) => vec![
+                "psql".to_string(),
+                "-U".to_string(),
+                username,
+                "-d".to_string(),
+                database_name,
+                "-v".to_string(),
+                "ON_ERROR_STOP=1".to_string(),
+                "-c".to_string(),
+                sql.to_string(),
+            ],
+            Type::AwsRds(AwsRdsEngine::MySql | AwsRdsEngine::MariaDB) => vec![
+                "mysql".to_string(),
+                "-u".to_string(),
+                username,
+                format!("-p{}", password.expose()),
+                database_name,
+                "-e".to_string(),
+                sql.to_string(),
+            ],
+            Type::Shared(SharedEngine::MongoDb) => {
+                bail!("MongoDB can not be seeded with SQL, use a seed binary instead")
+            }
+        };
+        let container_name = Self::database_container_name(project_name, db_type);
+
+        let CreateExecResults { id } = self
+            .docker
+            .create_exec(
+                &container_name,
+                CreateExecOptions {
+                    cmd: Some(cmd),
+                    attach_stdout: Some(true),
+                    attach_stderr: Some(true),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        let mut errors = Vec::new();
+        if let bollard::exec::StartExecResults::Attached { mut output, .. } =
+            self.docker.start_exec(&id, None).await?
+        {
+            while let Some(line) = output.next().await {
+                if let bollard::container::LogOutput::StdErr { message } = line? {
+                    errors.push(String::from_utf8_lossy(&message).trim_end().to_string());
+                }
+            }
+        }
+
+        match self.docker.inspect_exec(&id).await?.exit_code {
+            Some(0) | None => Ok(()),
+            Some(code) => bail!("exited with code {code}: {}", errors.join("\n")),
+        }
+    }
+
+    /// Stop a container, giving it `grace` to shut down before it is killed
+    pub async fn stop_container(&self, container_name: &str, grace: Duration) -> Result<()> {
+        self.docker
+            .stop_container(
+                container_name,
+                Some(StopContainerOptions {
+                    t: grace.as_secs() as i64,
+                }),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// The containers started for the resources of a project, running or not
+    pub async fn project_containers(&self, project_name: &str) -> Result<Vec<ContainerSummary>> {
+        let prefix = format!("shuttle_{project_name}_");
+        let containers = self
+            .docker
+            .list_containers(Some(ListContainersOptions::<String> {
+                all: true,
+                filters: HashMap::from([("name".to_string(), vec![prefix.clone()])]),
+                ..Default::default()
+            }))
+            .await?;
+
+        // The name filter matches anywhere in the name
+        Ok(containers
+            .into_iter()
+            .filter(|container| {
+                container
+                    .names
+                    .iter()
+                    .flatten()
+                    .any(|name| name.trim_start_matches('/').starts_with(&prefix))
+            })
+            .collect())
+    }
+
+    /// Remove a container along with its data
+    pub async fn remove_container(&self, container_name: &str) -> Result<()> {
+        self.docker
+            .remove_container(
+                container_name,
+                Some(RemoveContainerOptions {
+                    v: true,
+                    force: true,
+                    ..Default::default()
+                }),
+            )
+            .await?;
+
+        Ok(())
+    }
+
     fn get_container_first_host_port(
         &self,
         container: &ContainerInspectResponse,
@@ -88,14 +283,50 @@ impl LocalProvisioner {
         }
     }
 
+    /// Bind mount for keeping the data of a container of this kind in the data directory
+    fn data_bind(&self, kind: &str) -> Result<Option<String>> {
+        let (Some(data_dir), Some(container_path)) = (&self.data_dir, container_data_dir(kind))
+        else {
+            return Ok(None);
+        };
+        if self.ephemeral.contains(kind) {
+            return Ok(None);
+        }
+
+        let host_path = data_dir.join(kind);
+        std::fs::create_dir_all(&host_path)
+            .with_context(|| format!("failed to create {}", host_path.display()))?;
+
+        Ok(Some(format!("{}:{container_path}", host_path.display())))
+    }
+
     async fn get_container(
         &self,
         container_name: &str,
+        kind: &str,
         image: &str,
         port: &str,
         env: Option<Vec<String>>,
     ) -> Result<ContainerInspectResponse, Status> {
-        match self.docker.inspect_container(container_name, None).await {
+        let existing = match self.docker.inspect_container(container_name, None).await {
+            // A container from another image is replaced, its data is disposable
+            Ok(container)
+                if container
+                    .config
+                    .as_ref()
+                    .and_then(|config| config.image.as_deref())
+                    .is_some_and(|existing| existing != image) =>
+            {
+                trace!("replacing container {container_name} to use {image}");
+                self.remove_container(container_name)
+                    .await
+                    .map_err(|err| Status::internal(err.to_string()))?;
+                self.docker.inspect_container(container_name, None).await
+            }
+            res => res,
+        };
+
+        match existing {
             Ok(container) => {
                 trace!("found container {container_name}");
                 Ok(container)
@@ -103,7 +334,14 @@ impl LocalProvisioner {
             Err(bollard::errors::Error::DockerResponseServerError {
                 status_code: 404, ..
             }) => {
-                self.pull_image(image).await.expect("failed to pull image");
+                if !self.offline {
+                    self.pull_image(image).await.expect("failed to pull image");
+                } else if self.docker.inspect_image(image).await.is_err() {
+                    return Err(Status::unavailable(format!(
+                        "the image {image} is needed but can't be pulled offline. \
+                        Pull it with `docker pull {image}` while online."
+                    )));
+                }
                 trace!("will create container {container_name}");
                 let options = Some(CreateContainerOptions {
                     name: container_name,
@@ -120,6 +358,10 @@ impl LocalProvisioner {
                 );
                 let host_config = HostConfig {
                     port_bindings: Some(port_bindings),
+                    binds: self
+                        .data_bind(kind)
+                        .map_err(|err| Status::internal(format!("{err:#}")))?
+                        .map(|bind| vec![bind]),
                     ..Default::default()
                 };
 
@@ -159,11 +401,7 @@ impl LocalProvisioner {
     ) -> Result<DatabaseResponse, Status> {
         trace!("getting sql string for project '{project_name}'");
 
-        let database_name = match db_type {
-            database::Type::AwsRds(_) => db_name.unwrap_or_else(|| project_name.to_string()),
-            database::Type::Shared(SharedEngine::MongoDb) => "admin".to_string(),
-            _ => project_name.to_string(),
-        };
+        let database_name = database_name(project_name, db_type, db_name);
 
         let EngineConfig {
             r#type,
@@ -175,10 +413,11 @@ impl LocalProvisioner {
             env,
             is_ready_cmd,
         } = db_type_to_config(db_type, &database_name);
+        let image = self.images.get(&r#type).cloned().unwrap_or(image);
         let container_name = format!("shuttle_{project_name}_{type}");
 
         let container = self
-            .get_container(&container_name, &image, &port, env)
+            .get_container(&container_name, &r#type, &image, &port, env)
             .await?;
 
         let host_port = self.get_container_first_host_port(&container, &port);
@@ -219,10 +458,12 @@ impl LocalProvisioner {
             port,
         } = req;
 
-        let container_name = format!("shuttle_{project_name}_{container_name}");
+        let kind = container_name;
+        let image = self.images.get(&kind).cloned().unwrap_or(image);
+        let container_name = format!("shuttle_{project_name}_{kind}");
 
         let container = self
-            .get_container(&container_name, &image, &port, Some(env))
+            .get_container(&container_name, &kind, &image, &port, Some(env))
             .await?;
 
         let host_port = self.get_container_first_host_port(&container, &port);
@@ -386,18 +627,39 @@ fn print_layers(layers: &Vec<CreateImageInfo>) {
         .expect("to reset cursor position");
 }
 
-struct EngineConfig {
-    r#type: String,
-    image: String,
-    engine: String,
-    username: String,
-    password: Secret<String>,
-    port: String,
-    env: Option<Vec<String>>,
-    is_ready_cmd: Vec<String>,
+pub(crate) struct EngineConfig {
+    pub r#type: String,
+    pub image: String,
+    pub engine: String,
+    pub username: String,
+    pub password: Secret<String>,
+    pub port: String,
+    pub env: Option<Vec<String>>,
+    pub is_ready_cmd: Vec<String>,
+}
+
+/// Where containers of a kind keep their data, for the kinds whose data is worth keeping
+pub(crate) fn container_data_dir(kind: &str) -> Option<&'static str> {
+    match kind {
+        "shared_postgres" | "aws_rds_postgres" => Some("/var/lib/postgresql/data"),
+        "shared_mongodb" => Some("/data/db"),
+        "aws_rds_mariadb" | "aws_rds_mysql" => Some("/var/lib/mysql"),
+        "qdrant" => Some("/qdrant/storage"),
+        "redis" | "object_storage" => Some("/data"),
+        _ => None,
+    }
+}
+
+/// Name of the database a local run connects to
+pub(crate) fn database_name(project_name: &str, db_type: Type, db_name: Option<String>) -> String {
+    match db_type {
+        database::Type::AwsRds(_) => db_name.unwrap_or_else(|| project_name.to_string()),
+        database::Type::Shared(SharedEngine::MongoDb) => "admin".to_string(),
+        _ => project_name.to_string(),
+    }
 }
 
-fn db_type_to_config(db_type: Type, database_name: &str) -> EngineConfig {
+pub(crate) fn db_type_to_config(db_type: Type, database_name: &str) -> EngineConfig {
     match db_type {
         Type::Shared(SharedEngine::Postgres) => EngineConfig {
             r#type: "shared_postgres".to_string(),
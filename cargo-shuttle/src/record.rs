@@ -0,0 +1,492 @@
+//! Recording of the HTTP requests to a local run, to replay them against a later build and see
+//! which responses changed.
+//!
+//! A proxy in front of every service records the requests and responses of a session to a JSON
+//! file, which is rewritten after every exchange so that nothing is lost on ctrl-c.
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hyper::header::{HeaderMap, HeaderName};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use shuttle_proto::runtime;
+use shuttle_service::builder::BuiltService;
+use tokio::process::Child;
+use tokio::task::JoinHandle;
+use tracing::{debug, trace};
+
+use crate::args::RunArgs;
+use crate::local::{discovery_var, LocalServices};
+use crate::style::{self, Tone};
+use crate::tls::TlsProxy;
+use crate::{CommandOutcome, Shuttle};
+
+/// Headers that belong to a single connection, and are not passed on
+const HOP_BY_HOP: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+    "te",
+    "trailer",
+];
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Session {
+    pub exchanges: Vec<Exchange>,
+}
+
+/// A request to a service and the response it got
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Exchange {
+    pub service: String,
+    pub method: String,
+    /// Path and query of the request
+    pub uri: String,
+    pub request_headers: Vec<(String, String)>,
+    pub request_body: Body,
+    pub status: u16,
+    pub response_headers: Vec<(String, String)>,
+    pub response_body: Body,
+}
+
+/// A body, as text when it is valid UTF-8
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Body {
+    Text(String),
+    Base64(String),
+}
+
+impl Body {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Self::Text(text.to_string()),
+            Err(_) => Self::Base64(STANDARD.encode(bytes)),
+        }
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        match self {
+            Self::Text(text) => Ok(text.clone().into_bytes()),
+            Self::Base64(encoded) => STANDARD
+                .decode(encoded)
+                .context("invalid base64 body in the session"),
+        }
+    }
+}
+
+fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect()
+}
+
+/// Collects the exchanges of a session and keeps the session file up to date
+pub struct Recorder {
+    path: PathBuf,
+    session: Mutex<Session>,
+}
+
+impl Recorder {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            session: Mutex::new(Session::default()),
+        }
+    }
+
+    fn add(&self, exchange: Exchange) {
+        let mut session = self.session.lock().unwrap_or_else(|e| e.into_inner());
+        session.exchanges.push(exchange);
+
+        let res = serde_json::to_vec_pretty(&*session)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(std::fs::write(&self.path, json)?));
+        if let Err(err) = res {
+            debug!(error = %err, "failed to write the recorded session");
+        }
+    }
+}
+
+/// Records the exchanges with a service, which listens on `backend`. Stops when dropped.
+pub struct RecordingProxy {
+    pub backend: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl RecordingProxy {
+    /// Listen on `addr`, forwarding to a free port on localhost
+    pub async fn start(
+        addr: SocketAddr,
+        service_name: String,
+        recorder: Arc<Recorder>,
+    ) -> Result<Self> {
+        let listener = std::net::TcpListener::bind(addr)
+            .with_context(|| format!("failed to listen on {addr}"))?;
+        listener.set_nonblocking(true)?;
+        let backend = SocketAddr::new(
+            Ipv4Addr::LOCALHOST.into(),
+            portpicker::pick_unused_port().context("failed to find a free port")?,
+        );
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?;
+
+        let make_service = make_service_fn(move |_| {
+            let client = client.clone();
+            let service_name = service_name.clone();
+            let recorder = recorder.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    forward(
+                        request,
+                        backend,
+                        client.clone(),
+                        service_name.clone(),
+                        recorder.clone(),
+                    )
+                }))
+            }
+        });
+        let server = hyper::Server::from_tcp(listener)?.serve(make_service);
+        let task = tokio::spawn(async move {
+            if let Err(err) = server.await {
+                debug!(error = %err, "the recording proxy stopped");
+            }
+        });
+
+        Ok(Self { backend, task })
+    }
+}
+
+impl Drop for RecordingProxy {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn forward(
+    request: Request<hyper::Body>,
+    backend: SocketAddr,
+    client: reqwest::Client,
+    service_name: String,
+    recorder: Arc<Recorder>,
+) -> Result<Response<hyper::Body>, Infallible> {
+    match try_forward(request, backend, &client, &service_name, &recorder).await {
+        Ok(response) => Ok(response),
+        Err(err) => {
+            trace!(error = %err, "failed to forward a recorded request");
+            let mut response =
+                Response::new(format!("{service_name} could not be reached: {err}").into());
+            *response.status_mut() = StatusCode::BAD_GATEWAY;
+            Ok(response)
+        }
+    }
+}
+
+async fn try_forward(
+    request: Request<hyper::Body>,
+    backend: SocketAddr,
+    client: &reqwest::Client,
+    service_name: &str,
+    recorder: &Recorder,
+) -> Result<Response<hyper::Body>> {
+    let (parts, body) = request.into_parts();
+    let body = hyper::body::to_bytes(body).await?;
+    let uri = parts
+        .uri
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or("/")
+        .to_string();
+
+    let response = client
+        .request(parts.method.clone(), format!("http://{backend}{uri}"))
+        .headers(without_hop_by_hop(&parts.headers))
+        .body(body.clone())
+        .send()
+        .await?;
+    let status = response.status();
+    let headers = without_hop_by_hop(response.headers());
+    let response_body = response.bytes().await?;
+
+    recorder.add(Exchange {
+        service: service_name.to_string(),
+        method: parts.method.to_string(),
+        uri,
+        request_headers: header_pairs(&parts.headers),
+        request_body: Body::from_bytes(&body),
+        status: status.as_u16(),
+        response_headers: header_pairs(&headers),
+        response_body: Body::from_bytes(&response_body),
+    });
+
+    let mut proxied = Response::new(hyper::Body::from(response_body));
+    *proxied.status_mut() = status;
+    *proxied.headers_mut() = headers;
+
+    Ok(proxied)
+}
+
+fn without_hop_by_hop(headers: &HeaderMap) -> HeaderMap {
+    let mut headers = headers.clone();
+    for name in HOP_BY_HOP {
+        headers.remove(*name);
+    }
+
+    headers
+}
+
+/// How a replayed request compared to the recording
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Same,
+    Changed(String),
+    Skipped(String),
+}
+
+fn compare(exchange: &Exchange, status: u16, body: &[u8]) -> Result<Outcome> {
+    if status != exchange.status {
+        return Ok(Outcome::Changed(format!(
+            "status {} instead of {}",
+            status, exchange.status
+        )));
+    }
+    if body != exchange.response_body.to_bytes()? {
+        return Ok(Outcome::Changed("different body".to_string()));
+    }
+
+    Ok(Outcome::Same)
+}
+
+/// Send the requests of a recorded session to the services again, and report the responses that
+/// changed. `discovery` has the URLs of the running services. Returns how many changed.
+pub async fn replay(path: &Path, discovery: &BTreeMap<String, String>) -> Result<usize> {
+    let session: Session = serde_json::from_slice(
+        &std::fs::read(path).with_context(|| format!("failed to read {}", path.display()))?,
+    )
+    .with_context(|| format!("{} is not a recorded session", path.display()))?;
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        // The local HTTPS certificates are not necessarily trusted
+        .danger_accept_invalid_certs(true)
+        .build()?;
+
+    println!(
+        "{} {} requests from {}",
+        style::status("Replaying", Tone::Success),
+        session.exchanges.len(),
+        path.display()
+    );
+    let mut ready = Vec::new();
+    let mut changed = 0;
+    for exchange in &session.exchanges {
+        let outcome = match discovery.get(&discovery_var(&exchange.service)) {
+            Some(url) => {
+                if !ready.contains(url) {
+                    wait_until_listening(url).await?;
+                    ready.push(url.clone());
+                }
+                replay_exchange(&client, url, exchange).await?
+            }
+            None => Outcome::Skipped(format!("{} is not running", exchange.service)),
+        };
+
+        let line = format!("{} {} {}", exchange.service, exchange.method, exchange.uri);
+        match outcome {
+            Outcome::Same => println!("{} {line}", style::toned("        Same", Tone::Success)),
+            Outcome::Changed(how) => {
+                changed += 1;
+                println!(
+                    "{} {line}: {how}",
+                    style::toned("     Changed", Tone::Failure)
+                );
+            }
+            Outcome::Skipped(why) => println!(
+                "{} {line}: {why}",
+                style::toned("     Skipped", Tone::Warning)
+            ),
+        }
+    }
+
+    Ok(changed)
+}
+
+async fn replay_exchange(
+    client: &reqwest::Client,
+    url: &str,
+    exchange: &Exchange,
+) -> Result<Outcome> {
+    let method = exchange
+        .method
+        .parse()
+        .with_context(|| format!("invalid method {}", exchange.method))?;
+    let mut headers = HeaderMap::new();
+    for (name, value) in &exchange.request_headers {
+        // These describe the original connection
+        if ["host", "content-length"].contains(&name.as_str()) {
+            continue;
+        }
+        headers.append(HeaderName::from_bytes(name.as_bytes())?, value.parse()?);
+    }
+
+    let response = client
+        .request(method, format!("{url}{}", exchange.uri))
+        .headers(headers)
+        .body(exchange.request_body.to_bytes()?)
+        .send()
+        .await;
+    let response = match response {
+        Ok(response) => response,
+        Err(err) => return Ok(Outcome::Changed(format!("request failed: {err}"))),
+    };
+    let status = response.status().as_u16();
+    let body = response.bytes().await?;
+
+    compare(exchange, status, &body)
+}
+
+/// Services are started before they listen, so wait for that before sending requests
+async fn wait_until_listening(url: &str) -> Result<()> {
+    const TIMEOUT: Duration = Duration::from_secs(30);
+
+    let url = url::Url::parse(url)?;
+    let addr = url
+        .socket_addrs(|| None)?
+        .into_iter()
+        .next()
+        .with_context(|| format!("no address for {url}"))?;
+    tokio::time::timeout(TIMEOUT, async {
+        while tokio::net::TcpStream::connect(addr).await.is_err() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .with_context(|| format!("{url} is not listening after {}s", TIMEOUT.as_secs()))
+}
+
+impl Shuttle {
+    /// Start a proxy recording the requests to every service with `--record`
+    pub(crate) async fn start_recording_proxies(
+        run_args: &RunArgs,
+        services: &[BuiltService],
+    ) -> Result<Vec<RecordingProxy>> {
+        let Some(path) = &run_args.record else {
+            return Ok(Vec::new());
+        };
+
+        let recorder = Arc::new(Recorder::new(path.clone()));
+        let mut proxies = Vec::new();
+        for (idx, service) in services.iter().enumerate() {
+            let addr = SocketAddr::new(
+                run_args.host.expect("the local address to be resolved"),
+                run_args.port.expect("the local port to be resolved") + idx as u16,
+            );
+            proxies.push(
+                RecordingProxy::start(addr, service.service_name()?, recorder.clone()).await?,
+            );
+        }
+        println!("Recording the requests to {}", path.display());
+
+        Ok(proxies)
+    }
+
+    /// Where the services listen when they are behind proxies
+    pub(crate) fn proxy_backends(
+        tls_proxies: &[TlsProxy],
+        recording_proxies: &[RecordingProxy],
+    ) -> Vec<SocketAddr> {
+        tls_proxies
+            .iter()
+            .map(|proxy| proxy.backend)
+            .chain(recording_proxies.iter().map(|proxy| proxy.backend))
+            .collect()
+    }
+
+    /// Replay a recorded session against the services with `--replay`, then stop them
+    pub(crate) async fn replay_local_run(
+        session: &Path,
+        mut runtimes: Vec<(Child, runtime::Client)>,
+        local_services: &LocalServices,
+        grace: Duration,
+    ) -> Result<CommandOutcome> {
+        let res = replay(session, &local_services.discovery).await;
+        Shuttle::stop_runtimes(&mut runtimes, grace).await;
+
+        match res? {
+            0 => Ok(CommandOutcome::Ok),
+            changed => bail!("{changed} of the replayed requests got different responses"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exchange(status: u16, body: &str) -> Exchange {
+        Exchange {
+            service: "api".to_string(),
+            method: "GET".to_string(),
+            uri: "/todos?done=false".to_string(),
+            request_headers: vec![("accept".to_string(), "*/*".to_string())],
+            request_body: Body::Text(String::new()),
+            status,
+            response_headers: Vec::new(),
+            response_body: Body::Text(body.to_string()),
+        }
+    }
+
+    #[test]
+    fn bodies() {
+        assert_eq!(Body::from_bytes(b"hello"), Body::Text("hello".to_string()));
+        let binary = Body::from_bytes(&[0xff, 0x00, 0x10]);
+        assert_eq!(binary, Body::Base64("/wAQ".to_string()));
+        assert_eq!(binary.to_bytes().unwrap(), vec![0xff, 0x00, 0x10]);
+    }
+
+    #[test]
+    fn comparing() {
+        let recorded = exchange(200, "[]");
+        assert_eq!(compare(&recorded, 200, b"[]").unwrap(), Outcome::Same);
+        assert_eq!(
+            compare(&recorded, 500, b"[]").unwrap(),
+            Outcome::Changed("status 500 instead of 200".to_string())
+        );
+        assert_eq!(
+            compare(&recorded, 200, b"[1]").unwrap(),
+            Outcome::Changed("different body".to_string())
+        );
+    }
+
+    #[test]
+    fn session_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        let recorder = Recorder::new(path.clone());
+        recorder.add(exchange(200, "[]"));
+        recorder.add(exchange(404, "not found"));
+
+        let session: Session = serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+        assert_eq!(
+            session.exchanges,
+            vec![exchange(200, "[]"), exchange(404, "not found")]
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Scrubbing of secret values from everything the CLI displays or stores.
+//!
+//! Commands register the secret values they know about, after which any output can be passed
+//! through [`redact`]. This covers streamed build logs, runtime logs, and anything written to
+//! disk, so that secrets don't leak into terminals, CI logs or files.
+
+use std::sync::RwLock;
+
+use shuttle_common::LogItem;
+
+/// Secret values shorter than this are not masked, since hiding every `1` or `true` would make
+/// output unreadable
+const MIN_MASKED_LEN: usize = 4;
+
+const MASK: &str = "********";
+
+/// The known secret values, longest first so that a secret containing another one is masked
+/// as a whole
+static SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Add secret values to scrub from output
+pub fn register<I, S>(values: I)
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let mut secrets = SECRETS.write().unwrap_or_else(|e| e.into_inner());
+    for value in values {
+        let value = value.into();
+        if value.len() >= MIN_MASKED_LEN && !secrets.contains(&value) {
+            secrets.push(value);
+        }
+    }
+    secrets.sort_by_key(|value| std::cmp::Reverse(value.len()));
+}
+
+/// Replace all registered secret values in `text`
+pub fn redact(text: &str) -> String {
+    let secrets = SECRETS.read().unwrap_or_else(|e| e.into_inner());
+
+    mask(text, &secrets)
+}
+
+/// Redact the line of a log item
+pub fn redact_log_item(mut log_item: LogItem) -> LogItem {
+    log_item.line = redact(&log_item.line);
+
+    log_item
+}
+
+fn mask(text: &str, secrets: &[String]) -> String {
+    secrets.iter().fold(text.to_string(), |text, value| {
+        text.replace(value.as_str(), MASK)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masking() {
+        let secrets = ["hunter2222".to_string(), "hunter22".to_string()];
+
+        assert_eq!(
+            mask("password=hunter22 retries=1", &secrets),
+            "password=******** retries=1"
+        );
+        assert_eq!(mask("key hunter2222", &secrets), "key ********");
+        assert_eq!(mask("nothing here", &secrets), "nothing here");
+    }
+
+    #[test]
+    fn registering() {
+        register(["registered-secret", "1", "registered-secret"]);
+
+        assert_eq!(
+            redact("token: registered-secret, count: 1"),
+            "token: ********, count: 1"
+        );
+        assert_eq!(
+            SECRETS
+                .read()
+                .unwrap()
+                .iter()
+                .filter(|value| *value == "registered-secret")
+                .count(),
+            1
+        );
+    }
+}
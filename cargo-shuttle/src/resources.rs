@@ -0,0 +1,104 @@
+//! Commands for the resources of a project, like its databases
+
+use anyhow::{bail, Context, Result};
+use shuttle_common::models::resource::{get_resource_summaries, ConnectionDetails};
+use shuttle_common::resource;
+
+use crate::args::ConnectionFormat;
+use crate::{connect, suggestions, CommandOutcome, Shuttle};
+
+impl Shuttle {
+    /// The database of the project that a command acts on, which only has to be given when there
+    /// are several
+    pub(crate) async fn pick_database(
+        &self,
+        resource_type: Option<resource::Type>,
+    ) -> Result<resource::Response> {
+        let client = self.client.as_ref().unwrap();
+        let project_name = self.ctx.project_name();
+
+        let resources = client
+            .get_service_resources(project_name)
+            .await
+            .map_err(suggestions::resources::get_service_resources_failure)?;
+        let databases = resources
+            .iter()
+            .filter(|r| matches!(r.r#type, resource::Type::Database(_)))
+            .filter(|r| resource_type.map_or(true, |t| r.r#type == t))
+            .collect::<Vec<_>>();
+
+        match (databases.as_slice(), resource_type) {
+            ([database], _) => Ok((*database).clone()),
+            ([], Some(resource_type)) => bail!("{project_name} has no {resource_type}"),
+            ([], None) => bail!("{project_name} has no databases"),
+            (_, _) => bail!(
+                "{project_name} has several databases, pick one of {}",
+                databases
+                    .iter()
+                    .map(|r| r.r#type.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    pub(crate) async fn resource_connect(
+        &self,
+        resource_type: Option<resource::Type>,
+    ) -> Result<CommandOutcome> {
+        let project_name = self.ctx.project_name();
+
+        let database = self.pick_database(resource_type).await?;
+        let resource::Type::Database(db_type) = database.r#type else {
+            unreachable!("only databases are picked")
+        };
+        let connection_string =
+            get_resource_summaries(std::slice::from_ref(&database), project_name, true)
+                .into_iter()
+                .find_map(|summary| summary.connection_string)
+                .context("the database has no connection string")?;
+
+        let command = connect::ClientCommand::new(db_type, &connection_string)?;
+        match command.run()? {
+            Some(status) if status.success() => {}
+            Some(status) => bail!("{} exited with {status}", command.program),
+            None => {
+                println!(
+                    "{} is not installed, using a minimal REPL instead",
+                    command.program
+                );
+                connect::repl(db_type, &connection_string).await?;
+            }
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn resource_show(
+        &self,
+        resource_type: Option<resource::Type>,
+        format: ConnectionFormat,
+        var: &str,
+        show_secrets: bool,
+    ) -> Result<CommandOutcome> {
+        let project_name = self.ctx.project_name();
+
+        let database = self.pick_database(resource_type).await?;
+        let connection_string =
+            get_resource_summaries(std::slice::from_ref(&database), project_name, show_secrets)
+                .into_iter()
+                .find_map(|summary| summary.connection_string)
+                .context("the database has no connection string")?;
+        let details = ConnectionDetails::new(&database.r#type, &connection_string)
+            .context("invalid connection string")?;
+
+        match format {
+            ConnectionFormat::Uri => println!("{}", details.connection_string),
+            ConnectionFormat::Env => println!("{}", details.to_env_line(var)),
+            ConnectionFormat::Dotenv => print!("{}", details.to_dotenv(var)),
+            ConnectionFormat::Json => println!("{}", serde_json::to_string_pretty(&details)?),
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+}
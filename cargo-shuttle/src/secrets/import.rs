@@ -0,0 +1,291 @@
+//! Importing secrets from external secret managers.
+//!
+//! The managers are queried through their own CLIs (`aws` and `vault`), so that whatever
+//! credentials are configured for them in the environment are used as is.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::process::Command;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+
+/// A location to import secrets from, given as `<manager>:<path>`
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImportSource {
+    /// All parameters under a path in AWS Systems Manager Parameter Store
+    AwsSsm(String),
+    /// A single secret in AWS Secrets Manager
+    AwsSecretsManager(String),
+    /// A secret in a HashiCorp Vault KV engine
+    Vault(String),
+}
+
+impl FromStr for ImportSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (manager, path) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected `<manager>:<path>`, got `{s}`"))?;
+        if path.is_empty() {
+            return Err(format!("missing path in `{s}`"));
+        }
+
+        match manager {
+            "aws-ssm" => Ok(Self::AwsSsm(path.to_string())),
+            "aws-sm" => Ok(Self::AwsSecretsManager(path.to_string())),
+            "vault" => Ok(Self::Vault(path.to_string())),
+            _ => Err(format!(
+                "unknown secret manager `{manager}`, expected one of: aws-ssm, aws-sm, vault"
+            )),
+        }
+    }
+}
+
+impl Display for ImportSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::AwsSsm(path) => write!(f, "aws-ssm:{path}"),
+            Self::AwsSecretsManager(path) => write!(f, "aws-sm:{path}"),
+            Self::Vault(path) => write!(f, "vault:{path}"),
+        }
+    }
+}
+
+/// A secret fetched from an external manager
+#[derive(Debug, PartialEq)]
+pub struct ImportedSecret {
+    /// Where the value came from in the external manager
+    pub origin: String,
+    pub value: String,
+}
+
+impl ImportSource {
+    /// Fetch the secrets from this source, keyed by their Shuttle secret name
+    pub fn fetch(&self) -> Result<BTreeMap<String, ImportedSecret>> {
+        let output = match self {
+            Self::AwsSsm(path) => run(
+                "aws",
+                &[
+                    "ssm",
+                    "get-parameters-by-path",
+                    "--path",
+                    path,
+                    "--recursive",
+                    "--with-decryption",
+                    "--output",
+                    "json",
+                ],
+            )?,
+            Self::AwsSecretsManager(id) => run(
+                "aws",
+                &[
+                    "secretsmanager",
+                    "get-secret-value",
+                    "--secret-id",
+                    id,
+                    "--output",
+                    "json",
+                ],
+            )?,
+            Self::Vault(path) => run("vault", &["kv", "get", "-format=json", path])?,
+        };
+
+        let output: Value = serde_json::from_str(&output)
+            .with_context(|| format!("failed to parse the response for {self}"))?;
+
+        match self {
+            Self::AwsSsm(path) => parse_aws_ssm(path, &output),
+            Self::AwsSecretsManager(id) => parse_aws_secrets_manager(id, &output),
+            Self::Vault(path) => parse_vault(path, &output),
+        }
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("failed to run `{program}`. Is it installed and on the PATH?"))?;
+
+    if !output.status.success() {
+        bail!(
+            "`{program} {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    String::from_utf8(output.stdout).with_context(|| format!("`{program}` returned invalid UTF-8"))
+}
+
+/// Map a path in an external manager to a secret name, e.g. `database/url` to `DATABASE_URL`
+pub fn secret_name(path: &str) -> String {
+    path.trim_matches('/')
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn parse_aws_ssm(path: &str, output: &Value) -> Result<BTreeMap<String, ImportedSecret>> {
+    let parameters = output["Parameters"]
+        .as_array()
+        .context("missing `Parameters` in the Parameter Store response")?;
+
+    let mut secrets = BTreeMap::new();
+    for parameter in parameters {
+        let name = parameter["Name"]
+            .as_str()
+            .context("parameter without a `Name`")?;
+        let value = parameter["Value"]
+            .as_str()
+            .with_context(|| format!("parameter `{name}` has no value"))?;
+
+        secrets.insert(
+            secret_name(name.strip_prefix(path).unwrap_or(name)),
+            ImportedSecret {
+                origin: format!("aws-ssm:{name}"),
+                value: value.to_string(),
+            },
+        );
+    }
+
+    Ok(secrets)
+}
+
+/// JSON object secrets are split into one secret per key. Any other secret becomes a single
+/// secret named after the last segment of its id.
+fn parse_aws_secrets_manager(id: &str, output: &Value) -> Result<BTreeMap<String, ImportedSecret>> {
+    let secret_string = output["SecretString"]
+        .as_str()
+        .with_context(|| format!("secret `{id}` has no string value"))?;
+
+    match serde_json::from_str::<Value>(secret_string) {
+        Ok(Value::Object(fields)) => Ok(fields
+            .into_iter()
+            .map(|(key, value)| {
+                (
+                    secret_name(&key),
+                    ImportedSecret {
+                        origin: format!("aws-sm:{id}#{key}"),
+                        value: string_value(value),
+                    },
+                )
+            })
+            .collect()),
+        _ => Ok(BTreeMap::from([(
+            secret_name(id.rsplit('/').next().unwrap_or(id)),
+            ImportedSecret {
+                origin: format!("aws-sm:{id}"),
+                value: secret_string.to_string(),
+            },
+        )])),
+    }
+}
+
+/// Supports both version 1 and version 2 of the KV engine
+fn parse_vault(path: &str, output: &Value) -> Result<BTreeMap<String, ImportedSecret>> {
+    let data = &output["data"];
+    let fields = data["data"]
+        .as_object()
+        .or_else(|| data.as_object())
+        .with_context(|| format!("no secret data found at vault:{path}"))?;
+
+    Ok(fields
+        .iter()
+        .map(|(key, value)| {
+            (
+                secret_name(key),
+                ImportedSecret {
+                    origin: format!("vault:{path}#{key}"),
+                    value: string_value(value.clone()),
+                },
+            )
+        })
+        .collect())
+}
+
+fn string_value(value: Value) -> String {
+    match value {
+        Value::String(s) => s,
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn source_parsing() {
+        assert_eq!(
+            "aws-ssm:/myapp/".parse(),
+            Ok(ImportSource::AwsSsm("/myapp/".to_string()))
+        );
+        assert_eq!(
+            "aws-sm:prod/myapp".parse(),
+            Ok(ImportSource::AwsSecretsManager("prod/myapp".to_string()))
+        );
+        assert_eq!(
+            "vault:secret/myapp".parse(),
+            Ok(ImportSource::Vault("secret/myapp".to_string()))
+        );
+        assert!("gcp:projects/x".parse::<ImportSource>().is_err());
+        assert!("vault:".parse::<ImportSource>().is_err());
+        assert!("secret/myapp".parse::<ImportSource>().is_err());
+    }
+
+    #[test]
+    fn secret_names() {
+        assert_eq!(secret_name("database/url"), "DATABASE_URL");
+        assert_eq!(secret_name("/stripe-key/"), "STRIPE_KEY");
+        assert_eq!(secret_name("API_TOKEN"), "API_TOKEN");
+    }
+
+    #[test]
+    fn parse_responses() {
+        let ssm = json!({
+            "Parameters": [
+                { "Name": "/myapp/database/url", "Value": "postgres://db" },
+                { "Name": "/myapp/stripe-key", "Value": "sk_test" },
+            ]
+        });
+        let secrets = parse_aws_ssm("/myapp/", &ssm).unwrap();
+        assert_eq!(secrets["DATABASE_URL"].value, "postgres://db");
+        assert_eq!(
+            secrets["DATABASE_URL"].origin,
+            "aws-ssm:/myapp/database/url"
+        );
+        assert_eq!(secrets["STRIPE_KEY"].value, "sk_test");
+
+        let sm = json!({ "SecretString": "{\"username\": \"admin\", \"port\": 5432}" });
+        let secrets = parse_aws_secrets_manager("prod/db", &sm).unwrap();
+        assert_eq!(secrets["USERNAME"].value, "admin");
+        assert_eq!(secrets["PORT"].value, "5432");
+
+        let sm = json!({ "SecretString": "plain-value" });
+        let secrets = parse_aws_secrets_manager("prod/api-token", &sm).unwrap();
+        assert_eq!(secrets["API_TOKEN"].value, "plain-value");
+
+        let kv2 = json!({ "data": { "data": { "session_key": "abc" }, "metadata": {} } });
+        assert_eq!(
+            parse_vault("secret/myapp", &kv2).unwrap()["SESSION_KEY"].value,
+            "abc"
+        );
+        let kv1 = json!({ "data": { "session_key": "abc" } });
+        assert_eq!(
+            parse_vault("secret/myapp", &kv1).unwrap()["SESSION_KEY"].value,
+            "abc"
+        );
+    }
+}
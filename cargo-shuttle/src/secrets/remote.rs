@@ -0,0 +1,375 @@
+//! Commands for the secrets stored on the platform
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs::read_to_string;
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use crossterm::style::Stylize;
+use dialoguer::theme::ColorfulTheme;
+use dialoguer::Password;
+use indoc::formatdoc;
+use shuttle_common::models::secret::{self, get_secrets_metadata_table};
+use shuttle_common::resource;
+
+use crate::args::{GeneratedSecretFormat, SecretsArgs};
+use crate::secrets::import::ImportSource;
+use crate::{output, prompt, secrets, suggestions, CommandOutcome, Shuttle};
+
+impl Shuttle {
+    pub(crate) async fn secrets_list(&self, raw: bool) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let secrets = client
+            .get_secrets(self.ctx.project_name())
+            .await
+            .map_err(|err| {
+                suggestions::secrets::secrets_request_failure(err, "Fetching the secrets failed")
+            })?;
+
+        output::print(self.output, &secrets, |plain| {
+            get_secrets_metadata_table(&secrets, self.ctx.project_name(), raw || plain)
+        })?;
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn secret_set(
+        &self,
+        key: String,
+        value: Option<String>,
+        generate: Option<usize>,
+        format: GeneratedSecretFormat,
+    ) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let value = match (value, generate) {
+            (Some(value), _) => value,
+            (None, Some(len)) => secrets::generate_secret(len, format)?,
+            (None, None) => {
+                prompt::require("Pass the value, or --generate to generate one.")?;
+                Password::with_theme(&ColorfulTheme::default())
+                    .with_prompt(format!("Value of {key}"))
+                    .interact()?
+            }
+        };
+
+        client
+            .set_secret(self.ctx.project_name(), &key, &secret::SetRequest { value })
+            .await
+            .map_err(|err| {
+                suggestions::secrets::secrets_request_failure(err, "Setting the secret failed")
+            })?;
+
+        if generate.is_some() {
+            println!("Set secret {key} to a generated value");
+            println!("Run `cargo shuttle secrets get {key} --reveal` to see it.");
+        } else {
+            println!("Set secret {key}");
+        }
+        println!("The new value is used from the next deployment of the service.");
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn secret_get(&self, key: String, reveal: bool) -> Result<CommandOutcome> {
+        if !reveal {
+            bail!("Not showing the value of secret `{key}`. Pass `--reveal` to print it.");
+        }
+
+        let client = self.client.as_ref().unwrap();
+        let secret = client
+            .get_secret(self.ctx.project_name(), &key)
+            .await
+            .map_err(|err| {
+                suggestions::secrets::secrets_request_failure(err, "Fetching the secret failed")
+            })?;
+
+        println!("{}", secret.value);
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn secret_remove(
+        &self,
+        key: String,
+        no_confirm: bool,
+    ) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+
+        if !no_confirm
+            && !prompt::confirm(
+                format!("Remove secret {key}?"),
+                false,
+                "Pass --yes to remove the secret.",
+            )?
+        {
+            return Ok(CommandOutcome::Ok);
+        }
+
+        client
+            .delete_secret(self.ctx.project_name(), &key)
+            .await
+            .map_err(|err| {
+                suggestions::secrets::secrets_request_failure(err, "Removing the secret failed")
+            })?;
+
+        println!("Removed secret {key}");
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn secrets_clear(&self, no_confirm: bool) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+
+        if !no_confirm {
+            println!(
+                "{}",
+                formatdoc!(
+                    r#"
+                    WARNING:
+                        Are you sure you want to remove all secrets of "{}"?
+                        This action is permanent."#,
+                    self.ctx.project_name()
+                )
+                .bold()
+                .red()
+            );
+            if !prompt::confirm("Are you sure?", false, "Pass --yes to remove the secrets.")? {
+                return Ok(CommandOutcome::Ok);
+            }
+        }
+
+        client
+            .clear_secrets(self.ctx.project_name())
+            .await
+            .map_err(|err| {
+                suggestions::secrets::secrets_request_failure(err, "Clearing the secrets failed")
+            })?;
+
+        println!("Removed all secrets");
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) fn secrets_sync(&self, from: PathBuf, no_confirm: bool) -> Result<CommandOutcome> {
+        let dotenv =
+            read_to_string(&from).with_context(|| format!("failed to read {}", from.display()))?;
+        let new = secrets::parse_dotenv(&dotenv)
+            .with_context(|| format!("failed to parse {}", from.display()))?;
+
+        let secrets_file = self.ctx.working_directory().join("Secrets.toml");
+        let current = secrets::read_secrets_file(&secrets_file)?;
+
+        let diff = secrets::SecretsDiff::new(&current, &new);
+        if diff.is_empty() {
+            println!(
+                "{} is already in sync with {}",
+                secrets_file.display(),
+                from.display()
+            );
+            return Ok(CommandOutcome::Ok);
+        }
+
+        println!(
+            "Changes to {} from {}:",
+            secrets_file.display(),
+            from.display()
+        );
+        println!("{diff}");
+        println!();
+
+        if !no_confirm
+            && !prompt::confirm(
+                "Apply these changes?",
+                false,
+                "Pass --yes to apply the changes.",
+            )?
+        {
+            return Ok(CommandOutcome::Ok);
+        }
+
+        secrets::write_secrets_file(&secrets_file, &new)?;
+        println!("Updated {}", secrets_file.display());
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn secrets_diff(&self, secret_args: SecretsArgs) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let secrets_file = self
+            .secrets_file(&secret_args)?
+            .unwrap_or_else(|| secrets::default_secrets_file(self.ctx.working_directory()));
+        let local = secrets::read_secrets_file(&secrets_file)?;
+
+        let resources = client
+            .get_service_resources(self.ctx.project_name())
+            .await
+            .map_err(|err| {
+                suggestions::secrets::secrets_request_failure(err, "Fetching the secrets failed")
+            })?;
+        let remote = match resources
+            .into_iter()
+            .find(|resource| resource.r#type == resource::Type::Secrets)
+        {
+            Some(resource) => serde_json::from_value::<BTreeMap<String, String>>(resource.data)
+                .context("failed to parse the secrets of the service")?,
+            None => Default::default(),
+        };
+
+        let diff = secrets::SecretsDiff::new(&remote, &local);
+        if diff.is_empty() {
+            println!(
+                "{} matches the secrets of {}",
+                secrets_file.display(),
+                self.ctx.project_name()
+            );
+            return Ok(CommandOutcome::Ok);
+        }
+
+        println!(
+            "Changes to the secrets of {} when deploying with {}:",
+            self.ctx.project_name(),
+            secrets_file.display()
+        );
+        println!(
+            "{}",
+            secrets::format_fingerprinted_diff(&diff, &remote, &local)?
+        );
+        println!(
+            "{} added, {} changed, {} removed, {} unchanged",
+            diff.added.len(),
+            diff.changed.len(),
+            diff.removed.len(),
+            diff.unchanged,
+        );
+        println!();
+        println!(
+            "Values are shown as fingerprints that only compare within this output, since they \
+            are keyed with a new random key every time."
+        );
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn secret_rotate(
+        &self,
+        key: String,
+        length: usize,
+        format: GeneratedSecretFormat,
+        no_confirm: bool,
+    ) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let project_name = self.ctx.project_name();
+
+        let secrets = client.get_secrets(project_name).await.map_err(|err| {
+            suggestions::secrets::secrets_request_failure(err, "Fetching the secrets failed")
+        })?;
+        if !secrets.iter().any(|secret| secret.key == key) {
+            bail!(
+                "Secret {key} does not exist in {project_name}. \
+                Use `cargo shuttle secrets set {key} --generate` to create it."
+            );
+        }
+
+        if !no_confirm
+            && !prompt::confirm(
+                format!("Replace {key} with a new generated value?"),
+                false,
+                "Pass --yes to rotate the secret.",
+            )?
+        {
+            return Ok(CommandOutcome::Ok);
+        }
+
+        let value = secrets::generate_secret(length, format)?;
+        client
+            .set_secret(project_name, &key, &secret::SetRequest { value })
+            .await
+            .map_err(|err| {
+                suggestions::secrets::secrets_request_failure(err, "Setting the secret failed")
+            })?;
+        println!("Set secret {key} to a new value");
+        println!(
+            "The new value is used from the next deployment of the service. \
+            Run `cargo shuttle deploy` to use it now."
+        );
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn secrets_import(
+        &self,
+        sources: Vec<ImportSource>,
+        no_confirm: bool,
+    ) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+
+        let mut imported = BTreeMap::new();
+        for source in &sources {
+            println!("Fetching secrets from {source}");
+            let secrets = source
+                .fetch()
+                .with_context(|| format!("failed to import secrets from {source}"))?;
+            if secrets.is_empty() {
+                println!("{}", format!("No secrets found at {source}").yellow());
+            }
+            imported.extend(secrets);
+        }
+        if imported.is_empty() {
+            return Ok(CommandOutcome::Ok);
+        }
+
+        let existing = client
+            .get_secrets(self.ctx.project_name())
+            .await
+            .map_err(|err| {
+                suggestions::secrets::secrets_request_failure(err, "Fetching the secrets failed")
+            })?
+            .into_iter()
+            .map(|secret| secret.key)
+            .collect::<HashSet<_>>();
+
+        println!();
+        println!("Secrets to import into {}:", self.ctx.project_name());
+        for (key, secret) in &imported {
+            if existing.contains(key) {
+                println!("{}", format!("~ {key} <- {}", secret.origin).yellow());
+            } else {
+                println!("{}", format!("+ {key} <- {}", secret.origin).green());
+            }
+        }
+        println!();
+
+        if !no_confirm
+            && !prompt::confirm(
+                "Import these secrets?",
+                false,
+                "Pass --yes to import the secrets.",
+            )?
+        {
+            return Ok(CommandOutcome::Ok);
+        }
+
+        for (key, secret) in imported {
+            client
+                .set_secret(
+                    self.ctx.project_name(),
+                    &key,
+                    &secret::SetRequest {
+                        value: secret.value,
+                    },
+                )
+                .await
+                .map_err(|err| {
+                    suggestions::secrets::secrets_request_failure(
+                        err,
+                        &format!("Importing secret {key} failed"),
+                    )
+                })?;
+        }
+
+        println!("Imported secrets");
+        println!("The new values are used from the next deployment of the service.");
+
+        Ok(CommandOutcome::Ok)
+    }
+}
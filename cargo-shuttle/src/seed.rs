@@ -0,0 +1,125 @@
+//! Filling the databases of local runs with fixtures, from `[local.seed]` in Shuttle.toml.
+//!
+//! Databases that keep their data are seeded once, which is remembered with a marker file next
+//! to their data. The others are seeded whenever their container is new.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use shuttle_service::database::Type;
+use tokio::process::Command;
+use tracing::trace;
+
+use crate::config::LocalSeedConfig;
+use crate::provisioner_server::LocalProvisioner;
+use crate::style::{self, Tone};
+
+/// How the databases of a local run are seeded
+pub struct Seed {
+    pub config: LocalSeedConfig,
+    /// Where the SQL files are relative to, and the seed binary is built in
+    pub project_dir: PathBuf,
+    /// Seed the databases that were seeded before as well
+    pub reseed: bool,
+}
+
+/// A database about to be handed to a service
+pub struct SeedTarget<'a> {
+    pub project_name: &'a str,
+    pub db_type: Type,
+    pub db_name: Option<String>,
+    pub database_url: String,
+    /// Marks the database as seeded
+    pub marker: PathBuf,
+    /// The data of the database survives its container
+    pub kept: bool,
+    /// The container was there before this run
+    pub existed: bool,
+}
+
+impl Seed {
+    pub fn needed(&self, target: &SeedTarget) -> bool {
+        needed(self.reseed, &target.marker, target.kept, target.existed)
+    }
+
+    /// Run the SQL files and then the seed binary on a database
+    pub async fn run(
+        &self,
+        prov: &LocalProvisioner,
+        target: &SeedTarget<'_>,
+        offline: bool,
+    ) -> Result<()> {
+        for path in &self.config.sql {
+            let sql = fs::read_to_string(self.project_dir.join(path))
+                .with_context(|| format!("failed to read the seed file {}", path.display()))?;
+            trace!("seeding {} with {}", target.project_name, path.display());
+            prov.run_sql(
+                target.project_name,
+                target.db_type,
+                target.db_name.clone(),
+                &sql,
+            )
+            .await
+            .with_context(|| format!("failed to seed the database with {}", path.display()))?;
+        }
+
+        if let Some(bin) = &self.config.bin {
+            let mut cmd = Command::new("cargo");
+            cmd.args(["run", "--bin", bin])
+                .current_dir(&self.project_dir)
+                .env("DATABASE_URL", &target.database_url);
+            if offline {
+                cmd.arg("--offline");
+            }
+            let status = cmd.status().await.context("failed to run cargo")?;
+            if !status.success() {
+                bail!("the seed binary `{bin}` failed with {status}");
+            }
+        }
+
+        if target.kept {
+            fs::write(&target.marker, "")
+                .with_context(|| format!("failed to write {}", target.marker.display()))?;
+        }
+        println!(
+            "{} the {} database of {}",
+            style::status("Seeded", Tone::Success),
+            LocalProvisioner::database_kind(target.db_type),
+            target.project_name
+        );
+
+        Ok(())
+    }
+}
+
+/// File that marks the database of a kind as seeded. It is next to the data rather than in it,
+/// since database images expect to initialize empty directories.
+pub fn marker_path(data_dir: &Path, kind: &str) -> PathBuf {
+    data_dir.join(format!("{kind}.seeded"))
+}
+
+fn needed(reseed: bool, marker: &Path, kept: bool, existed: bool) -> bool {
+    reseed || if kept { !marker.exists() } else { !existed }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeding_needed() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = marker_path(dir.path(), "shared_postgres");
+
+        // Kept data is seeded until the marker is there, even in containers that existed
+        assert!(needed(false, &marker, true, true));
+        fs::write(&marker, "").unwrap();
+        assert!(!needed(false, &marker, true, false));
+        assert!(needed(true, &marker, true, true));
+
+        // Disposable data is seeded when its container is new
+        assert!(needed(false, &marker, false, false));
+        assert!(!needed(false, &marker, false, true));
+    }
+}
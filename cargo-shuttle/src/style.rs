@@ -0,0 +1,193 @@
+//! Colors and symbols of the output, for all commands.
+//!
+//! Whether to color follows the global `--color` flag. By default, `NO_COLOR` turns colors off
+//! and `CLICOLOR_FORCE` turns them on, and otherwise only terminals get them. The accent color and
+//! the symbols come from the `[theme]` table of the global configuration.
+
+use std::fmt::Display;
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+
+use crossterm::style::{style, Color, StyledContent, Stylize};
+use tracing::warn;
+
+use crate::args::{ColorChoice, OutputMode};
+use crate::config::ThemeConfig;
+
+/// The orange of Shuttle, in the 256-color palette
+const DEFAULT_ACCENT: u8 = 208;
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Theme {
+    /// Color of the 256-color palette
+    accent: u8,
+    ascii: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            accent: DEFAULT_ACCENT,
+            ascii: false,
+        }
+    }
+}
+
+impl Theme {
+    fn from_config(config: &ThemeConfig) -> Self {
+        let accent = match config.accent.as_deref().map(parse_color) {
+            Some(Some(accent)) => accent,
+            Some(None) => {
+                warn!(
+                    accent = ?config.accent,
+                    "unknown accent color in the theme, using the default one"
+                );
+                DEFAULT_ACCENT
+            }
+            None => DEFAULT_ACCENT,
+        };
+
+        Self {
+            accent,
+            ascii: config.ascii.unwrap_or_default(),
+        }
+    }
+}
+
+/// One of the eight basic colors by name, `orange`, or a number of the 256-color palette
+fn parse_color(color: &str) -> Option<u8> {
+    let basic = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+    match basic
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(color))
+    {
+        Some(index) => Some(index as u8),
+        None if color.eq_ignore_ascii_case("orange") => Some(DEFAULT_ACCENT),
+        None => color.parse().ok(),
+    }
+}
+
+/// Apply the color policy and the theme, before anything is printed
+pub fn init(choice: ColorChoice, output: OutputMode, theme: &ThemeConfig) {
+    let enabled = output == OutputMode::Table
+        && colors_enabled(
+            choice,
+            std::env::var("NO_COLOR").ok().as_deref(),
+            std::env::var("CLICOLOR_FORCE").ok().as_deref(),
+            std::io::stdout().is_terminal(),
+        );
+
+    crossterm::style::force_color_output(enabled);
+    // Used by the spinners and the prompts
+    dialoguer::console::set_colors_enabled(enabled);
+    dialoguer::console::set_colors_enabled_stderr(enabled);
+
+    let _ = THEME.set(Theme::from_config(theme));
+}
+
+fn colors_enabled(
+    choice: ColorChoice,
+    no_color: Option<&str>,
+    clicolor_force: Option<&str>,
+    is_terminal: bool,
+) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto if no_color.is_some_and(|value| !value.is_empty()) => false,
+        ColorChoice::Auto
+            if clicolor_force.is_some_and(|value| !value.is_empty() && value != "0") =>
+        {
+            true
+        }
+        ColorChoice::Auto => is_terminal,
+    }
+}
+
+fn theme() -> Theme {
+    THEME.get().copied().unwrap_or_default()
+}
+
+/// The accent color, for the templates of spinners and bars
+pub fn accent_code() -> u8 {
+    theme().accent
+}
+
+/// Whether to stick to ASCII instead of symbols like ●
+pub fn ascii() -> bool {
+    theme().ascii
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Tone {
+    Success,
+    Warning,
+    Failure,
+    Accent,
+}
+
+pub fn toned<D: Display>(content: D, tone: Tone) -> StyledContent<D> {
+    let content = style(content);
+    match tone {
+        Tone::Success => content.green(),
+        Tone::Warning => content.yellow(),
+        Tone::Failure => content.red(),
+        Tone::Accent => content.with(Color::AnsiValue(theme().accent)),
+    }
+}
+
+/// A status at the start of a line, right-aligned like the ones of cargo: `    Finished`
+pub fn status(status: &str, tone: Tone) -> StyledContent<String> {
+    toned(format!("{status:>12}"), tone).bold()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_policy() {
+        assert!(colors_enabled(ColorChoice::Auto, None, None, true));
+        assert!(!colors_enabled(ColorChoice::Auto, None, None, false));
+        assert!(!colors_enabled(ColorChoice::Auto, Some("1"), None, true));
+        // An empty NO_COLOR does not count
+        assert!(colors_enabled(ColorChoice::Auto, Some(""), None, true));
+        assert!(colors_enabled(ColorChoice::Auto, None, Some("1"), false));
+        assert!(!colors_enabled(ColorChoice::Auto, None, Some("0"), false));
+        assert!(!colors_enabled(
+            ColorChoice::Auto,
+            Some("1"),
+            Some("1"),
+            true
+        ));
+
+        // The flag wins over the environment
+        assert!(colors_enabled(ColorChoice::Always, Some("1"), None, false));
+        assert!(!colors_enabled(ColorChoice::Never, None, Some("1"), true));
+    }
+
+    #[test]
+    fn theme_config() {
+        assert_eq!(parse_color("Cyan"), Some(6));
+        assert_eq!(parse_color("orange"), Some(DEFAULT_ACCENT));
+        assert_eq!(parse_color("99"), Some(99));
+        assert_eq!(parse_color("teal"), None);
+
+        let theme = Theme::from_config(&ThemeConfig {
+            accent: Some("teal".to_string()),
+            ascii: Some(true),
+        });
+        assert_eq!(
+            theme,
+            Theme {
+                accent: DEFAULT_ACCENT,
+                ascii: true
+            }
+        );
+        assert_eq!(status("Finished", Tone::Success).content(), "    Finished");
+    }
+}
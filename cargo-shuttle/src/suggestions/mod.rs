@@ -4,3 +4,4 @@ pub mod deployment;
 pub mod logs;
 pub mod project;
 pub mod resources;
+pub mod secrets;
@@ -0,0 +1,18 @@
+use crossterm::style::Stylize;
+
+/// Used when a request to manage the secrets of a project fails.
+pub fn secrets_request_failure(err: anyhow::Error, title: &str) -> anyhow::Error {
+    println!();
+    println!("{}", title.red());
+    println!();
+    println!("Please check your project status:");
+    println!();
+    println!("cargo shuttle project status");
+    println!();
+    println!(
+        "If managing secrets fails repeatedly, please try restarting your project before trying again or contacting the team on the Discord server:"
+    );
+    println!();
+    println!("cargo shuttle project restart");
+    err
+}
@@ -0,0 +1,137 @@
+//! Commands for teams, the organizations that share projects between their members
+
+use anyhow::{bail, Context, Result};
+use shuttle_common::models::organization::{self, get_members_table, get_organizations_table};
+
+use crate::args::TeamArgs;
+use crate::{concurrent, output, prompt, CommandOutcome, Shuttle};
+
+impl Shuttle {
+    /// The team given with `--team`, by ID or name, or else the only one the user is in
+    pub(crate) async fn pick_team(
+        &self,
+        TeamArgs { team }: TeamArgs,
+    ) -> Result<organization::Response> {
+        let client = self.client.as_ref().unwrap();
+        let mut teams = client.get_organizations_list().await?;
+
+        match team {
+            Some(team) => {
+                let Some(idx) = teams
+                    .iter()
+                    .position(|org| org.id == team || org.display_name == team)
+                else {
+                    bail!(
+                        "You are not in a team called {team}. \
+                        See `cargo shuttle team list` for the ones you are in."
+                    );
+                };
+                Ok(teams.swap_remove(idx))
+            }
+            None if teams.len() == 1 => Ok(teams.remove(0)),
+            None if teams.is_empty() => {
+                bail!("You are not in any team. Create one with `cargo shuttle team create`.")
+            }
+            None => bail!("You are in several teams. Pass --team with the ID or name of one."),
+        }
+    }
+
+    pub(crate) async fn teams_list(&self, raw: bool) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let teams = client.get_organizations_list().await?;
+        let projects = concurrent::map(&teams, self.ctx.concurrency(), |org| {
+            client.get_organization_projects_list(&org.id)
+        })
+        .await;
+
+        let mut listed = Vec::with_capacity(teams.len());
+        for (org, projects) in teams.into_iter().zip(projects) {
+            let projects = projects
+                .with_context(|| format!("failed to get the projects of {}", org.display_name))?;
+            listed.push(organization::ProjectsResponse {
+                organization: org,
+                projects: projects.into_iter().map(|project| project.name).collect(),
+            });
+        }
+
+        output::print(self.output, &listed, |plain| {
+            get_organizations_table(&listed, raw || plain)
+        })?;
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn team_create(&self, name: &str) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        client
+            .create_organization(name)
+            .await
+            .context("failed to create the team")?;
+
+        println!("Created team {name}");
+        println!("Add members with `cargo shuttle team invite <USER_ID>`.");
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn team_members(&self, team: TeamArgs, raw: bool) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let team = self.pick_team(team).await?;
+        let members = client
+            .get_organization_members(&team.id)
+            .await
+            .context("failed to get the members of the team")?;
+
+        output::print(self.output, &members, |plain| {
+            get_members_table(&members, &team.display_name, raw || plain)
+        })?;
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn team_invite(
+        &self,
+        user_id: &str,
+        team: TeamArgs,
+    ) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let team = self.pick_team(team).await?;
+        client
+            .add_organization_member(&team.id, user_id)
+            .await
+            .context("failed to add the member")?;
+
+        println!("Added {user_id} to {}", team.display_name);
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn team_remove(
+        &self,
+        user_id: &str,
+        team: TeamArgs,
+        no_confirm: bool,
+    ) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let team = self.pick_team(team).await?;
+
+        if !no_confirm
+            && !prompt::confirm(
+                format!("Remove {user_id} from {}?", team.display_name),
+                false,
+                "Pass --yes to remove the member.",
+            )?
+        {
+            return Ok(CommandOutcome::Ok);
+        }
+
+        client
+            .remove_organization_member(&team.id, user_id)
+            .await
+            .context("failed to remove the member")?;
+
+        println!("Removed {user_id} from {}", team.display_name);
+
+        Ok(CommandOutcome::Ok)
+    }
+}
@@ -0,0 +1,309 @@
+//! Anonymous usage telemetry, which is off unless turned on with `cargo shuttle telemetry enable`.
+//!
+//! An event is the name of a command, how long it took, and the kind of error it failed with.
+//! Arguments, paths, project names and code are never recorded. Events are queued in the Shuttle
+//! config directory and sent in the background by the next command, so that sending them never
+//! holds up a command for long. `DO_NOT_TRACK` turns telemetry off whatever the configuration.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use shuttle_common::constants::API_URL_DEFAULT;
+use tokio::task::JoinHandle;
+use tracing::debug;
+
+use crate::client::http_client;
+use crate::config::{Config, GlobalConfig, GlobalConfigManager};
+use crate::errors::ErrorCode;
+use crate::{output, CommandOutcome, Shuttle};
+
+/// Events kept in the queue when they cannot be sent
+const MAX_QUEUED: usize = 500;
+/// How long the end of a command waits for the events to be sent
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Event {
+    /// RFC 3339 time the command started
+    pub timestamp: String,
+    /// The top-level command, like `deploy`
+    pub command: String,
+    pub duration_ms: u64,
+    /// What kind of error the command failed with, if any
+    pub error: Option<ErrorCode>,
+    /// Version of cargo-shuttle
+    pub version: String,
+    pub os: String,
+}
+
+/// Whether the global configuration turned telemetry on, and `DO_NOT_TRACK` does not turn it off
+pub fn is_enabled(configured: bool) -> bool {
+    configured && !do_not_track(std::env::var("DO_NOT_TRACK").ok().as_deref())
+}
+
+fn do_not_track(value: Option<&str>) -> bool {
+    value.is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Where events wait to be sent
+pub fn queue_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir().context(
+        "Could not find a configuration directory. Your operating system may not be supported.",
+    )?;
+
+    Ok(config_dir.join("shuttle").join("telemetry-queue.jsonl"))
+}
+
+/// The number of events waiting to be sent
+pub fn queued() -> usize {
+    queue_path()
+        .and_then(|path| read_queue(&path))
+        .map(|events| events.len())
+        .unwrap_or_default()
+}
+
+/// Drop the events waiting to be sent
+pub fn clear_queue() -> Result<()> {
+    match fs::remove_file(queue_path()?) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            Err(err).context("failed to remove the telemetry queue")
+        }
+        _ => Ok(()),
+    }
+}
+
+fn read_queue(path: &Path) -> Result<Vec<Event>> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err).context("failed to read the telemetry queue"),
+    };
+
+    Ok(contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn queue(path: &Path, events: &[Event]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    for event in events {
+        writeln!(file, "{}", serde_json::to_string(event)?)?;
+    }
+
+    // Events that cannot be sent, like without a network, do not pile up forever
+    let queued = read_queue(path)?;
+    if queued.len() > MAX_QUEUED {
+        let mut kept = String::new();
+        for event in &queued[queued.len() - MAX_QUEUED..] {
+            kept.push_str(&serde_json::to_string(event)?);
+            kept.push('\n');
+        }
+        fs::write(path, kept)?;
+    }
+
+    Ok(())
+}
+
+/// Send the queued events. The queue is moved aside first, so that commands running at the same
+/// time queue their events in a new one, and the events are put back if sending them can be
+/// retried.
+async fn flush(path: PathBuf, api_url: String) -> Result<()> {
+    let sending = path.with_extension("sending");
+    match fs::rename(&path, &sending) {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        res => res?,
+    }
+    let events = read_queue(&sending)?;
+    fs::remove_file(&sending)?;
+    if events.is_empty() {
+        return Ok(());
+    }
+
+    #[derive(Serialize)]
+    struct Batch<'a> {
+        events: &'a [Event],
+    }
+
+    let sent = http_client()
+        .post(format!("{api_url}/telemetry/events"))
+        .json(&Batch { events: &events })
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    match sent {
+        // An API that refuses the events, like one without this endpoint, would refuse them
+        // again, so they are dropped instead of being sent by every command
+        Err(err) if err.status().is_some_and(|status| status.is_client_error()) => Err(err.into()),
+        Err(err) => {
+            queue(&path, &events)?;
+            Err(err.into())
+        }
+        Ok(_) => Ok(()),
+    }
+}
+
+/// The telemetry of a run of cargo-shuttle
+pub struct Session {
+    command: &'static str,
+    timestamp: String,
+    started: Instant,
+    queue_path: PathBuf,
+    flush: JoinHandle<()>,
+}
+
+impl Session {
+    /// Start sending the events of earlier commands, if telemetry is on
+    pub fn start(command: &'static str, api_url: Option<String>) -> Option<Self> {
+        let mut config: Config<_, GlobalConfig> = Config::new(GlobalConfigManager);
+        if !config.exists() || config.open().is_err() {
+            return None;
+        }
+        let global = config.as_ref()?;
+        if !is_enabled(global.telemetry.unwrap_or_default()) {
+            return None;
+        }
+        let queue_path = queue_path().ok()?;
+        let api_url = api_url
+            .or_else(|| global.api_url())
+            .unwrap_or_else(|| API_URL_DEFAULT.to_string());
+
+        let flush = tokio::spawn({
+            let queue_path = queue_path.clone();
+            async move {
+                if let Err(err) = flush(queue_path, api_url).await {
+                    debug!(error = %err, "failed to send the telemetry events");
+                }
+            }
+        });
+
+        Some(Self {
+            command,
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            started: Instant::now(),
+            queue_path,
+            flush,
+        })
+    }
+
+    /// Queue the event of this command, and give the sending of the earlier ones a moment to
+    /// finish
+    pub async fn finish(self, error: Option<ErrorCode>) {
+        let event = Event {
+            timestamp: self.timestamp,
+            command: self.command.to_string(),
+            duration_ms: self.started.elapsed().as_millis() as u64,
+            error,
+            version: crate::VERSION.to_string(),
+            os: std::env::consts::OS.to_string(),
+        };
+        if let Err(err) = queue(&self.queue_path, &[event]) {
+            debug!(error = %err, "failed to queue the telemetry event");
+        }
+
+        let abort = self.flush.abort_handle();
+        if tokio::time::timeout(FLUSH_TIMEOUT, self.flush)
+            .await
+            .is_err()
+        {
+            debug!("gave up on sending the telemetry events");
+            abort.abort();
+        }
+    }
+}
+
+impl Shuttle {
+    pub(crate) fn telemetry_set(&mut self, enabled: bool) -> Result<CommandOutcome> {
+        self.ctx.set_telemetry(enabled)?;
+        if enabled {
+            println!("Telemetry is on. Thank you for helping to improve Shuttle!");
+            println!(
+                "Only the names of commands, their durations and the kinds of errors are sent."
+            );
+        } else {
+            clear_queue()?;
+            println!("Telemetry is off");
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) fn telemetry_status(&self) -> Result<CommandOutcome> {
+        let configured = self.ctx.telemetry();
+        let enabled = is_enabled(configured);
+        let queued = queued();
+        let status = serde_json::json!({
+            "enabled": enabled,
+            "queued_events": queued,
+        });
+
+        output::print(self.output, &status, |_| {
+            let mut status = if enabled {
+                format!("Telemetry is on, with {queued} events waiting to be sent")
+            } else if configured {
+                "Telemetry is off, because DO_NOT_TRACK is set".to_string()
+            } else {
+                "Telemetry is off. Turn it on with `cargo shuttle telemetry enable`.".to_string()
+            };
+            if let Ok(path) = queue_path() {
+                status.push_str(&format!("\nEvents are queued in {}", path.display()));
+            }
+
+            status
+        })?;
+
+        Ok(CommandOutcome::Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(command: &str) -> Event {
+        Event {
+            timestamp: "2024-03-01T10:00:00.000Z".to_string(),
+            command: command.to_string(),
+            duration_ms: 1200,
+            error: Some(ErrorCode::Network),
+            version: "0.43.0".to_string(),
+            os: "linux".to_string(),
+        }
+    }
+
+    #[test]
+    fn queueing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("telemetry-queue.jsonl");
+        assert!(read_queue(&path).unwrap().is_empty());
+
+        queue(&path, &[event("deploy"), event("logs")]).unwrap();
+        assert_eq!(
+            read_queue(&path).unwrap(),
+            vec![event("deploy"), event("logs")]
+        );
+
+        let many = (0..MAX_QUEUED).map(|_| event("status")).collect::<Vec<_>>();
+        queue(&path, &many).unwrap();
+        let queued = read_queue(&path).unwrap();
+        assert_eq!(queued.len(), MAX_QUEUED);
+        assert!(queued.iter().all(|event| event.command == "status"));
+    }
+
+    #[test]
+    fn do_not_track_values() {
+        assert!(do_not_track(Some("1")));
+        assert!(do_not_track(Some("true")));
+        assert!(!do_not_track(Some("0")));
+        assert!(!do_not_track(Some("")));
+        assert!(!do_not_track(None));
+    }
+}
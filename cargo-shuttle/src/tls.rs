@@ -0,0 +1,299 @@
+//! HTTPS for local runs.
+//!
+//! A local certificate authority is created once and kept in the Shuttle config directory, so
+//! that it only has to be trusted once. It signs a certificate for localhost, which a proxy in
+//! front of each service uses to terminate TLS.
+
+use std::fs;
+use std::io::BufReader;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, Utc};
+use crossterm::style::Stylize;
+use indoc::printdoc;
+use rcgen::{
+    date_time_ymd, BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType,
+    IsCa, KeyPair, KeyUsagePurpose, SanType,
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio_rustls::rustls::{self, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+use tracing::{debug, trace};
+
+use crate::args::RunArgs;
+use crate::Shuttle;
+
+const CA_CERT: &str = "ca.pem";
+const CA_KEY: &str = "ca-key.pem";
+const CERT: &str = "localhost.pem";
+const KEY: &str = "localhost-key.pem";
+
+/// Browsers reject server certificates that are valid for longer than this
+const CERT_DAYS: i64 = 825;
+
+/// Where the local certificate authority and certificates are kept
+pub fn certificates_dir() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir().context(
+        "Could not find a configuration directory. Your operating system may not be supported.",
+    )?;
+
+    Ok(config_dir.join("shuttle").join("tls"))
+}
+
+/// Load the certificate for localhost, creating it (and the authority signing it) if needed
+pub fn server_config(dir: &Path) -> Result<Arc<ServerConfig>> {
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+
+    let ca = match load_ca(dir)? {
+        Some(ca) => ca,
+        None => {
+            let ca = create_ca(dir)?;
+            print_trust_hint(&dir.join(CA_CERT));
+            ca
+        }
+    };
+    if needs_new_cert(&dir.join(CERT)) {
+        create_cert(dir, &ca)?;
+    }
+
+    let mut chain = read_certs(&dir.join(CERT))?;
+    chain.extend(read_certs(&dir.join(CA_CERT))?);
+    let key = read_key(&dir.join(KEY))?;
+
+    let mut config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(chain, key)
+        .context("invalid local certificate")?;
+    // Services are plain HTTP/1.1 behind the proxy
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    Ok(Arc::new(config))
+}
+
+fn load_ca(dir: &Path) -> Result<Option<Certificate>> {
+    let (Ok(cert), Ok(key)) = (
+        fs::read_to_string(dir.join(CA_CERT)),
+        fs::read_to_string(dir.join(CA_KEY)),
+    ) else {
+        return Ok(None);
+    };
+
+    let key = KeyPair::from_pem(&key).context("invalid local certificate authority key")?;
+    let params = CertificateParams::from_ca_cert_pem(&cert, key)
+        .context("invalid local certificate authority")?;
+
+    Ok(Some(Certificate::from_params(params)?))
+}
+
+fn create_ca(dir: &Path) -> Result<Certificate> {
+    trace!("creating a local certificate authority");
+    let mut params = CertificateParams::default();
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+    let mut name = DistinguishedName::new();
+    name.push(DnType::OrganizationName, "Shuttle local development");
+    name.push(DnType::CommonName, "Shuttle local development CA");
+    params.distinguished_name = name;
+
+    let ca = Certificate::from_params(params)?;
+    write_private(&dir.join(CA_KEY), &ca.serialize_private_key_pem())?;
+    fs::write(dir.join(CA_CERT), ca.serialize_pem()?)?;
+
+    Ok(ca)
+}
+
+fn needs_new_cert(path: &Path) -> bool {
+    let age = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+
+    // Renew a little before it expires
+    match age {
+        Some(age) => age > Duration::from_secs((CERT_DAYS as u64 - 30) * 24 * 60 * 60),
+        None => true,
+    }
+}
+
+fn create_cert(dir: &Path, ca: &Certificate) -> Result<()> {
+    trace!("creating a certificate for localhost");
+    let mut params = CertificateParams::new(vec!["localhost".to_string()]);
+    params
+        .subject_alt_names
+        .push(SanType::IpAddress(Ipv4Addr::LOCALHOST.into()));
+    params
+        .subject_alt_names
+        .push(SanType::IpAddress(std::net::Ipv6Addr::LOCALHOST.into()));
+    let mut name = DistinguishedName::new();
+    name.push(DnType::CommonName, "localhost");
+    params.distinguished_name = name;
+    let now = Utc::now();
+    let expiry = now + chrono::Duration::days(CERT_DAYS);
+    params.not_before = date_time_ymd(now.year(), now.month() as u8, now.day() as u8);
+    params.not_after = date_time_ymd(expiry.year(), expiry.month() as u8, expiry.day() as u8);
+
+    let cert = Certificate::from_params(params)?;
+    write_private(&dir.join(KEY), &cert.serialize_private_key_pem())?;
+    fs::write(dir.join(CERT), cert.serialize_pem_with_signer(ca)?)?;
+
+    Ok(())
+}
+
+/// Write a private key that only the user can read
+fn write_private(path: &Path, contents: &str) -> Result<()> {
+    fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+
+    Ok(())
+}
+
+fn read_certs(path: &Path) -> Result<Vec<rustls::Certificate>> {
+    let file =
+        fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))?;
+
+    Ok(certs.into_iter().map(rustls::Certificate).collect())
+}
+
+fn read_key(path: &Path) -> Result<rustls::PrivateKey> {
+    let file =
+        fs::File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let key = rustls_pemfile::pkcs8_private_keys(&mut BufReader::new(file))?
+        .pop()
+        .with_context(|| format!("no private key in {}", path.display()))?;
+
+    Ok(rustls::PrivateKey(key))
+}
+
+fn print_trust_hint(ca_path: &Path) {
+    let ca = ca_path.display();
+    let command = if cfg!(target_os = "macos") {
+        format!("sudo security add-trusted-cert -d -r trustRoot -k /Library/Keychains/System.keychain {ca}")
+    } else if cfg!(target_os = "windows") {
+        format!("certutil -addstore -user Root {ca}")
+    } else {
+        format!("sudo cp {ca} /usr/local/share/ca-certificates/shuttle-local-ca.crt && sudo update-ca-certificates")
+    };
+
+    printdoc! {"
+        {} Created a local certificate authority for HTTPS at {ca}
+        Trust it once, so that browsers accept the local certificates:
+
+            {command}
+
+        ",
+        "Note:".bold(),
+    };
+}
+
+/// Terminates TLS in front of a service, which listens on `backend`. Stops when dropped.
+pub struct TlsProxy {
+    pub backend: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl TlsProxy {
+    /// Listen on `addr`, forwarding to a free port on localhost
+    pub async fn start(addr: SocketAddr, config: Arc<ServerConfig>) -> Result<Self> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("failed to listen on {addr}"))?;
+        let backend = SocketAddr::new(
+            Ipv4Addr::LOCALHOST.into(),
+            portpicker::pick_unused_port().context("failed to find a free port")?,
+        );
+        let acceptor = TlsAcceptor::from(config);
+
+        let task = tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(conn) => conn,
+                    Err(err) => {
+                        debug!(error = %err, "failed to accept a connection");
+                        continue;
+                    }
+                };
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = forward(acceptor, stream, backend).await {
+                        trace!(error = %err, %peer, "https connection ended with an error");
+                    }
+                });
+            }
+        });
+
+        Ok(Self { backend, task })
+    }
+}
+
+impl Drop for TlsProxy {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn forward(acceptor: TlsAcceptor, stream: TcpStream, backend: SocketAddr) -> Result<()> {
+    let mut tls = acceptor.accept(stream).await?;
+    let mut service = TcpStream::connect(backend).await?;
+    tokio::io::copy_bidirectional(&mut tls, &mut service).await?;
+
+    Ok(())
+}
+
+impl Shuttle {
+    /// Start an HTTPS proxy in front of every service with `--tls`
+    pub(crate) async fn start_tls_proxies(
+        run_args: &RunArgs,
+        services_len: usize,
+    ) -> Result<Vec<TlsProxy>> {
+        if !run_args.tls {
+            return Ok(Vec::new());
+        }
+
+        let config = server_config(&certificates_dir()?)?;
+        let mut proxies = Vec::new();
+        for idx in 0..services_len as u16 {
+            let addr = SocketAddr::new(
+                run_args.host.expect("the local address to be resolved"),
+                run_args.port.expect("the local port to be resolved") + idx,
+            );
+            proxies.push(TlsProxy::start(addr, config.clone()).await?);
+        }
+
+        Ok(proxies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn certificates_are_reused() {
+        let dir = tempfile::tempdir().unwrap();
+
+        server_config(dir.path()).unwrap();
+        let ca = fs::read_to_string(dir.path().join(CA_CERT)).unwrap();
+        let cert = fs::read_to_string(dir.path().join(CERT)).unwrap();
+
+        server_config(dir.path()).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join(CA_CERT)).unwrap(), ca);
+        assert_eq!(fs::read_to_string(dir.path().join(CERT)).unwrap(), cert);
+
+        // A missing certificate is signed again by the same authority
+        fs::remove_file(dir.path().join(CERT)).unwrap();
+        server_config(dir.path()).unwrap();
+        assert_eq!(fs::read_to_string(dir.path().join(CA_CERT)).unwrap(), ca);
+        assert_ne!(fs::read_to_string(dir.path().join(CERT)).unwrap(), cert);
+    }
+}
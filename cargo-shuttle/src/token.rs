@@ -0,0 +1,100 @@
+//! Commands for API tokens, the scoped and expiring keys for CI systems and scripts
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use crossterm::style::Stylize;
+use indoc::formatdoc;
+use shuttle_common::models::token::{self, get_tokens_table};
+
+use crate::{output, prompt, CommandOutcome, Shuttle};
+
+impl Shuttle {
+    pub(crate) async fn token_create(
+        &self,
+        name: String,
+        scopes: Vec<token::Scope>,
+        expires: Option<Duration>,
+    ) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let expires_at = expires
+            .map(|expires| {
+                chrono::Duration::from_std(expires)
+                    .ok()
+                    .and_then(|expires| chrono::Utc::now().checked_add_signed(expires))
+                    .ok_or_else(|| anyhow!("the token can not expire that far in the future"))
+            })
+            .transpose()?;
+
+        let created = client
+            .create_token(&token::CreateRequest {
+                name,
+                scopes,
+                expires_at,
+            })
+            .await
+            .context("failed to create the API token")?;
+
+        output::print(self.output, &created, |_| {
+            let expires = match created.token.expires_at {
+                Some(expires_at) => {
+                    format!("expires at {}", expires_at.format("%Y-%m-%dT%H:%M:%SZ"))
+                }
+                None => "never expires".to_string(),
+            };
+            formatdoc! {"
+                Created the API token {} ({}), which {expires}.
+                Its key is only shown this once:
+
+                {}
+                ",
+                created.token.name.clone().bold(),
+                created.token.id,
+                created.key,
+            }
+        })?;
+        output::hint(
+            self.output,
+            "Set it as SHUTTLE_API_KEY in your CI system, and revoke it with `cargo shuttle token revoke`",
+        );
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn tokens_list(&self, raw: bool) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let tokens = client
+            .get_tokens()
+            .await
+            .context("failed to get the API tokens")?;
+
+        output::print(self.output, &tokens, |plain| {
+            get_tokens_table(&tokens, raw || plain)
+        })?;
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    pub(crate) async fn token_revoke(&self, id: &str, no_confirm: bool) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+
+        if !no_confirm
+            && !prompt::confirm(
+                format!("Revoke the API token {id}? Whatever uses it stops working."),
+                false,
+                "Pass --yes to revoke the token.",
+            )?
+        {
+            return Ok(CommandOutcome::Ok);
+        }
+
+        client
+            .revoke_token(id)
+            .await
+            .context("failed to revoke the API token")?;
+
+        println!("Revoked the API token {id}");
+
+        Ok(CommandOutcome::Ok)
+    }
+}
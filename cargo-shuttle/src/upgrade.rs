@@ -0,0 +1,381 @@
+//! Upgrading cargo-shuttle, for `cargo shuttle upgrade`.
+//!
+//! Binaries installed from the release archives are replaced in place, once the archive matches
+//! the checksum published next to it. Installs made by cargo or a package manager are left to
+//! them, and the command that upgrades them is printed instead.
+
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use crossterm::style::Stylize;
+use flate2::read::GzDecoder;
+use semver::Version;
+use serde::Deserialize;
+
+use crate::args::UpgradeArgs;
+use crate::progress::Progress;
+use crate::style::{self, Tone};
+use crate::{CommandOutcome, Shuttle, VERSION};
+
+pub const RELEASES_URL: &str = "https://github.com/shuttle-hq/shuttle/releases";
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/shuttle-hq/shuttle/releases/latest";
+
+/// How cargo-shuttle was installed on this machine
+#[derive(Debug, PartialEq)]
+pub enum Install {
+    /// `cargo install` or `cargo binstall`, which keep track of what they installed
+    Cargo { binstall: bool },
+    /// A system package manager, like pacman or Homebrew
+    PackageManager,
+    /// A binary from the release archives, like the ones of the install script
+    Binary,
+}
+
+impl Install {
+    /// Tell by where the executable is
+    pub fn detect(exe: &Path, cargo_home: Option<&Path>) -> Self {
+        if let Some(cargo_home) = cargo_home {
+            if exe.starts_with(cargo_home.join("bin")) {
+                // The install script puts binaries there too, without telling cargo
+                let installed_by_cargo = fs::read_to_string(cargo_home.join(".crates2.json"))
+                    .is_ok_and(|crates| crates.contains("\"cargo-shuttle "));
+
+                return if installed_by_cargo {
+                    Self::Cargo {
+                        binstall: cargo_home.join("bin").join("cargo-binstall").exists()
+                            || cargo_home.join("bin").join("cargo-binstall.exe").exists(),
+                    }
+                } else {
+                    Self::Binary
+                };
+            }
+        }
+
+        if ["/usr/", "/opt/homebrew/", "/nix/store/", "/home/linuxbrew/"]
+            .iter()
+            .any(|prefix| exe.starts_with(prefix))
+        {
+            Self::PackageManager
+        } else {
+            Self::Binary
+        }
+    }
+
+    /// The command that installs a version, for installs that are not replaced in place
+    pub fn command(&self, version: &Version) -> Option<String> {
+        match self {
+            Self::Cargo { binstall: true } => Some(format!(
+                "cargo binstall -y --locked cargo-shuttle@{version}"
+            )),
+            Self::Cargo { binstall: false } => {
+                Some(format!("cargo install --locked cargo-shuttle@{version}"))
+            }
+            Self::PackageManager | Self::Binary => None,
+        }
+    }
+}
+
+/// The target of the release archive that runs on this machine
+pub fn target() -> Option<&'static str> {
+    match (std::env::consts::OS, std::env::consts::ARCH) {
+        ("linux", "x86_64") => Some("x86_64-unknown-linux-musl"),
+        ("linux", "aarch64") => Some("aarch64-unknown-linux-musl"),
+        ("macos", "x86_64") => Some("x86_64-apple-darwin"),
+        ("windows", "x86_64") => Some("x86_64-pc-windows-msvc"),
+        _ => None,
+    }
+}
+
+/// `v0.43.0` as well as `0.43.0`
+pub fn parse_version(version: &str) -> Result<Version, semver::Error> {
+    version.trim_start_matches('v').parse()
+}
+
+pub async fn latest_version(client: &reqwest::Client) -> Result<Version> {
+    #[derive(Deserialize)]
+    struct Release {
+        tag_name: String,
+    }
+
+    let release: Release = client
+        .get(LATEST_RELEASE_URL)
+        // GitHub turns away requests without one
+        .header("User-Agent", "cargo-shuttle")
+        .send()
+        .await
+        .context("failed to look up the latest release")?
+        .error_for_status()
+        .context("failed to look up the latest release")?
+        .json()
+        .await
+        .context("failed to parse the latest release")?;
+
+    parse_version(&release.tag_name).with_context(|| {
+        format!(
+            "the latest release has an invalid tag: {}",
+            release.tag_name
+        )
+    })
+}
+
+pub fn archive_url(version: &Version, target: &str) -> String {
+    format!("{RELEASES_URL}/download/v{version}/cargo-shuttle-v{version}-{target}.tar.gz")
+}
+
+/// Check an archive against its `.sha256` file, which is in the format of `sha256sum`
+pub fn verify_checksum(archive: &[u8], checksum_file: &str) -> Result<()> {
+    let expected = checksum_file
+        .split_whitespace()
+        .next()
+        .context("the checksum file is empty")?
+        .to_ascii_lowercase();
+    let actual = ring::digest::digest(&ring::digest::SHA256, archive)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+
+    if expected != actual {
+        bail!("the checksum of the downloaded archive does not match, expected {expected} but got {actual}");
+    }
+
+    Ok(())
+}
+
+/// The executable in a release archive
+pub fn extract_binary(archive: &[u8]) -> Result<Vec<u8>> {
+    let mut entries = tar::Archive::new(GzDecoder::new(archive));
+    for entry in entries.entries().context("failed to read the archive")? {
+        let mut entry = entry.context("failed to read the archive")?;
+        let is_binary = entry
+            .path()?
+            .file_name()
+            .is_some_and(|name| name == "cargo-shuttle" || name == "cargo-shuttle.exe");
+        if is_binary {
+            let mut binary = Vec::new();
+            entry.read_to_end(&mut binary)?;
+
+            return Ok(binary);
+        }
+    }
+
+    bail!("the archive does not have a cargo-shuttle executable")
+}
+
+/// Put a new executable in the place of the running one. The new one is written next to it
+/// first, so that a failure part way through leaves the old one working.
+pub fn replace_exe(exe: &Path, binary: &[u8]) -> Result<()> {
+    let new = with_suffix(exe, ".new");
+    fs::write(&new, binary).with_context(|| format!("failed to write {}", new.display()))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&new, fs::Permissions::from_mode(0o755))?;
+    }
+    // Windows does not allow replacing a running executable, but does allow renaming it
+    #[cfg(windows)]
+    {
+        let old = with_suffix(exe, ".old");
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old).with_context(|| format!("failed to move {}", exe.display()))?;
+    }
+
+    fs::rename(&new, exe).with_context(|| format!("failed to replace {}", exe.display()))
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+
+    path.with_file_name(name)
+}
+
+impl Shuttle {
+    pub(crate) async fn upgrade(&self, args: UpgradeArgs, offline: bool) -> Result<CommandOutcome> {
+        if offline {
+            bail!("Upgrading needs network access, run it without --offline");
+        }
+
+        let current = parse_version(VERSION)?;
+        let client = self.ctx.http_client();
+        let version = match args.version {
+            Some(version) => version,
+            None => {
+                let latest = latest_version(&client).await?;
+                if latest <= current {
+                    println!("cargo-shuttle {current} is the latest version");
+                    return Ok(CommandOutcome::Ok);
+                }
+
+                latest
+            }
+        };
+        if version == current {
+            println!("cargo-shuttle {current} is already installed");
+            return Ok(CommandOutcome::Ok);
+        }
+
+        let exe = std::env::current_exe().context("failed to find the cargo-shuttle executable")?;
+        let exe = dunce::canonicalize(&exe).unwrap_or(exe);
+        let install = Install::detect(&exe, home::cargo_home().ok().as_deref());
+        if let Some(command) = install.command(&version) {
+            println!("cargo-shuttle was installed with cargo. Install {version} with:");
+            println!();
+            println!("{}", command.bold());
+
+            return Ok(CommandOutcome::Ok);
+        }
+        if install == Install::PackageManager {
+            println!(
+                "cargo-shuttle at {} was installed by a package manager. \
+                Upgrade it with the package manager, or find {version} at {}",
+                exe.display(),
+                RELEASES_URL
+            );
+
+            return Ok(CommandOutcome::Ok);
+        }
+
+        let Some(target) = target() else {
+            bail!(
+                "There are no release binaries for this platform. \
+                Install {version} with `cargo install --locked cargo-shuttle@{version}`"
+            );
+        };
+        let url = archive_url(&version, target);
+        let download = |url: String| {
+            let client = client.clone();
+            async move {
+                client
+                    .get(&url)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .with_context(|| format!("failed to download {url}"))?
+                    .bytes()
+                    .await
+                    .with_context(|| format!("failed to download {url}"))
+            }
+        };
+
+        let downloading = Progress::spinner(format!("Downloading cargo-shuttle {version}"));
+        let archive = download(url.clone()).await?;
+        let checksum = download(format!("{url}.sha256")).await?;
+        downloading.finish_and_clear();
+
+        verify_checksum(&archive, &String::from_utf8_lossy(&checksum))?;
+        let binary = extract_binary(&archive)?;
+        replace_exe(&exe, &binary)?;
+
+        println!(
+            "{} cargo-shuttle {current} to {version}",
+            if version > current {
+                style::status("Upgraded", Tone::Success)
+            } else {
+                style::status("Downgraded", Tone::Success)
+            }
+        );
+
+        Ok(CommandOutcome::Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+
+    #[test]
+    fn detect_install() {
+        let dir = tempfile::tempdir().unwrap();
+        let cargo_home = dir.path().join(".cargo");
+        let exe = cargo_home.join("bin").join("cargo-shuttle");
+        fs::create_dir_all(cargo_home.join("bin")).unwrap();
+
+        // Put there by the install script
+        assert_eq!(Install::detect(&exe, Some(&cargo_home)), Install::Binary);
+
+        fs::write(
+            cargo_home.join(".crates2.json"),
+            r#"{"installs":{"cargo-shuttle 0.43.0 (registry+https://github.com/rust-lang/crates.io-index)":{}}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            Install::detect(&exe, Some(&cargo_home)),
+            Install::Cargo { binstall: false }
+        );
+        fs::write(cargo_home.join("bin").join("cargo-binstall"), "").unwrap();
+        let install = Install::detect(&exe, Some(&cargo_home));
+        assert_eq!(install, Install::Cargo { binstall: true });
+        assert_eq!(
+            install.command(&Version::new(0, 44, 0)).unwrap(),
+            "cargo binstall -y --locked cargo-shuttle@0.44.0"
+        );
+
+        assert_eq!(
+            Install::detect(Path::new("/usr/bin/cargo-shuttle"), Some(&cargo_home)),
+            Install::PackageManager
+        );
+        assert_eq!(
+            Install::detect(Path::new("/home/me/bin/cargo-shuttle"), None),
+            Install::Binary
+        );
+    }
+
+    #[test]
+    fn release_archives() {
+        assert_eq!(parse_version("v0.43.0").unwrap(), Version::new(0, 43, 0));
+        assert_eq!(
+            archive_url(&Version::new(0, 43, 0), "x86_64-apple-darwin"),
+            "https://github.com/shuttle-hq/shuttle/releases/download/v0.43.0/cargo-shuttle-v0.43.0-x86_64-apple-darwin.tar.gz"
+        );
+
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::fast()));
+        for (path, contents) in [
+            (
+                "cargo-shuttle-x86_64-apple-darwin-v0.43.0/README.md",
+                "readme",
+            ),
+            (
+                "cargo-shuttle-x86_64-apple-darwin-v0.43.0/cargo-shuttle",
+                "binary",
+            ),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, contents.as_bytes())
+                .unwrap();
+        }
+        let archive = builder.into_inner().unwrap().finish().unwrap();
+        assert_eq!(extract_binary(&archive).unwrap(), b"binary");
+
+        let checksum = ring::digest::digest(&ring::digest::SHA256, &archive)
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect::<String>();
+        verify_checksum(
+            &archive,
+            &format!("{checksum}  cargo-shuttle-v0.43.0-x86_64-apple-darwin.tar.gz\n"),
+        )
+        .unwrap();
+        assert!(verify_checksum(b"tampered", &checksum).is_err());
+    }
+
+    #[test]
+    fn replaces_exe() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = dir.path().join("cargo-shuttle");
+        fs::write(&exe, "old").unwrap();
+
+        replace_exe(&exe, b"new").unwrap();
+        assert_eq!(fs::read(&exe).unwrap(), b"new");
+        assert!(!dir.path().join("cargo-shuttle.new").exists());
+    }
+}
@@ -0,0 +1,585 @@
+//! Upgrading the Shuttle crates of a project, for `cargo shuttle upgrade-deps`.
+//!
+//! The Shuttle crates are released together, so all of them are moved to the version of
+//! shuttle-runtime that deployments support. The edits keep the formatting and the comments of the
+//! manifests. Crates that are no longer released are pointed out instead, with what replaced them.
+
+use std::fs::read_to_string;
+
+use anyhow::{bail, Context, Result};
+use crossterm::style::Stylize;
+use semver::Version;
+use toml_edit::{Document, Item, TableLike, Value};
+use tracing::debug;
+
+use crate::args::UpgradeDepsArgs;
+use crate::style::{self, Tone};
+use crate::{output, prompt, upgrade, workspace, CommandOutcome, Shuttle, VERSION};
+
+pub const CHANGELOG_URL: &str =
+    "https://raw.githubusercontent.com/shuttle-hq/shuttle/main/CHANGELOG.md";
+
+/// The crates that are released together with shuttle-runtime
+const CRATES: &[&str] = &[
+    "shuttle-runtime",
+    "shuttle-service",
+    "shuttle-actix-web",
+    "shuttle-axum",
+    "shuttle-poem",
+    "shuttle-rocket",
+    "shuttle-salvo",
+    "shuttle-serenity",
+    "shuttle-thruster",
+    "shuttle-tide",
+    "shuttle-tower",
+    "shuttle-warp",
+    "shuttle-aws-rds",
+    "shuttle-opendal",
+    "shuttle-persist",
+    "shuttle-qdrant",
+    "shuttle-shared-db",
+    "shuttle-turso",
+];
+
+/// A crate that stopped being released
+#[derive(Debug, PartialEq)]
+pub struct Retired {
+    pub name: &'static str,
+    /// The first version without it
+    pub since: Version,
+    pub instead: &'static str,
+}
+
+const RETIRED: &[Retired] = &[
+    Retired {
+        name: "shuttle-static-folder",
+        since: Version::new(0, 29, 0),
+        instead: "List the folder under `assets` in Shuttle.toml",
+    },
+    Retired {
+        name: "shuttle-poise",
+        since: Version::new(0, 37, 0),
+        instead: "Use shuttle-serenity, which supports poise",
+    },
+    Retired {
+        name: "shuttle-secrets",
+        since: Version::new(0, 43, 0),
+        instead: "Use `#[shuttle_runtime::Secrets] secrets: shuttle_runtime::SecretStore`",
+    },
+    Retired {
+        name: "shuttle-metadata",
+        since: Version::new(0, 43, 0),
+        instead: "Use `#[shuttle_runtime::Metadata] metadata: shuttle_runtime::DeploymentMetadata`",
+    },
+];
+
+const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// A dependency that was moved to the new version
+#[derive(Debug, PartialEq)]
+pub struct Upgrade {
+    pub name: String,
+    /// The requirement as it was written, like `0.42.0`
+    pub from: String,
+    pub to: String,
+}
+
+/// What upgrading a manifest changed, and what it could not
+#[derive(Debug, Default, PartialEq)]
+pub struct ManifestChanges {
+    pub upgrades: Vec<Upgrade>,
+    pub retired: Vec<&'static Retired>,
+}
+
+impl ManifestChanges {
+    pub fn is_empty(&self) -> bool {
+        self.upgrades.is_empty() && self.retired.is_empty()
+    }
+}
+
+/// The oldest version a requirement like `0.42`, `^0.42.0` or `=0.42.0` allows, for the
+/// requirements that are a single version
+pub fn minimum_version(requirement: &str) -> Option<Version> {
+    let version = requirement
+        .trim()
+        .trim_start_matches(['^', '=', '~'])
+        .trim();
+    if version.contains([',', '*', '<', '>']) {
+        return None;
+    }
+
+    let parts = version.split('.').count();
+    let padded = match parts {
+        1 => format!("{version}.0.0"),
+        2 => format!("{version}.0"),
+        _ => version.to_string(),
+    };
+
+    padded.parse().ok()
+}
+
+/// Move the Shuttle crates of a manifest to `to`, in all the dependency tables, including the
+/// ones of `[workspace.dependencies]` and of targets
+pub fn upgrade_manifest(document: &mut Document, to: &Version) -> ManifestChanges {
+    let mut changes = ManifestChanges::default();
+
+    let mut tables: Vec<&mut dyn TableLike> = Vec::new();
+    let root = document.as_table_mut();
+    let (mut workspace, mut target, mut rest) = (None, None, Vec::new());
+    for (key, item) in root.iter_mut() {
+        match key.get() {
+            "workspace" => workspace = item.as_table_like_mut(),
+            "target" => target = item.as_table_like_mut(),
+            key if DEPENDENCY_TABLES.contains(&key) => rest.extend(item.as_table_like_mut()),
+            _ => {}
+        }
+    }
+    tables.extend(rest);
+    if let Some(dependencies) = workspace
+        .and_then(|workspace| workspace.get_mut("dependencies"))
+        .and_then(Item::as_table_like_mut)
+    {
+        tables.push(dependencies);
+    }
+    if let Some(target) = target {
+        for (_, platform) in target.iter_mut() {
+            let Some(platform) = platform.as_table_like_mut() else {
+                continue;
+            };
+            for (key, item) in platform.iter_mut() {
+                if DEPENDENCY_TABLES.contains(&key.get()) {
+                    tables.extend(item.as_table_like_mut());
+                }
+            }
+        }
+    }
+
+    for table in tables {
+        for (name, dependency) in table.iter_mut() {
+            let name = name.get();
+            if let Some(retired) = RETIRED
+                .iter()
+                .find(|retired| retired.name == name && *to >= retired.since)
+            {
+                changes.retired.push(retired);
+                continue;
+            }
+            if !CRATES.contains(&name) {
+                continue;
+            }
+            if let Some(upgrade) = upgrade_dependency(name, dependency, to) {
+                changes.upgrades.push(upgrade);
+            }
+        }
+    }
+
+    changes
+}
+
+fn upgrade_dependency(name: &str, dependency: &mut Item, to: &Version) -> Option<Upgrade> {
+    let version = match dependency {
+        Item::Value(Value::String(_)) => dependency.as_value_mut()?,
+        _ => {
+            let dependency = dependency.as_table_like_mut()?;
+            // Local checkouts and forks are left to whoever set them up, and inherited versions
+            // are upgraded in the workspace
+            if ["path", "git", "workspace"]
+                .iter()
+                .any(|key| dependency.contains_key(key))
+            {
+                return None;
+            }
+            dependency.get_mut("version")?.as_value_mut()?
+        }
+    };
+
+    let from = version.as_str()?.to_string();
+    if minimum_version(&from)? >= *to {
+        return None;
+    }
+    // Pinned versions stay pinned
+    let new = match from.trim_start().starts_with('=') {
+        true => format!("={to}"),
+        false => to.to_string(),
+    };
+    let decor = version.decor().clone();
+    *version = new.as_str().into();
+    *version.decor_mut() = decor;
+
+    Some(Upgrade {
+        name: name.to_string(),
+        from,
+        to: new,
+    })
+}
+
+/// The notable changes of a release, from the changelog
+#[derive(Debug, PartialEq)]
+pub struct Release {
+    pub version: Version,
+    pub breaking: Vec<String>,
+    pub features: Vec<String>,
+}
+
+/// The breaking changes and the features of the releases after `from`, up to `to`, from the
+/// changelog made by git-cliff
+pub fn highlights(changelog: &str, from: &Version, to: &Version) -> Vec<Release> {
+    let mut releases = Vec::new();
+    let mut current: Option<Release> = None;
+    let mut in_features = false;
+
+    for line in changelog.lines() {
+        if let Some(heading) = line.strip_prefix("## [") {
+            releases.extend(current.take());
+            in_features = false;
+            current = heading
+                .split_once(']')
+                .and_then(|(version, _)| version.parse::<Version>().ok())
+                .filter(|version| version > from && version <= to)
+                .map(|version| Release {
+                    version,
+                    breaking: Vec::new(),
+                    features: Vec::new(),
+                });
+        } else if let Some(section) = line.strip_prefix("### ") {
+            in_features = section.trim() == "Features";
+        } else if let (Some(release), Some(entry)) = (current.as_mut(), line.strip_prefix("- ")) {
+            let breaking = entry.contains("[**breaking**] ");
+            let entry = clean_entry(entry);
+            if breaking {
+                release.breaking.push(entry);
+            } else if in_features {
+                release.features.push(entry);
+            }
+        }
+    }
+    releases.extend(current);
+
+    releases.retain(|release| !release.breaking.is_empty() || !release.features.is_empty());
+    releases
+}
+
+/// An entry without the breaking marker and the links to the issue and the commit, and with its
+/// scope like `*(runtime)* ` as `runtime: `
+fn clean_entry(entry: &str) -> String {
+    let entry = entry.replace("[**breaking**] ", "");
+    let end = entry
+        .find(" ([#")
+        .or_else(|| entry.find(" - (["))
+        .unwrap_or(entry.len());
+    let entry = entry[..end].trim();
+
+    match entry
+        .strip_prefix("*(")
+        .and_then(|entry| entry.split_once(")* "))
+    {
+        Some((scope, entry)) => format!("{scope}: {entry}"),
+        None => entry.to_string(),
+    }
+}
+
+impl Shuttle {
+    pub(crate) async fn upgrade_deps(
+        &self,
+        args: UpgradeDepsArgs,
+        offline: bool,
+    ) -> Result<CommandOutcome> {
+        let to = match args.version {
+            Some(version) => version,
+            // The crates are released together with cargo-shuttle
+            None if offline => upgrade::parse_version(VERSION)?,
+            None => {
+                self.ctx
+                    .api_client()
+                    .get_api_versions()
+                    .await
+                    .context("failed to get the supported version, pick one with --version")?
+                    .runtime
+            }
+        };
+
+        let metadata = workspace::metadata(self.ctx.working_directory())?;
+        let mut manifests = vec![metadata
+            .workspace_root
+            .join("Cargo.toml")
+            .into_std_path_buf()];
+        for package in metadata.workspace_packages() {
+            let manifest = package.manifest_path.clone().into_std_path_buf();
+            if !manifests.contains(&manifest) {
+                manifests.push(manifest);
+            }
+        }
+
+        let mut edited = Vec::new();
+        for manifest in manifests {
+            let mut document = read_to_string(&manifest)
+                .with_context(|| format!("failed to read {}", manifest.display()))?
+                .parse::<toml_edit::Document>()
+                .with_context(|| format!("failed to parse {}", manifest.display()))?;
+            let changes = upgrade_manifest(&mut document, &to);
+            if !changes.is_empty() {
+                edited.push((manifest, document, changes));
+            }
+        }
+        if edited.is_empty() {
+            println!(
+                "The Shuttle crates of {} are on {to}",
+                self.ctx.project_name()
+            );
+            return Ok(CommandOutcome::Ok);
+        }
+
+        for (manifest, _, changes) in &edited {
+            println!("{}", manifest.display().to_string().bold());
+            for upgrade in &changes.upgrades {
+                println!("  {} {} -> {}", upgrade.name, upgrade.from, upgrade.to);
+            }
+            for retired in &changes.retired {
+                println!(
+                    "  {}",
+                    format!(
+                        "{} is not released since {}. {}",
+                        retired.name, retired.since, retired.instead
+                    )
+                    .yellow()
+                );
+            }
+        }
+        println!();
+
+        let from = edited
+            .iter()
+            .flat_map(|(_, _, changes)| &changes.upgrades)
+            .filter_map(|upgrade| minimum_version(&upgrade.from))
+            .min();
+        if let (Some(from), false) = (from, offline) {
+            self.print_changelog_highlights(&from, &to).await;
+        }
+
+        if args.dry_run
+            || edited
+                .iter()
+                .all(|(_, _, changes)| changes.upgrades.is_empty())
+        {
+            return Ok(CommandOutcome::Ok);
+        }
+        if !args.confirmation.yes
+            && !prompt::confirm(
+                "Apply these changes?",
+                true,
+                "Pass --yes to apply the changes.",
+            )?
+        {
+            return Ok(CommandOutcome::Ok);
+        }
+
+        for (manifest, document, changes) in &edited {
+            if changes.upgrades.is_empty() {
+                continue;
+            }
+            std::fs::write(manifest, document.to_string())
+                .with_context(|| format!("failed to write {}", manifest.display()))?;
+        }
+        println!(
+            "{} the Shuttle crates to {to}",
+            style::status("Upgraded", Tone::Success)
+        );
+
+        if args.check {
+            println!("{} the project", style::status("Checking", Tone::Success));
+            let status = tokio::process::Command::new("cargo")
+                .arg("check")
+                .current_dir(self.ctx.working_directory())
+                .status()
+                .await
+                .context("failed to run `cargo check`")?;
+            if !status.success() {
+                bail!(
+                    "`cargo check` failed after the upgrade. \
+                    The breaking changes above and the release notes at {} can help fixing it.",
+                    upgrade::RELEASES_URL
+                );
+            }
+        } else {
+            output::hint(
+                self.output,
+                "Run `cargo check` to find what the upgrade broke, if anything",
+            );
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    /// The breaking changes and the features of the Shuttle releases after `from`, up to `to`
+    pub(crate) async fn print_changelog_highlights(
+        &self,
+        from: &semver::Version,
+        to: &semver::Version,
+    ) {
+        /// Features shown for each release, to keep the summary short
+        const MAX_FEATURES: usize = 5;
+
+        let changelog = self
+            .ctx
+            .http_client()
+            .get(CHANGELOG_URL)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        let changelog = match changelog {
+            Ok(response) => response.text().await,
+            Err(err) => Err(err),
+        };
+        let changelog = match changelog {
+            Ok(changelog) => changelog,
+            Err(err) => {
+                debug!(error = %err, "failed to get the changelog");
+                println!(
+                    "See what changed since {from} in the release notes at {}",
+                    upgrade::RELEASES_URL
+                );
+                println!();
+                return;
+            }
+        };
+
+        for release in highlights(&changelog, from, to) {
+            println!("{}", format!("What changed in {}", release.version).bold());
+            for breaking in &release.breaking {
+                println!("  {} {breaking}", "Breaking:".red().bold());
+            }
+            for feature in release.features.iter().take(MAX_FEATURES) {
+                println!("  - {feature}");
+            }
+            if release.features.len() > MAX_FEATURES {
+                println!(
+                    "  {}",
+                    format!("and {} more", release.features.len() - MAX_FEATURES).dim()
+                );
+            }
+            println!();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requirements() {
+        assert_eq!(minimum_version("0.42"), Some(Version::new(0, 42, 0)));
+        assert_eq!(minimum_version("^0.42.1"), Some(Version::new(0, 42, 1)));
+        assert_eq!(minimum_version("= 0.42.0"), Some(Version::new(0, 42, 0)));
+        assert_eq!(minimum_version(">=0.40, <0.43"), None);
+        assert_eq!(minimum_version("*"), None);
+    }
+
+    #[test]
+    fn upgrades_manifests() {
+        let mut document = r#"
+[package]
+name = "my-app"
+
+[dependencies]
+axum = "0.7"
+shuttle-axum = "0.42.0" # the web framework
+shuttle-runtime = { version = "=0.41", default-features = false }
+shuttle-secrets = "0.42.0"
+shuttle-shared-db = { path = "../shuttle/resources/shared-db" }
+shuttle-turso = "0.43.0"
+shuttle-community-crate = "0.1.0"
+
+[dev-dependencies.shuttle-service]
+version = "0.40.0"
+
+[target.'cfg(unix)'.dependencies]
+shuttle-persist = "0.42"
+
+[workspace.dependencies]
+shuttle-qdrant = "0.39.0"
+"#
+        .parse::<Document>()
+        .unwrap();
+
+        let changes = upgrade_manifest(&mut document, &Version::new(0, 43, 0));
+        let upgraded = changes
+            .upgrades
+            .iter()
+            .map(|upgrade| (upgrade.name.as_str(), upgrade.to.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            upgraded,
+            [
+                ("shuttle-axum", "0.43.0"),
+                ("shuttle-runtime", "=0.43.0"),
+                ("shuttle-service", "0.43.0"),
+                ("shuttle-qdrant", "0.43.0"),
+                ("shuttle-persist", "0.43.0"),
+            ]
+        );
+        assert_eq!(changes.retired.len(), 1);
+        assert_eq!(changes.retired[0].name, "shuttle-secrets");
+
+        let manifest = document.to_string();
+        assert!(manifest.contains("shuttle-axum = \"0.43.0\" # the web framework\n"));
+        assert!(manifest
+            .contains("shuttle-runtime = { version = \"=0.43.0\", default-features = false }"));
+        assert!(
+            manifest.contains("shuttle-shared-db = { path = \"../shuttle/resources/shared-db\" }")
+        );
+        assert!(manifest.contains("shuttle-community-crate = \"0.1.0\""));
+        assert!(manifest.contains("[dev-dependencies.shuttle-service]\nversion = \"0.43.0\"\n"));
+
+        // Nothing left to do the second time
+        assert!(upgrade_manifest(&mut document, &Version::new(0, 43, 0))
+            .upgrades
+            .is_empty());
+    }
+
+    #[test]
+    fn changelog_highlights() {
+        let changelog = "\
+# Changelog
+
+## [0.43.0](https://github.com/shuttle-hq/shuttle/compare/v0.42.0..0.43.0) - 2024-04-02
+
+### Features
+
+- *(cargo-shuttle)* --debug ([#1689](https://github.com/shuttle-hq/shuttle/issues/1689)) - ([38510fb](https://github.com/shuttle-hq/shuttle/commit/38510fb))
+
+### Miscellaneous Tasks
+
+- Remove secrets, metadata crates ([#1688](https://github.com/shuttle-hq/shuttle/issues/1688)) - ([4ab5f08](https://github.com/shuttle-hq/shuttle/commit/4ab5f08))
+
+## [0.42.0](https://github.com/shuttle-hq/shuttle/compare/v0.41.0..v0.42.0) - 2024-03-18
+
+### Bug Fixes
+
+- *(runtime)* [**breaking**] Extract load phase - ([ba57785](https://github.com/shuttle-hq/shuttle/commit/ba57785))
+
+## [0.41.0](https://github.com/shuttle-hq/shuttle/compare/v0.40.0..v0.41.0) - 2024-03-07
+
+### Features
+
+- Too old to show - ([1234567](https://github.com/shuttle-hq/shuttle/commit/1234567))
+";
+
+        let releases = highlights(changelog, &Version::new(0, 41, 0), &Version::new(0, 43, 0));
+        assert_eq!(
+            releases,
+            [
+                Release {
+                    version: Version::new(0, 43, 0),
+                    breaking: Vec::new(),
+                    features: vec!["cargo-shuttle: --debug".to_string()],
+                },
+                Release {
+                    version: Version::new(0, 42, 0),
+                    breaking: vec!["runtime: Extract load phase".to_string()],
+                    features: Vec::new(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,230 @@
+//! Watching a project for file changes, for `cargo shuttle run --watch`
+
+use std::net::SocketAddr;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use crossterm::style::Stylize;
+use ignore::gitignore::Gitignore;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher as _};
+use shuttle_proto::runtime;
+use tokio::process::Child;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tracing::debug;
+
+use crate::args::RunArgs;
+use crate::local::LocalRun;
+use crate::style::{self, Tone};
+use crate::{CommandOutcome, Shuttle};
+
+/// How long no more changes have to come in before a batch of changes is reported. Saving
+/// several files at once, or an editor writing a file in steps, then causes a single rebuild.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Directories that change during builds and runs, rather than by editing the project
+const IGNORED_DIRS: [&str; 5] = [
+    "target",
+    ".git",
+    ".shuttle",
+    ".shuttle-storage",
+    ".shuttle-executables",
+];
+
+pub struct Watcher {
+    /// Stops watching when dropped
+    _watcher: RecommendedWatcher,
+    changes: UnboundedReceiver<PathBuf>,
+}
+
+impl Watcher {
+    /// Watch everything under `root`, except for the files ignored by its .gitignore
+    pub fn new(root: &Path) -> Result<Self> {
+        let (gitignore, err) = Gitignore::new(root.join(".gitignore"));
+        if let Some(err) = err {
+            debug!(error = %err, "failed to read .gitignore for watching");
+        }
+
+        let (tx, changes) = unbounded_channel();
+        let watched_root = root.to_path_buf();
+        let mut watcher =
+            notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+                Ok(event) if !event.kind.is_access() => {
+                    for path in event.paths {
+                        if is_relevant(&watched_root, &path, &gitignore) {
+                            // Only fails once the receiving watcher is dropped
+                            let _ = tx.send(path);
+                        }
+                    }
+                }
+                Ok(_) => {}
+                Err(err) => debug!(error = %err, "file watcher error"),
+            })
+            .context("failed to start watching for file changes")?;
+        watcher
+            .watch(root, RecursiveMode::Recursive)
+            .with_context(|| format!("failed to watch {}", root.display()))?;
+
+        Ok(Self {
+            _watcher: watcher,
+            changes,
+        })
+    }
+
+    /// Wait for the next batch of changed files
+    pub async fn changed(&mut self) -> Result<Vec<PathBuf>> {
+        next_batch(&mut self.changes, DEBOUNCE)
+            .await
+            .context("the file watcher stopped unexpectedly")
+    }
+}
+
+/// Whether a change to `path` should cause a rebuild
+fn is_relevant(root: &Path, path: &Path, gitignore: &Gitignore) -> bool {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    if relative.components().any(|component| {
+        matches!(component, Component::Normal(name) if IGNORED_DIRS.iter().any(|dir| name == *dir))
+    }) {
+        return false;
+    }
+
+    // Backup and swap files of editors
+    let file_name = relative
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    if file_name.ends_with('~')
+        || file_name.starts_with(".#")
+        || file_name.ends_with(".swp")
+        || file_name.ends_with(".swx")
+        || file_name == "4913"
+    {
+        return false;
+    }
+
+    !gitignore
+        .matched_path_or_any_parents(relative, path.is_dir())
+        .is_ignore()
+}
+
+/// Wait for a change, then collect the changes that follow it until none came in for
+/// `debounce`. Returns `None` when the sender is gone.
+async fn next_batch(
+    changes: &mut UnboundedReceiver<PathBuf>,
+    debounce: Duration,
+) -> Option<Vec<PathBuf>> {
+    let mut batch = vec![changes.recv().await?];
+    while let Ok(Some(path)) = tokio::time::timeout(debounce, changes.recv()).await {
+        if !batch.contains(&path) {
+            batch.push(path);
+        }
+    }
+
+    Some(batch)
+}
+
+impl Shuttle {
+    /// Rebuild and restart the services whenever files in the project change, until ctrl-c.
+    /// Local resources like database containers stay up between restarts.
+    pub(crate) async fn watch_local_run(
+        &self,
+        run_args: &RunArgs,
+        mut runtimes: Vec<(Child, runtime::Client)>,
+        local_run: &LocalRun,
+        proxy_backends: &[SocketAddr],
+    ) -> Result<CommandOutcome> {
+        let working_directory = self.ctx.working_directory();
+        let mut watcher = Watcher::new(working_directory)?;
+
+        loop {
+            println!("{}", "Watching for changes...".dim());
+            let changed = tokio::select! {
+                changed = watcher.changed() => changed?,
+                _ = tokio::signal::ctrl_c() => {
+                    println!("cargo-shuttle received ctrl-c. Stopping all the runtimes...");
+                    Shuttle::stop_runtimes(&mut runtimes, run_args.grace_period).await;
+                    return Ok(CommandOutcome::Ok);
+                }
+            };
+
+            let first = changed[0]
+                .strip_prefix(working_directory)
+                .unwrap_or(&changed[0]);
+            let others = match changed.len() - 1 {
+                0 => String::new(),
+                n => format!(" and {n} more"),
+            };
+            println!(
+                "{} {}{others}, restarting",
+                style::status("Changed", Tone::Warning),
+                first.display()
+            );
+            Shuttle::stop_runtimes(&mut runtimes, run_args.grace_period).await;
+
+            let services = match self.pre_local_run(run_args, local_run).await {
+                Ok(services) => services,
+                Err(err) => {
+                    println!("{}", format!("Build failed: {err:?}").red());
+                    continue;
+                }
+            };
+            let local_services = Shuttle::local_services(run_args, &services, proxy_backends)?;
+            for batch in Shuttle::start_batches(&services, run_args.all) {
+                for res in
+                    Shuttle::spin_local_runtimes(run_args, local_run, &local_services, batch).await
+                {
+                    match res {
+                        Ok(Some(runtime)) => runtimes.push(runtime),
+                        // The reason was already logged
+                        Ok(None) => {}
+                        Err(err) => println!("Runtime error: {err:?}"),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ignore::gitignore::GitignoreBuilder;
+
+    use super::*;
+
+    #[test]
+    fn relevant_paths() {
+        let root = Path::new("/project");
+        let mut builder = GitignoreBuilder::new(root);
+        builder.add_line(None, "*.log").unwrap();
+        let gitignore = builder.build().unwrap();
+        let relevant = |path: &str| is_relevant(root, &root.join(path), &gitignore);
+
+        assert!(relevant("src/main.rs"));
+        assert!(relevant("Cargo.toml"));
+        assert!(relevant("static/index.html"));
+        assert!(!relevant("target/debug/app"));
+        assert!(!relevant(".git/index"));
+        assert!(!relevant(".shuttle/logs/run-1.jsonl"));
+        assert!(!relevant("src/.main.rs.swp"));
+        assert!(!relevant("src/main.rs~"));
+        assert!(!relevant("debug.log"));
+    }
+
+    #[tokio::test]
+    async fn debouncing() {
+        let (tx, mut rx) = unbounded_channel();
+        tx.send(PathBuf::from("a.rs")).unwrap();
+        tx.send(PathBuf::from("b.rs")).unwrap();
+        tx.send(PathBuf::from("a.rs")).unwrap();
+
+        let batch = next_batch(&mut rx, Duration::from_millis(20))
+            .await
+            .unwrap();
+        assert_eq!(batch, [PathBuf::from("a.rs"), PathBuf::from("b.rs")]);
+
+        drop(tx);
+        assert!(next_batch(&mut rx, Duration::from_millis(20))
+            .await
+            .is_none());
+    }
+}
@@ -0,0 +1,181 @@
+//! Cached `cargo metadata`, which commands run to find the workspace and the name of the project.
+//!
+//! Running it can take seconds in big workspaces, so its results are kept for the rest of the
+//! process, and in the cache directory for the next commands. They are used until the lockfile or
+//! one of the manifests of the workspace changes. `--no-cache` leaves out the ones on disk.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use cargo_metadata::{Metadata, MetadataCommand};
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, trace};
+
+use crate::config::{ConfigManager, GlobalConfigManager};
+
+/// Results by working directory, for the rest of the process
+static CACHE: Mutex<BTreeMap<PathBuf, Cached>> = Mutex::new(BTreeMap::new());
+static DISK_CACHE: AtomicBool = AtomicBool::new(true);
+
+/// When a file the metadata depends on was last modified, if it exists
+type Stamp = (PathBuf, Option<SystemTime>);
+
+#[derive(Clone)]
+struct Cached {
+    stamps: Vec<Stamp>,
+    metadata: Arc<Metadata>,
+}
+
+/// How the metadata of a working directory is stored on disk
+#[derive(Deserialize, Serialize)]
+struct Stored {
+    stamps: Vec<Stamp>,
+    metadata: Metadata,
+}
+
+/// Keep the results on disk or not, for `--no-cache`
+pub fn set_disk_cache(enabled: bool) {
+    DISK_CACHE.store(enabled, Ordering::Relaxed);
+}
+
+/// The output of `cargo metadata` in `working_directory`
+pub fn metadata(working_directory: &Path) -> Result<Arc<Metadata>> {
+    let key =
+        dunce::canonicalize(working_directory).unwrap_or_else(|_| working_directory.to_path_buf());
+
+    if let Some(cached) = CACHE.lock().unwrap().get(&key).cloned() {
+        if is_fresh(&cached.stamps) {
+            return Ok(cached.metadata);
+        }
+    }
+    let disk_cache = DISK_CACHE.load(Ordering::Relaxed);
+    if let Some(cached) = disk_cache.then(|| load(&key)).flatten() {
+        trace!(working_directory = %key.display(), "using cached cargo metadata");
+        CACHE.lock().unwrap().insert(key, cached.clone());
+        return Ok(cached.metadata);
+    }
+
+    // NOTE: If crates cache is missing this blocks for several seconds during download
+    let metadata = MetadataCommand::new()
+        .current_dir(working_directory)
+        .exec()
+        .context("failed to get cargo metadata")?;
+    let cached = Cached {
+        stamps: stamps(&metadata),
+        metadata: Arc::new(metadata),
+    };
+    if disk_cache {
+        store(&key, &cached);
+    }
+    CACHE.lock().unwrap().insert(key, cached.clone());
+
+    Ok(cached.metadata)
+}
+
+/// The files that change the metadata when they change: the lockfile and the manifests
+fn stamps(metadata: &Metadata) -> Vec<Stamp> {
+    let root = metadata.workspace_root.as_std_path();
+    let mut files = vec![root.join("Cargo.toml"), root.join("Cargo.lock")];
+    for package in metadata.workspace_packages() {
+        let manifest = package.manifest_path.clone().into_std_path_buf();
+        if !files.contains(&manifest) {
+            files.push(manifest);
+        }
+    }
+
+    files
+        .into_iter()
+        .map(|file| {
+            let modified = modified(&file);
+            (file, modified)
+        })
+        .collect()
+}
+
+fn modified(file: &Path) -> Option<SystemTime> {
+    fs::metadata(file).and_then(|file| file.modified()).ok()
+}
+
+fn is_fresh(stamps: &[Stamp]) -> bool {
+    stamps
+        .iter()
+        .all(|(file, modified)| self::modified(file) == *modified)
+}
+
+fn cache_file(key: &Path) -> PathBuf {
+    let name = digest(&SHA256, key.to_string_lossy().as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+
+    GlobalConfigManager
+        .directory()
+        .join("cache")
+        .join("metadata")
+        .join(format!("{name}.json"))
+}
+
+fn load(key: &Path) -> Option<Cached> {
+    let stored = fs::read(cache_file(key)).ok()?;
+    let stored: Stored = serde_json::from_slice(&stored).ok()?;
+
+    is_fresh(&stored.stamps).then(|| Cached {
+        stamps: stored.stamps,
+        metadata: Arc::new(stored.metadata),
+    })
+}
+
+/// Store the metadata on disk. It only saves time, so failing to is not an error.
+fn store(key: &Path, cached: &Cached) {
+    let file = cache_file(key);
+    let stored = serde_json::to_vec(&Stored {
+        stamps: cached.stamps.clone(),
+        metadata: Metadata::clone(&cached.metadata),
+    })
+    .expect("to serialize cargo metadata");
+    let written = fs::create_dir_all(file.parent().expect("the cache file to be in a directory"))
+        .and_then(|_| fs::write(&file, stored));
+    if let Err(err) = written {
+        debug!(error = %err, "failed to store cargo metadata in the cache");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn freshness() {
+        let dir = tempfile::tempdir().unwrap();
+        let lockfile = dir.path().join("Cargo.lock");
+        fs::write(&lockfile, "").unwrap();
+
+        let stamps = vec![
+            (lockfile.clone(), modified(&lockfile)),
+            (dir.path().join("Cargo.toml"), None),
+        ];
+        assert!(is_fresh(&stamps));
+
+        // Changed since
+        let stamps = vec![(lockfile, Some(SystemTime::UNIX_EPOCH))];
+        assert!(!is_fresh(&stamps));
+    }
+
+    #[test]
+    fn reuses_metadata() {
+        set_disk_cache(false);
+        let working_directory = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap())
+            .join("../examples/axum/hello-world");
+
+        let first = metadata(&working_directory).unwrap();
+        let second = metadata(&working_directory).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+    }
+}
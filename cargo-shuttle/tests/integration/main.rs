@@ -1,8 +1,16 @@
 mod init;
 mod run;
 
-use cargo_shuttle::{Command, CommandOutcome, ProjectArgs, Shuttle, ShuttleArgs};
+use cargo_shuttle::{Command, CommandOutcome, ProjectArgs, Shuttle, ShuttleArgs, StatusArgs};
 use std::path::Path;
+use std::time::Duration;
+
+fn status() -> Command {
+    Command::Status(StatusArgs {
+        watch: false,
+        interval: Duration::from_secs(5),
+    })
+}
 
 /// creates a `cargo-shuttle` run instance with some reasonable defaults set.
 async fn cargo_shuttle_command(
@@ -21,7 +29,14 @@ async fn cargo_shuttle_command(
                     name: None,
                 },
                 offline: false,
+                force: false,
+                no_cache: false,
                 debug: false,
+                verbose: 0,
+                quiet: false,
+                output: Default::default(),
+                color: Default::default(),
+                arg_origins: Default::default(),
                 cmd,
             },
             false,
@@ -32,7 +47,7 @@ async fn cargo_shuttle_command(
 #[tokio::test]
 #[should_panic(expected = "failed to start `cargo metadata`: No such file or directory")]
 async fn fails_if_working_directory_does_not_exist() {
-    cargo_shuttle_command(Command::Status, "/path_that_does_not_exist")
+    cargo_shuttle_command(status(), "/path_that_does_not_exist")
         .await
         .unwrap();
 }
@@ -40,5 +55,5 @@ async fn fails_if_working_directory_does_not_exist() {
 #[tokio::test]
 #[should_panic(expected = "could not find `Cargo.toml` in `/` or any parent directory")]
 async fn fails_if_working_directory_not_part_of_cargo_workspace() {
-    cargo_shuttle_command(Command::Status, "/").await.unwrap();
+    cargo_shuttle_command(status(), "/").await.unwrap();
 }
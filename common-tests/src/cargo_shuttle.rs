@@ -27,11 +27,27 @@ pub async fn cargo_shuttle_run(working_directory: &str, external: bool) -> Strin
     };
 
     let run_args = RunArgs {
-        port,
+        port: Some(port),
+        host: None,
         external,
         release: false,
         raw: false,
+        watch: false,
+        all: false,
+        tls: false,
+        restart_on_crash: false,
+        health_check: None,
+        debugger: false,
+        wait_for_debugger: false,
+        env_vars: Vec::new(),
+        container: false,
+        record: None,
+        replay: None,
+        grace_period: Duration::from_secs(10),
+        reseed: false,
+        build_args: Default::default(),
         secret_args: Default::default(),
+        no_secrets: false,
     };
 
     let runner = Shuttle::new().unwrap().run(
@@ -42,7 +58,14 @@ pub async fn cargo_shuttle_run(working_directory: &str, external: bool) -> Strin
                 name: None,
             },
             offline: false,
+            force: false,
+            no_cache: false,
             debug: false,
+            verbose: 0,
+            quiet: false,
+            output: Default::default(),
+            color: Default::default(),
+            arg_origins: Default::default(),
             cmd: Command::Run(run_args),
         },
         false,
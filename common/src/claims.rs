@@ -7,7 +7,7 @@ use std::{
 
 use axum::extract::FromRequestParts;
 use bytes::Bytes;
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use headers::{Authorization, HeaderMapExt};
 use http::{request::Parts, Request, StatusCode};
 use http_body::combinators::UnsyncBoxBody;
@@ -242,6 +242,9 @@ pub struct Claim {
     pub limits: Limits,
     /// The account tier of the subject.
     pub tier: AccountTier,
+    /// The only projects this token can act on, for API tokens scoped to projects.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub projects: Option<Vec<String>>,
 }
 
 impl Claim {
@@ -265,9 +268,31 @@ impl Claim {
             token: None,
             limits: limits.into(),
             tier,
+            projects: None,
         }
     }
 
+    /// Limit the claim to `projects`, and to expire no later than `expires_at`.
+    pub fn restricted(
+        mut self,
+        projects: Option<Vec<String>>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        self.projects = projects;
+        if let Some(expires_at) = expires_at {
+            self.exp = self.exp.min(expires_at.timestamp().max(0) as usize);
+        }
+
+        self
+    }
+
+    /// Whether the claim can act on the project called `project_name`.
+    pub fn allows_project(&self, project_name: &str) -> bool {
+        self.projects
+            .as_ref()
+            .map_or(true, |projects| projects.iter().any(|p| p == project_name))
+    }
+
     pub fn into_token(self, encoding_key: &EncodingKey) -> Result<String, StatusCode> {
         if let Some(token) = self.token {
             Ok(token)
@@ -4,6 +4,12 @@
 pub const EXECUTABLE_DIRNAME: &str = ".shuttle-executables";
 /// Where general files will persist across deploys, relative to workspace root. Used by plugins.
 pub const STORAGE_DIRNAME: &str = ".shuttle-storage";
+/// Env var that moves the files of [`STORAGE_DIRNAME`] elsewhere, set by local runs with a
+/// configured data directory
+pub const STORAGE_PATH_ENV: &str = "SHUTTLE_STORAGE_PATH";
+/// Where cargo-shuttle keeps local state of a project, like the logs of local runs, relative to
+/// workspace root
+pub const LOCAL_STATE_DIRNAME: &str = ".shuttle";
 
 // URLs
 pub const API_URL_LOCAL: &str = "http://localhost:8001";
@@ -14,6 +20,7 @@ pub const API_URL_DEFAULT: &str = API_URL_LOCAL;
 pub const API_URL_DEFAULT: &str = API_URL_PRODUCTION;
 
 pub const SHUTTLE_STATUS_URL: &str = "https://status.shuttle.rs";
+pub const SHUTTLE_CONSOLE_URL: &str = "https://console.shuttle.rs";
 pub const SHUTTLE_LOGIN_URL: &str = "https://console.shuttle.rs/new-project";
 pub const SHUTTLE_GH_ISSUE_URL: &str = "https://github.com/shuttle-hq/shuttle/issues/new/choose";
 pub const SHUTTLE_INSTALL_DOCS_URL: &str = "https://docs.shuttle.rs/getting-started/installation";
@@ -135,6 +135,10 @@ impl DatabaseInfo {
             hostname_public,
         }
     }
+    pub fn database_name(&self) -> &str {
+        &self.database_name
+    }
+
     /// For connecting to the db from inside the Shuttle network
     pub fn connection_string_shuttle(&self) -> String {
         format!(
@@ -193,12 +197,48 @@ pub struct VersionInfo {
     pub gateway: semver::Version,
     /// Latest version of cargo-shuttle compatible with this gateway.
     pub cargo_shuttle: semver::Version,
+    /// Oldest version of cargo-shuttle this gateway supports. Gateways that do not send it support
+    /// the versions that are semver compatible with `cargo_shuttle`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cargo_shuttle_min: Option<semver::Version>,
     /// Latest version of shuttle-deployer compatible with this gateway.
     pub deployer: semver::Version,
     /// Latest version of shuttle-runtime compatible with the above deployer.
     pub runtime: semver::Version,
 }
 
+/// Where a version of cargo-shuttle stands with a gateway
+#[derive(Debug, PartialEq)]
+pub enum CliCompatibility {
+    /// The latest version, or a newer one that is compatible with it
+    Latest,
+    /// Supported, but a newer version exists
+    Outdated,
+    /// Older than the gateway supports
+    TooOld,
+    /// Newer than the gateway supports, like during a release
+    TooNew,
+}
+
+impl VersionInfo {
+    pub fn cli_compatibility(&self, cli: &semver::Version) -> CliCompatibility {
+        let latest = &self.cargo_shuttle;
+        let compatible = semvers_are_compatible(cli, latest);
+
+        if self.cargo_shuttle_min.as_ref().is_some_and(|min| cli < min)
+            || (cli < latest && !compatible)
+        {
+            CliCompatibility::TooOld
+        } else if cli > latest && !compatible {
+            CliCompatibility::TooNew
+        } else if cli < latest {
+            CliCompatibility::Outdated
+        } else {
+            CliCompatibility::Latest
+        }
+    }
+}
+
 /// Check if two versions are compatible based on the rule used by cargo:
 /// "Versions `a` and `b` are compatible if their left-most nonzero digit is the same."
 pub fn semvers_are_compatible(a: &semver::Version, b: &semver::Version) -> bool {
@@ -245,6 +285,45 @@ mod tests {
         ApiKey::parse("dh9z58jttoes3qv@").unwrap();
     }
 
+    #[test]
+    fn cli_compatibility() {
+        let version = |v: &str| semver::Version::from_str(v).unwrap();
+        let mut info = VersionInfo {
+            gateway: version("0.43.0"),
+            cargo_shuttle: version("0.43.2"),
+            cargo_shuttle_min: None,
+            deployer: version("0.43.0"),
+            runtime: version("0.43.0"),
+        };
+
+        assert_eq!(
+            info.cli_compatibility(&version("0.43.2")),
+            CliCompatibility::Latest
+        );
+        assert_eq!(
+            info.cli_compatibility(&version("0.43.3")),
+            CliCompatibility::Latest
+        );
+        assert_eq!(
+            info.cli_compatibility(&version("0.43.0")),
+            CliCompatibility::Outdated
+        );
+        assert_eq!(
+            info.cli_compatibility(&version("0.42.0")),
+            CliCompatibility::TooOld
+        );
+        assert_eq!(
+            info.cli_compatibility(&version("0.44.0")),
+            CliCompatibility::TooNew
+        );
+
+        info.cargo_shuttle_min = Some(version("0.43.1"));
+        assert_eq!(
+            info.cli_compatibility(&version("0.43.0")),
+            CliCompatibility::TooOld
+        );
+    }
+
     #[test]
     fn semver_compatibility_check_works() {
         let semver_tests = &[
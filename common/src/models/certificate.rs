@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+use comfy_table::{
+    modifiers::UTF8_ROUND_CORNERS,
+    presets::{NOTHING, UTF8_FULL},
+    Attribute, Cell, CellAlignment, Color, ContentArrangement, Table,
+};
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+
+/// The certificate of a custom domain of a project
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Response {
+    pub domain: String,
+    pub issued_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub renewal: RenewalState,
+}
+
+/// Where a certificate is in its renewal cycle
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, strum::Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum RenewalState {
+    /// Not close enough to its expiry to be renewed
+    Valid,
+    /// Within the days before its expiry in which it gets renewed
+    Due,
+    /// Expired without being renewed
+    Expired,
+}
+
+impl RenewalState {
+    fn color(&self) -> Color {
+        match self {
+            Self::Valid => Color::Green,
+            Self::Due => Color::Yellow,
+            Self::Expired => Color::Red,
+        }
+    }
+}
+
+pub fn get_certificates_table(certificates: &[Response], project_name: &str, raw: bool) -> String {
+    if certificates.is_empty() {
+        let s = format!("{project_name} has no custom domains with certificates\n");
+        if raw {
+            return s;
+        }
+
+        return s.yellow().bold().to_string();
+    }
+
+    let mut table = Table::new();
+
+    if raw {
+        table
+            .load_preset(NOTHING)
+            .set_content_arrangement(ContentArrangement::Disabled)
+            .set_header(vec![
+                Cell::new("Domain").set_alignment(CellAlignment::Left),
+                Cell::new("Issued").set_alignment(CellAlignment::Left),
+                Cell::new("Expires").set_alignment(CellAlignment::Left),
+                Cell::new("Renewal").set_alignment(CellAlignment::Left),
+            ]);
+    } else {
+        table
+            .load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+            .set_header(vec![
+                Cell::new("Domain")
+                    .set_alignment(CellAlignment::Center)
+                    .add_attribute(Attribute::Bold),
+                Cell::new("Issued")
+                    .set_alignment(CellAlignment::Center)
+                    .add_attribute(Attribute::Bold),
+                Cell::new("Expires")
+                    .set_alignment(CellAlignment::Center)
+                    .add_attribute(Attribute::Bold),
+                Cell::new("Renewal")
+                    .set_alignment(CellAlignment::Center)
+                    .add_attribute(Attribute::Bold),
+            ]);
+    }
+
+    for certificate in certificates {
+        let renewal = Cell::new(certificate.renewal);
+        table.add_row(vec![
+            Cell::new(&certificate.domain),
+            Cell::new(certificate.issued_at.format("%Y-%m-%dT%H:%M:%SZ")),
+            Cell::new(certificate.expires_at.format("%Y-%m-%dT%H:%M:%SZ")),
+            if raw {
+                renewal
+            } else {
+                renewal.fg(certificate.renewal.color())
+            },
+        ]);
+    }
+
+    format!("These are the certificates of the custom domains of {project_name}\n{table}\n")
+}
@@ -6,10 +6,14 @@ use comfy_table::{
 };
 use crossterm::style::Stylize;
 use serde::{Deserialize, Serialize};
-use std::{fmt::Display, str::FromStr};
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
 use uuid::Uuid;
 
 use crate::deployment::State;
+use crate::{
+    database::{self, SharedEngine},
+    resource,
+};
 
 /// Max length of strings in the git metadata
 pub const GIT_STRINGS_MAX_LENGTH: usize = 80;
@@ -17,7 +21,7 @@ pub const GIT_STRINGS_MAX_LENGTH: usize = 80;
 pub const CREATE_SERVICE_BODY_LIMIT: usize = 50_000_000;
 const GIT_OPTION_NONE_TEXT: &str = "N/A";
 
-#[derive(Deserialize, Serialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Response {
     pub id: Uuid,
     pub service_id: String,
@@ -208,4 +212,515 @@ pub struct DeploymentRequest {
     pub git_commit_msg: Option<String>,
     pub git_branch: Option<String>,
     pub git_dirty: Option<bool>,
+    /// Plain environment variables to set for the service
+    #[serde(default)]
+    pub env_vars: BTreeMap<String, String>,
+    /// Where to ship the runtime logs of the service, besides the Shuttle logger
+    #[serde(default)]
+    pub log_forwarding: Option<LogForwarding>,
+    /// Settings to provision the databases of the service with
+    #[serde(default)]
+    pub resource_overrides: Option<ResourceOverrides>,
+    /// Resources outside of Shuttle to hand to the service
+    #[serde(default)]
+    pub external_resources: Option<ExternalResources>,
+    /// Custom domains to serve the service on, and to redirect from
+    #[serde(default)]
+    pub domains: Option<Domains>,
+    /// Jobs to run on a schedule
+    #[serde(default)]
+    pub cron: Option<CronJobs>,
+}
+
+/// Settings of the databases of a service, by engine, as configured in the `[resources.*]`
+/// tables of Shuttle.toml. Deployers don't apply them yet, and refuse deploys that set them.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceOverrides {
+    pub postgres: Option<DatabaseOverrides>,
+    pub mysql: Option<DatabaseOverrides>,
+    pub mariadb: Option<DatabaseOverrides>,
+    pub mongodb: Option<DatabaseOverrides>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DatabaseOverrides {
+    /// Version of the engine, like `16` or `8.0`
+    pub version: Option<String>,
+    pub tier: Option<DatabaseTier>,
+    /// Extensions to create in the database, like `vector`. Postgres only.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// How many days backups are kept for
+    pub backup_retention_days: Option<u32>,
+}
+
+/// Size of the instance a database runs on
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, strum::Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum DatabaseTier {
+    Micro,
+    Small,
+    Medium,
+    Large,
+}
+
+impl ResourceOverrides {
+    /// The settings by engine name
+    pub fn engines(&self) -> Vec<(&'static str, &DatabaseOverrides)> {
+        [
+            ("postgres", &self.postgres),
+            ("mysql", &self.mysql),
+            ("mariadb", &self.mariadb),
+            ("mongodb", &self.mongodb),
+        ]
+        .into_iter()
+        .filter_map(|(engine, overrides)| overrides.as_ref().map(|overrides| (engine, overrides)))
+        .collect()
+    }
+
+    /// Check the settings before they are sent with a deployment
+    pub fn validate(&self) -> Result<(), String> {
+        for (engine, overrides) in self.engines() {
+            overrides
+                .validate(engine)
+                .map_err(|err| format!("[resources.{engine}]: {err}"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl DatabaseOverrides {
+    /// Days that backups can be kept for
+    const BACKUP_RETENTION_DAYS: std::ops::RangeInclusive<u32> = 1..=35;
+
+    fn validate(&self, engine: &str) -> Result<(), String> {
+        if let Some(version) = &self.version {
+            let supported: &[&str] = match engine {
+                "postgres" => &["13", "14", "15", "16"],
+                "mysql" => &["8.0"],
+                "mariadb" => &["10.6", "10.11", "11.4"],
+                "mongodb" => &["5.0", "6.0", "7.0"],
+                _ => &[],
+            };
+            if !supported.contains(&version.as_str()) {
+                return Err(format!(
+                    "version {version} is not supported, use one of {}",
+                    supported.join(", ")
+                ));
+            }
+        }
+
+        if !self.extensions.is_empty() && engine != "postgres" {
+            return Err("only Postgres databases have extensions".to_string());
+        }
+        if let Some(name) = self.extensions.iter().find(|name| {
+            name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        }) {
+            return Err(format!("'{name}' is not a valid extension name"));
+        }
+
+        if let Some(days) = self.backup_retention_days {
+            if !Self::BACKUP_RETENTION_DAYS.contains(&days) {
+                return Err(format!(
+                    "backups can be kept for {} to {} days, not {days}",
+                    Self::BACKUP_RETENTION_DAYS.start(),
+                    Self::BACKUP_RETENTION_DAYS.end()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Resources that live outside of Shuttle, like an existing RDS instance or a third party API, by
+/// name, as declared in the `[external.*]` tables of Shuttle.toml
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(transparent)]
+pub struct ExternalResources(pub BTreeMap<String, ExternalResource>);
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalResource {
+    /// Database type it stands in for, like `postgres` or `database::aws_rds::mysql`. Resource
+    /// annotations of that type are handed its connection string instead of a Shuttle database.
+    #[serde(rename = "type")]
+    pub r#type: Option<String>,
+    /// Name of the secret holding the connection string or URL
+    pub secret: String,
+    /// Environment variable the connection string is set in. `<NAME>_URL` by default.
+    pub env: Option<String>,
+}
+
+impl ExternalResources {
+    /// The resource that stands in for a type of database, if there is one
+    pub fn for_database(&self, db_type: database::Type) -> Option<(&str, &ExternalResource)> {
+        self.0
+            .iter()
+            .find(|(_, resource)| resource.database_type() == Ok(Some(db_type)))
+            .map(|(name, resource)| (name.as_str(), resource))
+    }
+
+    /// The environment variables set to the connection strings, with the secrets they are read
+    /// from
+    pub fn env_vars(&self) -> Vec<(String, &str)> {
+        self.0
+            .iter()
+            .map(|(name, resource)| (resource.env_var(name), resource.secret.as_str()))
+            .collect()
+    }
+
+    /// Check the definitions before they are used
+    pub fn validate(&self) -> Result<(), String> {
+        let mut stood_in_for = Vec::new();
+        for (name, resource) in &self.0 {
+            let err = |err: String| format!("[external.{name}]: {err}");
+
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(err(
+                    "names can only have letters, digits, - and _".to_string()
+                ));
+            }
+            if resource.secret.is_empty() {
+                return Err(err("`secret` cannot be empty".to_string()));
+            }
+            let env_var = resource.env_var(name);
+            let mut chars = env_var.chars();
+            if !chars
+                .next()
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+            {
+                return Err(err(format!(
+                    "'{env_var}' is not a valid environment variable name"
+                )));
+            }
+
+            if let Some(db_type) = resource.database_type().map_err(err)? {
+                if stood_in_for.contains(&db_type) {
+                    return Err(err(format!(
+                        "another external resource already stands in for {db_type}"
+                    )));
+                }
+                stood_in_for.push(db_type);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ExternalResource {
+    pub fn database_type(&self) -> Result<Option<database::Type>, String> {
+        let Some(r#type) = &self.r#type else {
+            return Ok(None);
+        };
+        let db_type = match r#type.as_str() {
+            "postgres" => database::Type::Shared(SharedEngine::Postgres),
+            "mongodb" => database::Type::Shared(SharedEngine::MongoDb),
+            _ => match r#type.parse::<resource::Type>()? {
+                resource::Type::Database(db_type) => db_type,
+                _ => return Err(format!("'{}' is not a database type", r#type)),
+            },
+        };
+
+        Ok(Some(db_type))
+    }
+
+    pub fn env_var(&self, name: &str) -> String {
+        self.env
+            .clone()
+            .unwrap_or_else(|| format!("{}_URL", name.to_uppercase().replace('-', "_")))
+    }
+}
+
+/// The custom domains of a service, as configured in the `[domains]` table of Shuttle.toml.
+/// Deployers don't sync them to the gateway yet, and refuse deploys that set them.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Domains {
+    /// Domains the service is served on, besides its default one
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Domains that permanently redirect to one of the aliases, like
+    /// `"example.com" = "www.example.com"`
+    #[serde(default)]
+    pub redirects: BTreeMap<String, String>,
+}
+
+impl Domains {
+    /// Every domain the service answers on, served or redirected
+    pub fn all(&self) -> impl Iterator<Item = &str> {
+        self.aliases
+            .iter()
+            .chain(self.redirects.keys())
+            .map(String::as_str)
+    }
+
+    /// Check the domains before they are used
+    pub fn validate(&self) -> Result<(), String> {
+        let mut seen = Vec::new();
+        for domain in self.all() {
+            if !is_valid_domain(domain) {
+                return Err(format!("'{domain}' is not a valid domain name"));
+            }
+            if seen.contains(&domain) {
+                return Err(format!(
+                    "'{domain}' is given more than once. A domain is either an alias or redirects."
+                ));
+            }
+            seen.push(domain);
+        }
+        for (from, to) in &self.redirects {
+            if !self.aliases.contains(to) {
+                return Err(format!(
+                    "'{from}' redirects to '{to}', which is not one of the `aliases`"
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A lowercase domain name of at least two labels, like `www.example.com`
+fn is_valid_domain(domain: &str) -> bool {
+    let labels = domain.split('.').collect::<Vec<_>>();
+
+    domain.len() <= 253
+        && labels.len() >= 2
+        && labels.iter().all(|label| {
+            (1..=63).contains(&label.len())
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        })
+}
+
+/// The scheduled jobs of a service by name, as configured in the `[cron.<name>]` tables of
+/// Shuttle.toml. Deployers don't schedule them yet, and refuse deploys that set them.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(transparent)]
+pub struct CronJobs(pub BTreeMap<String, CronJob>);
+
+/// A job that runs on a schedule, by calling an endpoint of the service or running a command in
+/// its container
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CronJob {
+    /// When the job runs, as a cron expression in UTC, like `0 3 * * *`
+    pub schedule: String,
+    /// Path of the service to send a POST request to, like `/tasks/cleanup`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Command to run in the container of the service
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+}
+
+impl CronJobs {
+    /// Check the names, schedules and targets of the jobs before they are used
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, job) in &self.0 {
+            validate_cron_job_name(name)?;
+            job.validate()
+                .map_err(|err| format!("job '{name}': {err}"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CronJob {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_cron_schedule(&self.schedule)?;
+        match (&self.path, &self.command) {
+            (Some(path), None) if !path.starts_with('/') => {
+                Err(format!("the path '{path}' must start with '/'"))
+            }
+            (Some(_), None) => Ok(()),
+            (None, Some(command)) if command.trim().is_empty() => {
+                Err("the command is empty".to_string())
+            }
+            (None, Some(_)) => Ok(()),
+            _ => Err("set either a `path` to call or a `command` to run".to_string()),
+        }
+    }
+
+    /// What the job does, for listings
+    pub fn target(&self) -> String {
+        match (&self.path, &self.command) {
+            (Some(path), _) => format!("POST {path}"),
+            (None, Some(command)) => format!("run `{command}`"),
+            (None, None) => String::new(),
+        }
+    }
+}
+
+/// Names of jobs are lowercase letters, digits and dashes, like `nightly-cleanup`
+pub fn validate_cron_job_name(name: &str) -> Result<(), String> {
+    let valid = (1..=63).contains(&name.len())
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "'{name}' is not a valid job name. Use lowercase letters, digits and dashes."
+        ))
+    }
+}
+
+/// Check a cron expression of five fields (minute, hour, day of month, month and day of week),
+/// or one of the `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly` shorthands
+pub fn validate_cron_schedule(schedule: &str) -> Result<(), String> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    const DAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+    let schedule = schedule.trim();
+    if schedule.starts_with('@') {
+        return match schedule {
+            "@hourly" | "@daily" | "@midnight" | "@weekly" | "@monthly" | "@yearly"
+            | "@annually" => Ok(()),
+            _ => Err(format!("'{schedule}' is not a cron shorthand")),
+        };
+    }
+
+    let fields = schedule.split_whitespace().collect::<Vec<_>>();
+    if fields.len() != 5 {
+        return Err(format!(
+            "'{schedule}' has {} fields instead of five: minute, hour, day of month, month and day of week",
+            fields.len()
+        ));
+    }
+
+    let specs: [(&str, u32, u32, &[&str]); 5] = [
+        ("minute", 0, 59, &[]),
+        ("hour", 0, 23, &[]),
+        ("day of month", 1, 31, &[]),
+        ("month", 1, 12, &MONTHS),
+        // 7 is Sunday too
+        ("day of week", 0, 7, &DAYS),
+    ];
+    for (field, (name, min, max, names)) in fields.iter().zip(specs) {
+        let value = |value: &str| -> Result<u32, String> {
+            let lowercase = value.to_ascii_lowercase();
+            let number = match names.iter().position(|name| *name == lowercase) {
+                Some(idx) => idx as u32 + min,
+                None => value
+                    .parse::<u32>()
+                    .map_err(|_| format!("'{value}' is not a valid {name}"))?,
+            };
+            if (min..=max).contains(&number) {
+                Ok(number)
+            } else {
+                Err(format!("the {name} {value} is not between {min} and {max}"))
+            }
+        };
+
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, Some(step)),
+                None => (part, None),
+            };
+            if let Some(step) = step {
+                if !step.parse::<u32>().is_ok_and(|step| step > 0) {
+                    return Err(format!("'{step}' is not a valid step in '{field}'"));
+                }
+            }
+            match range.split_once('-') {
+                _ if range == "*" => {}
+                Some((start, end)) => {
+                    if value(start)? > value(end)? {
+                        return Err(format!("the range '{range}' ends before it starts"));
+                    }
+                }
+                None => {
+                    value(range)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// An external endpoint the runtime logs of a service are forwarded to, as configured in the
+/// `[log-forwarding]` table of Shuttle.toml
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct LogForwarding {
+    #[serde(rename = "type")]
+    pub kind: LogForwardingKind,
+    /// URL to send the logs to. For Loki and OTLP this is the base URL of the server.
+    pub endpoint: String,
+    /// Name of the secret holding the API key or token for the endpoint
+    #[serde(default)]
+    pub api_key_secret: Option<String>,
+    /// Labels (Loki), tags (Datadog) or resource attributes (OTLP) added to every line
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, strum::Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum LogForwardingKind {
+    Loki,
+    Datadog,
+    /// Batches of log items as JSON, posted to any HTTP endpoint
+    Http,
+    /// OpenTelemetry logs over OTLP/HTTP with JSON encoding
+    Otlp,
+}
+
+impl LogForwarding {
+    /// Check the configuration without contacting the endpoint
+    pub fn validate(&self) -> Result<(), String> {
+        let url = url::Url::parse(&self.endpoint)
+            .map_err(|err| format!("invalid endpoint '{}': {err}", self.endpoint))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!(
+                "the endpoint '{}' must be an http or https URL",
+                self.endpoint
+            ));
+        }
+
+        if self.kind == LogForwardingKind::Datadog && self.api_key_secret.is_none() {
+            return Err("forwarding to Datadog needs an `api-key-secret`".to_string());
+        }
+
+        if self.kind == LogForwardingKind::Loki {
+            // Loki label names follow the Prometheus data model
+            let invalid = self.labels.keys().find(|name| {
+                let mut chars = name.chars();
+                !chars
+                    .next()
+                    .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                    || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+            });
+            if let Some(name) = invalid {
+                return Err(format!("'{name}' is not a valid Loki label name"));
+            }
+        }
+
+        Ok(())
+    }
 }
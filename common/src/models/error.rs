@@ -112,6 +112,23 @@ impl From<ProjectNotFound> for ApiError {
     }
 }
 
+#[derive(Debug, Error)]
+#[error("Deployments of project '{project_name}' are {lock}. Run `cargo shuttle project unlock` to allow deployments again.")]
+pub struct ProjectLocked {
+    pub project_name: String,
+    /// Who locked the project, when and why
+    pub lock: String,
+}
+
+impl From<ProjectLocked> for ApiError {
+    fn from(err: ProjectLocked) -> Self {
+        Self {
+            message: err.to_string(),
+            status_code: StatusCode::LOCKED.as_u16(),
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 #[error("Could not automatically delete the following resources: {0:?}. Please reach out to Shuttle support for help.")]
 pub struct ProjectHasResources(pub Vec<String>);
@@ -161,3 +178,17 @@ impl From<InvalidCustomDomain> for ApiError {
         Self::bad_request(err)
     }
 }
+
+#[derive(Debug, Error)]
+#[error("{domain} does not point at Shuttle: {reason}. Add a CNAME record for it with the value {target}, or A records with the addresses of {target}, wait for the change to propagate, and try again.")]
+pub struct CustomDomainDnsMisconfigured {
+    pub domain: String,
+    pub reason: String,
+    pub target: String,
+}
+
+impl From<CustomDomainDnsMisconfigured> for ApiError {
+    fn from(err: CustomDomainDnsMisconfigured) -> Self {
+        Self::bad_request(err)
+    }
+}
@@ -1,11 +1,14 @@
 pub mod admin;
+pub mod certificate;
 pub mod deployment;
 pub mod error;
 pub mod organization;
 pub mod project;
 pub mod resource;
+pub mod secret;
 pub mod service;
 pub mod stats;
+pub mod token;
 pub mod user;
 
 use anyhow::{Context, Result};
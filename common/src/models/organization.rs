@@ -1,3 +1,9 @@
+use comfy_table::{
+    modifiers::UTF8_ROUND_CORNERS,
+    presets::{NOTHING, UTF8_FULL},
+    Attribute, Cell, CellAlignment, ContentArrangement, Table,
+};
+use crossterm::style::Stylize;
 use serde::{Deserialize, Serialize};
 use strum::{Display, EnumString};
 
@@ -16,6 +22,16 @@ pub struct Response {
     pub is_admin: bool,
 }
 
+/// An organization with the names of its projects
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct ProjectsResponse {
+    #[serde(flatten)]
+    pub organization: Response,
+
+    /// Names of the projects owned by the organization
+    pub projects: Vec<String>,
+}
+
 /// Member of an organization
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct MemberResponse {
@@ -34,3 +50,80 @@ pub enum MemberRole {
     Admin,
     Member,
 }
+
+fn table(raw: bool, header: &[&str]) -> Table {
+    let mut table = Table::new();
+
+    if raw {
+        table
+            .load_preset(NOTHING)
+            .set_content_arrangement(ContentArrangement::Disabled)
+            .set_header(
+                header
+                    .iter()
+                    .map(|title| Cell::new(title).set_alignment(CellAlignment::Left)),
+            );
+    } else {
+        table
+            .load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+            .set_header(header.iter().map(|title| {
+                Cell::new(title)
+                    .set_alignment(CellAlignment::Center)
+                    .add_attribute(Attribute::Bold)
+            }));
+    }
+
+    table
+}
+
+pub fn get_organizations_table(organizations: &[ProjectsResponse], raw: bool) -> String {
+    if organizations.is_empty() {
+        let s = "You are not in any organization\n".to_string();
+        if raw {
+            return s;
+        }
+
+        return s.yellow().bold().to_string();
+    }
+
+    let mut table = table(raw, &["Organization ID", "Name", "Role", "Projects"]);
+    for ProjectsResponse {
+        organization,
+        projects,
+    } in organizations
+    {
+        let role = if organization.is_admin {
+            MemberRole::Admin
+        } else {
+            MemberRole::Member
+        };
+        table.add_row(vec![
+            Cell::new(&organization.id),
+            Cell::new(&organization.display_name),
+            Cell::new(role),
+            Cell::new(projects.join(", ")),
+        ]);
+    }
+
+    format!("These are the organizations you are in\n{table}\n")
+}
+
+pub fn get_members_table(members: &[MemberResponse], organization_name: &str, raw: bool) -> String {
+    if members.is_empty() {
+        let s = format!("{organization_name} has no members\n");
+        if raw {
+            return s;
+        }
+
+        return s.yellow().bold().to_string();
+    }
+
+    let mut table = table(raw, &["User ID", "Role"]);
+    for member in members {
+        table.add_row(vec![Cell::new(&member.id), Cell::new(&member.role)]);
+    }
+
+    format!("These are the members of {organization_name}\n{table}\n")
+}
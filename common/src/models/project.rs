@@ -2,6 +2,7 @@ use std::fmt::Display;
 use std::fmt::Formatter;
 use std::str::FromStr;
 
+use chrono::{DateTime, Utc};
 use comfy_table::{
     modifiers::UTF8_ROUND_CORNERS,
     presets::{NOTHING, UTF8_FULL},
@@ -167,6 +168,33 @@ pub struct Config {
     pub idle_minutes: u64,
 }
 
+/// A lock that blocks new deployments of a project
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Lock {
+    pub reason: String,
+    /// The user that locked the project
+    pub locked_by: String,
+    pub locked_at: DateTime<Utc>,
+}
+
+impl Display for Lock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "locked by {} at {}: {}",
+            self.locked_by,
+            self.locked_at.format("%Y-%m-%dT%H:%M:%SZ"),
+            self.reason
+        )
+    }
+}
+
+/// Body when locking a project
+#[derive(Deserialize, Serialize)]
+pub struct LockRequest {
+    pub reason: String,
+}
+
 #[derive(Clone, Deserialize, Serialize, Debug, PartialEq)]
 #[serde(tag = "owner_type", content = "owner_id", rename_all = "lowercase")]
 pub enum Owner {
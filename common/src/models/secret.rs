@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use comfy_table::{
+    modifiers::UTF8_ROUND_CORNERS,
+    presets::{NOTHING, UTF8_FULL},
+    Attribute, Cell, CellAlignment, ContentArrangement, Table,
+};
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+
+/// Metadata of a secret. Never contains the secret value.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Response {
+    pub key: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A secret together with its value
+#[derive(Deserialize, Serialize)]
+pub struct ValueResponse {
+    pub key: String,
+    pub value: String,
+}
+
+/// Body when setting the value of a secret
+#[derive(Deserialize, Serialize)]
+pub struct SetRequest {
+    pub value: String,
+}
+
+pub fn get_secrets_metadata_table(secrets: &[Response], project_name: &str, raw: bool) -> String {
+    if secrets.is_empty() {
+        let s = format!("No secrets are set for {project_name}\n");
+        if raw {
+            return s;
+        }
+
+        return s.yellow().bold().to_string();
+    }
+
+    let mut table = Table::new();
+
+    if raw {
+        table
+            .load_preset(NOTHING)
+            .set_content_arrangement(ContentArrangement::Disabled)
+            .set_header(vec![
+                Cell::new("Key").set_alignment(CellAlignment::Left),
+                Cell::new("Created").set_alignment(CellAlignment::Left),
+                Cell::new("Last updated").set_alignment(CellAlignment::Left),
+            ]);
+    } else {
+        table
+            .load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+            .set_header(vec![
+                Cell::new("Key")
+                    .set_alignment(CellAlignment::Center)
+                    .add_attribute(Attribute::Bold),
+                Cell::new("Created")
+                    .set_alignment(CellAlignment::Center)
+                    .add_attribute(Attribute::Bold),
+                Cell::new("Last updated")
+                    .set_alignment(CellAlignment::Center)
+                    .add_attribute(Attribute::Bold),
+            ]);
+    }
+
+    for secret in secrets {
+        table.add_row(vec![
+            Cell::new(&secret.key),
+            Cell::new(secret.created_at.format("%Y-%m-%dT%H:%M:%SZ")),
+            Cell::new(secret.updated_at.format("%Y-%m-%dT%H:%M:%SZ")),
+        ]);
+    }
+
+    format!("These secrets can be accessed by {project_name}\n{table}\n")
+}
@@ -0,0 +1,195 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use comfy_table::{
+    modifiers::UTF8_ROUND_CORNERS,
+    presets::{NOTHING, UTF8_FULL},
+    Attribute, Cell, CellAlignment, ContentArrangement, Table,
+};
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+use strum::{Display as StrumDisplay, EnumString};
+
+/// What an API token can do
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, StrumDisplay, EnumString)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Permission {
+    /// Read the status, deployments and resources of projects
+    Read,
+    /// Read the logs of deployments
+    Logs,
+    /// Push new deployments, which includes reading projects
+    Deploy,
+}
+
+/// A permission of an API token, on one project or on all of them. Written as
+/// `<permission>[:<project>]`, like `deploy:my-api`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct Scope {
+    pub permission: Permission,
+    pub project: Option<String>,
+}
+
+impl FromStr for Scope {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (permission, project) = match s.split_once(':') {
+            Some((permission, project)) if !project.is_empty() => {
+                (permission, Some(project.to_string()))
+            }
+            Some(_) => return Err(format!("scope '{s}' is missing the name of its project")),
+            None => (s, None),
+        };
+        let permission = permission.parse().map_err(|_| {
+            format!("'{permission}' is not a permission: use one of read, logs or deploy")
+        })?;
+
+        Ok(Self {
+            permission,
+            project,
+        })
+    }
+}
+
+impl Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.project {
+            Some(project) => write!(f, "{}:{project}", self.permission),
+            None => write!(f, "{}", self.permission),
+        }
+    }
+}
+
+impl TryFrom<String> for Scope {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Scope> for String {
+    fn from(scope: Scope) -> Self {
+        scope.to_string()
+    }
+}
+
+/// Body when creating an API token
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreateRequest {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    /// Never expires when not set
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// An API token. Never contains its key.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Response {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+/// A new API token, with its key. The key can not be read again later.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CreatedResponse {
+    #[serde(flatten)]
+    pub token: Response,
+    pub key: String,
+}
+
+pub fn get_tokens_table(tokens: &[Response], raw: bool) -> String {
+    if tokens.is_empty() {
+        let s = "You have no API tokens\n".to_string();
+        if raw {
+            return s;
+        }
+
+        return s.yellow().bold().to_string();
+    }
+
+    let header = ["ID", "Name", "Scopes", "Created", "Expires"];
+    let mut table = Table::new();
+    if raw {
+        table
+            .load_preset(NOTHING)
+            .set_content_arrangement(ContentArrangement::Disabled)
+            .set_header(
+                header
+                    .iter()
+                    .map(|title| Cell::new(title).set_alignment(CellAlignment::Left)),
+            );
+    } else {
+        table
+            .load_preset(UTF8_FULL)
+            .apply_modifier(UTF8_ROUND_CORNERS)
+            .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+            .set_header(header.iter().map(|title| {
+                Cell::new(title)
+                    .set_alignment(CellAlignment::Center)
+                    .add_attribute(Attribute::Bold)
+            }));
+    }
+
+    let now = Utc::now();
+    for token in tokens {
+        let expires = match token.expires_at {
+            Some(expires_at) if expires_at <= now => {
+                format!("{} (expired)", expires_at.format("%Y-%m-%dT%H:%M:%SZ"))
+            }
+            Some(expires_at) => expires_at.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+            None => "never".to_string(),
+        };
+        table.add_row(vec![
+            Cell::new(&token.id),
+            Cell::new(&token.name),
+            Cell::new(
+                token
+                    .scopes
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            Cell::new(token.created_at.format("%Y-%m-%dT%H:%M:%SZ")),
+            Cell::new(expires),
+        ]);
+    }
+
+    format!("These are your API tokens\n{table}\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_scopes() {
+        assert_eq!(
+            "deploy:my-api".parse::<Scope>().unwrap(),
+            Scope {
+                permission: Permission::Deploy,
+                project: Some("my-api".to_string()),
+            }
+        );
+        assert_eq!(
+            "logs".parse::<Scope>().unwrap(),
+            Scope {
+                permission: Permission::Logs,
+                project: None,
+            }
+        );
+        assert!("deploy:".parse::<Scope>().is_err());
+        assert!("admin:my-api".parse::<Scope>().is_err());
+
+        let scope: Scope = serde_json::from_str("\"read:my-api\"").unwrap();
+        assert_eq!(serde_json::to_string(&scope).unwrap(), "\"read:my-api\"");
+    }
+}
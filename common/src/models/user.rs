@@ -15,6 +15,16 @@ pub struct Response {
     pub subscriptions: Vec<Subscription>,
 }
 
+/// The account a request is authenticated as. Never contains the API key.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct AccountResponse {
+    pub name: String,
+    pub id: UserId,
+    pub account_tier: String,
+    /// The API token of the request, when it was not made with the key of the account
+    pub token: Option<super::token::Response>,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Subscription {
     pub id: String,
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::io::Read;
 use std::path::{Path, PathBuf};
@@ -12,6 +12,7 @@ use shuttle_common::{
     constants::{EXECUTABLE_DIRNAME, STORAGE_DIRNAME},
     deployment::DEPLOYER_END_MSG_BUILD_ERR,
     log::LogRecorder,
+    models::deployment::ExternalResources,
     LogItem,
 };
 use shuttle_service::builder::{build_workspace, BuiltService};
@@ -171,6 +172,8 @@ pub struct Queued {
     pub will_run_tests: bool,
     pub tracing_context: HashMap<String, String>,
     pub claim: Claim,
+    pub env_vars: BTreeMap<String, String>,
+    pub external_resources: ExternalResources,
 }
 
 impl Queued {
@@ -226,6 +229,8 @@ impl Queued {
             tracing_context: Default::default(),
             claim: Some(self.claim),
             secrets,
+            env_vars: self.env_vars,
+            external_resources: self.external_resources,
         };
 
         Ok(built)
@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     future::Future,
     net::{Ipv4Addr, SocketAddr},
     path::{Path, PathBuf},
@@ -17,6 +17,7 @@ use shuttle_common::{
         DEPLOYER_END_MSG_COMPLETED, DEPLOYER_END_MSG_CRASHED, DEPLOYER_END_MSG_STARTUP_ERR,
         DEPLOYER_END_MSG_STOPPED, DEPLOYER_RUNTIME_START_FAILED, DEPLOYER_RUNTIME_START_RESPONSE,
     },
+    models::deployment::ExternalResources,
     resource::{self, ResourceInput, Type},
     DatabaseResource, DbInput, SecretStore,
 };
@@ -232,6 +233,10 @@ pub struct Built {
     /// must be set if this run will perform requests to backends
     pub claim: Option<Claim>,
     pub secrets: HashMap<String, String>,
+    /// Environment variables set on the runtime, from the `[env-vars]` table of Shuttle.toml
+    pub env_vars: BTreeMap<String, String>,
+    /// Resources living outside of Shuttle, from the `[external.*]` tables of Shuttle.toml
+    pub external_resources: ExternalResources,
 }
 
 impl Built {
@@ -247,46 +252,42 @@ impl Built {
         provisioner_client: provisioner::Client,
     ) -> Result<JoinHandle<()>> {
         let project_path = builds_path.join(&self.service_name);
-        // This is the path to the users project with an embedded runtime.
-        let executable_path = project_path
-            .join(EXECUTABLE_DIRNAME)
-            .join(self.id.to_string());
         let cached_resources_path = project_path
             .join(EXECUTABLE_DIRNAME)
             .join(format!("{}.resources", self.id));
+        // The environment of the deployment is only known when deploying, so it is cached next to
+        // the resources for wakeups
+        let cached_env_path = project_path
+            .join(EXECUTABLE_DIRNAME)
+            .join(format!("{}.env", self.id));
 
         // Let the runtime expose its HTTP port
         let address = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), DEPLOYER_SERVICE_HTTP_PORT);
 
-        let runtime_client = runtime_manager
-            .lock()
-            .await
-            .create_runtime_client(
-                self.id,
-                project_path.as_path(),
-                self.service_name.clone(),
-                executable_path,
-            )
-            .await
-            .map_err(Error::Runtime)?;
-
         // Check for cached resources for this deployment id. This only succeeds on wakeup from idle or project restart.
-        let resources = if let Some(bytes) = std::fs::read(&cached_resources_path)
+        let (runtime_client, resources) = if let Some(bytes) = std::fs::read(&cached_resources_path)
             .ok()
             .and_then(|bytes| serde_json::from_slice(bytes.as_slice()).ok())
         {
             info!("Using cached resources");
-
-            bytes
+            let env = std::fs::read(&cached_env_path)
+                .ok()
+                .and_then(|bytes| serde_json::from_slice(bytes.as_slice()).ok())
+                .unwrap_or_default();
+            let runtime_client = self
+                .start_runtime(&runtime_manager, &project_path, &env)
+                .await?;
+
+            (runtime_client, bytes)
         }
         // Default case for handling resources and provisioning
         else {
             info!("Loading resources");
-            let claim = self
-                .claim
-                .expect("claim must be present when loading resources");
+            let claim = self.claim.clone().ok_or_else(|| {
+                Error::Load("claim must be present when loading resources".into())
+            })?;
 
-            let mut new_secrets = self.secrets;
+            let mut new_secrets = self.secrets.clone();
             let prev_resources = resource_manager
                 .get_resources(&self.service_id, claim.clone())
                 .await
@@ -321,6 +322,11 @@ impl Built {
                 })
                 .collect::<Vec<_>>();
 
+            let env = self.env(&new_secrets);
+            let runtime_client = self
+                .start_runtime(&runtime_manager, &project_path, &env)
+                .await?;
+
             let resources = load(
                 self.service_name.clone(),
                 runtime_client.clone(),
@@ -337,19 +343,25 @@ impl Built {
                 prev_resources,
                 resources,
                 new_secrets,
+                &self.external_resources,
             )
             .await
             .map_err(Error::Provision)?;
 
-            // cache the final resources output for use in wakeups
+            // cache the final resources output and environment for use in wakeups
             // this should only happen on deployment, and not on wakeups
             std::fs::write(
                 &cached_resources_path,
                 serde_json::to_vec(&resources).expect("resources to serialize"),
             )
             .map_err(|_| Error::Load("Failed to save resource cache".into()))?;
+            std::fs::write(
+                &cached_env_path,
+                serde_json::to_vec(&env).expect("environment to serialize"),
+            )
+            .map_err(|_| Error::Load("Failed to save environment cache".into()))?;
 
-            resources
+            (runtime_client, resources)
         };
 
         kill_old_deployments.await?;
@@ -365,6 +377,51 @@ impl Built {
 
         Ok(handler)
     }
+
+    /// Start the runtime of this deployment with `env` set on it
+    async fn start_runtime(
+        &self,
+        runtime_manager: &Mutex<RuntimeManager>,
+        project_path: &Path,
+        env: &HashMap<String, String>,
+    ) -> Result<runtime::Client> {
+        // This is the path to the users project with an embedded runtime.
+        let executable_path = project_path
+            .join(EXECUTABLE_DIRNAME)
+            .join(self.id.to_string());
+
+        runtime_manager
+            .lock()
+            .await
+            .create_runtime_client(
+                self.id,
+                project_path,
+                self.service_name.clone(),
+                executable_path,
+                env,
+            )
+            .await
+            .map_err(Error::Runtime)
+    }
+
+    /// The environment of the service: the variables of Shuttle.toml, and the connection strings
+    /// of external resources read from `secrets`
+    fn env(&self, secrets: &HashMap<String, String>) -> HashMap<String, String> {
+        let mut env = HashMap::from_iter(self.env_vars.clone());
+        for (name, secret) in self.external_resources.env_vars() {
+            match secrets.get(secret) {
+                Some(value) => {
+                    env.insert(name, value.clone());
+                }
+                None => warn!(
+                    env_var = %name,
+                    "secret {secret} of an external resource is not set, leaving its variable unset"
+                ),
+            }
+        }
+
+        env
+    }
 }
 
 #[instrument(name = "Sending load request", skip_all)]
@@ -451,6 +508,7 @@ async fn provision(
     prev_resources: Vec<resource::Response>,
     mut resources: Vec<Vec<u8>>,
     new_secrets: HashMap<String, String>,
+    external_resources: &ExternalResources,
 ) -> anyhow::Result<Vec<Vec<u8>>> {
     let mut resources_to_save: Vec<record_request::Resource> = Vec::new();
 
@@ -500,6 +558,24 @@ async fn provision(
                 // no config fields are used yet, but verify the format anyways
                 let config: DbInput = serde_json::from_value(shuttle_resource.config.clone())
                     .context("deserializing resource config")?;
+                // An external resource standing in for this type is used instead of provisioning one
+                if let Some((name, external)) = external_resources.for_database(db_type) {
+                    let connection_string =
+                        new_secrets.get(&external.secret).cloned().with_context(|| {
+                            format!(
+                                "The external resource {name} stands in for {db_type}, \
+                                but the secret {} with its connection string is not set",
+                                external.secret
+                            )
+                        })?;
+                    log(&shuttle_resource.r#type, &format!("Using external resource {name}"));
+                    *bytes = serde_json::to_vec(&ShuttleResourceOutput {
+                        output: DatabaseResource::ConnectionString(connection_string),
+                        custom: shuttle_resource.custom,
+                    })
+                    .expect("to serialize struct");
+                    continue;
+                }
                 // We pass a Null config right now because this is relevant only for updating the resources
                 // through the provisioner, which is something we don't support currently. If there will be
                 // config fields that are relevant for provisioner updates on top of resources, they should
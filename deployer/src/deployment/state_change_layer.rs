@@ -682,6 +682,8 @@ mod tests {
                 tracing_context: Default::default(),
                 claim: Default::default(),
                 secrets: Default::default(),
+                env_vars: Default::default(),
+                external_resources: Default::default(),
             })
             .await;
 
@@ -727,6 +729,8 @@ mod tests {
                 will_run_tests: false,
                 tracing_context: Default::default(),
                 claim: Default::default(),
+                env_vars: Default::default(),
+                external_resources: Default::default(),
             })
             .await;
 
@@ -786,6 +790,8 @@ mod tests {
             will_run_tests: false,
             tracing_context: Default::default(),
             claim: Default::default(),
+            env_vars: Default::default(),
+            external_resources: Default::default(),
         }
     }
 }
@@ -25,6 +25,8 @@ pub enum Error {
     Internal(#[from] anyhow::Error),
     #[error("Missing header: {0}")]
     MissingHeader(String),
+    #[error("The [{0}] section of Shuttle.toml is not supported by deployments yet, remove it to deploy")]
+    UnsupportedSection(&'static str),
 }
 
 impl Serialize for Error {
@@ -44,6 +46,7 @@ impl IntoResponse for Error {
     fn into_response(self) -> Response {
         let code = match self {
             Error::NotFound(_) => StatusCode::NOT_FOUND,
+            Error::UnsupportedSection(_) => StatusCode::BAD_REQUEST,
             _ => {
                 // We only want to emit error events for internal errors, not e.g. 404s.
                 error!(
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::str::FromStr;
 
 use anyhow::anyhow;
@@ -10,14 +11,16 @@ use axum::extract::{
 use axum::extract::{DefaultBodyLimit, Extension, Query};
 use axum::handler::Handler;
 use axum::middleware::{self, from_extractor};
+use axum::response::IntoResponse;
 use axum::routing::{delete, get, post, Router};
 use axum::Json;
-use chrono::{SecondsFormat, Utc};
-use hyper::{Request, StatusCode, Uri};
+use chrono::{DateTime, SecondsFormat, Utc};
+use hyper::{header, Request, StatusCode, Uri};
 use serde::{de::DeserializeOwned, Deserialize};
 use shuttle_service::builder::clean_crate;
 use tonic::Code;
 use tracing::{error, field, info, info_span, instrument, trace, warn};
+use ulid::Ulid;
 use uuid::Uuid;
 
 use shuttle_backends::{
@@ -28,10 +31,18 @@ use shuttle_backends::{
 };
 use shuttle_common::{
     claims::{Claim, Scope},
-    models::deployment::{DeploymentRequest, CREATE_SERVICE_BODY_LIMIT, GIT_STRINGS_MAX_LENGTH},
+    constants::EXECUTABLE_DIRNAME,
+    models::{
+        deployment::{DeploymentRequest, CREATE_SERVICE_BODY_LIMIT, GIT_STRINGS_MAX_LENGTH},
+        secret,
+    },
+    resource::Type,
     LogItem,
 };
-use shuttle_proto::logger::LogsRequest;
+use shuttle_proto::{
+    logger::LogsRequest,
+    resource_recorder::{self, record_request},
+};
 
 use crate::{
     deployment::{DeploymentManager, Queued},
@@ -89,6 +100,17 @@ impl RouterBuilder {
                 delete(delete_service_resource)
                     .layer(ScopedLayer::new(vec![Scope::ResourcesWrite])),
             )
+            .route(
+                "/projects/:project_name/secrets",
+                get(get_secrets.layer(ScopedLayer::new(vec![Scope::Secret])))
+                    .delete(clear_secrets.layer(ScopedLayer::new(vec![Scope::SecretWrite]))),
+            )
+            .route(
+                "/projects/:project_name/secrets/:key",
+                get(get_secret.layer(ScopedLayer::new(vec![Scope::Secret])))
+                    .put(set_secret.layer(ScopedLayer::new(vec![Scope::SecretWrite])))
+                    .delete(delete_secret.layer(ScopedLayer::new(vec![Scope::SecretWrite]))),
+            )
             .route(
                 "/projects/:project_name/deployments",
                 get(get_deployments).layer(ScopedLayer::new(vec![Scope::Service])),
@@ -102,6 +124,10 @@ impl RouterBuilder {
                     // This is kept for compatibility.
                     .put(|| async move {}),
             )
+            .route(
+                "/projects/:project_name/deployments/:deployment_id/artifact",
+                get(get_deployment_artifact.layer(ScopedLayer::new(vec![Scope::Deployment]))),
+            )
             .route(
                 "/projects/:project_name/ws/deployments/:deployment_id/logs",
                 get(get_logs_subscribe.layer(ScopedLayer::new(vec![Scope::Logs]))),
@@ -279,6 +305,203 @@ pub async fn delete_service_resource(
     Ok(Json(()))
 }
 
+/// The secrets of a project are the data of the Secrets resource of its service, which is named
+/// after the project. They are all stored together, so they share the times of that resource.
+async fn get_secrets_resource(
+    persistence: &mut Persistence,
+    service_id: &Ulid,
+    claim: Claim,
+) -> Result<(Vec<resource_recorder::Resource>, BTreeMap<String, String>)> {
+    let resources = persistence
+        .get_resources(service_id, claim)
+        .await?
+        .resources
+        .into_iter()
+        .filter(|resource| resource.service_id == service_id.to_string() && resource.is_active)
+        .collect::<Vec<_>>();
+    let secrets = resources
+        .iter()
+        .find(|resource| resource.r#type == Type::Secrets.to_string())
+        .map(|resource| serde_json::from_slice(&resource.data))
+        .transpose()
+        .map_err(|err| anyhow!("failed to parse the secrets data: {err}"))?
+        .unwrap_or_default();
+
+    Ok((resources, secrets))
+}
+
+/// Record `secrets` as the data of the Secrets resource. The other active resources of the service
+/// are recorded again with it, since recording deactivates the ones left out.
+async fn record_secrets(
+    persistence: &mut Persistence,
+    service_id: &Ulid,
+    claim: Claim,
+    resources: Vec<resource_recorder::Resource>,
+    secrets: &BTreeMap<String, String>,
+) -> Result<()> {
+    let secrets_type = Type::Secrets.to_string();
+    let mut records = resources
+        .into_iter()
+        .filter(|resource| resource.r#type != secrets_type)
+        .map(|resource| record_request::Resource {
+            r#type: resource.r#type,
+            config: resource.config,
+            data: resource.data,
+        })
+        .collect::<Vec<_>>();
+    records.push(record_request::Resource {
+        r#type: secrets_type,
+        config: serde_json::to_vec(&serde_json::Value::Null).expect("to serialize null"),
+        data: serde_json::to_vec(secrets).expect("to serialize secrets"),
+    });
+
+    let response = persistence
+        .insert_resources(records, service_id, claim)
+        .await?;
+    if !response.success {
+        return Err(anyhow!("Unable to record secrets: {}", response.message).into());
+    }
+
+    Ok(())
+}
+
+fn secret_response(
+    resource: Option<&resource_recorder::Resource>,
+    key: String,
+) -> secret::Response {
+    let time = |timestamp: Option<&prost_types::Timestamp>| {
+        timestamp
+            .and_then(|t| {
+                DateTime::from_timestamp(t.seconds, t.nanos.try_into().unwrap_or_default())
+            })
+            .unwrap_or_else(Utc::now)
+    };
+
+    secret::Response {
+        key,
+        created_at: time(resource.and_then(|r| r.created_at.as_ref())),
+        updated_at: time(resource.and_then(|r| r.last_updated.as_ref())),
+    }
+}
+
+#[instrument(skip_all, fields(shuttle.project.name = %project_name))]
+pub async fn get_secrets(
+    Extension(mut persistence): Extension<Persistence>,
+    Extension(claim): Extension<Claim>,
+    CustomErrorPath(project_name): CustomErrorPath<String>,
+) -> Result<Json<Vec<secret::Response>>> {
+    let Some(service) = persistence.get_service_by_name(&project_name).await? else {
+        return Ok(Json(Vec::new()));
+    };
+    let (resources, secrets) = get_secrets_resource(&mut persistence, &service.id, claim).await?;
+    let resource = resources
+        .iter()
+        .find(|resource| resource.r#type == Type::Secrets.to_string());
+
+    let secrets = secrets
+        .into_keys()
+        .map(|key| secret_response(resource, key))
+        .collect();
+
+    Ok(Json(secrets))
+}
+
+#[instrument(skip_all, fields(shuttle.project.name = %project_name, %key))]
+pub async fn get_secret(
+    Extension(mut persistence): Extension<Persistence>,
+    Extension(claim): Extension<Claim>,
+    CustomErrorPath((project_name, key)): CustomErrorPath<(String, String)>,
+) -> Result<Json<secret::ValueResponse>> {
+    let service = persistence
+        .get_service_by_name(&project_name)
+        .await?
+        .ok_or_else(|| Error::NotFound("secret not found".to_string()))?;
+    let (_, mut secrets) = get_secrets_resource(&mut persistence, &service.id, claim).await?;
+
+    let value = secrets
+        .remove(&key)
+        .ok_or_else(|| Error::NotFound("secret not found".to_string()))?;
+
+    Ok(Json(secret::ValueResponse { key, value }))
+}
+
+#[instrument(skip_all, fields(shuttle.project.name = %project_name, %key))]
+pub async fn set_secret(
+    Extension(mut persistence): Extension<Persistence>,
+    Extension(claim): Extension<Claim>,
+    CustomErrorPath((project_name, key)): CustomErrorPath<(String, String)>,
+    Json(request): Json<secret::SetRequest>,
+) -> Result<Json<secret::Response>> {
+    // Secrets can be set before the first deployment, which then creates the service
+    let service = persistence.get_or_create_service(&project_name).await?;
+    let (resources, mut secrets) =
+        get_secrets_resource(&mut persistence, &service.id, claim.clone()).await?;
+
+    secrets.insert(key.clone(), request.value);
+    record_secrets(
+        &mut persistence,
+        &service.id,
+        claim.clone(),
+        resources,
+        &secrets,
+    )
+    .await?;
+
+    let resource = persistence
+        .get_resource(&service.id, Type::Secrets, claim)
+        .await?
+        .resource;
+
+    Ok(Json(secret_response(resource.as_ref(), key)))
+}
+
+#[instrument(skip_all, fields(shuttle.project.name = %project_name, %key))]
+pub async fn delete_secret(
+    Extension(mut persistence): Extension<Persistence>,
+    Extension(claim): Extension<Claim>,
+    CustomErrorPath((project_name, key)): CustomErrorPath<(String, String)>,
+) -> Result<Json<()>> {
+    let service = persistence
+        .get_service_by_name(&project_name)
+        .await?
+        .ok_or_else(|| Error::NotFound("secret not found".to_string()))?;
+    let (resources, mut secrets) =
+        get_secrets_resource(&mut persistence, &service.id, claim.clone()).await?;
+
+    if secrets.remove(&key).is_none() {
+        return Err(Error::NotFound("secret not found".to_string()));
+    }
+    record_secrets(&mut persistence, &service.id, claim, resources, &secrets).await?;
+
+    Ok(Json(()))
+}
+
+#[instrument(skip_all, fields(shuttle.project.name = %project_name))]
+pub async fn clear_secrets(
+    Extension(mut persistence): Extension<Persistence>,
+    Extension(claim): Extension<Claim>,
+    CustomErrorPath(project_name): CustomErrorPath<String>,
+) -> Result<Json<()>> {
+    let Some(service) = persistence.get_service_by_name(&project_name).await? else {
+        return Ok(Json(()));
+    };
+    let (resources, secrets) =
+        get_secrets_resource(&mut persistence, &service.id, claim.clone()).await?;
+
+    if !secrets.is_empty() {
+        record_secrets(
+            &mut persistence,
+            &service.id,
+            claim,
+            resources,
+            &BTreeMap::new(),
+        )
+        .await?;
+    }
+
+    Ok(Json(()))
+}
+
 #[instrument(skip_all, fields(shuttle.project.name = %project_name, shuttle.service.name = %service_name))]
 pub async fn create_service(
     Extension(persistence): Extension<Persistence>,
@@ -287,6 +510,17 @@ pub async fn create_service(
     CustomErrorPath((project_name, service_name)): CustomErrorPath<(String, String)>,
     Rmp(deployment_req): Rmp<DeploymentRequest>,
 ) -> Result<Json<shuttle_common::models::deployment::Response>> {
+    // Refuse the sections this deployer can't act on, rather than deploying without them
+    let unsupported = [
+        ("log-forwarding", deployment_req.log_forwarding.is_some()),
+        ("resources", deployment_req.resource_overrides.is_some()),
+        ("domains", deployment_req.domains.is_some()),
+        ("cron", deployment_req.cron.is_some()),
+    ];
+    if let Some((section, _)) = unsupported.into_iter().find(|(_, present)| *present) {
+        return Err(Error::UnsupportedSection(section));
+    }
+
     let id = Uuid::new_v4();
     let now = Utc::now();
 
@@ -337,6 +571,8 @@ pub async fn create_service(
         will_run_tests: !deployment_req.no_test,
         tracing_context: Default::default(),
         claim,
+        env_vars: deployment_req.env_vars,
+        external_resources: deployment_req.external_resources.unwrap_or_default(),
     };
 
     deployment_manager.queue_push(queued).await;
@@ -419,6 +655,45 @@ pub async fn delete_deployment(
     }
 }
 
+/// The executable built for a deployment, for as long as it is kept on disk
+#[instrument(skip_all, fields(shuttle.project.name = %project_name, %deployment_id))]
+pub async fn get_deployment_artifact(
+    Extension(deployment_manager): Extension<DeploymentManager>,
+    Extension(persistence): Extension<Persistence>,
+    CustomErrorPath((project_name, deployment_id)): CustomErrorPath<(String, Uuid)>,
+) -> Result<impl IntoResponse> {
+    let deployment = persistence
+        .get_deployment(&deployment_id)
+        .await?
+        .ok_or_else(|| Error::NotFound("deployment not found".to_string()))?;
+    let service = persistence
+        .get_all_services()
+        .await?
+        .into_iter()
+        .find(|service| service.id == deployment.service_id)
+        .ok_or_else(|| Error::NotFound("service not found".to_string()))?;
+
+    let path = deployment_manager
+        .builds_path()
+        .join(service.name)
+        .join(EXECUTABLE_DIRNAME)
+        .join(deployment.id.to_string());
+    let artifact = match tokio::fs::read(&path).await {
+        Ok(artifact) => artifact,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Err(Error::NotFound(
+                "the executable of this deployment is no longer kept".to_string(),
+            ))
+        }
+        Err(err) => return Err(anyhow!("failed to read the executable: {err}").into()),
+    };
+
+    Ok((
+        [(header::CONTENT_TYPE, "application/octet-stream")],
+        artifact,
+    ))
+}
+
 #[instrument(skip_all, fields(shuttle.project.name = %project_name, %deployment_id))]
 pub async fn get_logs(
     Extension(deployment_manager): Extension<DeploymentManager>,
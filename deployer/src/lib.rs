@@ -68,6 +68,8 @@ pub async fn start(
             tracing_context: Default::default(),
             claim: None,
             secrets: Default::default(),
+            env_vars: Default::default(),
+            external_resources: Default::default(),
         };
         deployment_manager.run_push(built).await;
     }
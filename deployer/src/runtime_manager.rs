@@ -41,6 +41,7 @@ impl RuntimeManager {
         project_path: &Path,
         service_name: String,
         runtime_executable: PathBuf,
+        env: &HashMap<String, String>,
     ) -> anyhow::Result<runtime::Client> {
         trace!("making new client");
 
@@ -57,9 +58,10 @@ impl RuntimeManager {
                 .unwrap_or_default()
         );
 
-        let (mut process, runtime_client) = runner::start(port, runtime_executable, project_path)
-            .await
-            .context("failed to start shuttle runtime")?;
+        let (mut process, runtime_client) =
+            runner::start(port, runtime_executable, project_path, env)
+                .await
+                .context("failed to start shuttle runtime")?;
 
         let stdout = process
             .stdout
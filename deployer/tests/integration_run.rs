@@ -353,6 +353,8 @@ async fn make_and_built(crate_name: &str) -> (Built, PathBuf) {
                 shuttle_common::claims::AccountTier::Basic,
             )),
             secrets: Default::default(),
+            env_vars: Default::default(),
+            external_resources: Default::default(),
         },
         RESOURCES_PATH.into(), // is later joined with `service_name` to arrive at `crate_name`
     )
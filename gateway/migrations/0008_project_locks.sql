@@ -0,0 +1,5 @@
+-- A lock blocks new deployments of a project until it is removed
+ALTER TABLE projects ADD COLUMN lock_reason TEXT;
+ALTER TABLE projects ADD COLUMN locked_by TEXT;
+-- Unix timestamp in seconds
+ALTER TABLE projects ADD COLUMN locked_at INTEGER;
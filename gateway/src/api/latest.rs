@@ -1,5 +1,6 @@
+use std::collections::BTreeSet;
 use std::io::Cursor;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::ops::Sub;
 use std::sync::Arc;
 use std::time::Duration;
@@ -12,6 +13,7 @@ use axum::middleware::{self, from_extractor};
 use axum::response::Response;
 use axum::routing::{any, delete, get, post};
 use axum::{Json as AxumJson, Router};
+use chrono::{TimeZone, Utc};
 use fqdn::FQDN;
 use futures::Future;
 use http::header::AUTHORIZATION;
@@ -27,20 +29,23 @@ use shuttle_backends::project_name::ProjectName;
 use shuttle_backends::request_span;
 use shuttle_backends::ClaimExt;
 use shuttle_common::claims::{Claim, Scope, EXP_MINUTES};
+use shuttle_common::models::certificate::{self, RenewalState};
 use shuttle_common::models::error::{
-    ApiError, InvalidCustomDomain, InvalidOrganizationName, ProjectCorrupted,
-    ProjectHasBuildingDeployment, ProjectHasResources, ProjectHasRunningDeployment,
+    ApiError, CustomDomainDnsMisconfigured, InvalidCustomDomain, InvalidOrganizationName,
+    ProjectCorrupted, ProjectHasBuildingDeployment, ProjectHasResources,
+    ProjectHasRunningDeployment, ProjectLocked,
 };
 use shuttle_common::models::{admin::ProjectResponse, project, stats};
 use shuttle_common::models::{organization, service};
 use shuttle_common::{deployment, VersionInfo};
 use shuttle_proto::provisioner::provisioner_client::ProvisionerClient;
 use shuttle_proto::provisioner::Ping;
+use tokio::net::lookup_host;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::{Mutex, MutexGuard};
 use tower::ServiceBuilder;
 use tower_http::cors::CorsLayer;
-use tracing::{error, field, instrument, trace, Span};
+use tracing::{error, field, instrument, trace, warn, Span};
 use ttl_cache::TtlCache;
 use ulid::Ulid;
 use uuid::Uuid;
@@ -49,7 +54,7 @@ use x509_parser::parse_x509_certificate;
 use x509_parser::pem::parse_x509_pem;
 use x509_parser::time::ASN1Time;
 
-use crate::acme::{AccountWrapper, AcmeClient, CustomDomain};
+use crate::acme::{AccountWrapper, AcmeClient, AcmeClientError, CustomDomain};
 use crate::api::tracing::project_name_tracing_layer;
 use crate::auth::ScopedUser;
 use crate::service::{ContainerSettings, GatewayService};
@@ -145,20 +150,25 @@ async fn check_project_name(
 }
 async fn get_projects_list(
     State(RouterState { service, .. }): State<RouterState>,
-    Claim { sub, .. }: Claim,
+    claim: Claim,
 ) -> Result<AxumJson<Vec<project::Response>>, ApiError> {
+    let sub = &claim.sub;
     let mut projects = vec![];
-    for proj_id in service.permit_client.get_personal_projects(&sub).await? {
+    for proj_id in service.permit_client.get_personal_projects(sub).await? {
         let project = service.find_project_by_id(&proj_id).await?;
+        // Tokens scoped to projects only list those
+        if !claim.allows_project(&project.name) {
+            continue;
+        }
         let idle_minutes = project.state.idle_minutes();
         let owner = service
             .permit_client
-            .get_project_owner(&sub, &proj_id)
+            .get_project_owner(sub, &proj_id)
             .await?
             .into();
         let is_admin = service
             .permit_client
-            .allowed(&sub, &proj_id, "manage")
+            .allowed(sub, &proj_id, "manage")
             .await?;
 
         let response = project::Response {
@@ -429,6 +439,15 @@ async fn override_create_service(
     scoped_user: ScopedUser,
     req: Request<Body>,
 ) -> Result<Response<Body>, ApiError> {
+    // The CLI checks the lock too, but only this check holds for every client
+    if let Some(lock) = state.service.find_project_lock(&scoped_user.scope).await? {
+        return Err(ProjectLocked {
+            project_name: scoped_user.scope.to_string(),
+            lock: lock.to_string(),
+        }
+        .into());
+    }
+
     let user_id = scoped_user.claim.sub.clone();
     let posthog_client = state.posthog_client.clone();
     tokio::spawn(async move {
@@ -480,6 +499,43 @@ async fn override_get_delete_service(
     Ok(res)
 }
 
+#[instrument(skip_all, fields(shuttle.project.name = %scope))]
+async fn get_project_lock(
+    State(RouterState { service, .. }): State<RouterState>,
+    ScopedUser { scope, .. }: ScopedUser,
+) -> Result<AxumJson<Option<project::Lock>>, ApiError> {
+    let lock = service.find_project_lock(&scope).await?;
+
+    Ok(AxumJson(lock))
+}
+
+/// Block new deployments of a project until it is unlocked
+#[instrument(skip_all, fields(shuttle.project.name = %scope))]
+async fn lock_project(
+    State(RouterState { service, .. }): State<RouterState>,
+    ScopedUser { scope, claim }: ScopedUser,
+    AxumJson(request): AxumJson<project::LockRequest>,
+) -> Result<AxumJson<project::Lock>, ApiError> {
+    let lock = project::Lock {
+        reason: request.reason,
+        locked_by: claim.sub,
+        locked_at: Utc::now(),
+    };
+    service.set_project_lock(&scope, &lock).await?;
+
+    Ok(AxumJson(lock))
+}
+
+#[instrument(skip_all, fields(shuttle.project.name = %scope))]
+async fn unlock_project(
+    State(RouterState { service, .. }): State<RouterState>,
+    ScopedUser { scope, .. }: ScopedUser,
+) -> Result<(), ApiError> {
+    service.remove_project_lock(&scope).await?;
+
+    Ok(())
+}
+
 #[instrument(skip_all, fields(shuttle.project.name = %scoped_user.scope))]
 async fn route_project(
     State(RouterState {
@@ -994,6 +1050,157 @@ async fn renew_gateway_acme_certificate(
     ))
 }
 
+#[instrument(skip_all, fields(shuttle.project.name = %scope))]
+async fn get_project_certificates(
+    State(RouterState { service, .. }): State<RouterState>,
+    ScopedUser { scope, .. }: ScopedUser,
+) -> Result<AxumJson<Vec<certificate::Response>>, ApiError> {
+    let certificates = service
+        .find_custom_domains_for_project(&scope)
+        .await?
+        .iter()
+        .map(|domain| certificate_response(&domain.fqdn, &domain.certificate))
+        .collect::<Result<_, _>>()?;
+
+    Ok(AxumJson(certificates))
+}
+
+/// Issue a new certificate for a custom domain of a project, whether the current one is due for
+/// renewal or not
+#[instrument(skip_all, fields(shuttle.project.name = %scope, %fqdn))]
+async fn renew_project_certificate(
+    State(RouterState { service, .. }): State<RouterState>,
+    Extension(acme_client): Extension<AcmeClient>,
+    Extension(resolver): Extension<Arc<GatewayCertResolver>>,
+    ScopedUser { scope, .. }: ScopedUser,
+    CustomErrorPath((_, fqdn)): CustomErrorPath<(ProjectName, String)>,
+) -> Result<AxumJson<certificate::Response>, ApiError> {
+    let fqdn: FQDN = fqdn.parse().map_err(|_| InvalidCustomDomain)?;
+    // The domains of other projects are not found, like the ones that do not exist
+    if service
+        .project_details_for_custom_domain(&fqdn)
+        .await?
+        .project_name
+        != scope
+    {
+        return Err(crate::service::Error::CustomDomainNotFound.into());
+    }
+
+    let target = format!("{scope}.{}", service.context().settings.fqdn);
+    check_custom_domain_dns(&fqdn, &target).await?;
+
+    let (certs, private_key) = acme_client
+        .create_certificate(
+            &fqdn.to_string(),
+            ChallengeType::Http01,
+            service.credentials(),
+        )
+        .await
+        .map_err(|err| match err {
+            // The challenge is served by the gateway, so it fails when the domain points elsewhere
+            AcmeClientError::ChallengeInvalid | AcmeClientError::ChallengeTimeout => {
+                CustomDomainDnsMisconfigured {
+                    domain: fqdn.to_string(),
+                    reason: "Let's Encrypt could not reach it to verify it".to_string(),
+                    target: target.clone(),
+                }
+                .into()
+            }
+            err => ApiError::from(err),
+        })?;
+    service
+        .create_custom_domain(&scope, &fqdn, &certs, &private_key)
+        .await?;
+
+    let mut buf = Vec::new();
+    buf.extend(certs.as_bytes());
+    buf.extend(private_key.as_bytes());
+    resolver
+        .serve_pem(&fqdn.to_string(), Cursor::new(buf))
+        .await?;
+
+    Ok(AxumJson(certificate_response(&fqdn, &certs)?))
+}
+
+/// The validity and renewal state of the certificate of a custom domain
+fn certificate_response(fqdn: &FQDN, certificate: &str) -> Result<certificate::Response, ApiError> {
+    let (_, pem) = parse_x509_pem(certificate.as_bytes()).map_err(|err| {
+        ApiError::internal(&format!(
+            "Error while parsing the pem certificate for {fqdn}: {err}"
+        ))
+    })?;
+    let (_, x509_cert) = parse_x509_certificate(pem.contents.as_bytes()).map_err(|err| {
+        ApiError::internal(&format!(
+            "Error while parsing the certificate for {fqdn}: {err}"
+        ))
+    })?;
+
+    let validity = x509_cert.validity();
+    // The difference is `None` when the certificate expired
+    let renewal = match validity.not_after.sub(ASN1Time::now()) {
+        None => RenewalState::Expired,
+        Some(diff) if diff.whole_days() <= RENEWAL_VALIDITY_THRESHOLD_IN_DAYS => RenewalState::Due,
+        Some(_) => RenewalState::Valid,
+    };
+    let time = |time: ASN1Time| {
+        Utc.timestamp_opt(time.timestamp(), 0)
+            .single()
+            .ok_or_else(|| ApiError::internal(&format!("Invalid validity period for {fqdn}")))
+    };
+
+    Ok(certificate::Response {
+        domain: fqdn.to_string(),
+        issued_at: time(validity.not_before)?,
+        expires_at: time(validity.not_after)?,
+        renewal,
+    })
+}
+
+/// Check that a custom domain resolves to the addresses of `target`, the default domain of its
+/// project, since Let's Encrypt verifies it through the gateway
+async fn check_custom_domain_dns(
+    fqdn: &FQDN,
+    target: &str,
+) -> Result<(), CustomDomainDnsMisconfigured> {
+    async fn addresses(host: String) -> std::io::Result<BTreeSet<IpAddr>> {
+        Ok(lookup_host(format!("{host}:80"))
+            .await?
+            .map(|addr| addr.ip())
+            .collect())
+    }
+
+    let expected = match addresses(target.to_string()).await {
+        Ok(expected) => expected,
+        Err(error) => {
+            // Nothing to compare with, so Let's Encrypt is left to tell
+            warn!(
+                error = &error as &dyn std::error::Error,
+                target, "failed to resolve the default domain of a project"
+            );
+            return Ok(());
+        }
+    };
+    let misconfigured = |reason: String| CustomDomainDnsMisconfigured {
+        domain: fqdn.to_string(),
+        reason,
+        target: target.to_string(),
+    };
+
+    let actual = addresses(fqdn.to_string())
+        .await
+        .map_err(|_| misconfigured("it does not resolve to any address".to_string()))?;
+    if actual.is_disjoint(&expected) {
+        let actual = actual
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Err(misconfigured(format!("it resolves to {actual}")));
+    }
+
+    Ok(())
+}
+
 async fn get_projects(
     State(RouterState { service, .. }): State<RouterState>,
 ) -> Result<AxumJson<Vec<ProjectResponse>>, ApiError> {
@@ -1060,6 +1267,14 @@ impl ApiBuilder {
                         .layer(ScopedLayer::new(vec![Scope::CustomDomainCertificateRenew])),
                 ),
             )
+            .route(
+                "/projects/:project_name/certificates",
+                get(get_project_certificates.layer(ScopedLayer::new(vec![Scope::Project]))),
+            )
+            .route(
+                "/projects/:project_name/certificates/:fqdn/renew",
+                post(renew_project_certificate.layer(ScopedLayer::new(vec![Scope::ProjectWrite]))),
+            )
             .route(
                 "/admin/acme/gateway/renew",
                 post(
@@ -1135,6 +1350,12 @@ impl ApiBuilder {
                 delete(delete_project.layer(ScopedLayer::new(vec![Scope::ProjectWrite]))),
             )
             .route("/projects/name/:project_name", get(check_project_name))
+            .route(
+                "/projects/:project_name/lock",
+                get(get_project_lock.layer(ScopedLayer::new(vec![Scope::Project])))
+                    .put(lock_project.layer(ScopedLayer::new(vec![Scope::ProjectWrite])))
+                    .delete(unlock_project.layer(ScopedLayer::new(vec![Scope::ProjectWrite]))),
+            )
             .route(
                 // catch these deployer endpoints for extra metrics or processing before/after being proxied
                 "/projects/:project_name/services/:service_name",
@@ -1176,6 +1397,7 @@ impl ApiBuilder {
                         // For now, these use the same version as gateway (we release versions in lockstep).
                         // Only one version is officially compatible, but more are in reality.
                         cargo_shuttle: env!("CARGO_PKG_VERSION").parse().unwrap(),
+                        cargo_shuttle_min: None,
                         deployer: env!("CARGO_PKG_VERSION").parse().unwrap(),
                         runtime: CARGO_SHUTTLE_VERSION.parse().unwrap(),
                     })
@@ -1471,6 +1693,87 @@ pub mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn api_get_projects_with_project_token() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let service = Arc::new(
+            GatewayService::init(
+                world.args(),
+                world.pool(),
+                "".into(),
+                Box::<PermissionsMock>::default(),
+            )
+            .await?,
+        );
+
+        let (sender, mut receiver) = channel::<BoxedTask>(256);
+        tokio::spawn(async move {
+            while receiver.recv().await.is_some() {
+                // do not do any work with inbound requests
+            }
+        });
+
+        let mut router = ApiBuilder::new()
+            .with_service(Arc::clone(&service))
+            .with_sender(sender)
+            .with_default_routes()
+            .with_auth_service(world.context().auth_uri, "dummykey".to_string())
+            .into_router();
+
+        let neo_key = world.create_user("neo", AccountTier::Basic);
+        let authorization = Authorization::bearer(&neo_key).unwrap();
+
+        for project in ["matrix", "reloaded"] {
+            let resp = router
+                .call(
+                    Request::builder()
+                        .method("POST")
+                        .uri(format!("/projects/{project}"))
+                        .header("Content-Type", "application/json")
+                        .body("{\"idle_minutes\": 3}".into())
+                        .unwrap()
+                        .with_header(&authorization),
+                )
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+
+        let mut get_project_names = |key: &str| {
+            let call = router.call(
+                Request::builder()
+                    .method("GET")
+                    .uri("/projects")
+                    .body(Body::empty())
+                    .unwrap()
+                    .with_header(&Authorization::bearer(key).unwrap()),
+            );
+            async move {
+                let resp = call.await.unwrap();
+                assert_eq!(resp.status(), StatusCode::OK);
+                let body = to_bytes(resp.into_body()).await.unwrap();
+                let projects: Vec<project::Response> = serde_json::from_slice(&body).unwrap();
+                let mut names = projects
+                    .into_iter()
+                    .map(|project| project.name)
+                    .collect::<Vec<_>>();
+                names.sort();
+                names
+            }
+        };
+
+        assert_eq!(
+            get_project_names(&neo_key).await,
+            vec!["matrix", "reloaded"]
+        );
+
+        // A token scoped to one project does not list the other ones
+        let token_key = world.create_project_token("neo", &["matrix"]);
+        assert_eq!(get_project_names(&token_key).await, vec!["matrix"]);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn api_create_project_limits() -> anyhow::Result<()> {
         let world = World::new().await;
@@ -1551,6 +1854,33 @@ pub mod tests {
         Ok(())
     }
 
+    #[test_context(TestGateway)]
+    #[tokio::test]
+    async fn api_lock_project(gateway: &mut TestGateway) {
+        let mut matrix = gateway.create_project("matrix").await;
+        let lock = project::LockRequest {
+            reason: "incident 142".to_string(),
+        };
+
+        assert_eq!(
+            matrix.router_call_json(Method::PUT, "/lock", &lock).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            matrix.router_call(Method::POST, "/services/matrix").await,
+            StatusCode::LOCKED
+        );
+
+        assert_eq!(
+            matrix.router_call(Method::DELETE, "/lock").await,
+            StatusCode::OK
+        );
+        assert_ne!(
+            matrix.router_call(Method::POST, "/services/matrix").await,
+            StatusCode::LOCKED
+        );
+    }
+
     #[test_context(TestGateway)]
     #[tokio::test]
     async fn api_create_project_above_container_limit(gateway: &mut TestGateway) {
@@ -86,6 +86,11 @@ where
                 .map_err(|_| InvalidProjectName)?,
         };
 
+        // API tokens scoped to other projects do not get to know whether this one exists
+        if !claim.allows_project(&scope) {
+            return Err(ProjectNotFound(scope.to_string()).into());
+        }
+
         let RouterState { service, .. } = RouterState::from_ref(state);
 
         let allowed = claim.is_admin()
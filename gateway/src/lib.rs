@@ -525,6 +525,20 @@ pub mod tests {
             user.to_string()
         }
 
+        /// Create an API token of `user` that can only act on `projects`, and return its key
+        pub fn create_project_token(&self, user: &str, projects: &[&str]) -> String {
+            let key = format!("{user}-token-{}", projects.join("-"));
+            self.auth_service.lock().unwrap().tokens.insert(
+                key.clone(),
+                (
+                    user.to_string(),
+                    projects.iter().map(ToString::to_string).collect(),
+                ),
+            );
+
+            key
+        }
+
         /// Create a user with the given name and tier and return the authorization bearer for the user
         pub fn create_authorization_bearer(
             &self,
@@ -621,6 +635,8 @@ pub mod tests {
 
     struct AuthService {
         users: HashMap<String, AccountTier>,
+        /// API tokens scoped to projects, by key, with the user they belong to
+        tokens: HashMap<String, (String, Vec<String>)>,
         encoding_key: EncodingKey,
         public_key: Vec<u8>,
     }
@@ -635,6 +651,7 @@ pub mod tests {
 
             let this = Arc::new(Mutex::new(Self {
                 users: HashMap::new(),
+                tokens: HashMap::new(),
                 encoding_key,
                 public_key,
             }));
@@ -651,13 +668,17 @@ pub mod tests {
                     get(|extract::State(state): extract::State<Arc<Mutex<Self>>>, TypedHeader(bearer): TypedHeader<Authorization<Bearer>> | async move {
                         let state = state.lock().unwrap();
 
-                        if let Some(tier) = state.users.get(bearer.token()) {
-                            let claim = Claim::new(bearer.token().to_string(), (*tier).into(), *tier, *tier);
-                            let token = claim.into_token(&state.encoding_key)?;
-                            Ok(serde_json::to_vec(&ConvertResponse { token }).unwrap())
+                        let claim = if let Some(tier) = state.users.get(bearer.token()) {
+                            Claim::new(bearer.token().to_string(), (*tier).into(), *tier, *tier)
+                        } else if let Some((user, projects)) = state.tokens.get(bearer.token()) {
+                            let tier = state.users[user];
+                            Claim::new(user.clone(), tier.into(), tier, tier)
+                                .restricted(Some(projects.clone()), None)
                         } else {
-                            Err(StatusCode::NOT_FOUND)
-                        }
+                            return Err(StatusCode::NOT_FOUND);
+                        };
+                        let token = claim.into_token(&state.encoding_key)?;
+                        Ok(serde_json::to_vec(&ConvertResponse { token }).unwrap())
                     }),
                 )
                 .with_state(this.clone());
@@ -885,6 +906,30 @@ pub mod tests {
                 .unwrap()
         }
 
+        /// Send a request with a JSON body to the router for this project
+        pub async fn router_call_json(
+            &mut self,
+            method: Method,
+            sub_path: &str,
+            body: &impl serde::Serialize,
+        ) -> StatusCode {
+            let project_name = &self.project_name;
+
+            self.router
+                .call(
+                    Request::builder()
+                        .method(method)
+                        .uri(format!("/projects/{project_name}{sub_path}"))
+                        .header("Content-Type", "application/json")
+                        .body(serde_json::to_vec(body).unwrap().into())
+                        .unwrap()
+                        .with_header(&self.authorization),
+                )
+                .map_ok(|resp| resp.status())
+                .await
+                .unwrap()
+        }
+
         /// Just deploy the code at the path and don't wait for it to finish
         pub async fn just_deploy(&mut self, path: &str) {
             let path = canonicalize(path).expect("deploy path to be valid");
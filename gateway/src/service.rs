@@ -11,6 +11,7 @@ use axum::http::Request;
 use axum::response::Response;
 use bollard::container::StatsOptions;
 use bollard::{Docker, API_DEFAULT_VERSION};
+use chrono::{TimeZone, Utc};
 use fqdn::{Fqdn, FQDN};
 use http::{StatusCode, Uri};
 use hyper::client::connect::dns::GaiResolver;
@@ -29,7 +30,7 @@ use shuttle_common::constants::SHUTTLE_IDLE_DOCS_URL;
 use shuttle_common::models::error::{
     ApiError, ProjectNotFound, ProjectNotReady, ProjectUnavailable,
 };
-use shuttle_common::models::project::State;
+use shuttle_common::models::project::{self, State};
 use shuttle_common::models::user::UserId;
 use sqlx::error::DatabaseError;
 use sqlx::migrate::Migrator;
@@ -876,6 +877,85 @@ impl GatewayService {
         Ok(custom_domain)
     }
 
+    /// All the custom domains of a project, ordered by name
+    pub async fn find_custom_domains_for_project(
+        &self,
+        project_name: &str,
+    ) -> Result<Vec<CustomDomain>, Error> {
+        let custom_domains = query(
+            "SELECT fqdn, project_name, certificate, private_key FROM custom_domains AS cd JOIN projects AS p ON cd.project_id = p.project_id WHERE p.project_name = ?1 ORDER BY fqdn",
+        )
+        .bind(project_name)
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(|row| CustomDomain {
+            fqdn: row.get::<&str, _>("fqdn").parse().unwrap(),
+            project_name: row.try_get("project_name").unwrap(),
+            certificate: row.get("certificate"),
+            private_key: row.get("private_key"),
+        })
+        .collect();
+
+        Ok(custom_domains)
+    }
+
+    /// The lock that blocks new deployments of a project, if it has one
+    pub async fn find_project_lock(
+        &self,
+        project_name: &ProjectName,
+    ) -> Result<Option<project::Lock>, Error> {
+        let row =
+            query("SELECT lock_reason, locked_by, locked_at FROM projects WHERE project_name = ?1")
+                .bind(project_name)
+                .fetch_optional(&self.db)
+                .await?
+                .ok_or_else(|| ProjectNotFound(project_name.to_string()))?;
+
+        let (Some(reason), Some(locked_by), Some(locked_at)) = (
+            row.get::<Option<String>, _>("lock_reason"),
+            row.get::<Option<String>, _>("locked_by"),
+            row.get::<Option<i64>, _>("locked_at"),
+        ) else {
+            return Ok(None);
+        };
+        let locked_at = Utc
+            .timestamp_opt(locked_at, 0)
+            .single()
+            .ok_or_else(|| Error::InternalSafe("Invalid time of the project lock".to_string()))?;
+
+        Ok(Some(project::Lock {
+            reason,
+            locked_by,
+            locked_at,
+        }))
+    }
+
+    pub async fn set_project_lock(
+        &self,
+        project_name: &ProjectName,
+        lock: &project::Lock,
+    ) -> Result<(), Error> {
+        query("UPDATE projects SET lock_reason = ?1, locked_by = ?2, locked_at = ?3 WHERE project_name = ?4")
+            .bind(&lock.reason)
+            .bind(&lock.locked_by)
+            .bind(lock.locked_at.timestamp())
+            .bind(project_name)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_project_lock(&self, project_name: &ProjectName) -> Result<(), Error> {
+        query("UPDATE projects SET lock_reason = NULL, locked_by = NULL, locked_at = NULL WHERE project_name = ?1")
+            .bind(project_name)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn project_details_for_custom_domain(
         &self,
         fqdn: &Fqdn,
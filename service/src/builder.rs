@@ -8,6 +8,15 @@ use shuttle_common::constants::RUNTIME_NAME;
 use tokio::io::AsyncBufReadExt;
 use tracing::{debug, error, info, trace};
 
+use crate::CONTAINER_WORKDIR;
+
+/// The image deployments are built in, see the deployer in the Containerfile
+pub const PRODUCTION_BUILD_IMAGE: &str = "docker.io/library/rust:1.76.0-bookworm";
+
+/// Target directory of container builds, relative to the workspace. It is kept apart from the
+/// usual one, as the artifacts in it are built for the container.
+pub const CONTAINER_TARGET_DIR: &str = "target/shuttle-container";
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 /// This represents a compiled alpha service
 pub struct BuiltService {
@@ -59,12 +68,70 @@ fn extract_shuttle_toml_name(path: PathBuf) -> anyhow::Result<String> {
     Ok(name)
 }
 
+/// Extra options for the `cargo build` of a workspace
+#[derive(Clone, Debug, Default)]
+pub struct BuildOptions {
+    pub release_mode: bool,
+    /// Cargo profile to build with, instead of `dev` or `release`
+    pub profile: Option<String>,
+    pub features: Vec<String>,
+    pub no_default_features: bool,
+    pub target_dir: Option<PathBuf>,
+    /// Any other arguments to pass to `cargo build`
+    pub cargo_args: Vec<String>,
+    /// Keep debug info and symbols, also in release builds, so that a debugger can be attached
+    pub debug_info: bool,
+    /// Build with what is already downloaded, without using the network
+    pub offline: bool,
+    /// Build in a container of this image instead of on the host
+    pub container_image: Option<String>,
+}
+
+impl BuildOptions {
+    /// Name of the directory in the target directory that the profile builds to
+    fn profile_dir(&self) -> &str {
+        match self.profile.as_deref() {
+            Some("dev") | Some("test") => "debug",
+            Some("bench") => "release",
+            Some(profile) => profile,
+            None if self.release_mode => "release",
+            None => "debug",
+        }
+    }
+
+    /// Name of the profile, as used in `CARGO_PROFILE_<NAME>_*` variables
+    fn profile_env_name(&self) -> String {
+        let profile = match self.profile.as_deref() {
+            Some(profile) => profile,
+            None if self.release_mode => "release",
+            None => "dev",
+        };
+
+        profile.to_ascii_uppercase().replace('-', "_")
+    }
+}
+
 /// Given a project directory path, builds the crate
 pub async fn build_workspace(
     project_path: &Path,
     release_mode: bool,
     tx: tokio::sync::mpsc::Sender<String>,
     deployment: bool,
+) -> anyhow::Result<Vec<BuiltService>> {
+    let options = BuildOptions {
+        release_mode,
+        ..Default::default()
+    };
+
+    build_workspace_with_options(project_path, &options, tx, deployment).await
+}
+
+/// Given a project directory path, builds the crate with extra cargo options
+pub async fn build_workspace_with_options(
+    project_path: &Path,
+    options: &BuildOptions,
+    tx: tokio::sync::mpsc::Sender<String>,
+    deployment: bool,
 ) -> anyhow::Result<Vec<BuiltService>> {
     let project_path = project_path.to_owned();
     let manifest_path = project_path.join("Cargo.toml");
@@ -72,41 +139,46 @@ pub async fn build_workspace(
         bail!("Cargo manifest file not found: {}", manifest_path.display());
     }
 
-    // Cargo's "Downloading ..." lines are quite verbose.
-    // Instead, a custom message is printed if the download takes significant time.
-    // Cargo seems to have similar logic, where it prints nothing if this step takes little time.
-    let mut command = tokio::process::Command::new("cargo");
-    command
-        .arg("fetch")
-        .arg("--manifest-path")
-        .arg(&manifest_path)
-        .arg("--color=always")
-        .stdout(Stdio::null())
-        .stderr(Stdio::null());
-    let notification = tokio::spawn({
-        let tx = tx.clone();
-        async move {
-            tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-            tx.send("      Downloading crates...".into())
+    // Nothing is fetched offline, cargo is told to use what it has below. Container builds
+    // fetch for themselves.
+    if !options.offline && options.container_image.is_none() {
+        // Cargo's "Downloading ..." lines are quite verbose.
+        // Instead, a custom message is printed if the download takes significant time.
+        // Cargo seems to have similar logic, where it prints nothing if this step takes little time.
+        let mut command = tokio::process::Command::new("cargo");
+        command
+            .arg("fetch")
+            .arg("--manifest-path")
+            .arg(&manifest_path)
+            .arg("--color=always")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        let notification = tokio::spawn({
+            let tx = tx.clone();
+            async move {
+                tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+                tx.send("      Downloading crates...".into())
+                    .await
+                    .expect("log receiver to exist");
+            }
+        });
+        if !command.status().await?.success() {
+            tx.send("      Failed to fetch crates".into())
                 .await
                 .expect("log receiver to exist");
         }
-    });
-    if !command.status().await?.success() {
-        tx.send("      Failed to fetch crates".into())
-            .await
-            .expect("log receiver to exist");
+        notification.abort();
     }
-    notification.abort();
 
     let metadata = {
         // Modified implementaion of `cargo_metadata::MetadataCommand::exec` (from v0.15.3).
         // Uses tokio Command instead of std, to make this operation non-blocking.
-        let mut cmd = tokio::process::Command::from(
-            cargo_metadata::MetadataCommand::new()
-                .manifest_path(&manifest_path)
-                .cargo_command(),
-        );
+        let mut metadata_command = cargo_metadata::MetadataCommand::new();
+        metadata_command.manifest_path(&manifest_path);
+        if options.offline {
+            metadata_command.other_options(vec!["--offline".to_string()]);
+        }
+        let mut cmd = tokio::process::Command::from(metadata_command.cargo_command());
 
         let output = cmd.output().await?;
         if !output.status.success() {
@@ -146,11 +218,18 @@ pub async fn build_workspace(
         alpha_packages.push(member);
     }
 
+    let target_directory = match &options.container_image {
+        Some(_) => project_path.join(CONTAINER_TARGET_DIR),
+        None => options
+            .target_dir
+            .clone()
+            .unwrap_or_else(|| metadata.target_directory.clone().into_std_path_buf()),
+    };
     let services = compile(
         alpha_packages,
-        release_mode,
+        options,
         project_path.clone(),
-        metadata.target_directory.clone(),
+        target_directory,
         deployment,
         tx.clone(),
     )
@@ -181,6 +260,45 @@ pub async fn clean_crate(project_path: &Path) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// A `cargo build` of the workspace in a container of `image`, to be completed with more arguments
+fn container_build_command(
+    project_path: &Path,
+    image: &str,
+    envs: &[(String, String)],
+) -> tokio::process::Command {
+    let mut cmd = tokio::process::Command::new("docker");
+    cmd.arg("run")
+        .arg("--rm")
+        .arg("--volume")
+        .arg(format!("{}:{CONTAINER_WORKDIR}", project_path.display()))
+        .arg("--workdir")
+        .arg(CONTAINER_WORKDIR)
+        // Downloaded crates are kept with the artifacts, so that they outlive the container
+        .arg("--env")
+        .arg(format!(
+            "CARGO_HOME={CONTAINER_WORKDIR}/{CONTAINER_TARGET_DIR}/cargo-home"
+        ));
+    // The artifacts belong to the owner of the project, rather than to root
+    #[cfg(unix)]
+    if let Ok(metadata) = std::fs::metadata(project_path) {
+        use std::os::unix::fs::MetadataExt;
+        cmd.arg("--user")
+            .arg(format!("{}:{}", metadata.uid(), metadata.gid()));
+    }
+    for (name, value) in envs {
+        cmd.arg("--env").arg(format!("{name}={value}"));
+    }
+    cmd.arg(image)
+        .arg("cargo")
+        .arg("build")
+        .arg("--manifest-path")
+        .arg(format!("{CONTAINER_WORKDIR}/Cargo.toml"))
+        .arg("--target-dir")
+        .arg(CONTAINER_TARGET_DIR);
+
+    cmd
+}
+
 /// Make sure the project is a binary for alpha projects.
 fn ensure_binary(package: &Package) -> anyhow::Result<()> {
     if package.targets.iter().any(|target| target.is_bin()) {
@@ -192,7 +310,7 @@ fn ensure_binary(package: &Package) -> anyhow::Result<()> {
 
 async fn compile(
     packages: Vec<&Package>,
-    release_mode: bool,
+    options: &BuildOptions,
     project_path: PathBuf,
     target_path: impl Into<PathBuf>,
     deployment: bool,
@@ -204,12 +322,29 @@ async fn compile(
     }
     let target_path = target_path.into();
 
-    let mut cmd = tokio::process::Command::new("cargo");
-    cmd.arg("build")
-        .arg("--manifest-path")
-        .arg(manifest_path)
-        .arg("--color=always") // piping disables auto color, but we want it
-        .current_dir(project_path.as_path());
+    let mut envs = Vec::new();
+    if options.debug_info {
+        let profile = options.profile_env_name();
+        envs.push((format!("CARGO_PROFILE_{profile}_DEBUG"), "true".to_string()));
+        envs.push((format!("CARGO_PROFILE_{profile}_STRIP"), "none".to_string()));
+    }
+
+    let mut cmd = match &options.container_image {
+        Some(image) => container_build_command(&project_path, image, &envs),
+        None => {
+            let mut cmd = tokio::process::Command::new("cargo");
+            cmd.arg("build")
+                .arg("--manifest-path")
+                .arg(manifest_path)
+                .envs(envs)
+                .current_dir(project_path.as_path());
+            if let Some(target_dir) = &options.target_dir {
+                cmd.arg("--target-dir").arg(target_dir);
+            }
+            cmd
+        }
+    };
+    cmd.arg("--color=always"); // piping disables auto color, but we want it
 
     if deployment {
         cmd.arg("--jobs=4");
@@ -219,12 +354,26 @@ async fn compile(
         cmd.arg("--package").arg(package.name.as_str());
     }
 
-    let profile = if release_mode {
-        cmd.arg("--release");
-        "release"
-    } else {
-        "debug"
-    };
+    match &options.profile {
+        Some(profile) => {
+            cmd.arg("--profile").arg(profile);
+        }
+        None if options.release_mode => {
+            cmd.arg("--release");
+        }
+        None => {}
+    }
+    if !options.features.is_empty() {
+        cmd.arg("--features").arg(options.features.join(","));
+    }
+    if options.no_default_features {
+        cmd.arg("--no-default-features");
+    }
+    if options.offline {
+        cmd.arg("--offline");
+    }
+    cmd.args(&options.cargo_args);
+    let profile = options.profile_dir();
 
     cmd.stderr(Stdio::piped());
     cmd.stdout(Stdio::null());
@@ -4,7 +4,7 @@ use std::path::PathBuf;
 
 use async_trait::async_trait;
 use serde::{de::DeserializeOwned, Serialize};
-use shuttle_common::constants::STORAGE_DIRNAME;
+use shuttle_common::constants::{STORAGE_DIRNAME, STORAGE_PATH_ENV};
 pub use shuttle_common::{
     database,
     deployment::{DeploymentMetadata, Environment},
@@ -21,6 +21,9 @@ pub mod error;
 #[cfg(feature = "runner")]
 pub mod runner;
 
+/// Where the workspace is mounted in the containers of a containerized local run
+pub const CONTAINER_WORKDIR: &str = "/usr/src/app";
+
 /// Allows implementing plugins for the Shuttle main function.
 ///
 /// ## Creating your own Shuttle plugin
@@ -83,7 +86,9 @@ impl ResourceFactory {
         DeploymentMetadata {
             env: self.env,
             project_name: self.project_name.to_string(),
-            storage_path: PathBuf::from(STORAGE_DIRNAME),
+            storage_path: std::env::var_os(STORAGE_PATH_ENV)
+                .map(PathBuf::from)
+                .unwrap_or_else(|| PathBuf::from(STORAGE_DIRNAME)),
         }
     }
 }
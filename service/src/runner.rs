@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     path::{Path, PathBuf},
     process::Stdio,
 };
@@ -8,10 +9,95 @@ use shuttle_proto::runtime;
 use tokio::process;
 use tracing::info;
 
+use crate::CONTAINER_WORKDIR;
+
+/// Start the runtime at `runtime_executable`, with the extra environment variables in `env` set
+/// on its process.
 pub async fn start(
     port: u16,
     runtime_executable: PathBuf,
     project_path: &Path,
+    env: &HashMap<String, String>,
+) -> anyhow::Result<(process::Child, runtime::Client)> {
+    spawn(
+        port,
+        runtime_executable,
+        project_path,
+        env,
+        Stdio::inherit(),
+    )
+    .await
+}
+
+/// Like [`start`], with the stderr of the runtime piped as well, so that panics can be captured.
+pub async fn start_capturing_stderr(
+    port: u16,
+    runtime_executable: PathBuf,
+    project_path: &Path,
+    env: &HashMap<String, String>,
+) -> anyhow::Result<(process::Child, runtime::Client)> {
+    spawn(port, runtime_executable, project_path, env, Stdio::piped()).await
+}
+
+/// Like [`start`], with the runtime running in a container of `image` named
+/// `container_name`. The workspace at `project_path` is mounted at [`CONTAINER_WORKDIR`], and
+/// `runtime_executable` is the path of the runtime in the container.
+///
+/// The container shares the network of the host, so that the runtime and the service are reached
+/// like they are without a container, and local resources are reached on localhost.
+pub async fn start_in_container(
+    port: u16,
+    image: &str,
+    container_name: &str,
+    runtime_executable: &str,
+    project_path: &Path,
+    env: &HashMap<String, String>,
+) -> anyhow::Result<(process::Child, runtime::Client)> {
+    let port = &port.to_string();
+
+    let mut command = process::Command::new("docker");
+    command
+        .arg("run")
+        .arg("--rm")
+        .arg("--init")
+        .arg("--name")
+        .arg(container_name)
+        .arg("--network")
+        .arg("host")
+        .arg("--volume")
+        .arg(format!("{}:{CONTAINER_WORKDIR}", project_path.display()))
+        .arg("--workdir")
+        .arg(CONTAINER_WORKDIR);
+    // Only the names are on the command line, the values are taken from the environment of the
+    // docker process so that secrets don't show up in process lists
+    for name in env.keys() {
+        command.arg("--env").arg(name);
+    }
+    command
+        .arg(image)
+        .arg(runtime_executable)
+        .args(["--port", port]);
+
+    info!(%image, %runtime_executable, %port, "Spawning runtime container");
+    let runtime = command
+        .envs(env)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("spawning runtime container")?;
+
+    let runtime_client = runtime::get_client(port).await?;
+
+    Ok((runtime, runtime_client))
+}
+
+async fn spawn(
+    port: u16,
+    runtime_executable: PathBuf,
+    project_path: &Path,
+    env: &HashMap<String, String>,
+    stderr: Stdio,
 ) -> anyhow::Result<(process::Child, runtime::Client)> {
     let port = &port.to_string();
     let args = vec!["--port", port];
@@ -25,7 +111,9 @@ pub async fn start(
     )
     .current_dir(project_path)
     .args(&args)
+    .envs(env)
     .stdout(Stdio::piped())
+    .stderr(stderr)
     .kill_on_drop(true)
     .spawn()
     .context("spawning runtime process")?;